@@ -149,9 +149,34 @@ check(
     "core:event:allow-unlisten",
     "allow-get-startup-error",
     "allow-get-copy-events-page",
+    "allow-search-copy-events",
     "allow-get-history-detail",
     "allow-delete-copy-event",
+    "allow-pin-copy-event",
+    "allow-unpin-copy-event",
+    "allow-list-collections",
+    "allow-create-collection",
+    "allow-rename-collection",
+    "allow-delete-collection",
+    "allow-add-copy-event-to-collection",
+    "allow-remove-copy-event-from-collection",
+    "allow-list-tags",
+    "allow-tag-copy-event",
+    "allow-untag-copy-event",
+    "allow-rename-tag",
+    "allow-delete-tag",
+    "allow-get-copy-event-labels",
     "allow-clear-all-events",
+    "allow-verify-history-integrity",
+    "allow-check-history-integrity",
+    "allow-export-history-backup",
+    "allow-export-history",
+    "allow-import-history-backup",
+    "allow-import-history-jsonl",
+    "allow-import-clipboard-manager-history",
+    "allow-list-database-backups",
+    "allow-restore-database-backup",
+    "allow-delete-database-backup",
     "allow-copy-to-clipboard",
     "allow-get-app-settings",
     "allow-get-safe-diagnostics",
@@ -159,9 +184,15 @@ check(
     "allow-set-autostart-enabled",
     "allow-set-max-items",
     "allow-set-max-history-bytes",
+    "allow-set-max-age-days",
+    "allow-set-data-type-budget",
+    "allow-set-source-app-rule",
+    "allow-set-source-allow-only",
+    "allow-set-secret-policy",
     "allow-set-show-in-menu-bar",
     "allow-set-menu-bar-item-limit",
     "allow-set-move-restored-item-to-top",
+    "allow-set-move-recopied-item-to-top",
     "allow-set-compact-mode",
     "allow-set-language",
   ]),
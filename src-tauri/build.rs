@@ -3,9 +3,34 @@ fn main() {
         tauri_build::AppManifest::new().commands(&[
             "get_startup_error",
             "get_copy_events_page",
+            "search_copy_events",
             "get_history_detail",
             "delete_copy_event",
+            "pin_copy_event",
+            "unpin_copy_event",
+            "list_collections",
+            "create_collection",
+            "rename_collection",
+            "delete_collection",
+            "add_copy_event_to_collection",
+            "remove_copy_event_from_collection",
+            "list_tags",
+            "tag_copy_event",
+            "untag_copy_event",
+            "rename_tag",
+            "delete_tag",
+            "get_copy_event_labels",
             "clear_all_events",
+            "verify_history_integrity",
+            "check_history_integrity",
+            "export_history_backup",
+            "export_history",
+            "import_history_backup",
+            "import_history_jsonl",
+            "import_clipboard_manager_history",
+            "list_database_backups",
+            "restore_database_backup",
+            "delete_database_backup",
             "copy_to_clipboard",
             "get_app_settings",
             "get_safe_diagnostics",
@@ -13,9 +38,15 @@ fn main() {
             "set_autostart_enabled",
             "set_max_items",
             "set_max_history_bytes",
+            "set_max_age_days",
+            "set_data_type_budget",
+            "set_source_app_rule",
+            "set_source_allow_only",
+            "set_secret_policy",
             "set_show_in_menu_bar",
             "set_menu_bar_item_limit",
             "set_move_restored_item_to_top",
+            "set_move_recopied_item_to_top",
             "set_compact_mode",
             "set_language",
         ]),
@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(copy_stack_lib::run_cli(std::env::args_os().skip(1)));
+}
@@ -0,0 +1,824 @@
+//! The headless `copy-stack-cli` binary: history queries and edits against
+//! the app's database without starting Tauri.
+//!
+//! The database is opened read-only unless `--write` is passed, which only
+//! `delete`, `pin`, and `unpin` accept. Listing, details, and flavors follow
+//! the compact mode projection the app shows. Edits go straight to SQLite, so
+//! a running app picks them up on its next refresh rather than immediately.
+
+use crate::store::{
+    Database, HistoryDetailSeed, HistoryQuery, HistoryQueryError, HistorySummary, PinOutcome,
+};
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::Write;
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const MAX_LABEL_CHARS: usize = 80;
+const TEXT_TYPE: &str = "public.utf8-plain-text";
+
+const USAGE: &str = "usage: copy-stack-cli [--json] [--write] <command>
+
+commands:
+  list [--limit N] [--cursor CURSOR]           newest history first
+  search QUERY [--limit N] [--cursor CURSOR]   the app's search syntax
+  show HASH                                    one item and its flavors
+  cat HASH --flavor TYPE                       one flavor's raw bytes
+  delete HASH                                  needs --write
+  pin HASH | unpin HASH                        needs --write
+  stats                                        history totals
+";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CliCommand {
+    Help,
+    List {
+        cursor: Option<String>,
+        limit: Option<usize>,
+    },
+    Search {
+        query: String,
+        cursor: Option<String>,
+        limit: Option<usize>,
+    },
+    Show {
+        content_hash: String,
+    },
+    Cat {
+        content_hash: String,
+        flavor: String,
+    },
+    Delete {
+        content_hash: String,
+    },
+    Pin {
+        content_hash: String,
+        pinned: bool,
+    },
+    Stats,
+}
+
+impl CliCommand {
+    fn writes(&self) -> bool {
+        matches!(self, Self::Delete { .. } | Self::Pin { .. })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CliInvocation {
+    json: bool,
+    writable: bool,
+    command: CliCommand,
+}
+
+/// Runs one CLI invocation and returns the process exit code.
+pub fn run_cli<I>(args: I) -> i32
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let invocation = match CliInvocation::parse(args) {
+        Ok(invocation) => invocation,
+        Err(message) => {
+            eprintln!("copy-stack-cli: {message}\n\n{USAGE}");
+            return EXIT_USAGE;
+        }
+    };
+    if invocation.command == CliCommand::Help {
+        print!("{USAGE}");
+        return 0;
+    }
+
+    let result = Database::open_existing(invocation.writable)
+        .map_err(|error| format!("history database could not be opened: {}", message(&error)))
+        .and_then(|db| invocation.run(&db, &mut std::io::stdout().lock()));
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("copy-stack-cli: {message}");
+            EXIT_FAILURE
+        }
+    }
+}
+
+impl CliInvocation {
+    fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = OsString>,
+    {
+        let mut json = false;
+        let mut writable = false;
+        let mut cursor = None;
+        let mut limit = None;
+        let mut flavor = None;
+        let mut positional = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let arg = arg
+                .into_string()
+                .map_err(|_| "arguments must be valid UTF-8".to_string())?;
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next().and_then(|value| value.into_string().ok()))
+                    .ok_or_else(|| format!("{flag} requires a value"))
+            };
+            match flag {
+                "--json" if inline_value.is_none() => json = true,
+                "--write" if inline_value.is_none() => writable = true,
+                "-h" | "--help" if inline_value.is_none() => positional.insert(0, "help".into()),
+                "--cursor" => cursor = Some(value()?),
+                "--limit" => {
+                    let value = value()?;
+                    limit = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|limit| *limit > 0)
+                            .ok_or_else(|| format!("--limit must be a positive number: {value}"))?,
+                    );
+                }
+                "--flavor" => flavor = Some(value()?),
+                _ if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let name = positional.next().unwrap_or_else(|| "help".to_string());
+        let mut operand = |what: &str| {
+            positional
+                .next()
+                .ok_or_else(|| format!("{name} requires {what}"))
+        };
+        let command = match name.as_str() {
+            "help" => CliCommand::Help,
+            "list" => CliCommand::List {
+                cursor: cursor.take(),
+                limit: limit.take(),
+            },
+            "search" => CliCommand::Search {
+                query: operand("a query")?,
+                cursor: cursor.take(),
+                limit: limit.take(),
+            },
+            "show" => CliCommand::Show {
+                content_hash: operand("a content hash")?,
+            },
+            "cat" => CliCommand::Cat {
+                content_hash: operand("a content hash")?,
+                flavor: flavor
+                    .take()
+                    .ok_or_else(|| "cat requires --flavor".to_string())?,
+            },
+            "delete" => CliCommand::Delete {
+                content_hash: operand("a content hash")?,
+            },
+            "pin" | "unpin" => CliCommand::Pin {
+                content_hash: operand("a content hash")?,
+                pinned: name == "pin",
+            },
+            "stats" => CliCommand::Stats,
+            _ => return Err(format!("unknown command {name}")),
+        };
+
+        if let Some(extra) = positional.next() {
+            return Err(format!("unexpected argument {extra}"));
+        }
+        if cursor.is_some() || limit.is_some() {
+            return Err(format!("{name} takes no --cursor or --limit"));
+        }
+        if flavor.is_some() {
+            return Err(format!("{name} takes no --flavor"));
+        }
+        if json && matches!(command, CliCommand::Cat { .. }) {
+            return Err("cat writes raw bytes and takes no --json".to_string());
+        }
+        if command.writes() && !writable {
+            return Err(format!("{name} changes history; pass --write"));
+        }
+        Ok(Self {
+            json,
+            writable,
+            command,
+        })
+    }
+
+    fn run(&self, db: &Database, out: &mut dyn Write) -> Result<(), String> {
+        match &self.command {
+            CliCommand::Help => out.write_all(USAGE.as_bytes()).map_err(output_error),
+            CliCommand::List { cursor, limit } => {
+                let page = db
+                    .get_history_page(cursor.as_deref(), *limit)
+                    .map_err(database_error)?;
+                self.write_summaries(out, page.items.iter(), page.next_cursor.as_deref())
+            }
+            CliCommand::Search {
+                query,
+                cursor,
+                limit,
+            } => {
+                let query = HistoryQuery::parse(query).map_err(query_error)?;
+                let page = db
+                    .search_history_page(&query, cursor.as_deref(), *limit)
+                    .map_err(database_error)?;
+                self.write_summaries(
+                    out,
+                    page.items.iter().map(|result| &result.summary),
+                    page.next_cursor.as_deref(),
+                )
+            }
+            CliCommand::Show { content_hash } => self.show(db, content_hash, out),
+            CliCommand::Cat {
+                content_hash,
+                flavor,
+            } => {
+                let (seed, compact_mode) = detail_seed(db, content_hash)?;
+                let event = Database::history_event_from_seed(&seed, compact_mode)
+                    .map_err(database_error)?
+                    .ok_or_else(|| not_found(content_hash))?;
+                let data = event
+                    .items
+                    .iter()
+                    .flat_map(|item| &item.data_list)
+                    .find(|data| &data.r#type == flavor)
+                    .ok_or_else(|| format!("history item {content_hash} has no {flavor} flavor"))?;
+                out.write_all(&data.data).map_err(output_error)
+            }
+            CliCommand::Delete { content_hash } => {
+                detail_seed(db, content_hash)?;
+                db.delete_event(content_hash).map_err(database_error)?;
+                self.write_edit(out, content_hash, "deleted")
+            }
+            CliCommand::Pin {
+                content_hash,
+                pinned,
+            } => {
+                let outcome = if *pinned {
+                    db.pin_event(content_hash)
+                } else {
+                    db.unpin_event(content_hash)
+                }
+                .map_err(database_error)?;
+                match outcome {
+                    PinOutcome::Updated => {}
+                    PinOutcome::NotFound => return Err(not_found(content_hash)),
+                    PinOutcome::LimitReached => {
+                        return Err("the pinned item limit is reached".to_string())
+                    }
+                }
+                self.write_edit(
+                    out,
+                    content_hash,
+                    if *pinned { "pinned" } else { "unpinned" },
+                )
+            }
+            CliCommand::Stats => {
+                let stats = db.get_history_stats().map_err(database_error)?;
+                let compact_mode = db.get_compact_mode().map_err(database_error)?;
+                if self.json {
+                    return write_json(
+                        out,
+                        &CliStats {
+                            total_items: stats.total_items,
+                            total_bytes: stats.total_bytes,
+                            compact_visible_items: stats.compact_visible_items,
+                            compact_mode,
+                        },
+                    );
+                }
+                writeln!(
+                    out,
+                    "items: {}\nbytes: {}\ncompact items: {}\ncompact mode: {}",
+                    stats.total_items,
+                    stats.total_bytes,
+                    stats.compact_visible_items,
+                    if compact_mode { "on" } else { "off" }
+                )
+                .map_err(output_error)
+            }
+        }
+    }
+
+    fn write_summaries<'a>(
+        &self,
+        out: &mut dyn Write,
+        items: impl Iterator<Item = &'a HistorySummary>,
+        next_cursor: Option<&str>,
+    ) -> Result<(), String> {
+        if self.json {
+            return write_json(
+                out,
+                &CliPage {
+                    items: items.map(CliSummary::from).collect(),
+                    next_cursor,
+                },
+            );
+        }
+        for item in items {
+            writeln!(
+                out,
+                "{}  {}  {:<8} {}{}",
+                item.content_hash,
+                local_time(item.timestamp),
+                item.data_type,
+                if item.pinned { "* " } else { "" },
+                label(&item.data_type, &item.display)
+            )
+            .map_err(output_error)?;
+        }
+        if let Some(next_cursor) = next_cursor {
+            eprintln!("more: --cursor {next_cursor}");
+        }
+        Ok(())
+    }
+
+    fn show(&self, db: &Database, content_hash: &str, out: &mut dyn Write) -> Result<(), String> {
+        let (seed, compact_mode) = detail_seed(db, content_hash)?;
+        let event = Database::history_event_from_seed(&seed, compact_mode)
+            .map_err(database_error)?
+            .ok_or_else(|| not_found(content_hash))?;
+        let flavors = event
+            .items
+            .iter()
+            .enumerate()
+            .flat_map(|(item, item_data)| {
+                item_data.data_list.iter().map(move |data| CliFlavor {
+                    item,
+                    r#type: data.r#type.clone(),
+                    byte_count: data.data.len(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let data_type = if compact_mode {
+            "text".to_string()
+        } else {
+            seed.data_type.clone()
+        };
+        let text = event
+            .items
+            .iter()
+            .flat_map(|item| &item.data_list)
+            .find(|data| data.r#type == TEXT_TYPE)
+            .map(|data| String::from_utf8_lossy(&data.data));
+
+        if self.json {
+            return write_json(
+                out,
+                &CliDetail {
+                    content_hash,
+                    data_type: &data_type,
+                    timestamp: seed.timestamp,
+                    source_bundle_id: seed.source_bundle_id.as_deref(),
+                    is_remote_clipboard: seed.is_remote_clipboard,
+                    byte_count: seed.byte_count,
+                    flavors,
+                    text: text.as_deref(),
+                },
+            );
+        }
+        let mut lines = vec![
+            format!("hash: {content_hash}"),
+            format!("type: {data_type}"),
+            format!("copied: {}", local_time(seed.timestamp)),
+            format!(
+                "source: {}",
+                seed.source_bundle_id.as_deref().unwrap_or("unknown")
+            ),
+            format!("bytes: {}", seed.byte_count),
+        ];
+        if seed.is_remote_clipboard {
+            lines.push("remote: yes".to_string());
+        }
+        lines.push("flavors:".to_string());
+        lines.extend(flavors.iter().map(|flavor| {
+            format!(
+                "  [{}] {} ({} bytes)",
+                flavor.item, flavor.r#type, flavor.byte_count
+            )
+        }));
+        if let Some(text) = text {
+            lines.push(String::new());
+            lines.push(text.into_owned());
+        }
+        writeln!(out, "{}", lines.join("\n")).map_err(output_error)
+    }
+
+    fn write_edit(
+        &self,
+        out: &mut dyn Write,
+        content_hash: &str,
+        action: &str,
+    ) -> Result<(), String> {
+        if self.json {
+            return write_json(
+                out,
+                &CliEdit {
+                    content_hash,
+                    action,
+                },
+            );
+        }
+        writeln!(out, "{action} {content_hash}").map_err(output_error)
+    }
+}
+
+#[derive(Serialize)]
+struct CliSummary<'a> {
+    content_hash: &'a str,
+    data_type: &'a str,
+    display: String,
+    display_truncated: bool,
+    timestamp: i64,
+    source_bundle_id: Option<&'a str>,
+    is_remote_clipboard: bool,
+    byte_count: u64,
+    pinned: bool,
+    first_seen_at: i64,
+    last_seen_at: i64,
+    copy_count: u64,
+}
+
+impl<'a> From<&'a HistorySummary> for CliSummary<'a> {
+    fn from(summary: &'a HistorySummary) -> Self {
+        Self {
+            content_hash: &summary.content_hash,
+            data_type: &summary.data_type,
+            display: display_text(&summary.display),
+            display_truncated: summary.display_truncated,
+            timestamp: summary.timestamp,
+            source_bundle_id: summary.source_bundle_id.as_deref(),
+            is_remote_clipboard: summary.is_remote_clipboard,
+            byte_count: summary.byte_count,
+            pinned: summary.pinned,
+            first_seen_at: summary.first_seen_at,
+            last_seen_at: summary.last_seen_at,
+            copy_count: summary.copy_count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CliPage<'a> {
+    items: Vec<CliSummary<'a>>,
+    next_cursor: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct CliFlavor {
+    item: usize,
+    r#type: String,
+    byte_count: usize,
+}
+
+#[derive(Serialize)]
+struct CliDetail<'a> {
+    content_hash: &'a str,
+    data_type: &'a str,
+    timestamp: i64,
+    source_bundle_id: Option<&'a str>,
+    is_remote_clipboard: bool,
+    byte_count: u64,
+    flavors: Vec<CliFlavor>,
+    text: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct CliStats {
+    total_items: u64,
+    total_bytes: u64,
+    compact_visible_items: u64,
+    compact_mode: bool,
+}
+
+#[derive(Serialize)]
+struct CliEdit<'a> {
+    content_hash: &'a str,
+    action: &'a str,
+}
+
+fn detail_seed(db: &Database, content_hash: &str) -> Result<(HistoryDetailSeed, bool), String> {
+    let seed = db
+        .get_history_detail_seed(content_hash)
+        .map_err(database_error)?
+        .map_err(|_| "history integrity check failed".to_string())?
+        .ok_or_else(|| not_found(content_hash))?;
+    let compact_mode = db.get_compact_mode().map_err(database_error)?;
+    Ok((seed, compact_mode))
+}
+
+/// File rows list their names one per line; other displays are text.
+fn display_text(display: &[u8]) -> String {
+    match Database::parse_file_display(display) {
+        Some(file_display) => file_display
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => String::from_utf8_lossy(display).into_owned(),
+    }
+}
+
+/// One line of `display_text`, falling back to the type for displays that
+/// are not readable text.
+fn label(data_type: &str, display: &[u8]) -> String {
+    let text = display_text(display);
+    let label = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if label.is_empty() || label.contains('\u{fffd}') {
+        return data_type.to_uppercase();
+    }
+    if label.chars().count() <= MAX_LABEL_CHARS {
+        return label;
+    }
+    let mut label = label.chars().take(MAX_LABEL_CHARS - 1).collect::<String>();
+    label.push('…');
+    label
+}
+
+fn local_time(timestamp: i64) -> String {
+    Local.timestamp_millis_opt(timestamp).single().map_or_else(
+        || timestamp.to_string(),
+        |time| time.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )
+}
+
+fn write_json(out: &mut dyn Write, value: &impl Serialize) -> Result<(), String> {
+    serde_json::to_writer(&mut *out, value).map_err(|_| "output failed".to_string())?;
+    writeln!(out).map_err(output_error)
+}
+
+fn query_error(error: HistoryQueryError) -> String {
+    let reason = match error {
+        HistoryQueryError::TooLong => "it is too long",
+        HistoryQueryError::TooManyTerms => "it has too many terms",
+        HistoryQueryError::TooManyFilters => "it has too many filters",
+        HistoryQueryError::UnterminatedQuote => "a quote is not closed",
+        HistoryQueryError::EmptyFilterValue => "a filter has no value",
+        HistoryQueryError::UnknownDataType => "a type: filter names an unknown type",
+        HistoryQueryError::InvalidDate => "a date filter is not a date",
+        HistoryQueryError::InvalidSize => "a size: filter is not a size",
+        HistoryQueryError::InvalidBoolean => "a filter expects yes or no",
+    };
+    format!("invalid search query: {reason}")
+}
+
+fn not_found(content_hash: &str) -> String {
+    format!("no history item {content_hash}")
+}
+
+fn message(error: &rusqlite::Error) -> String {
+    match error {
+        rusqlite::Error::InvalidParameterName(message) => message.clone(),
+        error => error.to_string(),
+    }
+}
+
+fn database_error(error: rusqlite::Error) -> String {
+    format!("history database failed: {}", message(&error))
+}
+
+fn output_error(_: std::io::Error) -> String {
+    "output failed".to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use copy_event_listener::event::{Data, Event, Item};
+    use std::path::{Path, PathBuf};
+
+    fn args(values: &[&str]) -> Vec<OsString> {
+        values.iter().map(OsString::from).collect()
+    }
+
+    fn parse(values: &[&str]) -> Result<CliInvocation, String> {
+        CliInvocation::parse(args(values))
+    }
+
+    fn text_event(text: &str) -> Event {
+        Event {
+            items: vec![Item {
+                data_list: vec![Data {
+                    r#type: "public.utf8-plain-text".to_string(),
+                    data: text.as_bytes().to_vec(),
+                }],
+            }],
+        }
+    }
+
+    fn temp_database_path(label: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!(
+            "copy_stack_cli_{label}_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system time should be after epoch")
+                .as_nanos()
+        ));
+        std::fs::create_dir(&root).expect("test root should be created");
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o700))
+            .expect("test root should be private");
+        root.join("copy_stack.db")
+    }
+
+    fn run(db: &Database, values: &[&str]) -> Result<String, String> {
+        let invocation = parse(values)?;
+        let mut out = Vec::new();
+        invocation.run(db, &mut out)?;
+        Ok(String::from_utf8(out).expect("output should be UTF-8"))
+    }
+
+    fn remove(path: &Path) {
+        let _ = std::fs::remove_dir_all(path.parent().expect("database should have a parent"));
+    }
+
+    #[test]
+    fn arguments_parse_into_commands_and_writes_need_the_write_flag() {
+        assert_eq!(
+            parse(&["--json", "list", "--limit=5"]),
+            Ok(CliInvocation {
+                json: true,
+                writable: false,
+                command: CliCommand::List {
+                    cursor: None,
+                    limit: Some(5),
+                },
+            })
+        );
+        assert_eq!(
+            parse(&["cat", "abc", "--flavor", "public.html"]).map(|parsed| parsed.command),
+            Ok(CliCommand::Cat {
+                content_hash: "abc".to_string(),
+                flavor: "public.html".to_string(),
+            })
+        );
+        assert_eq!(
+            parse(&["search", "type:text deploy", "--cursor", "c"]).map(|parsed| parsed.command),
+            Ok(CliCommand::Search {
+                query: "type:text deploy".to_string(),
+                cursor: Some("c".to_string()),
+                limit: None,
+            })
+        );
+        assert_eq!(
+            parse(&[]).map(|parsed| parsed.command),
+            Ok(CliCommand::Help)
+        );
+        assert!(parse(&["--write", "unpin", "abc"]).is_ok());
+
+        for invalid in [
+            &["delete", "abc"][..],
+            &["pin", "abc"],
+            &["cat", "abc"],
+            &["--json", "cat", "abc", "--flavor", "public.png"],
+            &["show"],
+            &["show", "abc", "def"],
+            &["stats", "--limit", "3"],
+            &["list", "--limit", "0"],
+            &["list", "--flavor", "public.png"],
+            &["list", "--verbose"],
+            &["export"],
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn commands_read_history_read_only_and_edit_it_with_write() {
+        let path = temp_database_path("commands");
+        {
+            let db = Database::open_path(&path).expect("database should open");
+            db.insert_event(&text_event("first\n  copy"))
+                .expect("event should insert");
+            db.insert_event(&text_event("second copy"))
+                .expect("event should insert");
+        }
+
+        let db = Database::open_path_read_only(&path).expect("database should open read-only");
+        let listed = run(&db, &["list"]).expect("list should run");
+        let lines = listed.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("text     second copy"), "{listed}");
+        assert!(lines[1].ends_with("first copy"), "{listed}");
+        let content_hash = lines[1][..64].to_string();
+
+        let page = serde_json::from_str::<serde_json::Value>(
+            &run(&db, &["--json", "list", "--limit", "1"]).expect("list should run"),
+        )
+        .expect("list should print JSON");
+        assert_eq!(page["items"][0]["display"], "second copy");
+        assert!(page["next_cursor"].is_string());
+
+        let searched = run(&db, &["search", "first"]).expect("search should run");
+        assert!(searched.starts_with(&content_hash));
+        assert!(run(&db, &["search", "size:>nope"]).is_err());
+
+        let shown = serde_json::from_str::<serde_json::Value>(
+            &run(&db, &["--json", "show", &content_hash]).expect("show should run"),
+        )
+        .expect("show should print JSON");
+        assert_eq!(shown["data_type"], "text");
+        assert_eq!(shown["flavors"][0]["type"], "public.utf8-plain-text");
+        assert_eq!(shown["text"], "first\n  copy");
+        assert_eq!(
+            run(
+                &db,
+                &["cat", &content_hash, "--flavor", "public.utf8-plain-text"]
+            ),
+            Ok("first\n  copy".to_string())
+        );
+        assert!(run(&db, &["cat", &content_hash, "--flavor", "public.png"]).is_err());
+        assert!(run(&db, &["show", &"0".repeat(64)]).is_err());
+        assert!(run(&db, &["stats"])
+            .expect("stats should run")
+            .starts_with("items: 2\n"));
+
+        assert!(
+            run(&db, &["--write", "delete", &content_hash]).is_err(),
+            "a read-only database should refuse edits"
+        );
+        drop(db);
+
+        let db = Database::open_path(&path).expect("database should open");
+        assert_eq!(
+            run(&db, &["--write", "pin", &content_hash]),
+            Ok(format!("pinned {content_hash}\n"))
+        );
+        assert!(run(&db, &["list"])
+            .expect("list should run")
+            .starts_with(&format!("{content_hash}  ")));
+        assert_eq!(
+            run(&db, &["--write", "--json", "delete", &content_hash]),
+            Ok(format!(
+                "{{\"content_hash\":\"{content_hash}\",\"action\":\"deleted\"}}\n"
+            ))
+        );
+        assert!(run(&db, &["--write", "delete", &content_hash]).is_err());
+        assert_eq!(
+            run(&db, &["list"])
+                .expect("list should run")
+                .lines()
+                .count(),
+            1
+        );
+
+        db.set_compact_mode(true)
+            .expect("compact mode should be set");
+        let compact = run(&db, &["stats"]).expect("stats should run");
+        assert!(compact.ends_with("compact mode: on\n"), "{compact}");
+        drop(db);
+        remove(&path);
+    }
+
+    #[test]
+    fn clients_neither_migrate_nor_tighten_the_database() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_database_path("client_opens");
+        {
+            let db = Database::open_path(&path).expect("database should open");
+            db.insert_event(&text_event("copy"))
+                .expect("event should insert");
+        }
+        let set_mode = |mode| {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))
+                .expect("database mode should change")
+        };
+        let mode = || {
+            std::fs::metadata(&path)
+                .expect("database should exist")
+                .permissions()
+                .mode()
+                & 0o777
+        };
+
+        set_mode(0o640);
+        assert!(Database::open_path_read_only(&path).is_err());
+        assert_eq!(mode(), 0o640, "a read-only open must not tighten files");
+        set_mode(0o600);
+        drop(Database::open_path_read_only(&path).expect("database should open read-only"));
+
+        rusqlite::Connection::open(&path)
+            .expect("database should open")
+            .pragma_update(None, "user_version", 12)
+            .expect("version should be set");
+        assert!(Database::open_path_writable(&path).is_err());
+        assert!(Database::open_path_read_only(&path).is_err());
+        let version: i64 = rusqlite::Connection::open(&path)
+            .expect("database should open")
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .expect("version should load");
+        assert_eq!(version, 12, "only the app migrates");
+        assert!(!path.with_file_name("backups").exists());
+        remove(&path);
+    }
+}
@@ -15,15 +15,32 @@ pub enum ErrorCode {
     DatabaseUnavailable,
     DatabaseOperationFailed,
     HistoryItemNotFound,
+    PinnedLimitReached,
+    InvalidLabelName,
+    LabelNameTaken,
+    LabelNotFound,
+    LabelLimitReached,
     ClipboardWriteFailed,
     RestorePostProcessingFailed,
     InvalidSetting,
     InvalidHistoryCursor,
+    InvalidSearchQuery,
     StateUnavailable,
     AutostartUnavailable,
     AutostartVerificationFailed,
     HistoryMirrorFailed,
     CaptureRejected,
+    HistoryIntegrityFailed,
+    InvalidBackupPassphrase,
+    InvalidBackup,
+    BackupFileUnavailable,
+    DatabaseBackupNotFound,
+    DatabaseCorrupted,
+    InvalidHistoryImport,
+    HistoryImportFileUnavailable,
+    HistoryExportFileUnavailable,
+    ControlSocketUnavailable,
+    InvalidControlRequest,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
@@ -32,14 +49,31 @@ pub enum Operation {
     Startup,
     CaptureClipboard,
     LoadHistory,
+    SearchHistory,
     LoadHistoryDetail,
     RestoreClipboard,
+    PinHistory,
+    UnpinHistory,
+    LoadLabels,
+    UpdateCollections,
+    UpdateTags,
     DeleteHistory,
     ClearHistory,
     LoadSettings,
     UpdateSettings,
     UpdateAutostart,
     WriteHistoryMirror,
+    ExpireHistory,
+    VerifyHistory,
+    CheckHistory,
+    ExportBackup,
+    ImportBackup,
+    LoadDatabaseBackups,
+    RestoreDatabaseBackup,
+    DeleteDatabaseBackup,
+    ImportHistory,
+    ExportHistory,
+    ControlRequest,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
@@ -47,6 +81,10 @@ pub struct CommandError {
     pub code: ErrorCode,
     pub operation: Operation,
     pub retryable: bool,
+    /// A fixed, content-free code that narrows `code`, such as the detector
+    /// kind behind a rejected capture.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'static str>,
 }
 
 impl CommandError {
@@ -55,9 +93,15 @@ impl CommandError {
             code,
             operation,
             retryable,
+            detail: None,
         }
     }
 
+    pub const fn with_detail(mut self, detail: &'static str) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
     pub const fn database(operation: Operation) -> Self {
         Self::new(ErrorCode::DatabaseOperationFailed, operation, true)
     }
@@ -88,6 +132,8 @@ pub struct SafeDiagnostic {
     pub code: ErrorCode,
     pub operation: Operation,
     pub retryable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'static str>,
 }
 
 impl From<&CommandError> for SafeDiagnostic {
@@ -100,6 +146,7 @@ impl From<&CommandError> for SafeDiagnostic {
             code: error.code,
             operation: error.operation,
             retryable: error.retryable,
+            detail: error.detail,
         }
     }
 }
@@ -161,4 +208,25 @@ mod tests {
         assert!(serialized.contains("database_operation_failed"));
         assert!(serialized.contains("load_history_detail"));
     }
+
+    #[test]
+    fn diagnostic_detail_is_serialized_only_when_set() {
+        let plain = serde_json::to_string(&CommandError::database(Operation::LoadHistory))
+            .expect("error should serialize");
+        assert!(!plain.contains("detail"));
+
+        let log = DiagnosticLog::default();
+        let error = CommandError::new(
+            ErrorCode::CaptureRejected,
+            Operation::CaptureClipboard,
+            false,
+        )
+        .with_detail("capture.secret.jwt");
+        log.record(&error)
+            .expect("diagnostic record should succeed");
+        let serialized =
+            serde_json::to_string(&log.snapshot().expect("diagnostics should be readable"))
+                .expect("diagnostics should serialize");
+        assert!(serialized.contains(r#""detail":"capture.secret.jwt""#));
+    }
 }
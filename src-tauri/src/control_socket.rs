@@ -0,0 +1,412 @@
+//! The opt-in local control socket for scripting the running app.
+//!
+//! Clients send one JSON request per line and read one JSON response per
+//! line. Every request names the protocol `version` and a `command`, plus an
+//! optional `id` that the response echoes:
+//!
+//! ```text
+//! {"version":1,"id":7,"command":"page","page_size":20}
+//! {"version":1,"id":7,"ok":true,"result":{"items":[...],...}}
+//! ```
+//!
+//! Commands run through the matching Tauri command handler, so a failure
+//! carries the same `CommandError` the IPC boundary returns. The socket lives
+//! in a private `0700` directory, and connections from another user are
+//! closed without a response.
+
+use crate::command_error::{CommandError, CommandResult, ErrorCode, Operation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const CONTROL_PROTOCOL_VERSION: u32 = 1;
+pub const CONTROL_SOCKET_FILE_NAME: &str = "control.sock";
+
+/// Bounds one request line; the largest real request is a cursor and a hash.
+const MAX_CONTROL_REQUEST_BYTES: u64 = 64 * 1024;
+const MAX_CONTROL_CONNECTIONS: usize = 8;
+const CONTROL_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A control request's command and its arguments, named like the fields of
+/// the matching Tauri command.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    Page {
+        cursor: Option<String>,
+        page_size: Option<usize>,
+    },
+    Detail {
+        content_hash: String,
+    },
+    Restore {
+        content_hash: String,
+    },
+    Delete {
+        content_hash: String,
+    },
+    /// Runs only with `"confirm": true`, like `--clear-history --yes`.
+    Clear {
+        #[serde(default)]
+        confirm: bool,
+    },
+    Settings,
+}
+
+impl ControlCommand {
+    pub fn operation(&self) -> Operation {
+        match self {
+            Self::Page { .. } => Operation::LoadHistory,
+            Self::Detail { .. } => Operation::LoadHistoryDetail,
+            Self::Restore { .. } => Operation::RestoreClipboard,
+            Self::Delete { .. } => Operation::DeleteHistory,
+            Self::Clear { .. } => Operation::ClearHistory,
+            Self::Settings => Operation::LoadSettings,
+        }
+    }
+}
+
+pub type ControlDispatch = dyn Fn(ControlCommand) -> CommandResult<Value> + Send + Sync;
+
+#[derive(Deserialize)]
+struct ControlEnvelope {
+    version: u32,
+    #[serde(default)]
+    id: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct ControlResponse {
+    version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<CommandError>,
+}
+
+impl ControlResponse {
+    fn new(id: Option<Value>, outcome: CommandResult<Value>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            version: CONTROL_PROTOCOL_VERSION,
+            id,
+            ok: error.is_none(),
+            result,
+            error,
+        }
+    }
+}
+
+fn invalid_request(detail: &'static str) -> CommandError {
+    CommandError::new(
+        ErrorCode::InvalidControlRequest,
+        Operation::ControlRequest,
+        false,
+    )
+    .with_detail(detail)
+}
+
+/// Parses one request line and runs it through `dispatch`.
+fn handle_request_line(line: &[u8], dispatch: &ControlDispatch) -> ControlResponse {
+    let Ok(request) = serde_json::from_slice::<Value>(line) else {
+        return ControlResponse::new(None, Err(invalid_request("control.malformed_request")));
+    };
+    let Ok(envelope) = ControlEnvelope::deserialize(&request) else {
+        return ControlResponse::new(None, Err(invalid_request("control.malformed_request")));
+    };
+    if envelope.version != CONTROL_PROTOCOL_VERSION {
+        return ControlResponse::new(
+            envelope.id,
+            Err(invalid_request("control.unsupported_version")),
+        );
+    }
+    let outcome = match ControlCommand::deserialize(&request) {
+        Ok(ControlCommand::Clear { confirm: false }) => {
+            Err(invalid_request("control.unconfirmed_clear"))
+        }
+        Ok(command) => dispatch(command),
+        Err(_) => Err(invalid_request("control.unknown_command")),
+    };
+    ControlResponse::new(envelope.id, outcome)
+}
+
+/// The listening control socket. Dropping it does not stop the listener;
+/// call `shutdown` on exit.
+pub struct ControlSocket {
+    path: PathBuf,
+    stopping: Arc<AtomicBool>,
+}
+
+impl ControlSocket {
+    /// Prepares `directory` as a private directory and starts serving
+    /// `CONTROL_SOCKET_FILE_NAME` inside it.
+    #[cfg(unix)]
+    pub fn start(directory: &Path, dispatch: Arc<ControlDispatch>) -> Result<Self, &'static str> {
+        use crate::private_fs::{bind_private_socket, ensure_private_directory};
+
+        let directory = ensure_private_directory(directory)
+            .map_err(|_| "CONTROL_SOCKET_DIRECTORY_UNAVAILABLE")?;
+        let path = directory.join(CONTROL_SOCKET_FILE_NAME);
+        let listener = bind_private_socket(&path).map_err(|_| "CONTROL_SOCKET_BIND_FAILED")?;
+        let stopping = Arc::new(AtomicBool::new(false));
+        let worker_stopping = Arc::clone(&stopping);
+        std::thread::Builder::new()
+            .name("copy-stack-control-socket".to_string())
+            .spawn(move || serve(listener, dispatch, worker_stopping))
+            .map_err(|_| "CONTROL_SOCKET_THREAD_START_FAILED")?;
+        Ok(Self { path, stopping })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(directory: &Path, dispatch: Arc<ControlDispatch>) -> Result<Self, &'static str> {
+        let _ = (directory, dispatch);
+        Err("CONTROL_SOCKET_UNSUPPORTED")
+    }
+
+    /// Stops accepting connections and removes the socket file.
+    pub fn shutdown(&self) {
+        if self.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Wakes the blocked `accept` so the listener thread sees the flag.
+        #[cfg(unix)]
+        let _ = std::os::unix::net::UnixStream::connect(&self.path);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn serve(
+    listener: std::os::unix::net::UnixListener,
+    dispatch: Arc<ControlDispatch>,
+    stopping: Arc<AtomicBool>,
+) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        if stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        let Ok(stream) = stream else {
+            std::thread::sleep(Duration::from_millis(100));
+            continue;
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONTROL_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            continue;
+        }
+        let dispatch = Arc::clone(&dispatch);
+        let worker_connections = Arc::clone(&connections);
+        let spawned = std::thread::Builder::new()
+            .name("copy-stack-control-connection".to_string())
+            .spawn(move || {
+                serve_connection(stream, &*dispatch);
+                worker_connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        if spawned.is_err() {
+            connections.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_connection(stream: std::os::unix::net::UnixStream, dispatch: &ControlDispatch) {
+    use crate::private_fs::socket_peer_is_current_user;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    if socket_peer_is_current_user(&stream) != Ok(true) {
+        return;
+    }
+    if stream.set_read_timeout(Some(CONTROL_IDLE_TIMEOUT)).is_err() {
+        return;
+    }
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut writer = stream;
+
+    loop {
+        let mut line = Vec::new();
+        match (&mut reader)
+            .take(MAX_CONTROL_REQUEST_BYTES + 1)
+            .read_until(b'\n', &mut line)
+        {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        // An oversized line cannot be resynchronized, so it ends the session.
+        let too_large = line.len() as u64 > MAX_CONTROL_REQUEST_BYTES;
+        let response = if too_large {
+            ControlResponse::new(None, Err(invalid_request("control.request_too_large")))
+        } else if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        } else {
+            handle_request_line(&line, dispatch)
+        };
+        let Ok(mut encoded) = serde_json::to_vec(&response) else {
+            return;
+        };
+        encoded.push(b'\n');
+        if writer.write_all(&encoded).is_err() || too_large {
+            return;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+
+    fn echo_dispatch(command: ControlCommand) -> CommandResult<Value> {
+        match command {
+            ControlCommand::Detail { content_hash } if content_hash == "missing" => {
+                Err(CommandError::new(
+                    ErrorCode::HistoryItemNotFound,
+                    Operation::LoadHistoryDetail,
+                    false,
+                ))
+            }
+            command => Ok(Value::String(format!("{command:?}"))),
+        }
+    }
+
+    fn respond(line: &str) -> Value {
+        serde_json::to_value(handle_request_line(line.as_bytes(), &echo_dispatch))
+            .expect("response should serialize")
+    }
+
+    #[test]
+    fn request_lines_dispatch_versioned_commands_and_echo_ids() {
+        assert_eq!(
+            respond(r#"{"version":1,"id":7,"command":"page","page_size":20}"#),
+            serde_json::json!({
+                "version": 1,
+                "id": 7,
+                "ok": true,
+                "result": "Page { cursor: None, page_size: Some(20) }",
+            })
+        );
+        assert_eq!(
+            respond(r#"{"version":1,"command":"clear","confirm":true}"#)["result"],
+            "Clear { confirm: true }"
+        );
+        assert_eq!(
+            respond(r#"{"version":1,"id":"a","command":"detail","content_hash":"missing"}"#),
+            serde_json::json!({
+                "version": 1,
+                "id": "a",
+                "ok": false,
+                "error": {
+                    "code": "history_item_not_found",
+                    "operation": "load_history_detail",
+                    "retryable": false,
+                },
+            })
+        );
+
+        for (line, detail) in [
+            ("not json", "control.malformed_request"),
+            (r#"{"command":"clear"}"#, "control.malformed_request"),
+            (
+                r#"{"version":1,"command":"clear"}"#,
+                "control.unconfirmed_clear",
+            ),
+            (
+                r#"{"version":1,"command":"clear","confirm":false}"#,
+                "control.unconfirmed_clear",
+            ),
+            (
+                r#"{"version":2,"command":"clear"}"#,
+                "control.unsupported_version",
+            ),
+            (
+                r#"{"version":1,"command":"quit"}"#,
+                "control.unknown_command",
+            ),
+            (
+                r#"{"version":1,"command":"restore"}"#,
+                "control.unknown_command",
+            ),
+        ] {
+            let response = respond(line);
+            assert_eq!(response["ok"], false, "{line}");
+            assert_eq!(response["error"]["code"], "invalid_control_request");
+            assert_eq!(response["error"]["operation"], "control_request");
+            assert_eq!(response["error"]["detail"], detail, "{line}");
+        }
+    }
+
+    #[test]
+    fn the_socket_serves_json_lines_in_a_private_directory() {
+        let root = std::env::temp_dir().join(format!(
+            "copy-stack-control-socket-test-{}",
+            std::process::id()
+        ));
+        let directory = root.join("control");
+        std::fs::create_dir_all(&root).expect("test root should be created");
+        std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o700))
+            .expect("test root should be private");
+
+        let socket = ControlSocket::start(&directory, Arc::new(echo_dispatch))
+            .expect("control socket should start");
+        let path = directory.join(CONTROL_SOCKET_FILE_NAME);
+        let mode = |path: &Path| {
+            std::fs::symlink_metadata(path)
+                .expect("metadata should exist")
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        assert_eq!(mode(&directory), 0o700);
+        assert_eq!(mode(&path), 0o600);
+
+        let mut client = UnixStream::connect(&path).expect("client should connect");
+        client
+            .write_all(
+                b"{\"version\":1,\"id\":1,\"command\":\"settings\"}\n\n\
+                  {\"version\":1,\"id\":2,\"command\":\"delete\",\"content_hash\":\"abc\"}\n",
+            )
+            .expect("requests should write");
+        let mut responses = BufReader::new(client.try_clone().expect("client should clone"));
+        for expected in [
+            r#"{"version":1,"id":1,"ok":true,"result":"Settings"}"#,
+            r#"{"version":1,"id":2,"ok":true,"result":"Delete { content_hash: \"abc\" }"}"#,
+        ] {
+            let mut line = String::new();
+            responses
+                .read_line(&mut line)
+                .expect("response should read");
+            assert_eq!(line, format!("{expected}\n"));
+        }
+
+        client
+            .write_all(&vec![b'x'; MAX_CONTROL_REQUEST_BYTES as usize + 1])
+            .expect("oversized request should write");
+        let mut line = String::new();
+        responses
+            .read_line(&mut line)
+            .expect("response should read");
+        assert!(line.contains("control.request_too_large"), "{line}");
+        line.clear();
+        assert_eq!(
+            responses.read_line(&mut line).expect("session should end"),
+            0
+        );
+
+        socket.shutdown();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
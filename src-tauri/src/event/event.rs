@@ -1,9 +1,36 @@
 use copy_event_listener::event::{
     Data as ListenerData, Event as ListenerEvent, Item as ListenerItem,
 };
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::io::{Read, Write};
 
 const EVENT_BLOB_MAGIC: &[u8; 4] = b"CSB1";
+/// Like `CSB1`, with a flags byte before each data length. A compressed
+/// entry follows its stored length with its decoded length, then stores raw
+/// DEFLATE bytes.
+const COMPRESSED_EVENT_BLOB_MAGIC: &[u8; 4] = b"CSB2";
+/// Like `CSB2`, with every flavor's flags and lengths moved into a table in
+/// front of the payloads. Each table entry also stores its payload's offset
+/// from the end of the table, so one flavor can be read without the others.
+const INDEXED_EVENT_BLOB_MAGIC: &[u8; 4] = b"CSB3";
+const DATA_COMPRESSED: u8 = 0x01;
+/// A `CSB3` entry whose payload is stored outside the blob. Its stored bytes
+/// are the payload's SHA-256 digest, followed in the table by the payload's
+/// length like a compressed entry's decoded length.
+const DATA_SHARED: u8 = 0x02;
+pub const SHARED_DIGEST_BYTES: usize = 32;
+/// Text flavors large enough for DEFLATE to pay for its framing. Images and
+/// other binary flavors are already compressed and are stored as-is.
+const COMPRESSIBLE_TYPES: [&str; 3] = ["public.utf8-plain-text", "public.html", "public.rtf"];
+const MIN_COMPRESSED_DATA_BYTES: usize = 4 * 1024;
+/// Binary flavors this large are worth storing once across rows: the same
+/// image is often copied alone and then again inside a rich-text document.
+const MIN_SHARED_DATA_BYTES: usize = 16 * 1024;
 pub const MAX_EVENT_BLOB_BYTES: usize = 32 * 1024 * 1024;
 pub const MAX_EVENT_ITEMS: usize = 64;
 pub const MAX_EVENT_DATA_PER_ITEM: usize = 128;
@@ -25,80 +52,363 @@ pub struct ClipboardData {
     pub data: Vec<u8>,
 }
 
+/// A flavor payload split out of an event blob by `share_large_flavors`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedFlavor {
+    pub digest: [u8; SHARED_DIGEST_BYTES],
+    pub data: Vec<u8>,
+}
+
+/// Encodes `event` as `CSB3`, compressing the text flavors that shrink.
 pub fn encode_event_blob(event: &ListenerEvent) -> Result<Vec<u8>, String> {
     validate_event_shape(event)?;
 
+    let deflated = event
+        .items
+        .iter()
+        .flat_map(|item| &item.data_list)
+        .map(deflate_data)
+        .collect::<Vec<_>>();
+    let flavors = event
+        .items
+        .iter()
+        .enumerate()
+        .flat_map(|(item, listener_item)| {
+            listener_item.data_list.iter().map(move |data| (item, data))
+        })
+        .zip(&deflated)
+        .map(|((item, data), deflated)| EventBlobFlavor {
+            item,
+            data_type: &data.r#type,
+            flags: if deflated.is_some() {
+                DATA_COMPRESSED
+            } else {
+                0
+            },
+            decoded_length: data.data.len(),
+            stored: deflated.as_deref().unwrap_or(&data.data),
+        })
+        .collect::<Vec<_>>();
+
+    write_indexed_blob(event.items.len(), &flavors)
+}
+
+/// Encodes `event` as `CSB1`, every flavor stored as-is, for data read
+/// outside this version such as backup archives.
+pub fn encode_plain_event_blob(event: &ListenerEvent) -> Result<Vec<u8>, String> {
+    validate_event_shape(event)?;
+
     let mut output = Vec::with_capacity(encoded_event_size(event)?);
     output.extend_from_slice(EVENT_BLOB_MAGIC);
     write_u32(&mut output, checked_len(event.items.len(), "items")?);
-
     for item in &event.items {
         write_u32(
             &mut output,
             checked_len(item.data_list.len(), "item data_list")?,
         );
-
         for data in &item.data_list {
-            let data_type = data.r#type.as_bytes();
-            write_u32(&mut output, checked_len(data_type.len(), "data type")?);
-            output.extend_from_slice(data_type);
+            write_u32(&mut output, checked_len(data.r#type.len(), "data type")?);
+            output.extend_from_slice(data.r#type.as_bytes());
             write_u64(&mut output, checked_len_u64(data.data.len(), "data")?);
             output.extend_from_slice(&data.data);
         }
     }
+    Ok(output)
+}
 
-    if output.len() > MAX_EVENT_BLOB_BYTES {
-        return Err("event blob exceeds the configured byte limit".to_string());
+/// Moves every uncompressed binary flavor of at least
+/// `MIN_SHARED_DATA_BYTES` out of `blob`, leaving its SHA-256 digest in its
+/// place. Returns the `CSB3` blob that references the payloads, and the
+/// payloads in table order. A blob with nothing to share is returned as-is.
+pub fn share_large_flavors(blob: Vec<u8>) -> Result<(Vec<u8>, Vec<SharedFlavor>), String> {
+    let index = EventBlobIndex::parse(&blob)?;
+    let shared = index
+        .flavors
+        .iter()
+        .filter(|flavor| flavor.is_shareable())
+        .map(|flavor| SharedFlavor {
+            digest: Sha256::digest(flavor.stored).into(),
+            data: flavor.stored.to_vec(),
+        })
+        .collect::<Vec<_>>();
+    if shared.is_empty() {
+        return Ok((blob, shared));
     }
 
-    Ok(output)
+    let mut digests = shared.iter().map(|shared| &shared.digest[..]);
+    let flavors = index
+        .flavors
+        .iter()
+        .map(|flavor| {
+            if !flavor.is_shareable() {
+                return *flavor;
+            }
+            EventBlobFlavor {
+                flags: DATA_SHARED,
+                stored: digests.next().unwrap_or_default(),
+                ..*flavor
+            }
+        })
+        .collect::<Vec<_>>();
+    let referencing = write_indexed_blob(index.item_count, &flavors)?;
+    Ok((referencing, shared))
 }
 
-pub fn decode_event_blob(blob: &[u8]) -> Result<ListenerEvent, String> {
-    if blob.len() > MAX_EVENT_BLOB_BYTES {
-        return Err("event blob exceeds the configured byte limit".to_string());
+/// Replaces every shared flavor in `blob` with the payload `load` returns for
+/// its digest, after checking the payload's digest and length. A blob
+/// without shared flavors is returned as-is.
+pub fn inline_shared_flavors(
+    blob: Vec<u8>,
+    mut load: impl FnMut(&[u8; SHARED_DIGEST_BYTES]) -> Result<Vec<u8>, String>,
+) -> Result<Vec<u8>, String> {
+    let index = EventBlobIndex::parse(&blob)?;
+    if !index.flavors.iter().any(EventBlobFlavor::is_shared) {
+        return Ok(blob);
     }
 
-    let mut reader = BlobReader::new(blob);
-    reader.expect_magic(EVENT_BLOB_MAGIC)?;
-
-    let item_count = reader.read_u32()? as usize;
-    if item_count > MAX_EVENT_ITEMS {
-        return Err("event blob contains too many items".to_string());
+    let mut loaded = Vec::new();
+    for flavor in index.flavors.iter().filter(|flavor| flavor.is_shared()) {
+        let digest = flavor.shared_digest()?;
+        let data = load(&digest)?;
+        if data.len() != flavor.decoded_length || Sha256::digest(&data)[..] != digest[..] {
+            return Err("shared flavor does not match its digest".to_string());
+        }
+        loaded.push(data);
     }
-    let mut items = Vec::with_capacity(item_count);
+    let mut loaded = loaded.iter();
+    let flavors = index
+        .flavors
+        .iter()
+        .map(|flavor| {
+            if !flavor.is_shared() {
+                return *flavor;
+            }
+            EventBlobFlavor {
+                flags: 0,
+                stored: loaded.next().map_or(&[][..], Vec::as_slice),
+                ..*flavor
+            }
+        })
+        .collect::<Vec<_>>();
+    write_indexed_blob(index.item_count, &flavors)
+}
+
+pub fn decode_event_blob(blob: &[u8]) -> Result<ListenerEvent, String> {
+    EventBlobIndex::parse(blob)?.decode_event()
+}
 
-    for _ in 0..item_count {
-        let data_count = reader.read_u32()? as usize;
-        if data_count > MAX_EVENT_DATA_PER_ITEM {
-            return Err("event blob item contains too many data flavors".to_string());
+/// Decodes the first `data_type` flavor in item order, leaving the event's
+/// other flavors uncopied and compressed.
+pub fn decode_flavor(blob: &[u8], data_type: &str) -> Result<Option<Vec<u8>>, String> {
+    EventBlobIndex::parse(blob)?.decode_flavor(data_type)
+}
+
+/// The flavor table of an event blob in any of its encodings.
+///
+/// Parsing checks the blob's whole layout and every size limit, but copies
+/// and inflates no payload, so callers decode only the flavors they read.
+#[derive(Clone, Debug)]
+pub struct EventBlobIndex<'a> {
+    item_count: usize,
+    flavors: Vec<EventBlobFlavor<'a>>,
+}
+
+/// One flavor listed in an `EventBlobIndex`.
+#[derive(Clone, Copy, Debug)]
+pub struct EventBlobFlavor<'a> {
+    item: usize,
+    data_type: &'a str,
+    flags: u8,
+    decoded_length: usize,
+    stored: &'a [u8],
+}
+
+impl<'a> EventBlobIndex<'a> {
+    pub fn parse(blob: &'a [u8]) -> Result<Self, String> {
+        if blob.len() > MAX_EVENT_BLOB_BYTES {
+            return Err("event blob exceeds the configured byte limit".to_string());
         }
-        let mut data_list = Vec::with_capacity(data_count);
 
-        for _ in 0..data_count {
-            let data_type_length = reader.read_u32()? as usize;
-            if data_type_length > MAX_EVENT_TYPE_BYTES {
-                return Err("event blob data type exceeds the configured byte limit".to_string());
+        let mut reader = BlobReader::new(blob);
+        let (flagged, indexed) = match reader.read_bytes(EVENT_BLOB_MAGIC.len())? {
+            magic if magic == EVENT_BLOB_MAGIC => (false, false),
+            magic if magic == COMPRESSED_EVENT_BLOB_MAGIC => (true, false),
+            magic if magic == INDEXED_EVENT_BLOB_MAGIC => (true, true),
+            _ => return Err("invalid event blob header".to_string()),
+        };
+        // What the event would take as `CSB1`. Decompressed flavors are charged
+        // against the same limit before they are allocated.
+        let mut decoded_size = EVENT_BLOB_MAGIC.len() + 4;
+
+        let item_count = reader.read_u32()? as usize;
+        if item_count > MAX_EVENT_ITEMS {
+            return Err("event blob contains too many items".to_string());
+        }
+        let mut flavors = Vec::new();
+        // `CSB3` stored lengths, sliced out of the payloads after the table.
+        let mut indexed_lengths = Vec::new();
+        let mut payloads_length = 0_usize;
+
+        for item in 0..item_count {
+            decoded_size += 4;
+            let data_count = reader.read_u32()? as usize;
+            if data_count > MAX_EVENT_DATA_PER_ITEM {
+                return Err("event blob item contains too many data flavors".to_string());
+            }
+
+            for _ in 0..data_count {
+                let data_type_length = reader.read_u32()? as usize;
+                if data_type_length > MAX_EVENT_TYPE_BYTES {
+                    return Err(
+                        "event blob data type exceeds the configured byte limit".to_string()
+                    );
+                }
+                let data_type = std::str::from_utf8(reader.read_bytes(data_type_length)?)
+                    .map_err(|error| format!("invalid data type bytes: {}", error))?;
+                let flags = if flagged { reader.read_u8()? } else { 0 };
+                let supported = match flags {
+                    0 | DATA_COMPRESSED => true,
+                    DATA_SHARED => indexed,
+                    _ => false,
+                };
+                if !supported {
+                    return Err("event blob data flags are not supported".to_string());
+                }
+                let offset = if indexed {
+                    Some(reader.read_data_length()?)
+                } else {
+                    None
+                };
+                let stored_length = reader.read_data_length()?;
+                if flags == DATA_SHARED && stored_length != SHARED_DIGEST_BYTES {
+                    return Err("event blob shared flavor digest is malformed".to_string());
+                }
+                let decoded_length = if flags == 0 {
+                    stored_length
+                } else {
+                    reader.read_data_length()?
+                };
+                charge_decoded_size(&mut decoded_size, data_type_length, decoded_length)?;
+
+                let stored = match offset {
+                    Some(offset) => {
+                        if offset != payloads_length {
+                            return Err(
+                                "event blob offset table does not match its payloads".to_string()
+                            );
+                        }
+                        payloads_length = payloads_length
+                            .checked_add(stored_length)
+                            .ok_or_else(|| "event blob offset overflow".to_string())?;
+                        indexed_lengths.push(stored_length);
+                        &[][..]
+                    }
+                    None => reader.read_bytes(stored_length)?,
+                };
+                flavors.push(EventBlobFlavor {
+                    item,
+                    data_type,
+                    flags,
+                    decoded_length,
+                    stored,
+                });
             }
-            let data_type = String::from_utf8(reader.read_bytes(data_type_length)?.to_vec())
-                .map_err(|error| format!("invalid data type bytes: {}", error))?;
-            let data_length = usize::try_from(reader.read_u64()?)
-                .map_err(|_| "data length exceeds usize".to_string())?;
-            if data_length > MAX_EVENT_BLOB_BYTES {
-                return Err("event blob data flavor exceeds the configured byte limit".to_string());
+        }
+
+        if indexed {
+            let mut payloads = reader.read_bytes(payloads_length)?;
+            for (flavor, length) in flavors.iter_mut().zip(indexed_lengths) {
+                let (stored, rest) = payloads.split_at(length);
+                flavor.stored = stored;
+                payloads = rest;
             }
-            let data = reader.read_bytes(data_length)?.to_vec();
-            data_list.push(ListenerData {
-                r#type: data_type,
-                data,
+        }
+        reader.expect_finished()?;
+
+        Ok(Self {
+            item_count,
+            flavors,
+        })
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.item_count
+    }
+
+    pub fn flavors(&self) -> &[EventBlobFlavor<'a>] {
+        &self.flavors
+    }
+
+    pub fn decode_flavor(&self, data_type: &str) -> Result<Option<Vec<u8>>, String> {
+        self.flavors
+            .iter()
+            .find(|flavor| flavor.data_type == data_type)
+            .map(|flavor| flavor.decode().map(Cow::into_owned))
+            .transpose()
+    }
+
+    /// Decodes every item, keeping only the flavors whose type `keep`
+    /// accepts.
+    pub fn decode_flavors(&self, keep: impl Fn(&str) -> bool) -> Result<ListenerEvent, String> {
+        let mut items = (0..self.item_count)
+            .map(|_| ListenerItem {
+                data_list: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        for flavor in self.flavors.iter().filter(|flavor| keep(flavor.data_type)) {
+            items[flavor.item].data_list.push(ListenerData {
+                r#type: flavor.data_type.to_string(),
+                data: flavor.decode()?.into_owned(),
             });
         }
+        Ok(ListenerEvent { items })
+    }
 
-        items.push(ListenerItem { data_list });
+    pub fn decode_event(&self) -> Result<ListenerEvent, String> {
+        self.decode_flavors(|_| true)
     }
+}
 
-    reader.expect_finished()?;
-    Ok(ListenerEvent { items })
+impl<'a> EventBlobFlavor<'a> {
+    pub fn item(&self) -> usize {
+        self.item
+    }
+
+    pub fn data_type(&self) -> &'a str {
+        self.data_type
+    }
+
+    pub fn decoded_len(&self) -> usize {
+        self.decoded_length
+    }
+
+    /// Whether the payload lives outside the blob until
+    /// `inline_shared_flavors` puts it back.
+    pub fn is_shared(&self) -> bool {
+        self.flags == DATA_SHARED
+    }
+
+    /// Borrows a stored flavor from the blob and inflates a compressed one.
+    /// A shared flavor cannot be decoded from the blob alone.
+    pub fn decode(&self) -> Result<Cow<'a, [u8]>, String> {
+        match self.flags {
+            DATA_COMPRESSED => inflate_data(self.stored, self.decoded_length).map(Cow::Owned),
+            DATA_SHARED => Err("event blob data flavor is stored outside the blob".to_string()),
+            _ => Ok(Cow::Borrowed(self.stored)),
+        }
+    }
+
+    fn is_shareable(&self) -> bool {
+        self.flags == 0
+            && self.stored.len() >= MIN_SHARED_DATA_BYTES
+            && !COMPRESSIBLE_TYPES.contains(&self.data_type)
+    }
+
+    fn shared_digest(&self) -> Result<[u8; SHARED_DIGEST_BYTES], String> {
+        self.stored
+            .try_into()
+            .map_err(|_| "event blob shared flavor digest is malformed".to_string())
+    }
 }
 
 pub fn event_from_legacy_json(event_data: &str) -> serde_json::Result<ListenerEvent> {
@@ -162,6 +472,106 @@ fn encoded_event_size(event: &ListenerEvent) -> Result<usize, String> {
     Ok(size)
 }
 
+/// Writes `flavors`, grouped by item in order, as a `CSB3` blob.
+fn write_indexed_blob(
+    item_count: usize,
+    flavors: &[EventBlobFlavor<'_>],
+) -> Result<Vec<u8>, String> {
+    let payloads_length = flavors
+        .iter()
+        .try_fold(0_usize, |length, flavor| {
+            length.checked_add(flavor.stored.len())
+        })
+        .ok_or_else(|| "event size overflow".to_string())?;
+    if payloads_length > MAX_EVENT_BLOB_BYTES {
+        return Err("event blob exceeds the configured byte limit".to_string());
+    }
+
+    let mut output = Vec::new();
+    output.extend_from_slice(INDEXED_EVENT_BLOB_MAGIC);
+    write_u32(&mut output, checked_len(item_count, "items")?);
+
+    let mut offset = 0;
+    let mut remaining = flavors;
+    for item in 0..item_count {
+        let data_count = remaining
+            .iter()
+            .take_while(|flavor| flavor.item == item)
+            .count();
+        let (item_flavors, rest) = remaining.split_at(data_count);
+        remaining = rest;
+        write_u32(&mut output, checked_len(data_count, "item data_list")?);
+
+        for flavor in item_flavors {
+            let data_type = flavor.data_type.as_bytes();
+            write_u32(&mut output, checked_len(data_type.len(), "data type")?);
+            output.extend_from_slice(data_type);
+            output.push(flavor.flags);
+            write_u64(&mut output, checked_len_u64(offset, "data offset")?);
+            write_u64(&mut output, checked_len_u64(flavor.stored.len(), "data")?);
+            if flavor.flags != 0 {
+                write_u64(&mut output, checked_len_u64(flavor.decoded_length, "data")?);
+            }
+            offset += flavor.stored.len();
+        }
+    }
+    if !remaining.is_empty() {
+        return Err("event blob flavors are not grouped by item".to_string());
+    }
+
+    output.reserve_exact(payloads_length);
+    for flavor in flavors {
+        output.extend_from_slice(flavor.stored);
+    }
+    if output.len() > MAX_EVENT_BLOB_BYTES {
+        return Err("event blob exceeds the configured byte limit".to_string());
+    }
+
+    Ok(output)
+}
+
+/// Stores a text flavor compressed when that saves more than the decoded
+/// length field costs.
+fn deflate_data(data: &ListenerData) -> Option<Vec<u8>> {
+    if data.data.len() < MIN_COMPRESSED_DATA_BYTES
+        || !COMPRESSIBLE_TYPES.contains(&data.r#type.as_str())
+    {
+        return None;
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&data.data).ok()?;
+    let deflated = encoder.finish().ok()?;
+    (deflated.len() + 8 < data.data.len()).then_some(deflated)
+}
+
+/// Inflates at most `decoded_length + 1` bytes, so a stream that decodes to
+/// more than it declared fails without growing past the declared length.
+fn inflate_data(deflated: &[u8], decoded_length: usize) -> Result<Vec<u8>, String> {
+    let mut decoder = DeflateDecoder::new(deflated);
+    let mut data = Vec::with_capacity(decoded_length);
+    (&mut decoder)
+        .take(decoded_length as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|_| "event blob data flavor could not be decompressed".to_string())?;
+    if data.len() != decoded_length || decoder.total_in() != deflated.len() as u64 {
+        return Err("event blob data flavor does not match its decoded length".to_string());
+    }
+    Ok(data)
+}
+
+fn charge_decoded_size(
+    decoded_size: &mut usize,
+    data_type_length: usize,
+    data_length: usize,
+) -> Result<(), String> {
+    *decoded_size = decoded_size
+        .checked_add(4 + data_type_length + 8)
+        .and_then(|size| size.checked_add(data_length))
+        .filter(|size| *size <= MAX_EVENT_BLOB_BYTES)
+        .ok_or_else(|| "decoded event blob exceeds the configured byte limit".to_string())?;
+    Ok(())
+}
+
 fn checked_len(len: usize, label: &str) -> Result<u32, String> {
     u32::try_from(len).map_err(|_| format!("{} length exceeds u32", label))
 }
@@ -188,13 +598,8 @@ impl<'a> BlobReader<'a> {
         Self { blob, offset: 0 }
     }
 
-    fn expect_magic(&mut self, expected: &[u8]) -> Result<(), String> {
-        let actual = self.read_bytes(expected.len())?;
-        if actual == expected {
-            Ok(())
-        } else {
-            Err("invalid event blob header".to_string())
-        }
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
     }
 
     fn read_u32(&mut self) -> Result<u32, String> {
@@ -209,6 +614,15 @@ impl<'a> BlobReader<'a> {
         ]))
     }
 
+    fn read_data_length(&mut self) -> Result<usize, String> {
+        let data_length = usize::try_from(self.read_u64()?)
+            .map_err(|_| "data length exceeds usize".to_string())?;
+        if data_length > MAX_EVENT_BLOB_BYTES {
+            return Err("event blob data flavor exceeds the configured byte limit".to_string());
+        }
+        Ok(data_length)
+    }
+
     fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
         let end = self
             .offset
@@ -368,6 +782,289 @@ mod tests {
         assert!(error.contains("data flavor exceeds"));
     }
 
+    fn html_event(html: Vec<u8>) -> ListenerEvent {
+        ListenerEvent {
+            items: vec![ListenerItem {
+                data_list: vec![
+                    ListenerData {
+                        r#type: "public.html".to_string(),
+                        data: html,
+                    },
+                    ListenerData {
+                        r#type: "public.png".to_string(),
+                        data: vec![0x5a; 8 * 1024],
+                    },
+                ],
+            }],
+        }
+    }
+
+    /// A `CSB2` blob holding one compressed flavor that declares
+    /// `decoded_length` bytes.
+    fn compressed_blob(deflated: &[u8], decoded_length: u64) -> Vec<u8> {
+        let mut blob = COMPRESSED_EVENT_BLOB_MAGIC.to_vec();
+        write_u32(&mut blob, 1);
+        write_u32(&mut blob, 1);
+        write_u32(&mut blob, 11);
+        blob.extend_from_slice(b"public.html");
+        blob.push(DATA_COMPRESSED);
+        write_u64(&mut blob, deflated.len() as u64);
+        write_u64(&mut blob, decoded_length);
+        blob.extend_from_slice(deflated);
+        blob
+    }
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).expect("data should compress");
+        encoder.finish().expect("compression should finish")
+    }
+
+    #[test]
+    fn large_text_flavors_are_compressed_behind_the_offset_table() {
+        let html = b"<p>release notes</p>".repeat(1_024);
+        let event = html_event(html.clone());
+
+        let blob = encode_event_blob(&event).expect("event should encode");
+        assert_eq!(&blob[..4], INDEXED_EVENT_BLOB_MAGIC);
+        assert!(blob.len() < encoded_event_size(&event).expect("size should compute") / 2);
+        let decoded = decode_event_blob(&blob).expect("event should decode");
+        assert_eq!(decoded.items[0].data_list[0].data, html);
+        assert_eq!(decoded.items[0].data_list[1].r#type, "public.png");
+        assert_eq!(decoded.items[0].data_list[1].data, vec![0x5a; 8 * 1024]);
+        assert_eq!(
+            encode_event_blob(&decoded).expect("event should re-encode"),
+            blob
+        );
+
+        let small = html_event(b"<p>short</p>".to_vec());
+        let blob = encode_event_blob(&small).expect("event should encode");
+        assert_eq!(&blob[..4], INDEXED_EVENT_BLOB_MAGIC);
+        assert_eq!(
+            blob.len(),
+            encoded_event_size(&small).expect("size should compute") + 2 * (1 + 8)
+        );
+    }
+
+    #[test]
+    fn single_flavors_decode_without_inflating_the_others() {
+        let mut event = html_event(b"<p>release notes</p>".repeat(1_024));
+        event.items.push(ListenerItem {
+            data_list: vec![ListenerData {
+                r#type: "public.utf8-plain-text".to_string(),
+                data: b"release notes".to_vec(),
+            }],
+        });
+        let mut blob = encode_event_blob(&event).expect("event should encode");
+
+        let index = EventBlobIndex::parse(&blob).expect("table should parse");
+        assert_eq!(index.item_count(), 2);
+        let flavors = index
+            .flavors()
+            .iter()
+            .map(|flavor| (flavor.item(), flavor.data_type(), flavor.decoded_len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flavors,
+            vec![
+                (0, "public.html", 20 * 1_024),
+                (0, "public.png", 8 * 1024),
+                (1, "public.utf8-plain-text", 13),
+            ]
+        );
+        assert_eq!(
+            decode_flavor(&blob, "public.rtf").expect("blob should decode"),
+            None
+        );
+
+        // Declares the compressed HTML one byte longer than it inflates to.
+        let decoded_length_at = 4 + 4 + 4 + 4 + 11 + 1 + 8 + 8;
+        blob[decoded_length_at] = 1;
+        assert!(decode_event_blob(&blob).is_err());
+        assert_eq!(
+            decode_flavor(&blob, "public.utf8-plain-text").expect("text should decode"),
+            Some(b"release notes".to_vec())
+        );
+        let projected = EventBlobIndex::parse(&blob)
+            .and_then(|index| index.decode_flavors(|data_type| data_type != "public.html"))
+            .expect("the other flavors should decode");
+        assert_eq!(projected.items.len(), 2);
+        assert_eq!(projected.items[0].data_list.len(), 1);
+        assert_eq!(projected.items[0].data_list[0].r#type, "public.png");
+        assert_eq!(projected.items[1].data_list[0].data, b"release notes");
+    }
+
+    #[test]
+    fn single_flavors_decode_from_older_blob_encodings() {
+        let mut plain = EVENT_BLOB_MAGIC.to_vec();
+        write_u32(&mut plain, 1);
+        write_u32(&mut plain, 2);
+        for (data_type, data) in [("public.png", &[0, 1][..]), ("public.rtf", b"{\\rtf1}")] {
+            write_u32(&mut plain, data_type.len() as u32);
+            plain.extend_from_slice(data_type.as_bytes());
+            write_u64(&mut plain, data.len() as u64);
+            plain.extend_from_slice(data);
+        }
+        assert_eq!(
+            decode_flavor(&plain, "public.rtf").expect("CSB1 should decode"),
+            Some(b"{\\rtf1}".to_vec())
+        );
+        assert_eq!(
+            decode_event_blob(&plain)
+                .and_then(|event| encode_plain_event_blob(&event))
+                .expect("CSB1 should encode again"),
+            plain
+        );
+
+        let html = vec![b'x'; 64 * 1024];
+        let compressed = compressed_blob(&deflate(&html), html.len() as u64);
+        assert_eq!(
+            decode_flavor(&compressed, "public.html").expect("CSB2 should decode"),
+            Some(html)
+        );
+    }
+
+    #[test]
+    fn offset_tables_must_match_their_payloads() {
+        let event = html_event(b"<p>short</p>".to_vec());
+        let blob = encode_event_blob(&event).expect("event should encode");
+        // The PNG entry follows the 12-byte HTML payload.
+        let png_offset_at = 4 + 4 + 4 + (4 + 11 + 1 + 8 + 8) + 4 + 10 + 1;
+        assert_eq!(blob[png_offset_at], 12);
+
+        for offset in [0, 11, 13] {
+            let mut moved = blob.clone();
+            moved[png_offset_at] = offset;
+            let error = decode_event_blob(&moved).expect_err("a moved offset should fail");
+            assert!(error.contains("offset table"));
+        }
+
+        let mut truncated = blob.clone();
+        truncated.pop();
+        assert!(decode_flavor(&truncated, "public.html").is_err());
+        let mut trailing = blob;
+        trailing.push(0);
+        assert!(decode_flavor(&trailing, "public.html").is_err());
+    }
+
+    #[test]
+    fn compressed_flavors_are_bounded_by_their_declared_and_total_decoded_size() {
+        let zeros = deflate(&vec![0; 1024 * 1024]);
+
+        let error = decode_event_blob(&compressed_blob(&zeros, 1024))
+            .expect_err("a stream larger than declared should fail");
+        assert!(error.contains("decoded length"));
+        let error = decode_event_blob(&compressed_blob(&zeros, 2 * 1024 * 1024))
+            .expect_err("a stream smaller than declared should fail");
+        assert!(error.contains("decoded length"));
+        let error = decode_event_blob(&compressed_blob(&zeros, (MAX_EVENT_BLOB_BYTES + 1) as u64))
+            .expect_err("an oversized declared length should fail before inflating");
+        assert!(error.contains("data flavor exceeds"));
+        let error = decode_event_blob(&compressed_blob(&zeros, MAX_EVENT_BLOB_BYTES as u64))
+            .expect_err("the decoded event should fit the blob limit");
+        assert!(error.contains("decoded event blob exceeds"));
+
+        let mut trailing = zeros.clone();
+        trailing.push(0);
+        assert!(decode_event_blob(&compressed_blob(&trailing, 1024 * 1024)).is_err());
+        let mut flagged = compressed_blob(&zeros, 1024 * 1024);
+        flagged[4 + 4 + 4 + 4 + 11] = 0x02;
+        let error = decode_event_blob(&flagged).expect_err("unknown flags should fail");
+        assert!(error.contains("flags"));
+
+        let decoded = decode_event_blob(&compressed_blob(&zeros, 1024 * 1024))
+            .expect("a consistent stream should decode");
+        assert_eq!(decoded.items[0].data_list[0].data.len(), 1024 * 1024);
+    }
+
+    #[test]
+    fn large_binary_flavors_are_shared_by_digest_and_inlined_back() {
+        let png = (0..32 * 1024)
+            .map(|byte| (byte % 251) as u8)
+            .collect::<Vec<_>>();
+        let event = ListenerEvent {
+            items: vec![
+                ListenerItem {
+                    data_list: vec![
+                        ListenerData {
+                            r#type: "public.png".to_string(),
+                            data: png.clone(),
+                        },
+                        listener_data("public.utf8-plain-text"),
+                    ],
+                },
+                ListenerItem {
+                    data_list: vec![ListenerData {
+                        r#type: "public.tiff".to_string(),
+                        data: vec![7; 1024],
+                    }],
+                },
+            ],
+        };
+        let blob = encode_event_blob(&event).expect("event should encode");
+
+        let (referencing, shared) =
+            share_large_flavors(blob.clone()).expect("large flavors should be shared");
+        assert_eq!(
+            shared,
+            vec![SharedFlavor {
+                digest: Sha256::digest(&png).into(),
+                data: png.clone(),
+            }]
+        );
+        assert!(referencing.len() < blob.len() - png.len() + 64);
+        let index = EventBlobIndex::parse(&referencing).expect("references should parse");
+        assert!(index.flavors()[0].is_shared());
+        assert_eq!(index.flavors()[0].decoded_len(), png.len());
+        assert!(decode_flavor(&referencing, "public.png").is_err());
+        assert_eq!(
+            decode_flavor(&referencing, "public.tiff").expect("small flavors stay inline"),
+            Some(vec![7; 1024])
+        );
+
+        let inlined = inline_shared_flavors(referencing.clone(), |digest| {
+            assert_eq!(digest, &shared[0].digest);
+            Ok(png.clone())
+        })
+        .expect("the payload should inline");
+        assert_eq!(inlined, blob);
+        let small = encode_event_blob(&html_event(b"<p>hi</p>".to_vec()))
+            .expect("small events should encode");
+        let (unchanged, none) =
+            share_large_flavors(small.clone()).expect("small events should pass through");
+        assert_eq!(unchanged, small);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn inlined_flavors_must_match_their_digest_and_length() {
+        let png = vec![3; 20 * 1024];
+        let blob = encode_event_blob(&ListenerEvent {
+            items: vec![ListenerItem {
+                data_list: vec![ListenerData {
+                    r#type: "public.png".to_string(),
+                    data: png.clone(),
+                }],
+            }],
+        })
+        .expect("event should encode");
+        let (referencing, _) = share_large_flavors(blob).expect("the image should be shared");
+
+        let mut tampered = png.clone();
+        tampered[0] = 4;
+        assert!(inline_shared_flavors(referencing.clone(), |_| Ok(tampered.clone())).is_err());
+        assert!(inline_shared_flavors(referencing.clone(), |_| Ok(png[1..].to_vec())).is_err());
+        assert!(
+            inline_shared_flavors(referencing.clone(), |_| Err("missing".to_string())).is_err()
+        );
+
+        // A shared flag is only meaningful behind a `CSB3` offset table.
+        let mut flagged = compressed_blob(&deflate(&png), png.len() as u64);
+        let flags_at = 4 + 4 + 4 + 4 + "public.html".len();
+        flagged[flags_at] = DATA_SHARED;
+        assert!(decode_event_blob(&flagged).is_err());
+    }
+
     #[test]
     fn encoding_rejects_events_over_the_resource_budget() {
         let event = ListenerEvent {
@@ -0,0 +1,592 @@
+//! Passphrase-encrypted, portable history backups.
+//!
+//! An archive carries every setting and every history row, each row's
+//! restorable event as a plaintext `CSB1` event blob. The body is sealed once with
+//! AES-256-GCM under a key derived from the passphrase with PBKDF2-HMAC-SHA256.
+//! The header (format version, KDF cost, salt, and nonce) is the associated
+//! data, so a wrong passphrase, an edited header, and a damaged body are all
+//! rejected the same way, before any row is parsed. An archive from a newer
+//! format version is refused by its header alone.
+//!
+//! This module only seals, writes, reads, and parses archives. The database
+//! validates every row and merges it into history.
+
+use crate::event::MAX_EVENT_BLOB_BYTES;
+use crate::private_fs::{
+    create_private_temp_file, prepare_private_output_path, read_private_file, resolve_private_path,
+    PrivateFsError, PrivateFsErrorKind,
+};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use sha2::Sha256;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+
+const BACKUP_MAGIC: &[u8; 4] = b"CSBK";
+const BACKUP_FORMAT_VERSION: u8 = 1;
+const SALT_BYTES: usize = 16;
+const NONCE_BYTES: usize = 12;
+const KEY_BYTES: usize = 32;
+const HEADER_BYTES: usize = BACKUP_MAGIC.len() + 1 + 4 + SALT_BYTES + NONCE_BYTES;
+const KDF_ITERATIONS: u32 = 600_000;
+/// An archive asking for more rounds than this is refused rather than left to
+/// stall the import.
+const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+/// The largest history byte limit plus the pinned budget, with room to spare.
+const MAX_BACKUP_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+const MAX_BACKUP_SETTINGS: usize = 4096;
+const MAX_SETTING_BYTES: usize = 1024;
+const MAX_CONTENT_HASH_BYTES: usize = 64;
+
+pub const MIN_BACKUP_PASSPHRASE_CHARS: usize = 8;
+pub const MAX_BACKUP_PASSPHRASE_BYTES: usize = 1024;
+
+/// One history row as carried by an archive. Source and remote-clipboard
+/// metadata are not stored; import derives them from the event again.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryBackupRow {
+    pub content_hash: String,
+    /// The plaintext event blob. Export writes `CSB1`, with shared flavors
+    /// inlined, so any version can read it; import accepts any `CSB`
+    /// encoding.
+    pub event_data: Vec<u8>,
+    pub timestamp: i64,
+    pub pinned: bool,
+    pub first_seen_at: i64,
+    pub last_seen_at: i64,
+    pub copy_count: i64,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HistoryBackup {
+    /// Raw `settings` rows ordered by key.
+    pub settings: Vec<(String, String)>,
+    /// Rows oldest first.
+    pub rows: Vec<HistoryBackupRow>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HistoryBackupErrorKind {
+    /// The passphrase is shorter than `MIN_BACKUP_PASSPHRASE_CHARS` or longer
+    /// than `MAX_BACKUP_PASSPHRASE_BYTES`.
+    Passphrase,
+    Path(PrivateFsErrorKind),
+    Write,
+    /// No randomness was available for the salt or nonce.
+    Randomness,
+    /// The archive is malformed or does not open with the passphrase.
+    Rejected,
+    /// The archive was written in a newer format version than this build
+    /// reads.
+    UnsupportedVersion,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryBackupError {
+    kind: HistoryBackupErrorKind,
+    stage: &'static str,
+}
+
+impl HistoryBackupError {
+    pub fn kind(&self) -> HistoryBackupErrorKind {
+        self.kind
+    }
+
+    fn new(kind: HistoryBackupErrorKind, stage: &'static str) -> Self {
+        Self { kind, stage }
+    }
+
+    fn path(error: PrivateFsError, stage: &'static str) -> Self {
+        Self::new(HistoryBackupErrorKind::Path(error.kind()), stage)
+    }
+
+    fn rejected(stage: &'static str) -> Self {
+        Self::new(HistoryBackupErrorKind::Rejected, stage)
+    }
+}
+
+impl fmt::Display for HistoryBackupError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            HistoryBackupErrorKind::Passphrase => "the passphrase length is not accepted",
+            HistoryBackupErrorKind::Path(_) => "private path validation failed",
+            HistoryBackupErrorKind::Write => "the archive could not be written",
+            HistoryBackupErrorKind::Randomness => "no randomness was available",
+            HistoryBackupErrorKind::Rejected => "the archive is invalid or the passphrase is wrong",
+            HistoryBackupErrorKind::UnsupportedVersion => {
+                "the archive was written by a newer format version"
+            }
+        };
+        write!(
+            formatter,
+            "history backup failed during {}: {}",
+            self.stage, reason
+        )
+    }
+}
+
+impl std::error::Error for HistoryBackupError {}
+
+/// Seals `backup` and atomically installs it at `path` as a `0600` file.
+pub fn write_history_backup(
+    path: &Path,
+    passphrase: &str,
+    backup: &HistoryBackup,
+) -> Result<(), HistoryBackupError> {
+    write_history_backup_with(path, passphrase, backup, KDF_ITERATIONS)
+}
+
+/// Reads and opens the archive at `path`. Nothing is returned unless the
+/// whole archive authenticates and parses.
+pub fn read_history_backup(
+    path: &Path,
+    passphrase: &str,
+) -> Result<HistoryBackup, HistoryBackupError> {
+    validate_passphrase(passphrase)?;
+    let path = resolve_private_path(path)
+        .map_err(|error| HistoryBackupError::path(error, "resolve archive path"))?;
+    let archive = read_private_file(&path, MAX_BACKUP_BYTES)
+        .map_err(|error| HistoryBackupError::path(error, "read archive"))?
+        .ok_or_else(|| {
+            HistoryBackupError::new(
+                HistoryBackupErrorKind::Path(PrivateFsErrorKind::InvalidPath),
+                "read archive",
+            )
+        })?;
+    open_backup(&archive, passphrase)
+}
+
+fn write_history_backup_with(
+    path: &Path,
+    passphrase: &str,
+    backup: &HistoryBackup,
+    iterations: u32,
+) -> Result<(), HistoryBackupError> {
+    validate_passphrase(passphrase)?;
+    let output_path = prepare_private_output_path(path)
+        .map_err(|error| HistoryBackupError::path(error, "write path validation"))?;
+    let archive = seal_backup(backup, passphrase, iterations)?;
+
+    let mut temp = create_private_temp_file(&output_path, 0)
+        .map_err(|error| HistoryBackupError::path(error, "create temporary file"))?;
+    temp.file_mut().write_all(&archive).map_err(|_| {
+        HistoryBackupError::new(HistoryBackupErrorKind::Write, "write temporary file")
+    })?;
+    temp.sync_all()
+        .map_err(|error| HistoryBackupError::path(error, "sync temporary file"))?;
+    temp.commit(&output_path)
+        .map_err(|error| HistoryBackupError::path(error, "atomic replace"))
+}
+
+fn validate_passphrase(passphrase: &str) -> Result<(), HistoryBackupError> {
+    if passphrase.chars().count() < MIN_BACKUP_PASSPHRASE_CHARS
+        || passphrase.len() > MAX_BACKUP_PASSPHRASE_BYTES
+    {
+        return Err(HistoryBackupError::new(
+            HistoryBackupErrorKind::Passphrase,
+            "validate passphrase",
+        ));
+    }
+    Ok(())
+}
+
+fn seal_backup(
+    backup: &HistoryBackup,
+    passphrase: &str,
+    iterations: u32,
+) -> Result<Vec<u8>, HistoryBackupError> {
+    let mut salt = [0; SALT_BYTES];
+    let mut nonce = [0; NONCE_BYTES];
+    getrandom::getrandom(&mut salt)
+        .and_then(|()| getrandom::getrandom(&mut nonce))
+        .map_err(|_| {
+            HistoryBackupError::new(
+                HistoryBackupErrorKind::Randomness,
+                "generate salt and nonce",
+            )
+        })?;
+
+    let mut archive = Vec::with_capacity(HEADER_BYTES);
+    archive.extend_from_slice(BACKUP_MAGIC);
+    archive.push(BACKUP_FORMAT_VERSION);
+    archive.extend_from_slice(&iterations.to_le_bytes());
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&nonce);
+
+    let ciphertext = archive_cipher(passphrase, &salt, iterations)?
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &encode_body(backup)?,
+                aad: &archive,
+            },
+        )
+        .map_err(|_| HistoryBackupError::rejected("seal archive"))?;
+    archive.extend_from_slice(&ciphertext);
+    Ok(archive)
+}
+
+fn open_backup(archive: &[u8], passphrase: &str) -> Result<HistoryBackup, HistoryBackupError> {
+    if archive.len() < HEADER_BYTES {
+        return Err(HistoryBackupError::rejected("read header"));
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_BYTES);
+    let mut reader = ArchiveReader::new(header);
+    if reader.read_bytes(BACKUP_MAGIC.len())? != BACKUP_MAGIC {
+        return Err(HistoryBackupError::rejected("read header"));
+    }
+    let [version] = reader.read_array()?;
+    if version > BACKUP_FORMAT_VERSION {
+        return Err(HistoryBackupError::new(
+            HistoryBackupErrorKind::UnsupportedVersion,
+            "read header",
+        ));
+    }
+    if version < BACKUP_FORMAT_VERSION {
+        return Err(HistoryBackupError::rejected("read header"));
+    }
+    let iterations = reader.read_u32()?;
+    if !(1..=MAX_KDF_ITERATIONS).contains(&iterations) {
+        return Err(HistoryBackupError::rejected("read header"));
+    }
+    let salt = reader.read_bytes(SALT_BYTES)?;
+    let nonce = reader.read_bytes(NONCE_BYTES)?;
+
+    let body = archive_cipher(passphrase, salt, iterations)?
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| HistoryBackupError::rejected("open archive"))?;
+    decode_body(&body)
+}
+
+fn archive_cipher(
+    passphrase: &str,
+    salt: &[u8],
+    iterations: u32,
+) -> Result<Aes256Gcm, HistoryBackupError> {
+    let mut key = [0; KEY_BYTES];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new_from_slice(&key).map_err(|_| HistoryBackupError::rejected("derive key"))
+}
+
+fn encode_body(backup: &HistoryBackup) -> Result<Vec<u8>, HistoryBackupError> {
+    let too_large = || HistoryBackupError::rejected("encode archive");
+    let mut body = Vec::new();
+    write_len(&mut body, backup.settings.len()).ok_or_else(too_large)?;
+    for (key, value) in &backup.settings {
+        write_bytes(&mut body, key.as_bytes()).ok_or_else(too_large)?;
+        write_bytes(&mut body, value.as_bytes()).ok_or_else(too_large)?;
+    }
+
+    write_len(&mut body, backup.rows.len()).ok_or_else(too_large)?;
+    for row in &backup.rows {
+        write_bytes(&mut body, row.content_hash.as_bytes()).ok_or_else(too_large)?;
+        for value in [
+            row.timestamp,
+            row.first_seen_at,
+            row.last_seen_at,
+            row.copy_count,
+        ] {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.push(u8::from(row.pinned));
+        match row.expires_at {
+            Some(expires_at) => {
+                body.push(1);
+                body.extend_from_slice(&expires_at.to_le_bytes());
+            }
+            None => body.push(0),
+        }
+        let event_len = u64::try_from(row.event_data.len()).map_err(|_| too_large())?;
+        body.extend_from_slice(&event_len.to_le_bytes());
+        body.extend_from_slice(&row.event_data);
+    }
+    Ok(body)
+}
+
+fn decode_body(body: &[u8]) -> Result<HistoryBackup, HistoryBackupError> {
+    let mut reader = ArchiveReader::new(body);
+    let setting_count = reader.read_u32()? as usize;
+    if setting_count > MAX_BACKUP_SETTINGS {
+        return Err(HistoryBackupError::rejected("read settings"));
+    }
+    let mut settings = Vec::with_capacity(setting_count);
+    for _ in 0..setting_count {
+        settings.push((
+            reader.read_string(MAX_SETTING_BYTES)?,
+            reader.read_string(MAX_SETTING_BYTES)?,
+        ));
+    }
+
+    let row_count = reader.read_u32()? as usize;
+    let mut rows = Vec::new();
+    for _ in 0..row_count {
+        let content_hash = reader.read_string(MAX_CONTENT_HASH_BYTES)?;
+        let timestamp = reader.read_i64()?;
+        let first_seen_at = reader.read_i64()?;
+        let last_seen_at = reader.read_i64()?;
+        let copy_count = reader.read_i64()?;
+        let pinned = reader.read_flag()?;
+        let expires_at = if reader.read_flag()? {
+            Some(reader.read_i64()?)
+        } else {
+            None
+        };
+        let event_len = usize::try_from(reader.read_u64()?)
+            .ok()
+            .filter(|len| *len <= MAX_EVENT_BLOB_BYTES)
+            .ok_or_else(|| HistoryBackupError::rejected("read row"))?;
+        rows.push(HistoryBackupRow {
+            content_hash,
+            event_data: reader.read_bytes(event_len)?.to_vec(),
+            timestamp,
+            pinned,
+            first_seen_at,
+            last_seen_at,
+            copy_count,
+            expires_at,
+        });
+    }
+
+    if !reader.is_finished() {
+        return Err(HistoryBackupError::rejected("read trailing bytes"));
+    }
+    Ok(HistoryBackup { settings, rows })
+}
+
+fn write_len(output: &mut Vec<u8>, len: usize) -> Option<()> {
+    output.extend_from_slice(&u32::try_from(len).ok()?.to_le_bytes());
+    Some(())
+}
+
+fn write_bytes(output: &mut Vec<u8>, bytes: &[u8]) -> Option<()> {
+    write_len(output, bytes.len())?;
+    output.extend_from_slice(bytes);
+    Some(())
+}
+
+struct ArchiveReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ArchiveReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], HistoryBackupError> {
+        let end = self
+            .offset
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| HistoryBackupError::rejected("read archive"))?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], HistoryBackupError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, HistoryBackupError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, HistoryBackupError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, HistoryBackupError> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_flag(&mut self) -> Result<bool, HistoryBackupError> {
+        match self.read_array::<1>()? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(HistoryBackupError::rejected("read archive")),
+        }
+    }
+
+    fn read_string(&mut self, max_bytes: usize) -> Result<String, HistoryBackupError> {
+        let length = self.read_u32()? as usize;
+        if length > max_bytes {
+            return Err(HistoryBackupError::rejected("read archive"));
+        }
+        String::from_utf8(self.read_bytes(length)?.to_vec())
+            .map_err(|_| HistoryBackupError::rejected("read archive"))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs::Permissions;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Keeps the tests fast; the rounds are read back from the header.
+    const TEST_ITERATIONS: u32 = 1_000;
+    const PASSPHRASE: &str = "correct horse battery";
+
+    static NEXT_TEST_DIR: AtomicU64 = AtomicU64::new(1);
+
+    struct TestDirectory {
+        path: PathBuf,
+    }
+
+    impl TestDirectory {
+        fn new(label: &str) -> Self {
+            let sequence = NEXT_TEST_DIR.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!(
+                "copy-stack-history-backup-test-{}-{}-{}",
+                std::process::id(),
+                sequence,
+                label
+            ));
+            std::fs::create_dir(&path).expect("test directory should be created");
+            std::fs::set_permissions(&path, Permissions::from_mode(0o700))
+                .expect("test directory should be private");
+            Self { path }
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn backup() -> HistoryBackup {
+        HistoryBackup {
+            settings: vec![
+                ("compact_mode".to_string(), "true".to_string()),
+                ("max_items".to_string(), "42".to_string()),
+            ],
+            rows: vec![
+                HistoryBackupRow {
+                    content_hash: "a".repeat(64),
+                    event_data: b"CSB1 first".to_vec(),
+                    timestamp: 10,
+                    pinned: true,
+                    first_seen_at: 5,
+                    last_seen_at: 10,
+                    copy_count: 3,
+                    expires_at: None,
+                },
+                HistoryBackupRow {
+                    content_hash: "b".repeat(64),
+                    event_data: Vec::new(),
+                    timestamp: 20,
+                    pinned: false,
+                    first_seen_at: 20,
+                    last_seen_at: 20,
+                    copy_count: 1,
+                    expires_at: Some(99),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn archives_round_trip_through_a_private_file_without_plaintext() {
+        let directory = TestDirectory::new("round-trip");
+        let path = directory.path.join("history.csbk");
+        write_history_backup_with(&path, PASSPHRASE, &backup(), TEST_ITERATIONS)
+            .expect("archive should write");
+
+        let metadata = std::fs::metadata(&path).expect("archive should exist");
+        assert_eq!(metadata.mode() & 0o777, 0o600);
+        let entries: Vec<_> = std::fs::read_dir(&directory.path)
+            .expect("directory should list")
+            .collect();
+        assert_eq!(entries.len(), 1, "temporary files remained");
+        let bytes = std::fs::read(&path).expect("archive should read");
+        assert!(bytes.starts_with(BACKUP_MAGIC));
+        assert!(!bytes.windows(10).any(|window| window == b"CSB1 first"));
+
+        assert_eq!(
+            read_history_backup(&path, PASSPHRASE).expect("archive should open"),
+            backup()
+        );
+    }
+
+    #[test]
+    fn wrong_passphrases_and_edited_archives_are_rejected() {
+        let archive = seal_backup(&backup(), PASSPHRASE, TEST_ITERATIONS).expect("archive seals");
+        let rejected = |archive: &[u8], passphrase: &str| {
+            open_backup(archive, passphrase)
+                .expect_err("archive should be rejected")
+                .kind()
+        };
+
+        assert_eq!(
+            rejected(&archive, "incorrect horse battery"),
+            HistoryBackupErrorKind::Rejected
+        );
+        for index in [0, 5, SALT_BYTES + 9, HEADER_BYTES, archive.len() - 1] {
+            let mut edited = archive.clone();
+            edited[index] ^= 1;
+            assert_eq!(
+                rejected(&edited, PASSPHRASE),
+                HistoryBackupErrorKind::Rejected,
+                "byte {index}"
+            );
+        }
+        assert_eq!(
+            rejected(&archive[..archive.len() - 1], PASSPHRASE),
+            HistoryBackupErrorKind::Rejected
+        );
+
+        for (version, kind) in [
+            (0, HistoryBackupErrorKind::Rejected),
+            (
+                BACKUP_FORMAT_VERSION + 1,
+                HistoryBackupErrorKind::UnsupportedVersion,
+            ),
+        ] {
+            let mut edited = archive.clone();
+            edited[BACKUP_MAGIC.len()] = version;
+            assert_eq!(rejected(&edited, PASSPHRASE), kind, "version {version}");
+        }
+
+        let mut costly = archive.clone();
+        costly[5..9].copy_from_slice(&(MAX_KDF_ITERATIONS + 1).to_le_bytes());
+        assert_eq!(
+            rejected(&costly, PASSPHRASE),
+            HistoryBackupErrorKind::Rejected
+        );
+    }
+
+    #[test]
+    fn short_passphrases_write_nothing() {
+        let directory = TestDirectory::new("short-passphrase");
+        let path = directory.path.join("history.csbk");
+        let error = write_history_backup(&path, "short", &backup())
+            .expect_err("a short passphrase should be refused");
+        assert_eq!(error.kind(), HistoryBackupErrorKind::Passphrase);
+        assert!(!path.exists());
+        assert_eq!(
+            read_history_backup(&path, "short")
+                .expect_err("a short passphrase should be refused")
+                .kind(),
+            HistoryBackupErrorKind::Passphrase
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! Readable history reports for handing a slice of history to someone else.
+//!
+//! Unlike the JSONL mirror and backup archives, reports are meant to be read
+//! rather than restored: Markdown with fenced text and file lists, CSV with one
+//! row per item, and a self-contained HTML page with bounded image thumbnails.
+//! Each entry is built from an owned `HistoryDetailSeed` after the database
+//! lock is released, and the report is installed through a same-directory
+//! `0600` temporary file, so a reader never sees a partial report.
+
+use crate::private_fs::{
+    create_private_temp_file, prepare_private_output_path, PrivateFsError, PrivateFsErrorKind,
+};
+use crate::resource_policy::MAX_EXPORT_IMAGE_BYTES;
+use crate::store::{Database, HistoryDetailSeed, HistoryExportEntry};
+use chrono::{DateTime, SecondsFormat};
+use serde::Deserialize;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const REPORT_TITLE: &str = "Copy Stack History";
+const CSV_HEADER: &str = "timestamp,type,source_app,byte_count,text\r\n";
+const HTML_STYLE: &str = "body{font:14px/1.5 -apple-system,system-ui,sans-serif;margin:2rem auto;max-width:60rem;padding:0 1rem;color:#1d1d1f}\
+article{border-top:1px solid #d2d2d7;padding:1rem 0}\
+h2{font-size:1rem;margin:0}\
+.meta{color:#6e6e73;margin:.25rem 0 .75rem}\
+pre{white-space:pre-wrap;overflow-wrap:anywhere;background:#f5f5f7;border-radius:6px;padding:.75rem;margin:0}\
+figure{display:inline-block;margin:.5rem 1rem 0 0}\
+img{max-width:240px;max-height:240px;border:1px solid #d2d2d7;border-radius:4px}\
+figcaption{color:#6e6e73;font-size:12px}";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryExportFormat {
+    Markdown,
+    Csv,
+    Html,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HistoryExportErrorKind {
+    Path(PrivateFsErrorKind),
+    Write,
+    /// A verified row could not be read back as an entry.
+    Build,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryExportError {
+    kind: HistoryExportErrorKind,
+    stage: &'static str,
+}
+
+impl HistoryExportError {
+    pub fn kind(&self) -> HistoryExportErrorKind {
+        self.kind
+    }
+
+    fn new(kind: HistoryExportErrorKind, stage: &'static str) -> Self {
+        Self { kind, stage }
+    }
+
+    fn path(error: PrivateFsError, stage: &'static str) -> Self {
+        Self::new(HistoryExportErrorKind::Path(error.kind()), stage)
+    }
+
+    fn write(stage: &'static str) -> Self {
+        Self::new(HistoryExportErrorKind::Write, stage)
+    }
+}
+
+impl fmt::Display for HistoryExportError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            HistoryExportErrorKind::Path(_) => "private path validation failed",
+            HistoryExportErrorKind::Write => "the report could not be written",
+            HistoryExportErrorKind::Build => "a history row could not be read",
+        };
+        write!(
+            formatter,
+            "history export failed during {}: {}",
+            self.stage, reason
+        )
+    }
+}
+
+impl std::error::Error for HistoryExportError {}
+
+/// Writes `seeds` as a `format` report and atomically installs it at `path`
+/// as a `0600` file. Returns the number of entries written.
+pub fn write_history_export(
+    path: &Path,
+    format: HistoryExportFormat,
+    seeds: Vec<HistoryDetailSeed>,
+    compact_mode: bool,
+) -> Result<u64, HistoryExportError> {
+    let output_path = prepare_private_output_path(path)
+        .map_err(|error| HistoryExportError::path(error, "write path validation"))?;
+    let mut temp = create_private_temp_file(&output_path, 0)
+        .map_err(|error| HistoryExportError::path(error, "create temporary file"))?;
+    let exported_items = {
+        let mut writer = BufWriter::new(temp.file_mut());
+        let exported_items = write_report(&mut writer, format, seeds, compact_mode)?;
+        writer
+            .flush()
+            .map_err(|_| HistoryExportError::write("write temporary file"))?;
+        exported_items
+    };
+    temp.sync_all()
+        .map_err(|error| HistoryExportError::path(error, "sync temporary file"))?;
+    temp.commit(&output_path)
+        .map_err(|error| HistoryExportError::path(error, "atomic replace"))?;
+    Ok(exported_items)
+}
+
+fn write_report<W: Write>(
+    writer: &mut W,
+    format: HistoryExportFormat,
+    seeds: Vec<HistoryDetailSeed>,
+    compact_mode: bool,
+) -> Result<u64, HistoryExportError> {
+    let written = |result: io::Result<()>| {
+        result.map_err(|_| HistoryExportError::write("write temporary file"))
+    };
+    let with_images = format == HistoryExportFormat::Html;
+    let mut image_budget = MAX_EXPORT_IMAGE_BYTES;
+
+    written(match format {
+        HistoryExportFormat::Markdown => writeln!(writer, "# {REPORT_TITLE}"),
+        HistoryExportFormat::Csv => writer.write_all(CSV_HEADER.as_bytes()),
+        HistoryExportFormat::Html => write_html_header(writer),
+    })?;
+    let mut exported_items = 0;
+    for seed in seeds {
+        let entry = Database::build_history_export_entry(seed, compact_mode, with_images)
+            .map_err(|_| HistoryExportError::new(HistoryExportErrorKind::Build, "build entry"))?;
+        written(match format {
+            HistoryExportFormat::Markdown => write_markdown_entry(writer, &entry),
+            HistoryExportFormat::Csv => write_csv_row(writer, &entry),
+            HistoryExportFormat::Html => write_html_entry(writer, &entry, &mut image_budget),
+        })?;
+        exported_items += 1;
+    }
+    if format == HistoryExportFormat::Html {
+        written(writer.write_all(b"</body>\n</html>\n"))?;
+    }
+    Ok(exported_items)
+}
+
+fn write_markdown_entry<W: Write>(writer: &mut W, entry: &HistoryExportEntry) -> io::Result<()> {
+    write!(
+        writer,
+        "\n## {}\n\n- Type: {}\n",
+        readable_timestamp(entry.timestamp),
+        escape_markdown(&entry.data_type)
+    )?;
+    if let Some(source) = &entry.source_bundle_id {
+        writeln!(writer, "- Source: {}", escape_markdown(source))?;
+    }
+    writeln!(writer, "- Size: {} bytes", entry.byte_count)?;
+
+    if !entry.files.is_empty() {
+        writeln!(writer)?;
+        for file in &entry.files {
+            let suffix = if file.item_type == "folder" { "/" } else { "" };
+            writeln!(writer, "- {}{suffix}", escape_markdown(&file.name))?;
+        }
+    } else if !entry.text.trim().is_empty() {
+        let fence = "`".repeat(longest_backtick_run(&entry.text).max(2) + 1);
+        write!(writer, "\n{fence}text\n{}", entry.text)?;
+        if !entry.text.ends_with('\n') {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "{fence}")?;
+    }
+    Ok(())
+}
+
+fn write_csv_row<W: Write>(writer: &mut W, entry: &HistoryExportEntry) -> io::Result<()> {
+    let timestamp = DateTime::from_timestamp_millis(entry.timestamp)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_else(|| entry.timestamp.to_string());
+    write!(
+        writer,
+        "{},{},{},{},{}\r\n",
+        csv_field(&timestamp),
+        csv_field(&entry.data_type),
+        csv_field(entry.source_bundle_id.as_deref().unwrap_or_default()),
+        entry.byte_count,
+        csv_field(&entry.text)
+    )
+}
+
+fn write_html_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    write!(
+        writer,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'none'; img-src data:; style-src 'unsafe-inline'\">\n\
+         <title>{REPORT_TITLE}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>{REPORT_TITLE}</h1>\n"
+    )
+}
+
+fn write_html_entry<W: Write>(
+    writer: &mut W,
+    entry: &HistoryExportEntry,
+    image_budget: &mut usize,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "<article>\n<h2>{}</h2>\n<p class=\"meta\">{}",
+        readable_timestamp(entry.timestamp),
+        escape_html(&entry.data_type)
+    )?;
+    if let Some(source) = &entry.source_bundle_id {
+        write!(writer, " · {}", escape_html(source))?;
+    }
+    writeln!(writer, " · {} bytes</p>", entry.byte_count)?;
+
+    if !entry.files.is_empty() {
+        writer.write_all(b"<ul>\n")?;
+        for file in &entry.files {
+            let suffix = if file.item_type == "folder" { "/" } else { "" };
+            writeln!(writer, "<li>{}{suffix}</li>", escape_html(&file.name))?;
+        }
+        writer.write_all(b"</ul>\n")?;
+    } else if !entry.text.trim().is_empty() {
+        writeln!(writer, "<pre>{}</pre>", escape_html(&entry.text))?;
+    }
+
+    for image in &entry.images {
+        let label = escape_html(&image.label);
+        if image.data.len() > *image_budget {
+            writeln!(writer, "<p class=\"meta\">{label}</p>")?;
+            continue;
+        }
+        *image_budget -= image.data.len();
+        writeln!(
+            writer,
+            "<figure><img src=\"data:{};base64,{}\" alt=\"{label}\"><figcaption>{label}</figcaption></figure>",
+            escape_html(&image.media_type),
+            base64(&image.data)
+        )?;
+    }
+    writer.write_all(b"</article>\n")
+}
+
+fn readable_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp_millis(timestamp)
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\n' | '\r' => escaped.push(' '),
+            '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '<' | '>' | '(' | ')' | '#' | '+'
+            | '-' | '!' | '|' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Quotes a field as RFC 4180 requires, and defuses text a spreadsheet
+/// would run as a formula.
+fn csv_field(value: &str) -> String {
+    let formula = value.starts_with(['=', '+', '-', '@', '\t', '\r']);
+    if !formula && !value.contains([',', '"', '\n', '\r']) {
+        return value.to_string();
+    }
+    let mut quoted = String::with_capacity(value.len() + 3);
+    quoted.push('"');
+    if formula {
+        quoted.push('\'');
+    }
+    quoted.push_str(&value.replace('"', "\"\""));
+    quoted.push('"');
+    quoted
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |triple, (index, byte)| {
+                triple | (u32::from(*byte) << (16 - 8 * index))
+            });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[((triple >> (18 - 6 * index)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::encode_event_blob;
+    use crate::store::{FileDisplayItem, HistoryExportImage};
+    use copy_event_listener::event::{Data, Event, Item};
+
+    fn entry(text: &str) -> HistoryExportEntry {
+        HistoryExportEntry {
+            timestamp: 1_710_000_000_000,
+            data_type: "text".to_string(),
+            source_bundle_id: Some("com.example.notes".to_string()),
+            byte_count: text.len() as u64,
+            text: text.to_string(),
+            files: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    fn rendered(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut output = Vec::new();
+        write(&mut output).expect("entry should render");
+        String::from_utf8(output).expect("report should be UTF-8")
+    }
+
+    #[test]
+    fn markdown_fences_outlast_the_text_and_lists_files() {
+        let text = rendered(|output| write_markdown_entry(output, &entry("a ```` b")));
+        assert!(text.contains("## 2024-03-09 16:00:00 UTC"));
+        assert!(text.contains("- Source: com.example.notes"));
+        assert!(text.contains("\n`````text\na ```` b\n`````\n"));
+
+        let mut files = entry("");
+        files.data_type = "file".to_string();
+        files.files = vec![
+            FileDisplayItem {
+                item_type: "file".to_string(),
+                name: "[draft]*.md".to_string(),
+            },
+            FileDisplayItem {
+                item_type: "folder".to_string(),
+                name: "Photos".to_string(),
+            },
+        ];
+        let text = rendered(|output| write_markdown_entry(output, &files));
+        assert!(text.contains("\n- \\[draft\\]\\*.md\n- Photos/\n"));
+        assert!(!text.contains("```"));
+    }
+
+    #[test]
+    fn csv_rows_quote_fields_and_defuse_formulas() {
+        let text =
+            rendered(|output| write_csv_row(output, &entry("=HYPERLINK(\"x\")\nsecond line")));
+        assert_eq!(
+            text,
+            "2024-03-09T16:00:00.000Z,text,com.example.notes,27,\"'=HYPERLINK(\"\"x\"\")\nsecond line\"\r\n"
+        );
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("-1"), "\"'-1\"");
+    }
+
+    #[test]
+    fn html_entries_escape_text_and_stop_embedding_images_past_the_budget() {
+        let mut with_images = entry("<script>alert('x')</script>");
+        with_images.images = vec![
+            HistoryExportImage {
+                label: "Image".to_string(),
+                media_type: "image/png".to_string(),
+                data: b"png".to_vec(),
+            },
+            HistoryExportImage {
+                label: "\"large\"".to_string(),
+                media_type: "image/png".to_string(),
+                data: vec![0; 8],
+            },
+        ];
+        let mut budget = 4;
+        let text = rendered(|output| write_html_entry(output, &with_images, &mut budget));
+        assert!(text.contains("<pre>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;</pre>"));
+        assert!(text.contains("<img src=\"data:image/png;base64,cG5n\" alt=\"Image\">"));
+        assert!(text.contains("<p class=\"meta\">&quot;large&quot;</p>"));
+        assert_eq!(budget, 1);
+    }
+
+    #[test]
+    fn base64_matches_the_standard_alphabet_and_padding() {
+        for (input, expected) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (&[0xfb, 0xff], "+/8="),
+        ] {
+            assert_eq!(base64(input), expected);
+        }
+    }
+
+    #[test]
+    fn reports_are_built_from_owned_seeds() {
+        let event = Event {
+            items: vec![Item {
+                data_list: vec![Data {
+                    r#type: "public.utf8-plain-text".to_string(),
+                    data: b"shared\0 note".to_vec(),
+                }],
+            }],
+        };
+        let seed = HistoryDetailSeed {
+            content_hash: "a".repeat(64),
+            event_data: encode_event_blob(&event).expect("event should encode"),
+            data_type: "text".to_string(),
+            display: b"shared note".to_vec(),
+            compact_display: None,
+            timestamp: 1_710_000_000_000,
+            source_bundle_id: None,
+            is_remote_clipboard: false,
+            byte_count: 12,
+        };
+
+        let mut output = Vec::new();
+        assert_eq!(
+            write_report(&mut output, HistoryExportFormat::Csv, vec![seed], false),
+            Ok(1)
+        );
+        assert_eq!(
+            String::from_utf8(output).expect("report should be UTF-8"),
+            format!("{CSV_HEADER}2024-03-09T16:00:00.000Z,text,,12,shared note\r\n")
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Clipy indexes history in a Realm database but keeps each clip's
+//! pasteboard contents in its own `<UUID>.data` file: an `NSKeyedArchiver`
+//! archive of `CPYClipData`. Only those files are read, so the string, RTF,
+//! PDF, file name, and URL fields come back as the matching pasteboard
+//! flavors. Archived `NSImage`s are not decoded, which leaves an image-only
+//! clip rejected.
+//!
+//! The index is not read either, so a clip is dated by its data file's
+//! modification time, which Clipy sets when it saves the clip.
+
+use super::{
+    file_url_from_path, modified_millis, pasteboard_items, read_import_file, HistoryImport,
+    HistoryImportError, HistoryImportErrorKind, ImportedHistoryRow, TEXT_TYPE,
+};
+use crate::private_fs::{resolve_private_path, PrivateFsErrorKind};
+use copy_event_listener::event::{Data, Event, Item};
+use plist::{Dictionary, Value};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// A clip holds at most one capture's worth of flavors, plus an archived
+/// image that is skipped.
+const MAX_CLIPY_DATA_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Reads every `.data` file in a Clipy data directory, or one such file.
+pub(super) fn read_clipy_history(path: &Path) -> Result<HistoryImport, HistoryImportError> {
+    let path = resolve_private_path(path)
+        .map_err(|error| HistoryImportError::path(error, "resolve Clipy path"))?;
+    let metadata = std::fs::symlink_metadata(&path)
+        .map_err(|error| path_error(&error, "inspect Clipy path"))?;
+    if !metadata.is_dir() {
+        let items =
+            read_clip(&path)?.ok_or_else(|| HistoryImportError::rejected("decode Clipy clip"))?;
+        let timestamp = modified_millis(&path, "inspect Clipy clip")?;
+        return Ok(HistoryImport {
+            rows: vec![clip_row(items, timestamp)],
+            rejected_items: 0,
+        });
+    }
+
+    let mut clip_paths = Vec::<PathBuf>::new();
+    for entry in
+        std::fs::read_dir(&path).map_err(|error| path_error(&error, "list Clipy directory"))?
+    {
+        let entry = entry.map_err(|error| path_error(&error, "list Clipy directory"))?;
+        let is_file = entry
+            .file_type()
+            .map_err(|error| path_error(&error, "list Clipy directory"))?
+            .is_file();
+        if is_file && entry.path().extension().is_some_and(|ext| ext == "data") {
+            clip_paths.push(entry.path());
+        }
+    }
+
+    let mut clips = Vec::new();
+    let mut import = HistoryImport::default();
+    for clip_path in clip_paths {
+        match read_clip(&clip_path) {
+            Ok(Some(items)) => {
+                let timestamp = modified_millis(&clip_path, "inspect Clipy clip")?;
+                clips.push((timestamp, clip_path, items));
+            }
+            Ok(None) => import.rejected_items += 1,
+            Err(error) if error.kind() == HistoryImportErrorKind::Rejected => {
+                import.rejected_items += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    clips.sort_by(|left, right| (left.0, &left.1).cmp(&(right.0, &right.1)));
+    import.rows = clips
+        .into_iter()
+        .map(|(timestamp, _, items)| clip_row(items, timestamp))
+        .collect();
+    Ok(import)
+}
+
+fn path_error(error: &std::io::Error, stage: &'static str) -> HistoryImportError {
+    let kind = match error.kind() {
+        std::io::ErrorKind::NotFound => PrivateFsErrorKind::NotFound,
+        kind => PrivateFsErrorKind::Io(kind),
+    };
+    HistoryImportError::new(HistoryImportErrorKind::Path(kind), stage)
+}
+
+fn clip_row(items: Vec<Item>, timestamp: i64) -> ImportedHistoryRow {
+    ImportedHistoryRow {
+        event: Event { items },
+        timestamp,
+        source_bundle_id: None,
+        is_remote_clipboard: false,
+        content_hash: None,
+    }
+}
+
+/// Returns the clip's pasteboard items, or `None` when none of its fields
+/// map to a flavor.
+fn read_clip(path: &Path) -> Result<Option<Vec<Item>>, HistoryImportError> {
+    let contents = read_import_file(path, MAX_CLIPY_DATA_BYTES, "read Clipy clip")?;
+    let archive = Value::from_reader(Cursor::new(contents))
+        .map_err(|_| HistoryImportError::rejected("decode Clipy clip"))?;
+    let archive = KeyedArchive::new(&archive)
+        .ok_or_else(|| HistoryImportError::rejected("decode Clipy clip"))?;
+
+    let mut flavors = Vec::new();
+    if let Some(text) = archive
+        .string("stringValue")
+        .filter(|text| !text.is_empty())
+    {
+        flavors.push(flavor(TEXT_TYPE, text.as_bytes()));
+    }
+    if let Some(rtf) = archive.data("RTFData").filter(|data| !data.is_empty()) {
+        flavors.push(flavor("public.rtf", rtf));
+    }
+    if let Some(pdf) = archive.data("PDF").filter(|data| !data.is_empty()) {
+        flavors.push(flavor("com.adobe.pdf", pdf));
+    }
+    if let Some(url) = archive.strings("URL").into_iter().next() {
+        flavors.push(flavor("public.url", url.as_bytes()));
+    }
+    let file_urls = archive
+        .strings("filenames")
+        .into_iter()
+        .filter_map(file_url_from_path)
+        .collect();
+
+    let items = pasteboard_items(flavors, file_urls);
+    Ok((!items.is_empty()).then_some(items))
+}
+
+fn flavor(pasteboard_type: &str, data: &[u8]) -> Data {
+    Data {
+        r#type: pasteboard_type.to_string(),
+        data: data.to_vec(),
+    }
+}
+
+/// The root object of an `NSKeyedArchiver` archive, with its fields
+/// resolved through the `$objects` table.
+struct KeyedArchive<'a> {
+    objects: &'a [Value],
+    root: &'a Dictionary,
+}
+
+impl<'a> KeyedArchive<'a> {
+    fn new(archive: &'a Value) -> Option<Self> {
+        let archive = archive.as_dictionary()?;
+        if archive.get("$archiver")?.as_string()? != "NSKeyedArchiver" {
+            return None;
+        }
+        let objects = archive.get("$objects")?.as_array()?;
+        let root = archive
+            .get("$top")?
+            .as_dictionary()?
+            .get("root")
+            .and_then(|root| resolve(objects, root))?
+            .as_dictionary()?;
+        Some(Self { objects, root })
+    }
+
+    fn field(&self, key: &str) -> Option<&'a Value> {
+        resolve(self.objects, self.root.get(key)?)
+    }
+
+    fn string(&self, key: &str) -> Option<&'a str> {
+        string_value(self.objects, self.field(key)?)
+    }
+
+    fn data(&self, key: &str) -> Option<&'a [u8]> {
+        let value = self.field(key)?;
+        value
+            .as_data()
+            .or_else(|| resolve(self.objects, value.as_dictionary()?.get("NS.data")?)?.as_data())
+    }
+
+    fn strings(&self, key: &str) -> Vec<&'a str> {
+        let objects = self
+            .field(key)
+            .and_then(Value::as_dictionary)
+            .and_then(|array| array.get("NS.objects"))
+            .and_then(Value::as_array);
+        objects
+            .into_iter()
+            .flatten()
+            .filter_map(|value| string_value(self.objects, resolve(self.objects, value)?))
+            .collect()
+    }
+}
+
+/// Follows a UID reference into `$objects`; `$null` resolves to nothing.
+fn resolve<'a>(objects: &'a [Value], value: &'a Value) -> Option<&'a Value> {
+    let value = match value.as_uid() {
+        Some(uid) => objects.get(usize::try_from(uid.get()).ok()?)?,
+        None => value,
+    };
+    (value.as_string() != Some("$null")).then_some(value)
+}
+
+fn string_value<'a>(objects: &'a [Value], value: &'a Value) -> Option<&'a str> {
+    value
+        .as_string()
+        .or_else(|| resolve(objects, value.as_dictionary()?.get("NS.string")?)?.as_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::history_import::TestDirectory;
+
+    #[test]
+    fn clipy_clips_read_back_as_pasteboard_flavors() {
+        let directory = TestDirectory::new("clipy");
+        let text_path = directory.write(
+            "0B5E6E3C-2A55-4A57-8E7A-6C8A3F2D1E01.data",
+            include_bytes!("fixtures/clipy_text.data"),
+        );
+        let files_path = directory.write(
+            "5F1D2C3B-4A59-4E68-9D7C-1B2A3C4D5E02.data",
+            include_bytes!("fixtures/clipy_files.data"),
+        );
+        directory.write(
+            "9A8B7C6D-5E4F-4A3B-8C2D-1E0F9A8B7C03.data",
+            include_bytes!("fixtures/clipy_image.data"),
+        );
+        directory.write("A1B2C3D4-E5F6-4789-8ABC-DEF012345604.data", b"corrupt");
+        directory.write("default.realm", b"index");
+        let times = [(&text_path, 1_000), (&files_path, 2_000)];
+        for (path, seconds) in times {
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .and_then(|file| {
+                    file.set_modified(
+                        std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds),
+                    )
+                })
+                .expect("clip time should be set");
+        }
+
+        let import = read_clipy_history(&directory.path).expect("Clipy directory should read");
+        assert_eq!(import.rejected_items, 2);
+        assert_eq!(import.rows.len(), 2);
+
+        let text = &import.rows[0];
+        assert_eq!(text.timestamp, 1_000_000);
+        assert_eq!(text.event.items.len(), 1);
+        assert_eq!(text.event.items[0].data_list[0].r#type, TEXT_TYPE);
+        assert_eq!(text.event.items[0].data_list[0].data, b"Copied with Clipy");
+        assert_eq!(text.event.items[0].data_list[1].r#type, "public.rtf");
+
+        let files = &import.rows[1];
+        assert_eq!(files.timestamp, 2_000_000);
+        assert_eq!(files.event.items.len(), 2);
+        assert_eq!(
+            files.event.items[0].data_list[0].data,
+            b"file:///Users/me/Notes.md"
+        );
+        assert_eq!(
+            files.event.items[1].data_list[0].data,
+            b"file:///Users/me/Q1%20report.pdf"
+        );
+
+        let single = read_clipy_history(&files_path).expect("one clip should read");
+        assert_eq!(single.rows.len(), 1);
+        assert_eq!(
+            read_clipy_history(&directory.path.join("default.realm"))
+                .expect_err("index should be rejected")
+                .kind(),
+            HistoryImportErrorKind::Rejected
+        );
+    }
+}
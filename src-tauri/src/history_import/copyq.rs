@@ -0,0 +1,271 @@
+//! CopyQ saves each tab as a big-endian `QDataStream`: a `qint32` item
+//! count, then one MIME map per item, newest first. An item map starts with
+//! a `qint32` entry count, or `-2` followed by the count in the current
+//! layout, whose MIME names are shortened by a one-digit prefix code. Every
+//! entry is a `QString` MIME name, a `bool` that marks `qCompress` data, and
+//! a `QByteArray` payload.
+//!
+//! Tabs keep no copy times, so entries are ordered back from the tab file's
+//! modification time, one millisecond apart.
+
+use super::{
+    modified_millis, pasteboard_items, read_import_file, HistoryImport, HistoryImportError,
+    ImportedHistoryRow, TEXT_TYPE,
+};
+use crate::private_fs::resolve_private_path;
+use copy_event_listener::event::{Data, Event, Item};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::path::Path;
+
+const MAX_COPYQ_TAB_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// The item marker of the layout with shortened MIME names.
+const SHORT_MIME_ITEM: i32 = -2;
+
+/// Prefixes restored in front of a shortened MIME name, by its leading digit.
+const MIME_PREFIXES: [&str; 5] = [
+    "",
+    "application/x-copyq-",
+    "text/",
+    "application/",
+    "image/",
+];
+
+pub(super) fn read_copyq_history(path: &Path) -> Result<HistoryImport, HistoryImportError> {
+    let path = resolve_private_path(path)
+        .map_err(|error| HistoryImportError::path(error, "resolve CopyQ path"))?;
+    let contents = read_import_file(&path, MAX_COPYQ_TAB_BYTES, "read CopyQ tab")?;
+    let newest = modified_millis(&path, "inspect CopyQ tab")?;
+    parse_copyq_tab(&contents, newest)
+}
+
+fn parse_copyq_tab(contents: &[u8], newest: i64) -> Result<HistoryImport, HistoryImportError> {
+    let mut stream = DataStream { contents };
+    let item_count = stream.count()?;
+    let mut entries = Vec::new();
+    for _ in 0..item_count {
+        entries.push(stream.item()?);
+    }
+    if !stream.contents.is_empty() {
+        return Err(HistoryImportError::rejected("parse CopyQ tab"));
+    }
+
+    let mut import = HistoryImport::default();
+    // Oldest first, like every other reader.
+    for (age, entry) in entries.into_iter().enumerate().rev() {
+        let items = pasteboard_items_from_mime(entry);
+        if items.is_empty() {
+            import.rejected_items += 1;
+            continue;
+        }
+        import.rows.push(ImportedHistoryRow {
+            event: Event { items },
+            timestamp: newest.saturating_sub(age as i64),
+            source_bundle_id: None,
+            is_remote_clipboard: false,
+            content_hash: None,
+        });
+    }
+    Ok(import)
+}
+
+fn pasteboard_items_from_mime(entry: Vec<(String, Vec<u8>)>) -> Vec<Item> {
+    let mut flavors = Vec::new();
+    let mut file_urls = Vec::new();
+    for (mime, data) in entry {
+        if mime == "text/uri-list" {
+            file_urls.extend(
+                String::from_utf8_lossy(&data)
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| line.starts_with("file:///"))
+                    .map(|line| line.as_bytes().to_vec()),
+            );
+            continue;
+        }
+        let pasteboard_type = match mime.as_str() {
+            "text/plain" | "text/plain;charset=utf-8" => TEXT_TYPE,
+            "text/html" => "public.html",
+            "text/rtf" | "text/richtext" | "application/rtf" => "public.rtf",
+            "image/png" => "public.png",
+            "image/jpeg" => "public.jpeg",
+            "image/tiff" => "public.tiff",
+            "image/gif" => "public.gif",
+            "image/bmp" => "public.bmp",
+            "image/webp" => "public.webp",
+            _ => continue,
+        };
+        flavors.push(Data {
+            r#type: pasteboard_type.to_string(),
+            data,
+        });
+    }
+    pasteboard_items(flavors, file_urls)
+}
+
+struct DataStream<'a> {
+    contents: &'a [u8],
+}
+
+impl DataStream<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], HistoryImportError> {
+        if self.contents.len() < len {
+            return Err(HistoryImportError::rejected("parse CopyQ tab"));
+        }
+        let (head, tail) = self.contents.split_at(len);
+        self.contents = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, HistoryImportError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn count(&mut self) -> Result<usize, HistoryImportError> {
+        let count = self.u32()? as i32;
+        non_negative(count)
+    }
+
+    /// A length-prefixed field; `0xffffffff` marks a null value, read as
+    /// empty.
+    fn bytes(&mut self) -> Result<&[u8], HistoryImportError> {
+        match self.u32()? {
+            u32::MAX => Ok(&[]),
+            len => self.take(len as usize),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, HistoryImportError> {
+        let pairs = self.bytes()?.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return Err(HistoryImportError::rejected("parse CopyQ tab"));
+        }
+        let units = pairs.map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|_| HistoryImportError::rejected("parse CopyQ tab"))
+    }
+
+    fn item(&mut self) -> Result<Vec<(String, Vec<u8>)>, HistoryImportError> {
+        let marker = self.u32()? as i32;
+        let short_mime = marker == SHORT_MIME_ITEM;
+        let entry_count = if short_mime {
+            self.count()?
+        } else {
+            non_negative(marker)?
+        };
+
+        let mut entry = Vec::new();
+        for _ in 0..entry_count {
+            let mime = self.string()?;
+            let compressed = match self.take(1)?[0] {
+                0 => false,
+                1 => true,
+                _ => return Err(HistoryImportError::rejected("parse CopyQ tab")),
+            };
+            let data = self.bytes()?;
+            let data = if compressed {
+                uncompress(data)?
+            } else {
+                data.to_vec()
+            };
+            let mime = if short_mime {
+                match expand_mime(&mime) {
+                    Some(mime) => mime,
+                    None => continue,
+                }
+            } else {
+                mime
+            };
+            entry.push((mime, data));
+        }
+        Ok(entry)
+    }
+}
+
+fn non_negative(count: i32) -> Result<usize, HistoryImportError> {
+    usize::try_from(count).map_err(|_| HistoryImportError::rejected("parse CopyQ tab"))
+}
+
+fn expand_mime(short: &str) -> Option<String> {
+    let mut chars = short.chars();
+    let prefix = MIME_PREFIXES.get(chars.next()?.to_digit(10)? as usize)?;
+    Some(format!("{prefix}{}", chars.as_str()))
+}
+
+/// Reverses `qCompress`: a big-endian expected length, then a zlib stream.
+fn uncompress(data: &[u8]) -> Result<Vec<u8>, HistoryImportError> {
+    let (expected, stream) = data
+        .split_first_chunk::<4>()
+        .ok_or_else(|| HistoryImportError::rejected("uncompress CopyQ data"))?;
+    let expected = u32::from_be_bytes(*expected) as u64;
+    let mut output = Vec::new();
+    ZlibDecoder::new(stream)
+        .take(expected.saturating_add(1))
+        .read_to_end(&mut output)
+        .map_err(|_| HistoryImportError::rejected("uncompress CopyQ data"))?;
+    if output.len() as u64 != expected {
+        return Err(HistoryImportError::rejected("uncompress CopyQ data"));
+    }
+    Ok(output)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::history_import::{HistoryImportErrorKind, TestDirectory};
+
+    #[test]
+    fn copyq_tab_items_map_to_pasteboard_flavors_newest_last() {
+        let directory = TestDirectory::new("copyq");
+        let path = directory.write(
+            "copyq_tab_JmNsaXBib2FyZA==.dat",
+            include_bytes!("fixtures/copyq_tab.dat"),
+        );
+
+        let newest = modified_millis(&path, "inspect").expect("tab should have a time");
+        let import = read_copyq_history(&path).expect("CopyQ tab should read");
+        assert_eq!(import.rejected_items, 1);
+        assert_eq!(import.rows.len(), 3);
+
+        let files = &import.rows[0];
+        assert_eq!(files.timestamp, newest - 3);
+        assert_eq!(files.event.items.len(), 2);
+        assert_eq!(files.event.items[0].data_list[0].r#type, TEXT_TYPE);
+        assert_eq!(files.event.items[0].data_list[1].r#type, "public.file-url");
+        assert_eq!(
+            files.event.items[1].data_list[0].data,
+            b"file:///home/me/b%20c.txt"
+        );
+
+        let html = &import.rows[1];
+        assert_eq!(html.timestamp, newest - 1);
+        assert_eq!(html.event.items[0].data_list[0].r#type, "public.html");
+        assert_eq!(html.event.items[0].data_list[0].data, b"<p>compressed</p>");
+        assert_eq!(html.event.items[0].data_list[1].r#type, TEXT_TYPE);
+
+        let text = &import.rows[2];
+        assert_eq!(text.timestamp, newest);
+        assert_eq!(text.event.items.len(), 1);
+        assert_eq!(text.event.items[0].data_list.len(), 1);
+        assert_eq!(
+            text.event.items[0].data_list[0].data,
+            "Grüße from CopyQ".as_bytes()
+        );
+    }
+
+    #[test]
+    fn truncated_copyq_tabs_are_rejected() {
+        let fixture = include_bytes!("fixtures/copyq_tab.dat");
+        for contents in [&fixture[..fixture.len() - 1], &[0xff, 0xff, 0xff, 0xfe][..]] {
+            assert_eq!(
+                parse_copyq_tab(contents, 0)
+                    .expect_err("tab should be rejected")
+                    .kind(),
+                HistoryImportErrorKind::Rejected
+            );
+        }
+    }
+}
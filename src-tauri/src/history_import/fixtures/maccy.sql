@@ -0,0 +1,38 @@
+-- A Maccy Storage.sqlite reduced to the tables the importer reads.
+CREATE TABLE ZHISTORYITEM (
+    Z_PK INTEGER PRIMARY KEY,
+    Z_ENT INTEGER,
+    Z_OPT INTEGER,
+    ZNUMBEROFCOPIES INTEGER,
+    ZFIRSTCOPIEDAT TIMESTAMP,
+    ZLASTCOPIEDAT TIMESTAMP,
+    ZAPPLICATION VARCHAR,
+    ZPIN VARCHAR,
+    ZTITLE VARCHAR
+);
+CREATE TABLE ZHISTORYITEMCONTENT (
+    Z_PK INTEGER PRIMARY KEY,
+    Z_ENT INTEGER,
+    Z_OPT INTEGER,
+    ZITEM INTEGER,
+    ZTYPE VARCHAR,
+    ZVALUE BLOB
+);
+
+INSERT INTO ZHISTORYITEM VALUES (1, 1, 1, 2, 699999000.0, 700000000.5, 'com.apple.Safari', NULL, 'Hello from Maccy');
+INSERT INTO ZHISTORYITEMCONTENT VALUES (1, 2, 1, 1, 'public.utf8-plain-text', CAST('Hello from Maccy' AS BLOB));
+INSERT INTO ZHISTORYITEMCONTENT VALUES (2, 2, 1, 1, 'public.html', CAST('<b>Hello</b> from Maccy' AS BLOB));
+
+INSERT INTO ZHISTORYITEM VALUES (2, 1, 1, 1, 700000100.0, 700000100.0, 'com.apple.finder', NULL, 'a.txt b.txt');
+INSERT INTO ZHISTORYITEMCONTENT VALUES (3, 2, 1, 2, 'public.file-url', CAST('file:///Users/me/a.txt' AS BLOB));
+INSERT INTO ZHISTORYITEMCONTENT VALUES (4, 2, 1, 2, 'public.file-url', CAST('file:///Users/me/b.txt' AS BLOB));
+INSERT INTO ZHISTORYITEMCONTENT VALUES (5, 2, 1, 2, 'public.utf8-plain-text', CAST('a.txt' || char(10) || 'b.txt' AS BLOB));
+
+INSERT INTO ZHISTORYITEM VALUES (3, 1, 1, 1, 700000200.0, 700000200.0, 'com.agilebits.onepassword7', NULL, 'hunter2');
+INSERT INTO ZHISTORYITEMCONTENT VALUES (6, 2, 1, 3, 'public.utf8-plain-text', CAST('hunter2' AS BLOB));
+INSERT INTO ZHISTORYITEMCONTENT VALUES (7, 2, 1, 3, 'org.nspasteboard.ConcealedType', X'');
+
+INSERT INTO ZHISTORYITEM VALUES (4, 1, 1, 1, 700000300.0, 700000300.0, NULL, NULL, 'no contents');
+
+INSERT INTO ZHISTORYITEM VALUES (5, 1, 1, 1, NULL, NULL, NULL, NULL, 'no date');
+INSERT INTO ZHISTORYITEMCONTENT VALUES (8, 2, 1, 5, 'public.utf8-plain-text', CAST('no date' AS BLOB));
@@ -0,0 +1,221 @@
+//! The JSONL reader accepts the snapshots `history_mirror` writes. A record
+//! with any field cut short by `max_data_bytes` cannot be restored as it was
+//! copied, so it is rejected rather than imported partially.
+
+use super::{read_import_file, HistoryImport, HistoryImportError, ImportedHistoryRow};
+use crate::private_fs::resolve_private_path;
+use copy_event_listener::event::{Data, Event, Item};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Hex doubles every binary flavor, so a mirror of the largest history is
+/// bounded like a backup archive.
+const MAX_HISTORY_JSONL_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Reads a JSONL history mirror. A line that is not a mirror record rejects
+/// the whole file; a record with truncated data only counts as rejected.
+pub fn read_history_jsonl(path: &Path) -> Result<HistoryImport, HistoryImportError> {
+    let path = resolve_private_path(path)
+        .map_err(|error| HistoryImportError::path(error, "resolve JSONL path"))?;
+    let contents = read_import_file(&path, MAX_HISTORY_JSONL_BYTES, "read JSONL")?;
+    parse_history_jsonl(&contents)
+}
+
+fn parse_history_jsonl(contents: &[u8]) -> Result<HistoryImport, HistoryImportError> {
+    let mut import = HistoryImport::default();
+    for line in contents.split(|byte| *byte == b'\n') {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let record = serde_json::from_slice::<JsonlRecord>(line)
+            .map_err(|_| HistoryImportError::rejected("parse JSONL record"))?;
+        match record.into_row()? {
+            Some(row) => import.rows.push(row),
+            None => import.rejected_items += 1,
+        }
+    }
+    Ok(import)
+}
+
+#[derive(Deserialize)]
+struct JsonlRecord {
+    content_hash: String,
+    timestamp: i64,
+    display: JsonlBytes,
+    event_data: JsonlEvent,
+    #[serde(default)]
+    source_bundle_id: Option<String>,
+    #[serde(default)]
+    is_remote_clipboard: bool,
+}
+
+#[derive(Deserialize)]
+struct JsonlEvent {
+    items: Vec<JsonlItem>,
+}
+
+#[derive(Deserialize)]
+struct JsonlItem {
+    data_list: Vec<JsonlData>,
+}
+
+#[derive(Deserialize)]
+struct JsonlData {
+    #[serde(rename = "type")]
+    data_type: String,
+    data: JsonlBytes,
+}
+
+#[derive(Deserialize)]
+struct JsonlBytes {
+    byte_len: usize,
+    truncated: bool,
+    encoding: String,
+    value: String,
+}
+
+impl JsonlRecord {
+    /// Returns the record as an imported row, or `None` when any of its
+    /// data was truncated.
+    fn into_row(self) -> Result<Option<ImportedHistoryRow>, HistoryImportError> {
+        if self.display.truncated
+            || self
+                .event_data
+                .items
+                .iter()
+                .flat_map(|item| &item.data_list)
+                .any(|data| data.data.truncated)
+        {
+            return Ok(None);
+        }
+
+        let mut items = Vec::with_capacity(self.event_data.items.len());
+        for item in self.event_data.items {
+            let mut data_list = Vec::with_capacity(item.data_list.len());
+            for data in item.data_list {
+                data_list.push(Data {
+                    r#type: data.data_type,
+                    data: data.data.into_bytes()?,
+                });
+            }
+            items.push(Item { data_list });
+        }
+        Ok(Some(ImportedHistoryRow {
+            event: Event { items },
+            timestamp: self.timestamp,
+            source_bundle_id: self.source_bundle_id,
+            is_remote_clipboard: self.is_remote_clipboard,
+            content_hash: Some(self.content_hash),
+        }))
+    }
+}
+
+impl JsonlBytes {
+    fn into_bytes(self) -> Result<Vec<u8>, HistoryImportError> {
+        let bytes = match self.encoding.as_str() {
+            "utf8" => self.value.into_bytes(),
+            "hex" => decode_hex(&self.value)
+                .ok_or_else(|| HistoryImportError::rejected("decode JSONL hex"))?,
+            _ => return Err(HistoryImportError::rejected("decode JSONL data")),
+        };
+        if bytes.len() != self.byte_len {
+            return Err(HistoryImportError::rejected("decode JSONL data"));
+        }
+        Ok(bytes)
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    fn nibble(digit: u8) -> Option<u8> {
+        match digit {
+            b'0'..=b'9' => Some(digit - b'0'),
+            b'a'..=b'f' => Some(digit - b'a' + 10),
+            b'A'..=b'F' => Some(digit - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let pairs = value.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| Some((nibble(pair[0])? << 4) | nibble(pair[1])?))
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::history_import::{HistoryImportErrorKind, TestDirectory};
+
+    fn record(data: &str, truncated: bool) -> String {
+        format!(
+            r#"{{"content_hash":"{hash}","data_type":"png","timestamp":20,"display":{{"byte_len":3,"truncated":false,"encoding":"utf8","value":"PNG"}},"event_data":{{"items":[{{"data_list":[{data}]}}]}},"source_bundle_id":"com.example.paint","is_remote_clipboard":true}}"#,
+            hash = "b".repeat(64),
+            data = data.replace("TRUNCATED", if truncated { "true" } else { "false" }),
+        )
+    }
+
+    #[test]
+    fn mirror_records_read_back_as_events_and_truncated_records_are_rejected() {
+        let directory = TestDirectory::new("read");
+        let text = r#"{"content_hash":"aaaa","data_type":"text","timestamp":10,"display":{"byte_len":6,"truncated":false,"encoding":"utf8","value":"éclai"},"event_data":{"items":[{"data_list":[{"type":"public.utf8-plain-text","data":{"byte_len":7,"truncated":false,"encoding":"utf8","value":"éclair"}}]}]}}"#;
+        let binary = record(
+            r#"{"type":"public.png","data":{"byte_len":4,"truncated":TRUNCATED,"encoding":"hex","value":"0001FEff"}}"#,
+            false,
+        );
+        let truncated = record(
+            r#"{"type":"public.png","data":{"byte_len":4,"truncated":TRUNCATED,"encoding":"hex","value":"0001"}}"#,
+            true,
+        );
+        let path = directory.write(
+            "history.jsonl",
+            format!("{text}\n\n{binary}\n{truncated}\n").as_bytes(),
+        );
+
+        let import = read_history_jsonl(&path).expect("mirror should read");
+        assert_eq!(import.rejected_items, 1);
+        assert_eq!(import.rows.len(), 2);
+        assert_eq!(import.rows[0].timestamp, 10);
+        assert_eq!(
+            import.rows[0].event.items[0].data_list[0].data,
+            "éclair".as_bytes()
+        );
+        assert_eq!(import.rows[0].source_bundle_id, None);
+        assert!(!import.rows[0].is_remote_clipboard);
+        assert_eq!(import.rows[1].content_hash, Some("b".repeat(64)));
+        assert_eq!(
+            import.rows[1].event.items[0].data_list[0].data,
+            vec![0, 1, 254, 255]
+        );
+        assert_eq!(
+            import.rows[1].source_bundle_id.as_deref(),
+            Some("com.example.paint")
+        );
+        assert!(import.rows[1].is_remote_clipboard);
+    }
+
+    #[test]
+    fn malformed_mirror_lines_reject_the_whole_file() {
+        for data in [
+            r#"{"type":"public.png","data":{"byte_len":4,"truncated":TRUNCATED,"encoding":"hex","value":"00"}}"#,
+            r#"{"type":"public.png","data":{"byte_len":1,"truncated":TRUNCATED,"encoding":"hex","value":"zz"}}"#,
+            r#"{"type":"public.png","data":{"byte_len":1,"truncated":TRUNCATED,"encoding":"base64","value":"AA=="}}"#,
+        ] {
+            assert_eq!(
+                parse_history_jsonl(record(data, false).as_bytes())
+                    .expect_err("record should be rejected")
+                    .kind(),
+                HistoryImportErrorKind::Rejected,
+                "{data}"
+            );
+        }
+        assert_eq!(
+            parse_history_jsonl(b"{\"not\":\"a record\"}\n")
+                .expect_err("line should be rejected")
+                .kind(),
+            HistoryImportErrorKind::Rejected
+        );
+    }
+}
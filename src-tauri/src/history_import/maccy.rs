@@ -0,0 +1,227 @@
+//! Maccy keeps history in a Core Data SQLite store. Each `ZHISTORYITEM` row
+//! owns the pasteboard flavors Maccy saved for it as `ZHISTORYITEMCONTENT`
+//! rows keyed by their pasteboard type, so flavors come back unchanged;
+//! only repeated file URLs are split into their own pasteboard items.
+//!
+//! The store belongs to Maccy, so it is only opened read-only and its files
+//! keep their modes.
+
+use super::{
+    pasteboard_items, HistoryImport, HistoryImportError, HistoryImportErrorKind,
+    ImportedHistoryRow, FILE_URL_TYPE,
+};
+use crate::private_fs::{resolve_private_path, PrivateFsErrorKind};
+use copy_event_listener::event::{Data, Event};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Core Data dates count seconds from 2001-01-01T00:00:00Z.
+const CORE_DATA_EPOCH_SECONDS: f64 = 978_307_200.0;
+/// Like a Clipy clip, an item holds at most one capture's worth of flavors.
+/// A larger item is rejected without its flavors being read.
+const MAX_MACCY_ITEM_BYTES: i64 = 128 * 1024 * 1024;
+/// A store holding more flavor bytes than this is refused before any is read.
+const MAX_MACCY_STORE_BYTES: i64 = 1024 * 1024 * 1024;
+
+pub(super) fn read_maccy_history(path: &Path) -> Result<HistoryImport, HistoryImportError> {
+    let path = resolve_private_path(path)
+        .map_err(|error| HistoryImportError::path(error, "resolve Maccy path"))?;
+    // A missing store would otherwise be created empty by the open below.
+    if std::fs::symlink_metadata(&path).is_err() {
+        return Err(HistoryImportError::new(
+            HistoryImportErrorKind::Path(PrivateFsErrorKind::NotFound),
+            "open Maccy store",
+        ));
+    }
+    let conn = Connection::open_with_flags(
+        &path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX
+            | OpenFlags::SQLITE_OPEN_NOFOLLOW,
+    )
+    .map_err(|_| HistoryImportError::rejected("open Maccy store"))?;
+    let store_bytes = conn
+        .query_row(
+            "SELECT total(length(ZVALUE)) FROM ZHISTORYITEMCONTENT",
+            [],
+            |row| row.get::<_, f64>(0),
+        )
+        .map_err(|_| HistoryImportError::rejected("measure Maccy store"))?;
+    if store_bytes > MAX_MACCY_STORE_BYTES as f64 {
+        return Err(HistoryImportError::rejected("measure Maccy store"));
+    }
+    read_maccy_store(&conn, MAX_MACCY_ITEM_BYTES)
+        .map_err(|_| HistoryImportError::rejected("read Maccy store"))
+}
+
+fn read_maccy_store(conn: &Connection, max_item_bytes: i64) -> rusqlite::Result<HistoryImport> {
+    let mut contents = HashMap::<i64, Vec<Data>>::new();
+    let mut item_bytes = HashMap::<i64, i64>::new();
+    let mut oversized = HashSet::<i64>::new();
+    let mut statement = conn.prepare(
+        "SELECT ZITEM, ZTYPE, length(ZVALUE), CASE WHEN length(ZVALUE) <= ?1 THEN ZVALUE END
+         FROM ZHISTORYITEMCONTENT
+         WHERE ZITEM IS NOT NULL AND ZTYPE IS NOT NULL AND ZVALUE IS NOT NULL
+         ORDER BY Z_PK",
+    )?;
+    let mut rows = statement.query([max_item_bytes])?;
+    while let Some(row) = rows.next()? {
+        let item_id = row.get::<_, i64>(0)?;
+        let bytes = item_bytes.entry(item_id).or_default();
+        *bytes = bytes.saturating_add(row.get(2)?);
+        if *bytes > max_item_bytes {
+            oversized.insert(item_id);
+            contents.remove(&item_id);
+            continue;
+        }
+        contents.entry(item_id).or_default().push(Data {
+            r#type: row.get(1)?,
+            data: row.get(3)?,
+        });
+    }
+
+    let mut import = HistoryImport::default();
+    let mut statement = conn.prepare(
+        "SELECT Z_PK, ZLASTCOPIEDAT, ZAPPLICATION FROM ZHISTORYITEM
+         ORDER BY ZLASTCOPIEDAT, Z_PK",
+    )?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        let item_id = row.get::<_, i64>(0)?;
+        let copied_at = row.get::<_, Option<f64>>(1)?;
+        let source_bundle_id = row.get::<_, Option<String>>(2)?;
+        if oversized.contains(&item_id) {
+            import.rejected_items += 1;
+            continue;
+        }
+        let flavors = contents.remove(&item_id).unwrap_or_default();
+        let (file_urls, flavors) = flavors
+            .into_iter()
+            .partition::<Vec<_>, _>(|data| data.r#type == FILE_URL_TYPE);
+        let items = pasteboard_items(
+            flavors,
+            file_urls.into_iter().map(|data| data.data).collect(),
+        );
+
+        match copied_at.and_then(core_data_millis) {
+            Some(timestamp) if !items.is_empty() => import.rows.push(ImportedHistoryRow {
+                event: Event { items },
+                timestamp,
+                source_bundle_id,
+                is_remote_clipboard: false,
+                content_hash: None,
+            }),
+            _ => import.rejected_items += 1,
+        }
+    }
+    Ok(import)
+}
+
+fn core_data_millis(seconds: f64) -> Option<i64> {
+    let millis = ((seconds + CORE_DATA_EPOCH_SECONDS) * 1000.0).round();
+    (millis.is_finite() && millis >= 0.0 && millis < i64::MAX as f64).then_some(millis as i64)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::history_import::{TestDirectory, TEXT_TYPE};
+
+    #[test]
+    fn maccy_items_read_back_with_their_flavors_dates_and_sources() {
+        let directory = TestDirectory::new("maccy");
+        let path = directory.path.join("Storage.sqlite");
+        Connection::open(&path)
+            .expect("fixture store should open")
+            .execute_batch(include_str!("fixtures/maccy.sql"))
+            .expect("fixture store should load");
+
+        let import = read_maccy_history(&path).expect("Maccy store should read");
+        assert_eq!(import.rejected_items, 2);
+        assert_eq!(import.rows.len(), 3);
+
+        let text = &import.rows[0];
+        assert_eq!(text.timestamp, 1_678_307_200_500);
+        assert_eq!(text.source_bundle_id.as_deref(), Some("com.apple.Safari"));
+        assert_eq!(text.event.items.len(), 1);
+        assert_eq!(text.event.items[0].data_list[0].r#type, TEXT_TYPE);
+        assert_eq!(text.event.items[0].data_list[0].data, b"Hello from Maccy");
+        assert_eq!(text.event.items[0].data_list[1].r#type, "public.html");
+
+        let files = &import.rows[1];
+        assert_eq!(files.event.items.len(), 2);
+        assert_eq!(files.event.items[0].data_list[0].r#type, TEXT_TYPE);
+        assert_eq!(
+            files.event.items[0].data_list[1].data,
+            b"file:///Users/me/a.txt"
+        );
+        assert_eq!(
+            files.event.items[1].data_list[0].data,
+            b"file:///Users/me/b.txt"
+        );
+
+        let concealed = &import.rows[2];
+        assert!(concealed.event.items[0]
+            .data_list
+            .iter()
+            .any(|data| data.r#type == "org.nspasteboard.ConcealedType"));
+    }
+
+    #[test]
+    fn oversized_maccy_items_are_rejected_and_the_store_is_left_alone() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = TestDirectory::new("maccy-oversized");
+        let path = directory.path.join("Storage.sqlite");
+        let fixture = Connection::open(&path).expect("fixture store should open");
+        fixture
+            .execute_batch(include_str!("fixtures/maccy.sql"))
+            .expect("fixture store should load");
+        fixture
+            .execute(
+                "INSERT INTO ZHISTORYITEMCONTENT VALUES (9, 2, 1, 1, 'public.tiff', zeroblob(64))",
+                [],
+            )
+            .expect("large flavor should insert");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))
+            .expect("store mode should update");
+
+        let import = read_maccy_store(&fixture, 64).expect("Maccy store should read");
+        assert_eq!(import.rejected_items, 3);
+        assert_eq!(import.rows.len(), 2);
+        assert_eq!(
+            import.rows[0].source_bundle_id.as_deref(),
+            Some("com.apple.finder")
+        );
+
+        let import = read_maccy_history(&path).expect("Maccy store should read");
+        assert_eq!(import.rows.len(), 3);
+        assert_eq!(
+            std::fs::metadata(&path)
+                .expect("store metadata should load")
+                .permissions()
+                .mode()
+                & 0o777,
+            0o644
+        );
+    }
+
+    #[test]
+    fn files_that_are_not_maccy_stores_are_rejected() {
+        let directory = TestDirectory::new("maccy-invalid");
+        let path = directory.write("Storage.sqlite", b"not a database");
+        assert_eq!(
+            read_maccy_history(&path)
+                .expect_err("file should be rejected")
+                .kind(),
+            HistoryImportErrorKind::Rejected
+        );
+        assert!(matches!(
+            read_maccy_history(&directory.path.join("missing.sqlite"))
+                .expect_err("missing store should fail")
+                .kind(),
+            HistoryImportErrorKind::Path(_)
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! Reading history written in other formats back as clipboard events.
+//!
+//! Readers here only parse. Every entry comes back as an `Event` with the
+//! timestamp and source metadata it was recorded with, and the database runs
+//! it through the same pasteboard, resource, and classification policy as a
+//! capture before merging it into history by `content_hash`.
+//!
+//! Besides the JSONL snapshots `history_mirror` writes, entries can be read
+//! from the on-disk stores of other clipboard managers. Their flavors are
+//! mapped to the pasteboard types a capture of the same copy would carry;
+//! an entry with nothing that maps counts as rejected.
+
+mod clipy;
+mod copyq;
+mod jsonl;
+mod maccy;
+
+pub use jsonl::read_history_jsonl;
+
+use crate::private_fs::{read_private_file, PrivateFsError, PrivateFsErrorKind};
+use copy_event_listener::event::{Data, Event, Item};
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+const TEXT_TYPE: &str = "public.utf8-plain-text";
+const FILE_URL_TYPE: &str = "public.file-url";
+
+/// One history entry read from another format.
+#[derive(Clone, Debug)]
+pub struct ImportedHistoryRow {
+    pub event: Event,
+    /// The ordering timestamp the entry was recorded with, in Unix
+    /// milliseconds.
+    pub timestamp: i64,
+    pub source_bundle_id: Option<String>,
+    pub is_remote_clipboard: bool,
+    /// The `content_hash` the format recorded, if any. Import rejects an
+    /// entry that no longer classifies to it.
+    pub content_hash: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HistoryImport {
+    /// Entries in the order they were read.
+    pub rows: Vec<ImportedHistoryRow>,
+    /// Entries the reader could not restore as they were recorded.
+    pub rejected_items: u64,
+}
+
+/// Another clipboard manager whose history can be imported.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardManager {
+    /// Maccy's Core Data store, `Storage.sqlite`.
+    Maccy,
+    /// A CopyQ tab file, `copyq_tab_*.dat`.
+    #[serde(rename = "copyq")]
+    CopyQ,
+    /// Clipy's clip data directory, or one `.data` file from it.
+    Clipy,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HistoryImportErrorKind {
+    Path(PrivateFsErrorKind),
+    /// The file is not in the expected format.
+    Rejected,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryImportError {
+    kind: HistoryImportErrorKind,
+    stage: &'static str,
+}
+
+impl HistoryImportError {
+    pub fn kind(&self) -> HistoryImportErrorKind {
+        self.kind
+    }
+
+    fn new(kind: HistoryImportErrorKind, stage: &'static str) -> Self {
+        Self { kind, stage }
+    }
+
+    fn path(error: PrivateFsError, stage: &'static str) -> Self {
+        Self::new(HistoryImportErrorKind::Path(error.kind()), stage)
+    }
+
+    fn rejected(stage: &'static str) -> Self {
+        Self::new(HistoryImportErrorKind::Rejected, stage)
+    }
+}
+
+impl fmt::Display for HistoryImportError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            HistoryImportErrorKind::Path(_) => "private path validation failed",
+            HistoryImportErrorKind::Rejected => "the file is not in the expected format",
+        };
+        write!(
+            formatter,
+            "history import failed during {}: {}",
+            self.stage, reason
+        )
+    }
+}
+
+impl std::error::Error for HistoryImportError {}
+
+/// Reads the history another clipboard manager keeps at `path`.
+pub fn read_clipboard_manager_history(
+    manager: ClipboardManager,
+    path: &Path,
+) -> Result<HistoryImport, HistoryImportError> {
+    match manager {
+        ClipboardManager::Maccy => maccy::read_maccy_history(path),
+        ClipboardManager::CopyQ => copyq::read_copyq_history(path),
+        ClipboardManager::Clipy => clipy::read_clipy_history(path),
+    }
+}
+
+/// Reads a resolved file through `read_private_file`, treating a missing
+/// file like any other unusable path.
+fn read_import_file(
+    path: &Path,
+    max_bytes: u64,
+    stage: &'static str,
+) -> Result<Vec<u8>, HistoryImportError> {
+    read_private_file(path, max_bytes)
+        .map_err(|error| HistoryImportError::path(error, stage))?
+        .ok_or_else(|| {
+            HistoryImportError::new(
+                HistoryImportErrorKind::Path(PrivateFsErrorKind::NotFound),
+                stage,
+            )
+        })
+}
+
+/// The modification time of a file the reader just read, in Unix
+/// milliseconds. Formats that keep no per-entry time are ordered from it.
+fn modified_millis(path: &Path, stage: &'static str) -> Result<i64, HistoryImportError> {
+    let modified = std::fs::symlink_metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|error| {
+            HistoryImportError::new(
+                HistoryImportErrorKind::Path(PrivateFsErrorKind::Io(error.kind())),
+                stage,
+            )
+        })?;
+    Ok(modified.duration_since(UNIX_EPOCH).map_or(0, |duration| {
+        i64::try_from(duration.as_millis()).unwrap_or(i64::MAX)
+    }))
+}
+
+/// Lays flavors out the way the pasteboard delivers a copy: every flavor on
+/// the first item, and one item per file after the first, so a multi-file
+/// copy classifies like a captured one.
+fn pasteboard_items(flavors: Vec<Data>, file_urls: Vec<Vec<u8>>) -> Vec<Item> {
+    let mut file_urls = file_urls.into_iter();
+    let mut first = flavors;
+    if let Some(file_url) = file_urls.next() {
+        first.push(Data {
+            r#type: FILE_URL_TYPE.to_string(),
+            data: file_url,
+        });
+    }
+    if first.is_empty() {
+        return Vec::new();
+    }
+
+    let mut items = vec![Item { data_list: first }];
+    items.extend(file_urls.map(|file_url| Item {
+        data_list: vec![Data {
+            r#type: FILE_URL_TYPE.to_string(),
+            data: file_url,
+        }],
+    }));
+    items
+}
+
+/// A `file://` URL for an absolute path, percent-encoding everything outside
+/// the unreserved set and `/`.
+fn file_url_from_path(path: &str) -> Option<Vec<u8>> {
+    if !path.starts_with('/') {
+        return None;
+    }
+    let mut url = b"file://".to_vec();
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'.' | b'_' | b'~') {
+            url.push(byte);
+        } else {
+            url.extend_from_slice(format!("%{byte:02X}").as_bytes());
+        }
+    }
+    Some(url)
+}
+
+#[cfg(all(test, unix))]
+struct TestDirectory {
+    path: std::path::PathBuf,
+}
+
+#[cfg(all(test, unix))]
+impl TestDirectory {
+    fn new(label: &str) -> Self {
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        static NEXT_TEST_DIR: AtomicU64 = AtomicU64::new(1);
+
+        let sequence = NEXT_TEST_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "copy-stack-history-import-test-{}-{}-{}",
+            std::process::id(),
+            sequence,
+            label
+        ));
+        std::fs::create_dir(&path).expect("test directory should be created");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+            .expect("test directory should be private");
+        Self { path }
+    }
+
+    /// Writes a `0600` file into the directory.
+    fn write(&self, name: &str, contents: &[u8]) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = self.path.join(name);
+        std::fs::write(&path, contents).expect("test file should be written");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .expect("test file should be private");
+        path
+    }
+}
+
+#[cfg(all(test, unix))]
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_after_the_first_get_their_own_pasteboard_items() {
+        let items = pasteboard_items(
+            vec![Data {
+                r#type: TEXT_TYPE.to_string(),
+                data: b"a b".to_vec(),
+            }],
+            vec![b"file:///a".to_vec(), b"file:///b".to_vec()],
+        );
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].data_list.len(), 2);
+        assert_eq!(items[0].data_list[1].r#type, FILE_URL_TYPE);
+        assert_eq!(items[1].data_list[0].data, b"file:///b");
+        assert!(pasteboard_items(Vec::new(), Vec::new()).is_empty());
+
+        assert_eq!(
+            file_url_from_path("/Users/me/Q1 report#2.pdf").as_deref(),
+            Some(&b"file:///Users/me/Q1%20report%232.pdf"[..])
+        );
+        assert_eq!(file_url_from_path("relative"), None);
+    }
+}
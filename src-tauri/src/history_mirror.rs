@@ -6,13 +6,12 @@
 //! replacement outside the application's database mutex. Owned-row scheduling
 //! remains available for isolated tests.
 
-use crate::event::decode_event_blob;
+use crate::event::EventBlobIndex;
 use crate::private_fs::{
     create_private_temp_file, harden_private_file_if_exists, prepare_private_output_path,
     resolve_private_path, PrivateFsError, PrivateFsErrorKind,
 };
-use crate::store::Database;
-use copy_event_listener::event::Event;
+use crate::store::{Database, PayloadCipher};
 use serde::Serialize;
 use std::fmt;
 use std::io::{BufWriter, Write};
@@ -37,6 +36,9 @@ pub struct HistorySnapshotRow {
     pub timestamp: i64,
     pub source_bundle_id: Option<String>,
     pub is_remote_clipboard: bool,
+    /// Collection and tag names the row belongs to, sorted by name.
+    pub collections: Vec<String>,
+    pub tags: Vec<String>,
 }
 
 #[cfg(test)]
@@ -56,6 +58,8 @@ impl HistorySnapshotRow {
             timestamp,
             source_bundle_id: None,
             is_remote_clipboard: false,
+            collections: Vec::new(),
+            tags: Vec::new(),
         }
     }
 
@@ -68,6 +72,12 @@ impl HistorySnapshotRow {
         self.is_remote_clipboard = is_remote_clipboard;
         self
     }
+
+    pub fn with_labels(mut self, collections: &[&str], tags: &[&str]) -> Self {
+        self.collections = collections.iter().map(|name| name.to_string()).collect();
+        self.tags = tags.iter().map(|name| name.to_string()).collect();
+        self
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -196,6 +206,7 @@ impl HistoryMirror {
     pub fn start_database(
         config: HistoryMirrorConfig,
         database_path: PathBuf,
+        cipher: PayloadCipher,
     ) -> Result<Self, HistoryMirrorError> {
         let database_path = resolve_private_path(&database_path)
             .map_err(|error| HistoryMirrorError::path(error, "resolve database source"))?;
@@ -203,7 +214,7 @@ impl HistoryMirror {
             .map_err(|error| HistoryMirrorError::path(error, "validate database source"))?;
         Self::start_with_source(
             config,
-            SnapshotSource::Database(database_path),
+            SnapshotSource::Database(database_path, cipher),
             Arc::new(NoopHook),
         )
     }
@@ -225,7 +236,7 @@ impl HistoryMirror {
 
     /// Requests a refresh from the worker-owned database connection.
     pub fn schedule_refresh(&self) -> Result<u64, HistoryMirrorError> {
-        if !matches!(&self.shared.source, SnapshotSource::Database(_)) {
+        if !matches!(&self.shared.source, SnapshotSource::Database(..)) {
             return Err(HistoryMirrorError::new(
                 HistoryMirrorErrorKind::SnapshotRead,
                 "schedule database refresh for owned-row mirror",
@@ -393,7 +404,7 @@ impl HistoryMirror {
     ) -> Result<Self, HistoryMirrorError> {
         config.path = prepare_private_output_path(&config.path)
             .map_err(|error| HistoryMirrorError::path(error, "start path validation"))?;
-        if matches!(&source, SnapshotSource::Database(path) if path == &config.path) {
+        if matches!(&source, SnapshotSource::Database(path, _) if path == &config.path) {
             return Err(HistoryMirrorError::new(
                 HistoryMirrorErrorKind::SnapshotRead,
                 "reject database as mirror destination",
@@ -468,7 +479,9 @@ struct Shared {
 enum SnapshotSource {
     #[cfg(test)]
     ScheduledRows,
-    Database(PathBuf),
+    /// Reads the database at the path, opening its sealed payloads with the
+    /// cipher.
+    Database(PathBuf, PayloadCipher),
 }
 
 #[derive(Default)]
@@ -607,9 +620,9 @@ fn write_snapshot_inner(
             }
         } else {
             match &shared.source {
-                SnapshotSource::Database(path) => {
+                SnapshotSource::Database(path, cipher) => {
                     let mut write_error = None;
-                    Database::visit_history_snapshot_rows_from_path(path, |row| {
+                    Database::visit_history_snapshot_rows_from_path(path, cipher, |row| {
                         match write_jsonl_row(&mut writer, &row, shared.config.max_data_bytes) {
                             Ok(()) => true,
                             Err(error) => {
@@ -668,13 +681,14 @@ fn write_jsonl_row<W: Write>(
     row: &HistorySnapshotRow,
     max_data_bytes: usize,
 ) -> Result<(), HistoryMirrorError> {
-    let event = decode_event_blob(&row.event_data).map_err(|_| {
-        HistoryMirrorError::new(
-            HistoryMirrorErrorKind::EventDecode,
-            "decode persisted event",
-        )
-    })?;
-    let record = history_jsonl_record(row, &event, max_data_bytes);
+    let record = EventBlobIndex::parse(&row.event_data)
+        .and_then(|index| history_jsonl_record(row, &index, max_data_bytes))
+        .map_err(|_| {
+            HistoryMirrorError::new(
+                HistoryMirrorErrorKind::EventDecode,
+                "decode persisted event",
+            )
+        })?;
     serde_json::to_writer(&mut *writer, &record).map_err(|_| {
         HistoryMirrorError::new(HistoryMirrorErrorKind::Serialize, "serialize JSONL row")
     })?;
@@ -748,6 +762,10 @@ struct HistoryJsonlRecord<'a> {
     source_bundle_id: Option<&'a str>,
     #[serde(skip_serializing_if = "is_false")]
     is_remote_clipboard: bool,
+    #[serde(skip_serializing_if = "is_empty")]
+    collections: &'a [String],
+    #[serde(skip_serializing_if = "is_empty")]
+    tags: &'a [String],
 }
 
 #[derive(Serialize)]
@@ -812,41 +830,46 @@ impl HistoryJsonlBytes {
     }
 }
 
+/// Decodes one flavor at a time, so a row's large images are never held
+/// decoded together, and stored flavors are only borrowed from the blob.
 fn history_jsonl_record<'a>(
     row: &'a HistorySnapshotRow,
-    event: &Event,
+    event: &EventBlobIndex<'_>,
     max_data_bytes: usize,
-) -> HistoryJsonlRecord<'a> {
-    HistoryJsonlRecord {
+) -> Result<HistoryJsonlRecord<'a>, String> {
+    let mut items = (0..event.item_count())
+        .map(|_| HistoryJsonlItem {
+            data_list: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for flavor in event.flavors() {
+        items[flavor.item()].data_list.push(HistoryJsonlData {
+            data_type: flavor.data_type().to_string(),
+            data: HistoryJsonlBytes::new(&flavor.decode()?, max_data_bytes),
+        });
+    }
+
+    Ok(HistoryJsonlRecord {
         content_hash: &row.content_hash,
         data_type: &row.data_type,
         timestamp: row.timestamp,
         display: HistoryJsonlBytes::new(&row.display, max_data_bytes),
-        event_data: HistoryJsonlEvent {
-            items: event
-                .items
-                .iter()
-                .map(|item| HistoryJsonlItem {
-                    data_list: item
-                        .data_list
-                        .iter()
-                        .map(|data| HistoryJsonlData {
-                            data_type: data.r#type.clone(),
-                            data: HistoryJsonlBytes::new(&data.data, max_data_bytes),
-                        })
-                        .collect(),
-                })
-                .collect(),
-        },
+        event_data: HistoryJsonlEvent { items },
         source_bundle_id: row.source_bundle_id.as_deref(),
         is_remote_clipboard: row.is_remote_clipboard,
-    }
+        collections: &row.collections,
+        tags: &row.tags,
+    })
 }
 
 fn is_false(value: &bool) -> bool {
     !*value
 }
 
+fn is_empty(values: &&[String]) -> bool {
+    values.is_empty()
+}
+
 fn hex_bytes(bytes: &[u8]) -> String {
     const HEX: &[u8; 16] = b"0123456789abcdef";
     let mut output = String::with_capacity(bytes.len() * 2);
@@ -861,7 +884,7 @@ fn hex_bytes(bytes: &[u8]) -> String {
 mod tests {
     use super::*;
     use crate::event::encode_event_blob;
-    use copy_event_listener::event::{Data, Item};
+    use copy_event_listener::event::{Data, Event, Item};
     use serde_json::Value;
     use std::fs::Permissions;
     use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
@@ -925,6 +948,7 @@ mod tests {
     fn start_database_with<F>(
         config: HistoryMirrorConfig,
         database_path: PathBuf,
+        cipher: PayloadCipher,
         hook: F,
     ) -> Result<HistoryMirror, HistoryMirrorError>
     where
@@ -936,7 +960,7 @@ mod tests {
             .map_err(|error| HistoryMirrorError::path(error, "validate test database source"))?;
         HistoryMirror::start_with_source(
             config,
-            SnapshotSource::Database(database_path),
+            SnapshotSource::Database(database_path, cipher),
             Arc::new(ClosureHook(hook)),
         )
     }
@@ -1012,7 +1036,8 @@ mod tests {
             "éclair".as_bytes(),
             20,
         )
-        .with_pasteboard_metadata(Some("com.example.source".to_string()), true);
+        .with_pasteboard_metadata(Some("com.example.source".to_string()), true)
+        .with_labels(&["Snippets"], &["french", "pastry"]);
 
         mirror
             .schedule(vec![binary, utf8])
@@ -1030,10 +1055,14 @@ mod tests {
         assert_eq!(values[0]["display"]["value"], "écl");
         assert_eq!(values[0]["source_bundle_id"], "com.example.source");
         assert_eq!(values[0]["is_remote_clipboard"], true);
+        assert_eq!(values[0]["collections"], serde_json::json!(["Snippets"]));
+        assert_eq!(values[0]["tags"], serde_json::json!(["french", "pastry"]));
         assert_eq!(values[1]["display"]["encoding"], "hex");
         assert_eq!(values[1]["display"]["value"], "000102ff");
         assert!(values[1].get("source_bundle_id").is_none());
         assert!(values[1].get("is_remote_clipboard").is_none());
+        assert!(values[1].get("collections").is_none());
+        assert!(values[1].get("tags").is_none());
         assert_eq!(
             std::fs::symlink_metadata(&output)
                 .expect("output metadata should exist")
@@ -1216,6 +1245,7 @@ mod tests {
         let mirror = start_database_with(
             HistoryMirrorConfig::new(output.clone(), 4_096).with_debounce(Duration::ZERO),
             database_path,
+            db.payload_cipher(),
             move |stage, generation| {
                 if stage == WriteStage::AfterSync && generation == 1 {
                     if let Some(sender) = lock_recover(&entered_tx).take() {
@@ -1318,6 +1348,7 @@ mod tests {
         let mirror = HistoryMirror::start_database(
             HistoryMirrorConfig::new(output.clone(), 4_096).with_debounce(Duration::ZERO),
             database_path,
+            db.payload_cipher(),
         )
         .expect("database mirror should start");
         mirror
@@ -87,6 +87,7 @@ impl LanguagePreference {
 
 pub(crate) struct NativeStrings {
     pub(crate) settings_ellipsis: &'static str,
+    pub(crate) pinned_clipboard_items: &'static str,
     pub(crate) recent_clipboard_items: &'static str,
     pub(crate) no_clipboard_items: &'static str,
     pub(crate) open_history: &'static str,
@@ -121,6 +122,7 @@ pub(crate) struct NativeStrings {
 
 const ENGLISH_STRINGS: NativeStrings = NativeStrings {
     settings_ellipsis: "Settings…",
+    pinned_clipboard_items: "Pinned items",
     recent_clipboard_items: "Recent clipboard items",
     no_clipboard_items: "No clipboard items yet",
     open_history: "Open history",
@@ -155,6 +157,7 @@ const ENGLISH_STRINGS: NativeStrings = NativeStrings {
 
 const SIMPLIFIED_CHINESE_STRINGS: NativeStrings = NativeStrings {
     settings_ellipsis: "设置…",
+    pinned_clipboard_items: "已固定的项目",
     recent_clipboard_items: "最近的剪贴板项目",
     no_clipboard_items: "暂无剪贴板项目",
     open_history: "打开历史记录",
@@ -189,6 +192,7 @@ const SIMPLIFIED_CHINESE_STRINGS: NativeStrings = NativeStrings {
 
 const TRADITIONAL_CHINESE_STRINGS: NativeStrings = NativeStrings {
     settings_ellipsis: "設定…",
+    pinned_clipboard_items: "已釘選的項目",
     recent_clipboard_items: "最近的剪貼簿項目",
     no_clipboard_items: "尚無剪貼簿項目",
     open_history: "開啟歷史記錄",
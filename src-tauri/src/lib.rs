@@ -17,34 +17,59 @@ macro_rules! debug_error {
     };
 }
 
+mod cli;
 mod command_error;
+mod control_socket;
 pub mod event;
+mod history_backup;
+mod history_export;
+mod history_import;
 mod history_mirror;
 mod i18n;
 mod lifecycle;
 mod pasteboard_protocol;
 mod private_fs;
 mod resource_policy;
+mod secret_detection;
 mod startup;
 mod store;
 mod tray;
 #[cfg(target_os = "macos")]
 mod tray_preview;
 
+pub use cli::run_cli;
 pub use startup::StartupOptions;
 
 use crate::command_error::{
     CommandError, CommandResult, DiagnosticLog, ErrorCode, Operation, SafeDiagnostic,
 };
+use crate::control_socket::{ControlCommand, ControlSocket};
+use crate::history_backup::{
+    read_history_backup, write_history_backup, HistoryBackupError, HistoryBackupErrorKind,
+};
+use crate::history_export::{
+    write_history_export, HistoryExportError, HistoryExportErrorKind, HistoryExportFormat,
+};
+use crate::history_import::{
+    read_clipboard_manager_history, read_history_jsonl, ClipboardManager, HistoryImport,
+    HistoryImportError, HistoryImportErrorKind,
+};
 use crate::history_mirror::{HistoryMirror, HistoryMirrorConfig};
 use crate::i18n::{native_strings, Language, LanguagePreference};
-use crate::lifecycle::AutostartBackend;
-use crate::pasteboard_protocol::{assess_event, prepare_event_for_restore};
-use crate::resource_policy::prepare_capture_event;
-use crate::store::{AppSettings, Database, HistoryDetail, HistoryPage, MAX_MENU_BAR_ITEM_LIMIT};
+use crate::lifecycle::{AutostartBackend, RemoteActionTarget};
+use crate::pasteboard_protocol::{prepare_event_for_restore, SkipReason};
+use crate::secret_detection::{SecretAction, SecretKind};
+use crate::store::{
+    is_known_data_type, AppSettings, BlobFileReport, CaptureOutcome, Database, DatabaseBackup,
+    HistoryBackupReport, HistoryCheckReport, HistoryCollection, HistoryDetail, HistoryExportReport,
+    HistoryImportReport, HistoryIntegrityReport, HistoryLabelFilter, HistoryLabels, HistoryPage,
+    HistoryQuery, HistorySearchPage, HistoryTag, LabelRejection, LabelResult, PinOutcome,
+    SourceAppRule, MAX_MENU_BAR_ITEM_LIMIT,
+};
 use copy_event_listener::clipboard::ClipboardListener;
 use copy_event_listener::event::Event;
 use serde::Serialize;
+use std::path::Path;
 use std::sync::{mpsc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::menu::{
@@ -57,6 +82,9 @@ use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 const RESTORE_SUPPRESSION_TTL: Duration = Duration::from_secs(5);
 const HISTORY_MIRROR_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 const CAPTURE_TRAY_REFRESH_DEBOUNCE: Duration = Duration::from_millis(100);
+const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const RETENTION_SWEEP_MIN_WAIT: Duration = Duration::from_secs(1);
+const MAX_AGE_DAYS_LIMIT: u32 = 3_650;
 const OPEN_APP_SETTINGS_ID: &str = "app-menu::open-settings";
 pub(crate) const CAPTURE_REJECTED_EVENT: &str = "capture-rejected";
 pub(crate) const APP_OPERATION_ERROR_EVENT: &str = "app-operation-error";
@@ -67,6 +95,8 @@ pub struct AppState {
     pub(crate) pending_restore_suppression: Mutex<Option<PendingRestoreSuppression>>,
     pub(crate) history_mirror: Option<HistoryMirror>,
     tray_refresh: Option<TrayRefreshScheduler>,
+    retention_sweep: Option<RetentionSweeper>,
+    control_socket: Option<ControlSocket>,
     diagnostics: DiagnosticLog,
 }
 
@@ -83,7 +113,8 @@ pub(crate) struct PendingRestoreSuppression {
 #[derive(Clone, Copy, Debug, Serialize)]
 struct CaptureRejectedNotice {
     code: &'static str,
-    size_bucket: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bucket: Option<&'static str>,
 }
 
 enum TrayRefreshMessage {
@@ -150,6 +181,105 @@ fn run_tray_refresh_worker(
     }
 }
 
+enum RetentionSweepMessage {
+    Reschedule,
+    Shutdown,
+}
+
+/// Applies the retention limits on a timer so age-based expiry still happens
+/// while nothing is being copied.
+struct RetentionSweeper {
+    sender: mpsc::Sender<RetentionSweepMessage>,
+}
+
+impl RetentionSweeper {
+    fn start(app: AppHandle) -> Result<Self, &'static str> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("copy-stack-retention-sweep".to_string())
+            .spawn(move || {
+                run_retention_sweep_worker(receiver, RETENTION_SWEEP_INTERVAL, || {
+                    sweep_expired_history(&app)
+                });
+            })
+            .map_err(|_| "RETENTION_SWEEP_THREAD_START_FAILED")?;
+        Ok(Self { sender })
+    }
+
+    /// Sweeps now and re-arms the timer, so a newly stored auto-expiring row
+    /// is deleted on time rather than at the next hourly sweep.
+    fn reschedule(&self) {
+        let _ = self.sender.send(RetentionSweepMessage::Reschedule);
+    }
+
+    fn shutdown(&self) {
+        let _ = self.sender.send(RetentionSweepMessage::Shutdown);
+    }
+}
+
+/// Calls `sweep` every `interval`, or sooner when it reports an earlier
+/// auto-expiry.
+fn run_retention_sweep_worker(
+    receiver: mpsc::Receiver<RetentionSweepMessage>,
+    interval: Duration,
+    mut sweep: impl FnMut() -> Option<Duration>,
+) {
+    let mut wait = interval;
+    loop {
+        match receiver.recv_timeout(wait) {
+            Ok(RetentionSweepMessage::Reschedule) | Err(mpsc::RecvTimeoutError::Timeout) => {
+                wait = sweep().map_or(interval, |due| due.min(interval));
+            }
+            Ok(RetentionSweepMessage::Shutdown) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return
+            }
+        }
+    }
+}
+
+/// Returns how long until the next auto-expiring row is due.
+fn sweep_expired_history<R: Runtime>(app: &AppHandle<R>) -> Option<Duration> {
+    let state = app.try_state::<AppState>()?;
+    let (deleted, next_expiry_at) = match state.db.lock() {
+        Ok(db) => (db.cleanup_old_events(), db.next_expiry_at()),
+        Err(_) => {
+            report_retention_sweep_failure(app, &state, ErrorCode::DatabaseUnavailable);
+            return None;
+        }
+    };
+    match deleted {
+        Ok(0) => {}
+        Ok(_) => {
+            if let Some(mirror) = state.history_mirror.as_ref() {
+                if mirror.schedule_refresh().is_err() {
+                    report_retention_sweep_failure(app, &state, ErrorCode::HistoryMirrorFailed);
+                }
+            }
+            if tray::sync(app).is_err() || tray::notify_history_changed(app).is_err() {
+                report_retention_sweep_failure(app, &state, ErrorCode::StateUnavailable);
+            }
+        }
+        Err(_) => {
+            report_retention_sweep_failure(app, &state, ErrorCode::DatabaseOperationFailed);
+        }
+    }
+
+    // The floor keeps a row that failed to delete from spinning the worker.
+    let next_expiry_at = next_expiry_at.ok().flatten()?;
+    let remaining = next_expiry_at.saturating_sub(chrono::Utc::now().timestamp_millis());
+    Some(Duration::from_millis(u64::try_from(remaining).unwrap_or(0)).max(RETENTION_SWEEP_MIN_WAIT))
+}
+
+fn report_retention_sweep_failure<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &AppState,
+    code: ErrorCode,
+) {
+    let error = CommandError::new(code, Operation::ExpireHistory, true);
+    let _ = state.diagnostics.record(&error);
+    let _ = app.emit(APP_OPERATION_ERROR_EVENT, &error);
+}
+
 fn record_command_error(state: &AppState, error: CommandError) -> CommandError {
     let _ = state.diagnostics.record(&error);
     error
@@ -167,6 +297,30 @@ fn publish_startup_error<R: Runtime>(app: &AppHandle<R>, error: CommandError) {
     let _ = app.emit(APP_OPERATION_ERROR_EVENT, &error);
 }
 
+/// Describes what startup reconciliation found in the flavor blob directory,
+/// as content-free diagnostics: orphaned files were removed, and rows whose
+/// file was missing were quarantined.
+fn blob_file_diagnostics(report: &BlobFileReport) -> Vec<CommandError> {
+    let mut diagnostics = Vec::new();
+    if report.orphaned_files > 0 {
+        diagnostics.push(
+            CommandError::new(
+                ErrorCode::DatabaseOperationFailed,
+                Operation::Startup,
+                false,
+            )
+            .with_detail("blob_files.orphaned"),
+        );
+    }
+    if report.missing_files > 0 {
+        diagnostics.push(
+            CommandError::new(ErrorCode::HistoryIntegrityFailed, Operation::Startup, false)
+                .with_detail("blob_files.missing"),
+        );
+    }
+    diagnostics
+}
+
 pub(crate) fn report_restore_post_processing_failure<R: Runtime>(
     app: &AppHandle<R>,
     state: &AppState,
@@ -204,6 +358,70 @@ fn database_error(state: &AppState, operation: Operation) -> CommandError {
     record_command_error(state, CommandError::database(operation))
 }
 
+/// A stored row failed its MAC or chain check; its content is not used.
+fn integrity_error(state: &AppState, operation: Operation) -> CommandError {
+    record_command_error(
+        state,
+        CommandError::new(ErrorCode::HistoryIntegrityFailed, operation, false),
+    )
+}
+
+/// Maps a failed archive read or write to a code; the path and passphrase are
+/// never part of the error.
+fn backup_error(
+    state: &AppState,
+    operation: Operation,
+    error: &HistoryBackupError,
+) -> CommandError {
+    let error = match error.kind() {
+        HistoryBackupErrorKind::Passphrase => {
+            CommandError::new(ErrorCode::InvalidBackupPassphrase, operation, false)
+        }
+        HistoryBackupErrorKind::Rejected | HistoryBackupErrorKind::UnsupportedVersion => {
+            CommandError::new(ErrorCode::InvalidBackup, operation, false)
+        }
+        HistoryBackupErrorKind::Path(_) => {
+            CommandError::new(ErrorCode::BackupFileUnavailable, operation, false)
+        }
+        HistoryBackupErrorKind::Write | HistoryBackupErrorKind::Randomness => {
+            CommandError::new(ErrorCode::BackupFileUnavailable, operation, true)
+        }
+    };
+    record_command_error(state, error)
+}
+
+fn history_import_error(error: &HistoryImportError) -> CommandError {
+    match error.kind() {
+        HistoryImportErrorKind::Rejected => CommandError::new(
+            ErrorCode::InvalidHistoryImport,
+            Operation::ImportHistory,
+            false,
+        ),
+        HistoryImportErrorKind::Path(_) => CommandError::new(
+            ErrorCode::HistoryImportFileUnavailable,
+            Operation::ImportHistory,
+            false,
+        ),
+    }
+}
+
+fn history_export_error(state: &AppState, error: &HistoryExportError) -> CommandError {
+    let error = match error.kind() {
+        HistoryExportErrorKind::Path(_) => CommandError::new(
+            ErrorCode::HistoryExportFileUnavailable,
+            Operation::ExportHistory,
+            false,
+        ),
+        HistoryExportErrorKind::Write => CommandError::new(
+            ErrorCode::HistoryExportFileUnavailable,
+            Operation::ExportHistory,
+            true,
+        ),
+        HistoryExportErrorKind::Build => CommandError::database(Operation::ExportHistory),
+    };
+    record_command_error(state, error)
+}
+
 fn state_error(state: &AppState, operation: Operation) -> CommandError {
     record_command_error(state, CommandError::state(operation))
 }
@@ -216,8 +434,15 @@ fn database_unavailable(state: &AppState, operation: Operation) -> CommandError
 }
 
 fn history_cursor_is_valid(cursor: &str) -> bool {
-    let mut parts = cursor.splitn(3, ':');
-    parts.next() == Some("v1")
+    let mut parts = cursor.splitn(4, ':');
+    let position_is_valid = match parts.next() {
+        Some("v2") => parts
+            .next()
+            .is_some_and(|pinned| matches!(pinned, "0" | "1")),
+        Some("v1") => true,
+        _ => false,
+    };
+    position_is_valid
         && parts
             .next()
             .is_some_and(|timestamp| timestamp.parse::<i64>().is_ok())
@@ -227,6 +452,7 @@ fn history_cursor_is_valid(cursor: &str) -> bool {
                     .bytes()
                     .all(|byte| byte.is_ascii_hexdigit() && !byte.is_ascii_uppercase())
         })
+        && parts.next().is_none()
 }
 
 fn schedule_history_mirror(state: &AppState) -> CommandResult<()> {
@@ -254,6 +480,8 @@ fn get_copy_events_page(
     state: State<'_, AppState>,
     cursor: Option<String>,
     page_size: Option<usize>,
+    collection_id: Option<i64>,
+    tag: Option<String>,
 ) -> CommandResult<HistoryPage> {
     if cursor
         .as_deref()
@@ -272,8 +500,50 @@ fn get_copy_events_page(
         .db
         .lock()
         .map_err(|_| database_unavailable(&state, Operation::LoadHistory))?;
-    db.get_history_page(cursor.as_deref(), page_size)
-        .map_err(|_| database_error(&state, Operation::LoadHistory))
+    db.get_filtered_history_page(
+        cursor.as_deref(),
+        page_size,
+        &HistoryLabelFilter { collection_id, tag },
+    )
+    .map_err(|_| database_error(&state, Operation::LoadHistory))
+}
+
+#[tauri::command]
+fn search_copy_events(
+    state: State<'_, AppState>,
+    query: String,
+    cursor: Option<String>,
+    page_size: Option<usize>,
+) -> CommandResult<HistorySearchPage> {
+    if cursor
+        .as_deref()
+        .is_some_and(|cursor| !history_cursor_is_valid(cursor))
+    {
+        return Err(record_command_error(
+            &state,
+            CommandError::new(
+                ErrorCode::InvalidHistoryCursor,
+                Operation::SearchHistory,
+                false,
+            ),
+        ));
+    }
+    let query = HistoryQuery::parse(&query).map_err(|_| {
+        record_command_error(
+            &state,
+            CommandError::new(
+                ErrorCode::InvalidSearchQuery,
+                Operation::SearchHistory,
+                false,
+            ),
+        )
+    })?;
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::SearchHistory))?;
+    db.search_history_page(&query, cursor.as_deref(), page_size)
+        .map_err(|_| database_error(&state, Operation::SearchHistory))
 }
 
 #[tauri::command]
@@ -289,6 +559,7 @@ fn get_history_detail(
         let seed = db
             .get_history_detail_seed(&content_hash)
             .map_err(|_| database_error(&state, Operation::LoadHistoryDetail))?
+            .map_err(|_| integrity_error(&state, Operation::LoadHistoryDetail))?
             .ok_or_else(|| {
                 record_command_error(
                     &state,
@@ -327,6 +598,259 @@ fn delete_copy_event(
     tray::sync(&app).map_err(|_| state_error(&state, Operation::DeleteHistory))
 }
 
+#[tauri::command]
+fn pin_copy_event(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    content_hash: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::PinHistory))?;
+        db.pin_event(&content_hash)
+            .map_err(|_| database_error(&state, Operation::PinHistory))?
+    };
+    pin_outcome_result(&state, outcome, Operation::PinHistory)?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::PinHistory))
+}
+
+#[tauri::command]
+fn unpin_copy_event(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    content_hash: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UnpinHistory))?;
+        db.unpin_event(&content_hash)
+            .map_err(|_| database_error(&state, Operation::UnpinHistory))?
+    };
+    pin_outcome_result(&state, outcome, Operation::UnpinHistory)?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::UnpinHistory))
+}
+
+fn pin_outcome_result(
+    state: &AppState,
+    outcome: PinOutcome,
+    operation: Operation,
+) -> CommandResult<()> {
+    let code = match outcome {
+        PinOutcome::Updated => return Ok(()),
+        PinOutcome::NotFound => ErrorCode::HistoryItemNotFound,
+        PinOutcome::LimitReached => ErrorCode::PinnedLimitReached,
+    };
+    Err(record_command_error(
+        state,
+        CommandError::new(code, operation, false),
+    ))
+}
+
+#[tauri::command]
+fn list_collections(state: State<'_, AppState>) -> CommandResult<Vec<HistoryCollection>> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::LoadLabels))?;
+    db.list_collections()
+        .map_err(|_| database_error(&state, Operation::LoadLabels))
+}
+
+#[tauri::command]
+fn create_collection(state: State<'_, AppState>, name: String) -> CommandResult<HistoryCollection> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateCollections))?;
+        db.create_collection(&name)
+            .map_err(|_| database_error(&state, Operation::UpdateCollections))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateCollections)
+}
+
+#[tauri::command]
+fn rename_collection(
+    state: State<'_, AppState>,
+    collection_id: i64,
+    name: String,
+) -> CommandResult<HistoryCollection> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateCollections))?;
+        db.rename_collection(collection_id, &name)
+            .map_err(|_| database_error(&state, Operation::UpdateCollections))?
+    };
+    let collection = label_outcome_result(&state, outcome, Operation::UpdateCollections)?;
+    schedule_history_mirror(&state)?;
+    Ok(collection)
+}
+
+#[tauri::command]
+fn delete_collection(state: State<'_, AppState>, collection_id: i64) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateCollections))?;
+        db.delete_collection(collection_id)
+            .map_err(|_| database_error(&state, Operation::UpdateCollections))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateCollections)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn add_copy_event_to_collection(
+    state: State<'_, AppState>,
+    collection_id: i64,
+    content_hash: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateCollections))?;
+        db.add_to_collection(collection_id, &content_hash)
+            .map_err(|_| database_error(&state, Operation::UpdateCollections))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateCollections)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn remove_copy_event_from_collection(
+    state: State<'_, AppState>,
+    collection_id: i64,
+    content_hash: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateCollections))?;
+        db.remove_from_collection(collection_id, &content_hash)
+            .map_err(|_| database_error(&state, Operation::UpdateCollections))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateCollections)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn list_tags(state: State<'_, AppState>) -> CommandResult<Vec<HistoryTag>> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::LoadLabels))?;
+    db.list_tags()
+        .map_err(|_| database_error(&state, Operation::LoadLabels))
+}
+
+#[tauri::command]
+fn tag_copy_event(
+    state: State<'_, AppState>,
+    content_hash: String,
+    tag: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateTags))?;
+        db.tag_event(&content_hash, &tag)
+            .map_err(|_| database_error(&state, Operation::UpdateTags))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateTags)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn untag_copy_event(
+    state: State<'_, AppState>,
+    content_hash: String,
+    tag: String,
+) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateTags))?;
+        db.untag_event(&content_hash, &tag)
+            .map_err(|_| database_error(&state, Operation::UpdateTags))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateTags)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn rename_tag(state: State<'_, AppState>, tag: String, name: String) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateTags))?;
+        db.rename_tag(&tag, &name)
+            .map_err(|_| database_error(&state, Operation::UpdateTags))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateTags)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn delete_tag(state: State<'_, AppState>, tag: String) -> CommandResult<()> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateTags))?;
+        db.delete_tag(&tag)
+            .map_err(|_| database_error(&state, Operation::UpdateTags))?
+    };
+    label_outcome_result(&state, outcome, Operation::UpdateTags)?;
+    schedule_history_mirror(&state)
+}
+
+#[tauri::command]
+fn get_copy_event_labels(
+    state: State<'_, AppState>,
+    content_hash: String,
+) -> CommandResult<HistoryLabels> {
+    let outcome = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::LoadLabels))?;
+        db.get_event_labels(&content_hash)
+            .map_err(|_| database_error(&state, Operation::LoadLabels))?
+    };
+    label_outcome_result(&state, outcome, Operation::LoadLabels)
+}
+
+fn label_outcome_result<T>(
+    state: &AppState,
+    outcome: LabelResult<T>,
+    operation: Operation,
+) -> CommandResult<T> {
+    let code = match outcome {
+        Ok(value) => return Ok(value),
+        Err(LabelRejection::InvalidName) => ErrorCode::InvalidLabelName,
+        Err(LabelRejection::NameTaken) => ErrorCode::LabelNameTaken,
+        Err(LabelRejection::LabelNotFound) => ErrorCode::LabelNotFound,
+        Err(LabelRejection::ItemNotFound) => ErrorCode::HistoryItemNotFound,
+        Err(LabelRejection::LimitReached) => ErrorCode::LabelLimitReached,
+    };
+    Err(record_command_error(
+        state,
+        CommandError::new(code, operation, false),
+    ))
+}
+
 #[tauri::command]
 fn clear_all_events(app: AppHandle, state: State<'_, AppState>) -> CommandResult<()> {
     {
@@ -338,7 +862,260 @@ fn clear_all_events(app: AppHandle, state: State<'_, AppState>) -> CommandResult
             .map_err(|_| database_error(&state, Operation::ClearHistory))?;
     }
     schedule_history_mirror(&state)?;
-    tray::sync(&app).map_err(|_| state_error(&state, Operation::ClearHistory))
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::ClearHistory))
+}
+
+#[tauri::command]
+fn verify_history_integrity(state: State<'_, AppState>) -> CommandResult<HistoryIntegrityReport> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::VerifyHistory))?;
+    db.verify_history_integrity()
+        .map_err(|_| database_error(&state, Operation::VerifyHistory))?
+        .map_err(|_| integrity_error(&state, Operation::VerifyHistory))
+}
+
+/// Moves rows that no longer open, decode, or match their hash out of
+/// history. A database SQLite itself reports as damaged is left untouched.
+#[tauri::command]
+fn check_history_integrity(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CommandResult<HistoryCheckReport> {
+    let report = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::CheckHistory))?;
+        db.check_history_integrity()
+            .map_err(|_| database_error(&state, Operation::CheckHistory))?
+            .map_err(|_| {
+                record_command_error(
+                    &state,
+                    CommandError::new(ErrorCode::DatabaseCorrupted, Operation::CheckHistory, false),
+                )
+            })?
+    };
+    if report.quarantined_items > 0 {
+        schedule_history_mirror(&state)?;
+        tray::sync(&app).map_err(|_| state_error(&state, Operation::CheckHistory))?;
+        tray::notify_history_changed(&app)
+            .map_err(|_| state_error(&state, Operation::CheckHistory))?;
+    }
+    Ok(report)
+}
+
+/// Writes settings and the verified history to an encrypted archive. The key
+/// derivation and file write run after the database lock is released.
+#[tauri::command]
+fn export_history_backup(
+    state: State<'_, AppState>,
+    path: String,
+    passphrase: String,
+) -> CommandResult<HistoryBackupReport> {
+    let backup = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::ExportBackup))?;
+        db.history_backup()
+            .map_err(|_| database_error(&state, Operation::ExportBackup))?
+            .map_err(|_| integrity_error(&state, Operation::ExportBackup))?
+    };
+    write_history_backup(Path::new(&path), &passphrase, &backup)
+        .map_err(|error| backup_error(&state, Operation::ExportBackup, &error))?;
+    Ok(HistoryBackupReport {
+        exported_items: backup.rows.len() as u64,
+    })
+}
+
+/// Writes the rows matching a search `filter` as a readable report. Only the
+/// seeds are copied under the database lock; decoding, image reads, and the
+/// file write run after it is released.
+#[tauri::command]
+fn export_history(
+    state: State<'_, AppState>,
+    format: HistoryExportFormat,
+    filter: String,
+    path: String,
+) -> CommandResult<HistoryExportReport> {
+    let query = HistoryQuery::parse(&filter).map_err(|_| {
+        record_command_error(
+            &state,
+            CommandError::new(
+                ErrorCode::InvalidSearchQuery,
+                Operation::ExportHistory,
+                false,
+            ),
+        )
+    })?;
+    let (seeds, compact_mode) = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::ExportHistory))?;
+        let seeds = db
+            .history_export_seeds(&query)
+            .map_err(|_| database_error(&state, Operation::ExportHistory))?
+            .map_err(|_| integrity_error(&state, Operation::ExportHistory))?;
+        let compact_mode = db
+            .get_compact_mode()
+            .map_err(|_| database_error(&state, Operation::ExportHistory))?;
+        (seeds, compact_mode)
+    };
+    let exported_items = write_history_export(Path::new(&path), format, seeds, compact_mode)
+        .map_err(|error| history_export_error(&state, &error))?;
+    Ok(HistoryExportReport { exported_items })
+}
+
+/// Restores an archive's settings and merges its rows into history. The
+/// archive is opened and parsed before the database lock is taken.
+#[tauri::command]
+fn import_history_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    passphrase: String,
+) -> CommandResult<HistoryImportReport> {
+    let backup = read_history_backup(Path::new(&path), &passphrase)
+        .map_err(|error| backup_error(&state, Operation::ImportBackup, &error))?;
+    let (report, language) = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::ImportBackup))?;
+        let report = db
+            .import_history_backup(&backup)
+            .map_err(|_| database_error(&state, Operation::ImportBackup))?
+            .map_err(|_| {
+                record_command_error(
+                    &state,
+                    CommandError::new(ErrorCode::InvalidBackup, Operation::ImportBackup, false),
+                )
+            })?;
+        let language = db
+            .get_language()
+            .map_err(|_| database_error(&state, Operation::ImportBackup))?;
+        (report, language)
+    };
+
+    schedule_history_mirror(&state)?;
+    replace_app_menu(&app, language.resolve())
+        .map_err(|_| state_error(&state, Operation::ImportBackup))?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::ImportBackup))?;
+    tray::notify_language_changed(&app)
+        .map_err(|_| state_error(&state, Operation::ImportBackup))?;
+    tray::notify_history_changed(&app).map_err(|_| state_error(&state, Operation::ImportBackup))?;
+    Ok(report)
+}
+
+/// Merges a JSONL history mirror into history. The file is read and parsed
+/// before the database lock is taken.
+#[tauri::command]
+fn import_history_jsonl(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> CommandResult<HistoryImportReport> {
+    let import = read_history_jsonl(Path::new(&path))
+        .map_err(|error| record_command_error(&state, history_import_error(&error)))?;
+    merge_history_import(&app, &state, &import)
+}
+
+/// Imports the history another clipboard manager keeps at `path`. The
+/// returned report counts only that manager's entries.
+#[tauri::command]
+fn import_clipboard_manager_history(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    manager: ClipboardManager,
+    path: String,
+) -> CommandResult<HistoryImportReport> {
+    let import = read_clipboard_manager_history(manager, Path::new(&path))
+        .map_err(|error| record_command_error(&state, history_import_error(&error)))?;
+    merge_history_import(&app, &state, &import)
+}
+
+fn merge_history_import(
+    app: &AppHandle,
+    state: &AppState,
+    import: &HistoryImport,
+) -> CommandResult<HistoryImportReport> {
+    let report = {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(state, Operation::ImportHistory))?;
+        db.import_history(import)
+            .map_err(|_| database_error(state, Operation::ImportHistory))?
+    };
+
+    schedule_history_mirror(state)?;
+    tray::sync(app).map_err(|_| state_error(state, Operation::ImportHistory))?;
+    tray::notify_history_changed(app).map_err(|_| state_error(state, Operation::ImportHistory))?;
+    Ok(report)
+}
+
+#[tauri::command]
+fn list_database_backups(state: State<'_, AppState>) -> CommandResult<Vec<DatabaseBackup>> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::LoadDatabaseBackups))?;
+    db.list_database_backups()
+        .map_err(|_| database_error(&state, Operation::LoadDatabaseBackups))
+}
+
+/// Replaces the database with a snapshot from `list_database_backups`. The
+/// copy runs under the database lock, since it swaps the connection itself.
+#[tauri::command]
+fn restore_database_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> CommandResult<()> {
+    let language = {
+        let mut db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::RestoreDatabaseBackup))?;
+        db.restore_database_backup(&name)
+            .map_err(|_| database_error(&state, Operation::RestoreDatabaseBackup))?
+            .map_err(|_| {
+                record_command_error(
+                    &state,
+                    CommandError::new(
+                        ErrorCode::DatabaseBackupNotFound,
+                        Operation::RestoreDatabaseBackup,
+                        false,
+                    ),
+                )
+            })?;
+        db.get_language()
+            .map_err(|_| database_error(&state, Operation::RestoreDatabaseBackup))?
+    };
+
+    schedule_history_mirror(&state)?;
+    replace_app_menu(&app, language.resolve())
+        .map_err(|_| state_error(&state, Operation::RestoreDatabaseBackup))?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::RestoreDatabaseBackup))?;
+    tray::notify_language_changed(&app)
+        .map_err(|_| state_error(&state, Operation::RestoreDatabaseBackup))?;
+    tray::notify_history_changed(&app)
+        .map_err(|_| state_error(&state, Operation::RestoreDatabaseBackup))
+}
+
+/// Deletes a snapshot from `list_database_backups`, such as the one kept from
+/// before payload encryption. A snapshot that is already gone is not an error.
+#[tauri::command]
+fn delete_database_backup(state: State<'_, AppState>, name: String) -> CommandResult<()> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::DeleteDatabaseBackup))?;
+    db.delete_database_backup(&name)
+        .map_err(|_| database_error(&state, Operation::DeleteDatabaseBackup))
 }
 
 #[tauri::command]
@@ -355,6 +1132,7 @@ fn copy_to_clipboard(
         let seed = db
             .get_restore_seed(&content_hash)
             .map_err(|_| database_error(&state, Operation::RestoreClipboard))?
+            .map_err(|_| integrity_error(&state, Operation::RestoreClipboard))?
             .ok_or_else(|| {
                 record_command_error(
                     &state,
@@ -752,7 +1530,7 @@ fn set_max_history_bytes(
     state: State<'_, AppState>,
     max_history_bytes: u64,
 ) -> CommandResult<()> {
-    if !(16 * 1024 * 1024..=4 * 1024 * 1024 * 1024).contains(&max_history_bytes) {
+    if !history_bytes_limit_is_valid(max_history_bytes) {
         return Err(record_command_error(
             &state,
             CommandError::new(ErrorCode::InvalidSetting, Operation::UpdateSettings, false),
@@ -772,6 +1550,115 @@ fn set_max_history_bytes(
     tray::notify_history_changed(&app).map_err(|_| state_error(&state, Operation::UpdateSettings))
 }
 
+/// Per-type budgets reuse the global limit ranges; a `None` limit defers to
+/// the global one, and clearing both removes the budget.
+#[tauri::command]
+fn set_data_type_budget(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    data_type: String,
+    max_items: Option<u32>,
+    max_bytes: Option<u64>,
+) -> CommandResult<()> {
+    if !is_known_data_type(&data_type)
+        || max_items.is_some_and(|max_items| !(1..=1_000).contains(&max_items))
+        || max_bytes.is_some_and(|max_bytes| !history_bytes_limit_is_valid(max_bytes))
+    {
+        return Err(record_command_error(
+            &state,
+            CommandError::new(ErrorCode::InvalidSetting, Operation::UpdateSettings, false),
+        ));
+    }
+    {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+        db.set_data_type_budget(&data_type, max_items, max_bytes)
+            .and_then(|_| db.cleanup_old_events())
+            .map_err(|_| database_error(&state, Operation::UpdateSettings))?;
+    }
+    schedule_history_mirror(&state)?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::UpdateSettings))?;
+    tray::notify_history_changed(&app).map_err(|_| state_error(&state, Operation::UpdateSettings))
+}
+
+fn history_bytes_limit_is_valid(max_bytes: u64) -> bool {
+    (16 * 1024 * 1024..=4 * 1024 * 1024 * 1024).contains(&max_bytes)
+}
+
+#[tauri::command]
+fn set_max_age_days(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    max_age_days: u32,
+) -> CommandResult<()> {
+    if max_age_days > MAX_AGE_DAYS_LIMIT {
+        return Err(record_command_error(
+            &state,
+            CommandError::new(ErrorCode::InvalidSetting, Operation::UpdateSettings, false),
+        ));
+    }
+    {
+        let db = state
+            .db
+            .lock()
+            .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+        db.set_max_age_days(max_age_days)
+            .and_then(|_| db.cleanup_old_events())
+            .map_err(|_| database_error(&state, Operation::UpdateSettings))?;
+    }
+    schedule_history_mirror(&state)?;
+    tray::sync(&app).map_err(|_| state_error(&state, Operation::UpdateSettings))?;
+    tray::notify_history_changed(&app).map_err(|_| state_error(&state, Operation::UpdateSettings))
+}
+
+/// `rule: None` removes any rule for the bundle identifier. Existing history
+/// from a newly denied application is kept until the user deletes it.
+#[tauri::command]
+fn set_source_app_rule(
+    state: State<'_, AppState>,
+    bundle_id: String,
+    rule: Option<SourceAppRule>,
+) -> CommandResult<()> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+    match db.set_source_app_rule(&bundle_id, rule) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(record_command_error(
+            &state,
+            CommandError::new(ErrorCode::InvalidSetting, Operation::UpdateSettings, false),
+        )),
+        Err(_) => Err(database_error(&state, Operation::UpdateSettings)),
+    }
+}
+
+#[tauri::command]
+fn set_source_allow_only(state: State<'_, AppState>, source_allow_only: bool) -> CommandResult<()> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+    db.set_source_allow_only(source_allow_only)
+        .map_err(|_| database_error(&state, Operation::UpdateSettings))
+}
+
+#[tauri::command]
+fn set_secret_policy(
+    state: State<'_, AppState>,
+    kind: SecretKind,
+    action: SecretAction,
+) -> CommandResult<()> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+    db.set_secret_policy(kind, action)
+        .map_err(|_| database_error(&state, Operation::UpdateSettings))
+}
+
 #[tauri::command]
 fn set_show_in_menu_bar(
     app: AppHandle,
@@ -825,6 +1712,19 @@ fn set_move_restored_item_to_top(
         .map_err(|_| database_error(&state, Operation::UpdateSettings))
 }
 
+#[tauri::command]
+fn set_move_recopied_item_to_top(
+    state: State<'_, AppState>,
+    move_recopied_item_to_top: bool,
+) -> CommandResult<()> {
+    let db = state
+        .db
+        .lock()
+        .map_err(|_| database_unavailable(&state, Operation::UpdateSettings))?;
+    db.set_move_recopied_item_to_top(move_recopied_item_to_top)
+        .map_err(|_| database_error(&state, Operation::UpdateSettings))
+}
+
 #[tauri::command]
 fn set_compact_mode(
     app: AppHandle,
@@ -892,14 +1792,39 @@ fn start_clipboard_event_pipeline(app_handle: AppHandle) -> Result<(), &'static
                 }
 
                 let state = event_app_handle.state::<AppState>();
-                if !assess_event(&event).should_record() {
-                    debug_log!("[copy_stack] skipped clipboard event by protocol policy");
-                    continue;
-                }
+                let policy = match state.db.lock() {
+                    Ok(db) => match db.capture_policy() {
+                        Ok(policy) => policy,
+                        Err(_) => {
+                            let _ = state
+                                .diagnostics
+                                .record(&CommandError::database(Operation::CaptureClipboard));
+                            debug_error!("[copy_stack] capture policy unavailable");
+                            continue;
+                        }
+                    },
+                    Err(_) => {
+                        let _ = state
+                            .diagnostics
+                            .record(&CommandError::state(Operation::CaptureClipboard));
+                        debug_error!("[copy_stack] database state unavailable");
+                        continue;
+                    }
+                };
 
-                let event = match prepare_capture_event(event) {
-                    Ok(prepared) => prepared.event,
-                    Err(rejection) => {
+                let prepared = match Database::prepare_capture(event, &policy) {
+                    Ok(CaptureOutcome::Prepared(prepared)) => prepared,
+                    Ok(CaptureOutcome::Skipped(SkipReason::SourceApplication)) => {
+                        debug_log!(
+                            "[copy_stack] skipped clipboard event by source application policy"
+                        );
+                        continue;
+                    }
+                    Ok(CaptureOutcome::Skipped(_)) => {
+                        debug_log!("[copy_stack] skipped clipboard event by protocol policy");
+                        continue;
+                    }
+                    Ok(CaptureOutcome::Rejected(rejection)) => {
                         let error = CommandError::new(
                             ErrorCode::CaptureRejected,
                             Operation::CaptureClipboard,
@@ -910,37 +1835,31 @@ fn start_clipboard_event_pipeline(app_handle: AppHandle) -> Result<(), &'static
                             CAPTURE_REJECTED_EVENT,
                             CaptureRejectedNotice {
                                 code: rejection.kind.code(),
-                                size_bucket: rejection.size_bucket.code(),
+                                size_bucket: Some(rejection.size_bucket.code()),
                             },
                         );
                         debug_log!("[copy_stack] rejected clipboard event by resource policy");
                         continue;
                     }
-                };
-
-                let compact_mode = match state.db.lock() {
-                    Ok(db) => match db.get_compact_mode() {
-                        Ok(compact_mode) => compact_mode,
-                        Err(_) => {
-                            let _ = state
-                                .diagnostics
-                                .record(&CommandError::database(Operation::CaptureClipboard));
-                            debug_error!("[copy_stack] clipboard settings unavailable");
-                            continue;
-                        }
-                    },
-                    Err(_) => {
-                        let _ = state
-                            .diagnostics
-                            .record(&CommandError::state(Operation::CaptureClipboard));
-                        debug_error!("[copy_stack] database state unavailable");
+                    Ok(CaptureOutcome::Secret(kind)) => {
+                        let error = CommandError::new(
+                            ErrorCode::CaptureRejected,
+                            Operation::CaptureClipboard,
+                            false,
+                        )
+                        .with_detail(kind.code());
+                        let _ = state.diagnostics.record(&error);
+                        let _ = event_app_handle.emit(
+                            CAPTURE_REJECTED_EVENT,
+                            CaptureRejectedNotice {
+                                code: kind.code(),
+                                size_bucket: None,
+                            },
+                        );
+                        debug_log!("[copy_stack] rejected clipboard event by secret policy");
                         continue;
                     }
-                };
-
-                let prepared = match Database::prepare_history_event(&event, compact_mode) {
-                    Ok(Some(prepared)) => prepared,
-                    Ok(None) => {
+                    Ok(CaptureOutcome::Unsupported) => {
                         debug_log!("[copy_stack] skipped unsupported clipboard event");
                         continue;
                     }
@@ -952,6 +1871,7 @@ fn start_clipboard_event_pipeline(app_handle: AppHandle) -> Result<(), &'static
                         continue;
                     }
                 };
+                let expires = prepared.expires();
                 let event_hash = prepared.content_hash().to_string();
 
                 if should_skip_pending_restore_event(&state, &event_hash) {
@@ -979,6 +1899,11 @@ fn start_clipboard_event_pipeline(app_handle: AppHandle) -> Result<(), &'static
                         if schedule_history_mirror(&state).is_err() {
                             debug_error!("[copy_stack] history mirror scheduling failed");
                         }
+                        if expires {
+                            if let Some(retention_sweep) = state.retention_sweep.as_ref() {
+                                retention_sweep.reschedule();
+                            }
+                        }
                     }
                     Ok(false) => {
                         debug_log!("[copy_stack] clipboard event filtered before persistence");
@@ -1024,15 +1949,152 @@ fn start_clipboard_event_pipeline(app_handle: AppHandle) -> Result<(), &'static
     Ok(())
 }
 
+/// Runs one control-socket command through its Tauri command handler on the
+/// main thread, where the IPC boundary runs it too.
+fn dispatch_control_command(
+    app: &AppHandle,
+    command: ControlCommand,
+) -> CommandResult<serde_json::Value> {
+    let operation = command.operation();
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let main_app = app.clone();
+    app.run_on_main_thread(move || {
+        let _ = sender.send(run_control_command(&main_app, command));
+    })
+    .map_err(|_| CommandError::state(operation))?;
+    receiver
+        .recv()
+        .map_err(|_| CommandError::state(operation))?
+}
+
+fn run_control_command(
+    app: &AppHandle,
+    command: ControlCommand,
+) -> CommandResult<serde_json::Value> {
+    let operation = command.operation();
+    let state = managed_app_state(app, operation)?;
+    let edits_history = matches!(
+        command,
+        ControlCommand::Restore { .. }
+            | ControlCommand::Delete { .. }
+            | ControlCommand::Clear { .. }
+    );
+    let result = match command {
+        ControlCommand::Page { cursor, page_size } => {
+            serde_json::to_value(get_copy_events_page(state, cursor, page_size, None, None)?)
+        }
+        ControlCommand::Detail { content_hash } => {
+            serde_json::to_value(get_history_detail(state, content_hash)?)
+        }
+        ControlCommand::Restore { content_hash } => {
+            serde_json::to_value(copy_to_clipboard(app.clone(), state, content_hash)?)
+        }
+        ControlCommand::Delete { content_hash } => {
+            serde_json::to_value(delete_copy_event(app.clone(), state, content_hash)?)
+        }
+        ControlCommand::Clear { .. } => serde_json::to_value(clear_all_events(app.clone(), state)?),
+        ControlCommand::Settings => serde_json::to_value(get_app_settings(state)?),
+    };
+    // The main webview only reloads history for its own commands.
+    if edits_history {
+        let _ = tray::notify_history_changed(app);
+    }
+    result.map_err(|_| CommandError::state(operation))
+}
+
+/// The app state behind a request from outside the IPC boundary, which can
+/// arrive before setup has opened the database.
+fn managed_app_state(app: &AppHandle, operation: Operation) -> CommandResult<State<'_, AppState>> {
+    app.try_state::<AppState>().ok_or(CommandError::new(
+        ErrorCode::DatabaseUnavailable,
+        operation,
+        true,
+    ))
+}
+
+struct AppRemoteActions<'a>(&'a AppHandle);
+
+impl RemoteActionTarget for AppRemoteActions<'_> {
+    fn restore(&self, content_hash: &str) -> CommandResult<()> {
+        let state = managed_app_state(self.0, Operation::RestoreClipboard)?;
+        copy_to_clipboard(self.0.clone(), state, content_hash.to_string())
+    }
+
+    fn delete(&self, content_hash: &str) -> CommandResult<()> {
+        let state = managed_app_state(self.0, Operation::DeleteHistory)?;
+        delete_copy_event(self.0.clone(), state, content_hash.to_string())
+    }
+
+    fn set_compact_mode(&self, compact_mode: bool) -> CommandResult<()> {
+        let state = managed_app_state(self.0, Operation::UpdateSettings)?;
+        set_compact_mode(self.0.clone(), state, compact_mode)
+    }
+
+    fn clear_history(&self) -> CommandResult<()> {
+        let state = managed_app_state(self.0, Operation::ClearHistory)?;
+        clear_all_events(self.0.clone(), state)
+    }
+
+    fn open_settings(&self) -> CommandResult<()> {
+        tray::show_page(self.0, tray::SETTINGS_PAGE)
+            .map_err(|_| CommandError::state(Operation::LoadSettings))
+    }
+}
+
+/// Runs the actions a duplicate launch forwarded, or only activates the main
+/// window when it forwarded none.
+fn handle_remote_launch(app: &AppHandle, args: Vec<String>) {
+    let actions = match StartupOptions::from_args(args.into_iter().skip(1).map(Into::into)) {
+        Ok(options) => options.remote_actions,
+        Err(_) => {
+            report_remote_action_failure(
+                app,
+                CommandError::new(
+                    ErrorCode::InvalidControlRequest,
+                    Operation::ControlRequest,
+                    false,
+                )
+                .with_detail("remote.invalid_arguments"),
+            );
+            Vec::new()
+        }
+    };
+    if actions.is_empty() {
+        if let Err(_error) = lifecycle::activate_main_window(app) {
+            debug_error!(
+                "[copy_stack] second-instance activation failed: {}",
+                _error.code()
+            );
+        }
+        return;
+    }
+    let result = lifecycle::dispatch_remote_actions(&AppRemoteActions(app), &actions);
+    // The main webview only reloads history for its own commands.
+    let _ = tray::notify_history_changed(app);
+    if let Err(error) = result {
+        report_remote_action_failure(app, error);
+    }
+}
+
+fn report_remote_action_failure(app: &AppHandle, error: CommandError) {
+    if let Some(state) = app.try_state::<AppState>() {
+        let _ = state.diagnostics.record(&error);
+    }
+    let _ = app.emit(APP_OPERATION_ERROR_EVENT, &error);
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run(startup_options: StartupOptions) -> Result<(), String> {
     let app = tauri::Builder::default()
-        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
-            if let Err(_error) = lifecycle::activate_main_window(app) {
-                debug_error!(
-                    "[copy_stack] second-instance activation failed: {}",
-                    _error.code()
-                );
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            // Forwarded actions run on the main thread, like the Tauri
+            // commands they share handlers with.
+            let main_app = app.clone();
+            if app
+                .run_on_main_thread(move || handle_remote_launch(&main_app, args))
+                .is_err()
+            {
+                debug_error!("[copy_stack] second-instance arguments were dropped");
             }
         }))
         .plugin(tauri_plugin_autostart::init(
@@ -1102,11 +2164,34 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
                     ),
                 );
             }
+            let blob_file_diagnostics = match db.reconcile_blob_files() {
+                Ok(report) => blob_file_diagnostics(&report),
+                Err(_) => vec![CommandError::new(
+                    ErrorCode::DatabaseOperationFailed,
+                    Operation::Startup,
+                    false,
+                )
+                .with_detail("blob_files.reconcile_failed")],
+            };
+            // Imported before the mirror starts, so its first snapshot
+            // already holds the merged rows.
+            if let Some(path) = startup_options.import_history_jsonl.as_ref() {
+                let imported = read_history_jsonl(path)
+                    .map_err(|error| history_import_error(&error))
+                    .and_then(|import| {
+                        db.import_history(&import)
+                            .map_err(|_| CommandError::database(Operation::ImportHistory))
+                    });
+                if let Err(error) = imported {
+                    publish_startup_error(&app_handle, error);
+                }
+            }
             let history_mirror = match startup_options.history_jsonl.as_ref() {
                 Some(config) => match db.history_mirror_database_path() {
                     Ok(database_path) => match HistoryMirror::start_database(
                         HistoryMirrorConfig::new(config.path.clone(), config.max_data_bytes),
                         database_path,
+                        db.payload_cipher(),
                     ) {
                         Ok(mirror) => Some(mirror),
                         Err(_) => {
@@ -1147,13 +2232,59 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
                 }
             };
 
+            let retention_sweep = match RetentionSweeper::start(app_handle.clone()) {
+                Ok(sweeper) => Some(sweeper),
+                Err(_) => {
+                    publish_startup_error(
+                        &app_handle,
+                        CommandError::new(ErrorCode::StartupFailed, Operation::Startup, false),
+                    );
+                    None
+                }
+            };
+
+            let control_socket = match startup_options.control_socket_directory.as_ref() {
+                Some(directory) => {
+                    let control_app = app_handle.clone();
+                    match ControlSocket::start(
+                        directory,
+                        std::sync::Arc::new(move |command: ControlCommand| {
+                            dispatch_control_command(&control_app, command)
+                        }),
+                    ) {
+                        Ok(socket) => Some(socket),
+                        Err(_) => {
+                            publish_startup_error(
+                                &app_handle,
+                                CommandError::new(
+                                    ErrorCode::ControlSocketUnavailable,
+                                    Operation::Startup,
+                                    false,
+                                ),
+                            );
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+
             app.manage(AppState {
                 db: Mutex::new(db),
                 pending_restore_suppression: Mutex::new(None),
                 history_mirror,
                 tray_refresh,
+                retention_sweep,
+                control_socket,
                 diagnostics: DiagnosticLog::default(),
             });
+            // Times the auto-expiring rows carried over from the last session.
+            if let Some(retention_sweep) = app_handle
+                .try_state::<AppState>()
+                .and_then(|state| state.inner().retention_sweep.as_ref())
+            {
+                retention_sweep.reschedule();
+            }
             if let (Some(status), Some(state)) = (
                 app_handle.try_state::<StartupStatus>(),
                 app_handle.try_state::<AppState>(),
@@ -1164,6 +2295,11 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
                     }
                 }
             }
+            if let Some(state) = app_handle.try_state::<AppState>() {
+                for error in &blob_file_diagnostics {
+                    let _ = state.diagnostics.record(error);
+                }
+            }
 
             let language = match resolved_language(&app_handle) {
                 Ok(language) => language,
@@ -1215,14 +2351,47 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
                     CommandError::new(ErrorCode::StartupFailed, Operation::Startup, false),
                 );
             }
+
+            // A first launch runs its own actions once everything is ready.
+            if let Err(error) = lifecycle::dispatch_remote_actions(
+                &AppRemoteActions(&app_handle),
+                &startup_options.remote_actions,
+            ) {
+                publish_startup_error(&app_handle, error);
+            }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_startup_error,
             get_copy_events_page,
+            search_copy_events,
             get_history_detail,
             delete_copy_event,
+            pin_copy_event,
+            unpin_copy_event,
+            list_collections,
+            create_collection,
+            rename_collection,
+            delete_collection,
+            add_copy_event_to_collection,
+            remove_copy_event_from_collection,
+            list_tags,
+            tag_copy_event,
+            untag_copy_event,
+            rename_tag,
+            delete_tag,
+            get_copy_event_labels,
             clear_all_events,
+            verify_history_integrity,
+            check_history_integrity,
+            export_history_backup,
+            export_history,
+            import_history_backup,
+            import_history_jsonl,
+            import_clipboard_manager_history,
+            list_database_backups,
+            restore_database_backup,
+            delete_database_backup,
             copy_to_clipboard,
             get_app_settings,
             get_safe_diagnostics,
@@ -1230,9 +2399,15 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
             set_autostart_enabled,
             set_max_items,
             set_max_history_bytes,
+            set_max_age_days,
+            set_data_type_budget,
+            set_source_app_rule,
+            set_source_allow_only,
+            set_secret_policy,
             set_show_in_menu_bar,
             set_menu_bar_item_limit,
             set_move_restored_item_to_top,
+            set_move_recopied_item_to_top,
             set_compact_mode,
             set_language
         ])
@@ -1245,6 +2420,12 @@ pub fn run(startup_options: StartupOptions) -> Result<(), String> {
                 if let Some(tray_refresh) = state.tray_refresh.as_ref() {
                     tray_refresh.shutdown();
                 }
+                if let Some(retention_sweep) = state.retention_sweep.as_ref() {
+                    retention_sweep.shutdown();
+                }
+                if let Some(control_socket) = state.control_socket.as_ref() {
+                    control_socket.shutdown();
+                }
                 if let Some(mirror) = state.history_mirror.as_ref() {
                     if mirror.shutdown(HISTORY_MIRROR_SHUTDOWN_TIMEOUT).is_err() {
                         let error = CommandError::new(
@@ -1299,6 +2480,57 @@ mod lib_tests {
         worker.join().expect("tray refresh worker should stop");
     }
 
+    #[test]
+    fn retention_sweep_runs_each_interval_until_shutdown() {
+        let (sender, receiver) = mpsc::channel();
+        let (swept_sender, swept_receiver) = mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            run_retention_sweep_worker(receiver, Duration::from_millis(10), || {
+                let _ = swept_sender.send(());
+                None
+            });
+        });
+
+        for _ in 0..2 {
+            swept_receiver
+                .recv_timeout(Duration::from_secs(1))
+                .expect("idle interval should trigger a sweep");
+        }
+        sender
+            .send(RetentionSweepMessage::Shutdown)
+            .expect("shutdown should queue");
+        worker.join().expect("retention sweep worker should stop");
+    }
+
+    #[test]
+    fn retention_sweep_wakes_for_the_next_auto_expiry() {
+        let (sender, receiver) = mpsc::channel();
+        let (swept_sender, swept_receiver) = mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            let mut sweeps = 0;
+            run_retention_sweep_worker(receiver, Duration::from_secs(3600), || {
+                sweeps += 1;
+                let _ = swept_sender.send(sweeps);
+                (sweeps == 1).then_some(Duration::from_millis(10))
+            });
+        });
+
+        sender
+            .send(RetentionSweepMessage::Reschedule)
+            .expect("reschedule should queue");
+        for expected in 1..=2 {
+            assert_eq!(
+                swept_receiver.recv_timeout(Duration::from_secs(1)),
+                Ok(expected),
+                "a due expiry should trigger a sweep before the hourly interval"
+            );
+        }
+        sender
+            .send(RetentionSweepMessage::Shutdown)
+            .expect("shutdown should queue");
+        worker.join().expect("retention sweep worker should stop");
+    }
+
     #[test]
     fn restore_protocol_metadata_keeps_suppression_hash_stable_in_both_modes() {
         let original = Event {
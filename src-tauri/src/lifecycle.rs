@@ -1,3 +1,5 @@
+use crate::command_error::CommandResult;
+use crate::startup::RemoteAction;
 use tauri::{AppHandle, Manager, Runtime, WebviewWindow};
 
 const MAIN_WINDOW_LABEL: &str = "main";
@@ -151,9 +153,38 @@ pub(crate) fn set_autostart_enabled(
     Ok(actual)
 }
 
+/// The app operations a forwarded launch can run, each backed by the same
+/// handler as the matching Tauri command.
+pub(crate) trait RemoteActionTarget {
+    fn restore(&self, content_hash: &str) -> CommandResult<()>;
+    fn delete(&self, content_hash: &str) -> CommandResult<()>;
+    fn set_compact_mode(&self, compact_mode: bool) -> CommandResult<()>;
+    fn clear_history(&self) -> CommandResult<()>;
+    fn open_settings(&self) -> CommandResult<()>;
+}
+
+/// Runs `actions` in order and stops at the first failure, so a later action
+/// never runs on a state the user did not expect.
+pub(crate) fn dispatch_remote_actions(
+    target: &impl RemoteActionTarget,
+    actions: &[RemoteAction],
+) -> CommandResult<()> {
+    for action in actions {
+        match action {
+            RemoteAction::Restore(content_hash) => target.restore(content_hash)?,
+            RemoteAction::Delete(content_hash) => target.delete(content_hash)?,
+            RemoteAction::SetCompactMode(compact_mode) => target.set_compact_mode(*compact_mode)?,
+            RemoteAction::ClearHistory => target.clear_history()?,
+            RemoteAction::OpenSettings => target.open_settings()?,
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::command_error::{CommandError, ErrorCode, Operation};
     use std::cell::{Cell, RefCell};
 
     #[derive(Default)]
@@ -316,4 +347,86 @@ mod tests {
             Err(LifecycleError::AutostartReadFailed)
         );
     }
+
+    #[derive(Default)]
+    struct FakeRemoteTarget {
+        calls: RefCell<Vec<String>>,
+        fail_delete: bool,
+    }
+
+    impl FakeRemoteTarget {
+        fn record(&self, call: String) -> CommandResult<()> {
+            self.calls.borrow_mut().push(call);
+            Ok(())
+        }
+    }
+
+    impl RemoteActionTarget for FakeRemoteTarget {
+        fn restore(&self, content_hash: &str) -> CommandResult<()> {
+            self.record(format!("restore {content_hash}"))
+        }
+
+        fn delete(&self, content_hash: &str) -> CommandResult<()> {
+            self.record(format!("delete {content_hash}"))?;
+            if self.fail_delete {
+                return Err(CommandError::new(
+                    ErrorCode::HistoryItemNotFound,
+                    Operation::DeleteHistory,
+                    false,
+                ));
+            }
+            Ok(())
+        }
+
+        fn set_compact_mode(&self, compact_mode: bool) -> CommandResult<()> {
+            self.record(format!("compact {compact_mode}"))
+        }
+
+        fn clear_history(&self) -> CommandResult<()> {
+            self.record("clear".to_string())
+        }
+
+        fn open_settings(&self) -> CommandResult<()> {
+            self.record("settings".to_string())
+        }
+    }
+
+    #[test]
+    fn remote_actions_run_in_order_and_stop_at_the_first_failure() {
+        let actions = [
+            RemoteAction::SetCompactMode(true),
+            RemoteAction::Restore("aa".to_string()),
+            RemoteAction::Delete("bb".to_string()),
+            RemoteAction::ClearHistory,
+            RemoteAction::OpenSettings,
+        ];
+
+        let target = FakeRemoteTarget::default();
+        assert_eq!(dispatch_remote_actions(&target, &actions), Ok(()));
+        assert_eq!(
+            target.calls.borrow().as_slice(),
+            [
+                "compact true",
+                "restore aa",
+                "delete bb",
+                "clear",
+                "settings"
+            ]
+        );
+
+        let failing = FakeRemoteTarget {
+            fail_delete: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            dispatch_remote_actions(&failing, &actions)
+                .expect_err("the failed delete should stop dispatch")
+                .code,
+            ErrorCode::HistoryItemNotFound
+        );
+        assert_eq!(
+            failing.calls.borrow().as_slice(),
+            ["compact true", "restore aa", "delete bb"]
+        );
+    }
 }
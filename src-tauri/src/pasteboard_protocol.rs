@@ -11,6 +11,8 @@ pub const TYPEIT4ME_CLIPPING_TYPE: &str = "com.typeit4me.clipping";
 pub const PASTEBOARD_GENERATOR_TYPE: &str = "Pasteboard generator type";
 pub const ONEPASSWORD_TYPE: &str = "com.agilebits.onepassword";
 pub const REMOTE_CLIPBOARD_TYPE: &str = "com.apple.is-remote-clipboard";
+/// Longest source bundle identifier that is persisted or accepted in a rule.
+pub const MAX_SOURCE_BUNDLE_ID_BYTES: usize = 255;
 
 const TRANSIENT_TYPES: [&str; 4] = [
     TRANSIENT_TYPE,
@@ -31,6 +33,8 @@ pub enum SkipReason {
     Concealed,
     Transient,
     AutoGenerated,
+    /// The user's source application policy excludes the event's source.
+    SourceApplication,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -56,6 +60,32 @@ impl PasteboardAssessment {
     }
 }
 
+/// User-configured capture rules keyed by source bundle identifier.
+///
+/// Identifiers compare ASCII case-insensitively, like macOS bundle IDs. In
+/// allow-only mode an event without a known source is skipped, because it
+/// cannot be shown to come from an allowed application.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SourceAppPolicy {
+    pub denied: Vec<String>,
+    pub allowed: Vec<String>,
+    pub allow_only: bool,
+}
+
+impl SourceAppPolicy {
+    fn allows(&self, source_bundle_id: Option<&str>) -> bool {
+        let source = source_bundle_id.filter(|source| !source.is_empty());
+        let listed = |bundle_ids: &[String]| {
+            source.is_some_and(|source| {
+                bundle_ids
+                    .iter()
+                    .any(|bundle_id| bundle_id.eq_ignore_ascii_case(source))
+            })
+        };
+        !listed(&self.denied) && (!self.allow_only || listed(&self.allowed))
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum RestorePreparationError {
     EmptyEvent,
@@ -104,6 +134,25 @@ pub fn assess_event(event: &Event) -> PasteboardAssessment {
     }
 }
 
+/// Applies the source application policy to an event that passed `assess_event`.
+///
+/// Events the protocol already skipped are returned unchanged. A policy skip
+/// drops the parsed metadata, matching how protocol skips carry none.
+pub fn apply_source_policy(
+    assessment: PasteboardAssessment,
+    policy: &SourceAppPolicy,
+) -> PasteboardAssessment {
+    if !assessment.should_record() || policy.allows(assessment.metadata.source_bundle_id.as_deref())
+    {
+        return assessment;
+    }
+
+    PasteboardAssessment {
+        disposition: HistoryDisposition::Skip(SkipReason::SourceApplication),
+        metadata: PasteboardMetadata::default(),
+    }
+}
+
 /// Returns the first source marker as strict UTF-8, preserving an empty string.
 ///
 /// If the first source marker is malformed, the source is treated as unknown.
@@ -501,6 +550,49 @@ mod tests {
         assert_eq!(restored.items[1].data_list[0].r#type, "public.html");
     }
 
+    #[test]
+    fn source_policy_denies_listed_apps_and_enforces_allow_only_mode() {
+        let from = |source: Option<&str>| {
+            let mut data_list = vec![text_data()];
+            if let Some(source) = source {
+                data_list.push(data(SOURCE_TYPE, source.as_bytes()));
+            }
+            assess_event(&event(data_list))
+        };
+        let skipped = HistoryDisposition::Skip(SkipReason::SourceApplication);
+        let mut policy = SourceAppPolicy {
+            denied: vec!["com.example.Vault".to_string()],
+            allowed: vec!["com.example.Editor".to_string()],
+            allow_only: false,
+        };
+
+        let denied = apply_source_policy(from(Some("COM.EXAMPLE.VAULT")), &policy);
+        assert_eq!(denied.disposition, skipped);
+        assert_eq!(denied.metadata, PasteboardMetadata::default());
+        for source in [None, Some(""), Some("com.example.Other")] {
+            assert!(apply_source_policy(from(source), &policy).should_record());
+        }
+
+        policy.allow_only = true;
+        assert!(apply_source_policy(from(Some("com.example.editor")), &policy).should_record());
+        for source in [None, Some(""), Some("com.example.Other")] {
+            assert_eq!(
+                apply_source_policy(from(source), &policy).disposition,
+                skipped
+            );
+        }
+
+        let concealed = assess_event(&event(vec![
+            text_data(),
+            data(CONCEALED_TYPE, b""),
+            data(SOURCE_TYPE, b"com.example.Vault"),
+        ]));
+        assert_eq!(
+            apply_source_policy(concealed, &policy).disposition,
+            HistoryDisposition::Skip(SkipReason::Concealed)
+        );
+    }
+
     #[test]
     fn restore_writes_empty_source_when_source_is_unknown() {
         for source in [None, Some("")] {
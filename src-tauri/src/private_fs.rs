@@ -21,6 +21,8 @@ use std::fs::{DirBuilder, OpenOptions, Permissions};
 #[cfg(unix)]
 use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
 #[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
 use std::path::Component;
 
 pub const PRIVATE_DIRECTORY_MODE: u32 = 0o700;
@@ -41,6 +43,7 @@ pub enum PrivateFsErrorKind {
     InsufficientPermissions,
     PathChanged,
     AlreadyExists,
+    TooLarge,
     Io(io::ErrorKind),
 }
 
@@ -94,6 +97,7 @@ impl fmt::Display for PrivateFsError {
             PrivateFsErrorKind::InsufficientPermissions => "required owner permissions are missing",
             PrivateFsErrorKind::PathChanged => "the object changed while it was being validated",
             PrivateFsErrorKind::AlreadyExists => "the object already exists",
+            PrivateFsErrorKind::TooLarge => "the file exceeds its size limit",
             PrivateFsErrorKind::Io(_) => "the operating system rejected the operation",
         };
 
@@ -446,6 +450,58 @@ pub fn harden_sqlite_files(database_path: &Path) -> Result<(), PrivateFsError> {
     }
 }
 
+/// Checks that an existing regular file is owned by the current user, has one
+/// link, and is exactly `0600`, without changing it.
+///
+/// Missing files are accepted. Read-only clients use this where the app would
+/// tighten a file, so they never modify the app's files.
+pub fn verify_private_file_if_exists(path: &Path) -> Result<(), PrivateFsError> {
+    #[cfg(unix)]
+    {
+        let path = normalize_absolute_path(path)?;
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(PrivateFsError::io("inspect", "file", &error)),
+        };
+        validate_private_file_metadata(&metadata, current_euid(), true, "verify", "file")
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        Err(PrivateFsError::new(
+            "verify",
+            "file",
+            PrivateFsErrorKind::UnsupportedPlatform,
+        ))
+    }
+}
+
+/// Checks SQLite's main file and any sidecars that currently exist like
+/// `verify_private_file_if_exists`, changing none of them.
+pub fn verify_sqlite_files(database_path: &Path) -> Result<(), PrivateFsError> {
+    #[cfg(unix)]
+    {
+        let database_path = normalize_absolute_path(database_path)?;
+        verify_private_file_if_exists(&database_path)?;
+        for suffix in ["-wal", "-shm", "-journal"] {
+            verify_private_file_if_exists(&sqlite_sidecar_path(&database_path, suffix))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = database_path;
+        Err(PrivateFsError::new(
+            "verify",
+            "SQLite files",
+            PrivateFsErrorKind::UnsupportedPlatform,
+        ))
+    }
+}
+
 /// Creates a same-directory, exclusively-created `0600` temporary file.
 ///
 /// The name is deterministic enough to diagnose but collision-safe through
@@ -500,6 +556,220 @@ pub fn create_private_temp_file(
     }
 }
 
+/// Reads a private regular file of at most `max_bytes`.
+///
+/// Missing files return `None`. The file is tightened to `0600` like
+/// `harden_private_file_if_exists` and read through a descriptor opened
+/// without following symlinks.
+pub fn read_private_file(path: &Path, max_bytes: u64) -> Result<Option<Vec<u8>>, PrivateFsError> {
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+
+        let path = normalize_absolute_path(path)?;
+        if !path_exists_no_follow(&path)? {
+            return Ok(None);
+        }
+        harden_private_file_if_exists(&path)?;
+
+        let file = open_existing_no_follow(&path, false, "file")?;
+        let metadata = file
+            .metadata()
+            .map_err(|error| PrivateFsError::io("inspect", "file", &error))?;
+        validate_private_file_metadata(&metadata, current_euid(), true, "read", "file")?;
+        validate_path_identity(
+            &path,
+            FileIdentity::from_metadata(&metadata),
+            "read",
+            "file",
+        )?;
+        if metadata.len() > max_bytes {
+            return Err(PrivateFsError::new(
+                "read",
+                "file",
+                PrivateFsErrorKind::TooLarge,
+            ));
+        }
+
+        let mut contents = Vec::new();
+        file.take(max_bytes.saturating_add(1))
+            .read_to_end(&mut contents)
+            .map_err(|error| PrivateFsError::io("read", "file", &error))?;
+        if contents.len() as u64 > max_bytes {
+            return Err(PrivateFsError::new(
+                "read",
+                "file",
+                PrivateFsErrorKind::TooLarge,
+            ));
+        }
+        Ok(Some(contents))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (path, max_bytes);
+        Err(PrivateFsError::new(
+            "read",
+            "file",
+            PrivateFsErrorKind::UnsupportedPlatform,
+        ))
+    }
+}
+
+/// Binds a Unix socket at `path` inside an already-private directory.
+///
+/// A socket left at `path` by an earlier run of the current user is
+/// replaced; any other object there is refused. The new socket is `0600`.
+#[cfg(unix)]
+pub fn bind_private_socket(path: &Path) -> Result<UnixListener, PrivateFsError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let path = normalize_absolute_path(path)?;
+    match std::fs::symlink_metadata(&path) {
+        Ok(metadata) => {
+            if !metadata.file_type().is_socket() {
+                return Err(PrivateFsError::new(
+                    "bind",
+                    "socket",
+                    PrivateFsErrorKind::AlreadyExists,
+                ));
+            }
+            if metadata.uid() != current_euid() {
+                return Err(PrivateFsError::new(
+                    "bind",
+                    "socket",
+                    PrivateFsErrorKind::WrongOwner,
+                ));
+            }
+            std::fs::remove_file(&path)
+                .map_err(|error| PrivateFsError::io("replace", "socket", &error))?;
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(PrivateFsError::io("inspect", "socket", &error)),
+    }
+
+    let listener =
+        UnixListener::bind(&path).map_err(|error| PrivateFsError::io("bind", "socket", &error))?;
+    std::fs::set_permissions(&path, Permissions::from_mode(PRIVATE_FILE_MODE))
+        .map_err(|error| PrivateFsError::io("harden", "socket", &error))?;
+    Ok(listener)
+}
+
+/// Reports whether the process on the other end of `stream` runs as the
+/// current effective user, from the credentials the kernel recorded when it
+/// connected.
+#[cfg(unix)]
+pub fn socket_peer_is_current_user(stream: &UnixStream) -> Result<bool, PrivateFsError> {
+    Ok(socket_peer_euid(stream)? == current_euid())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn socket_peer_euid(stream: &UnixStream) -> Result<u32, PrivateFsError> {
+    use std::os::unix::io::AsRawFd;
+
+    #[repr(C)]
+    struct PeerCredentials {
+        pid: i32,
+        uid: u32,
+        gid: u32,
+    }
+
+    const SOL_SOCKET: i32 = 1;
+    const SO_PEERCRED: i32 = 17;
+
+    unsafe extern "C" {
+        fn getsockopt(
+            socket: i32,
+            level: i32,
+            name: i32,
+            value: *mut std::ffi::c_void,
+            len: *mut u32,
+        ) -> i32;
+    }
+
+    let mut credentials = PeerCredentials {
+        pid: 0,
+        uid: u32::MAX,
+        gid: u32::MAX,
+    };
+    let mut len = std::mem::size_of::<PeerCredentials>() as u32;
+    // SAFETY: `value` and `len` describe a live `ucred`-sized buffer that
+    // outlives the call.
+    let result = unsafe {
+        getsockopt(
+            stream.as_raw_fd(),
+            SOL_SOCKET,
+            SO_PEERCRED,
+            (&mut credentials as *mut PeerCredentials).cast(),
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(PrivateFsError::io(
+            "inspect",
+            "socket peer",
+            &io::Error::last_os_error(),
+        ));
+    }
+    if len as usize != std::mem::size_of::<PeerCredentials>() {
+        return Err(PrivateFsError::new(
+            "inspect",
+            "socket peer",
+            PrivateFsErrorKind::Io(io::ErrorKind::InvalidData),
+        ));
+    }
+    Ok(credentials.uid)
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+fn socket_peer_euid(stream: &UnixStream) -> Result<u32, PrivateFsError> {
+    use std::os::unix::io::AsRawFd;
+
+    unsafe extern "C" {
+        fn getpeereid(socket: i32, euid: *mut u32, egid: *mut u32) -> i32;
+    }
+
+    let mut euid = u32::MAX;
+    let mut egid = u32::MAX;
+    // SAFETY: both out-pointers refer to live locals for the whole call.
+    if unsafe { getpeereid(stream.as_raw_fd(), &mut euid, &mut egid) } != 0 {
+        return Err(PrivateFsError::io(
+            "inspect",
+            "socket peer",
+            &io::Error::last_os_error(),
+        ));
+    }
+    Ok(euid)
+}
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))
+))]
+fn socket_peer_euid(_stream: &UnixStream) -> Result<u32, PrivateFsError> {
+    Err(PrivateFsError::new(
+        "inspect",
+        "socket peer",
+        PrivateFsErrorKind::Io(io::ErrorKind::Unsupported),
+    ))
+}
+
 #[cfg(unix)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 struct FileIdentity {
@@ -1134,6 +1404,25 @@ mod tests {
         assert_eq!(error.kind(), PrivateFsErrorKind::MultipleHardLinks);
     }
 
+    #[test]
+    fn verification_reports_loose_files_without_tightening_them() {
+        let root = TestDirectory::new("verify");
+        let database = root.path.join("copy_stack.db");
+        verify_sqlite_files(&database).expect("missing files should be accepted");
+        std::fs::write(&database, b"database").expect("file should be created");
+        std::fs::set_permissions(&database, Permissions::from_mode(PRIVATE_FILE_MODE))
+            .expect("test permissions should change");
+        verify_sqlite_files(&database).expect("a private database should verify");
+
+        let wal = sqlite_sidecar_path(&database, "-wal");
+        std::fs::write(&wal, b"wal").expect("sidecar should be created");
+        std::fs::set_permissions(&wal, Permissions::from_mode(0o640))
+            .expect("test permissions should change");
+        let error = verify_sqlite_files(&database).expect_err("a loose sidecar must be rejected");
+        assert_eq!(error.kind(), PrivateFsErrorKind::InsufficientPermissions);
+        assert_eq!(mode(&wal), 0o640);
+    }
+
     #[test]
     fn rejects_symlinked_components_and_does_not_touch_the_target() {
         let root = TestDirectory::new("symlinks");
@@ -1373,4 +1662,28 @@ mod tests {
         assert_eq!(value, "snapshot");
         temp.commit(&output).expect("snapshot should commit");
     }
+
+    #[test]
+    fn private_sockets_replace_stale_sockets_and_accept_only_the_same_user() {
+        let root = TestDirectory::new("socket");
+        let path = root.path.join("control.sock");
+        drop(bind_private_socket(&path).expect("socket should bind"));
+        let listener = bind_private_socket(&path).expect("stale socket should be replaced");
+        assert_eq!(mode(&path), PRIVATE_FILE_MODE);
+
+        let client = UnixStream::connect(&path).expect("client should connect");
+        let (server, _) = listener.accept().expect("connection should be accepted");
+        assert_eq!(socket_peer_is_current_user(&server), Ok(true));
+        assert_eq!(socket_peer_is_current_user(&client), Ok(true));
+
+        let file = root.path.join("history.jsonl");
+        std::fs::write(&file, b"{}").expect("file should be written");
+        assert_eq!(
+            bind_private_socket(&file)
+                .expect_err("a regular file should not be replaced")
+                .kind(),
+            PrivateFsErrorKind::AlreadyExists
+        );
+        assert!(file.exists());
+    }
 }
@@ -13,7 +13,12 @@ pub const MAX_PREVIEW_IMAGE_BYTES: usize = 4 * 1024 * 1024;
 pub const MAX_PREVIEW_IMAGE_PIXELS: u64 = 20_000_000;
 pub const MAX_PREVIEW_SEGMENTS: usize = 32;
 pub const MAX_DETAIL_IPC_BYTES: usize = 8 * 1024 * 1024;
+/// Image bytes one HTML export report embeds in total; later images are
+/// listed by label only.
+pub const MAX_EXPORT_IMAGE_BYTES: usize = 64 * 1024 * 1024;
 pub const MAX_HISTORY_BYTES: u64 = 256 * 1024 * 1024;
+pub const MAX_PINNED_ITEMS: u64 = 100;
+pub const MAX_PINNED_BYTES: u64 = 64 * 1024 * 1024;
 
 const INLINE_ATTACHMENT_PLACEHOLDER: char = '\u{fffc}';
 
@@ -5,11 +5,36 @@ use std::path::PathBuf;
 const DEFAULT_JSONL_MAX_DATA_BYTES: usize = 4096;
 const JSONL_PATH_FLAG: &str = "--copy-stack-history-jsonl";
 const JSONL_MAX_DATA_BYTES_FLAG: &str = "--copy-stack-history-jsonl-max-data-bytes";
+const JSONL_IMPORT_FLAG: &str = "--copy-stack-import-history-jsonl";
+const CONTROL_SOCKET_FLAG: &str = "--copy-stack-control-socket";
+const RESTORE_FLAG: &str = "--restore";
+const DELETE_FLAG: &str = "--delete";
+const COMPACT_MODE_FLAG: &str = "--compact-mode";
+const CLEAR_HISTORY_FLAG: &str = "--clear-history";
+const CONFIRM_FLAG: &str = "--yes";
+const OPEN_FLAG: &str = "--open";
 pub(crate) const AUTOSTART_LAUNCH_FLAG: &str = "--copy-stack-autostart";
 
+/// An action a launch asks the app to take. A duplicate launch forwards its
+/// actions to the running app instead of starting a second one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RemoteAction {
+    Restore(String),
+    Delete(String),
+    SetCompactMode(bool),
+    ClearHistory,
+    OpenSettings,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct StartupOptions {
     pub history_jsonl: Option<HistoryJsonlConfig>,
+    /// A JSONL mirror to merge into history once the database is open.
+    pub import_history_jsonl: Option<PathBuf>,
+    /// The private directory to serve the local control socket from.
+    pub control_socket_directory: Option<PathBuf>,
+    /// Actions to run, in argument order, once the app is ready.
+    pub remote_actions: Vec<RemoteAction>,
     pub launched_at_login: bool,
     pub had_invalid_arguments: bool,
 }
@@ -19,11 +44,15 @@ impl StartupOptions {
         Self::from_args(std::env::args_os().skip(1))
     }
 
-    fn from_args<I>(args: I) -> Result<Self, String>
+    pub(crate) fn from_args<I>(args: I) -> Result<Self, String>
     where
         I: IntoIterator<Item = OsString>,
     {
         let mut history_jsonl_path = None;
+        let mut import_history_jsonl = None;
+        let mut control_socket_directory = None;
+        let mut remote_actions = Vec::new();
+        let mut clear_history_confirmed = false;
         let mut max_data_bytes = DEFAULT_JSONL_MAX_DATA_BYTES;
         let mut launched_at_login = false;
         let mut args = args.into_iter().peekable();
@@ -35,6 +64,26 @@ impl StartupOptions {
 
             if arg == AUTOSTART_LAUNCH_FLAG {
                 launched_at_login = true;
+            } else if arg == CLEAR_HISTORY_FLAG {
+                remote_actions.push(RemoteAction::ClearHistory);
+            } else if arg == CONFIRM_FLAG {
+                clear_history_confirmed = true;
+            } else if let Some(hash) = flag_value(arg, RESTORE_FLAG, &mut args)? {
+                remote_actions.push(RemoteAction::Restore(hash));
+            } else if let Some(hash) = flag_value(arg, DELETE_FLAG, &mut args)? {
+                remote_actions.push(RemoteAction::Delete(hash));
+            } else if let Some(value) = flag_value(arg, COMPACT_MODE_FLAG, &mut args)? {
+                let compact_mode = match value.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(format!("{} takes on or off", COMPACT_MODE_FLAG)),
+                };
+                remote_actions.push(RemoteAction::SetCompactMode(compact_mode));
+            } else if let Some(page) = flag_value(arg, OPEN_FLAG, &mut args)? {
+                if page != "settings" {
+                    return Err(format!("{} takes settings", OPEN_FLAG));
+                }
+                remote_actions.push(RemoteAction::OpenSettings);
             } else if let Some(path) = arg.strip_prefix(&format!("{}=", JSONL_PATH_FLAG)) {
                 history_jsonl_path = Some(PathBuf::from(path));
             } else if arg == JSONL_PATH_FLAG {
@@ -42,6 +91,20 @@ impl StartupOptions {
                     .next()
                     .ok_or_else(|| format!("{} requires a file path", JSONL_PATH_FLAG))?;
                 history_jsonl_path = Some(PathBuf::from(path));
+            } else if let Some(path) = arg.strip_prefix(&format!("{}=", JSONL_IMPORT_FLAG)) {
+                import_history_jsonl = Some(PathBuf::from(path));
+            } else if arg == JSONL_IMPORT_FLAG {
+                let path = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a file path", JSONL_IMPORT_FLAG))?;
+                import_history_jsonl = Some(PathBuf::from(path));
+            } else if let Some(path) = arg.strip_prefix(&format!("{}=", CONTROL_SOCKET_FLAG)) {
+                control_socket_directory = Some(PathBuf::from(path));
+            } else if arg == CONTROL_SOCKET_FLAG {
+                let path = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a directory path", CONTROL_SOCKET_FLAG))?;
+                control_socket_directory = Some(PathBuf::from(path));
             } else if let Some(value) = arg.strip_prefix(&format!("{}=", JSONL_MAX_DATA_BYTES_FLAG))
             {
                 max_data_bytes = parse_max_data_bytes(value)?;
@@ -54,17 +117,49 @@ impl StartupOptions {
             }
         }
 
+        // Clearing cannot be undone, so it needs the confirmation beside it.
+        if remote_actions.contains(&RemoteAction::ClearHistory) && !clear_history_confirmed {
+            return Err(format!("{} requires {}", CLEAR_HISTORY_FLAG, CONFIRM_FLAG));
+        }
+
         Ok(Self {
             history_jsonl: history_jsonl_path.map(|path| HistoryJsonlConfig {
                 path,
                 max_data_bytes,
             }),
+            import_history_jsonl,
+            control_socket_directory,
+            remote_actions,
             launched_at_login,
             had_invalid_arguments: false,
         })
     }
 }
 
+/// Reads the non-empty value of `flag`, given as `flag=<value>` or as the
+/// next argument, or returns `None` when `arg` is another flag.
+fn flag_value<I>(arg: &str, flag: &str, args: &mut I) -> Result<Option<String>, String>
+where
+    I: Iterator<Item = OsString>,
+{
+    let value = if arg == flag {
+        args.next()
+            .map(|value| value.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("{} requires a value", flag))?
+    } else if let Some(value) = arg
+        .strip_prefix(flag)
+        .and_then(|rest| rest.strip_prefix('='))
+    {
+        value.to_string()
+    } else {
+        return Ok(None);
+    };
+    if value.is_empty() {
+        return Err(format!("{} requires a value", flag));
+    }
+    Ok(Some(value))
+}
+
 fn parse_max_data_bytes(value: &str) -> Result<usize, String> {
     value
         .parse::<usize>()
@@ -113,5 +208,105 @@ mod tests {
 
         assert!(options.launched_at_login);
         assert!(options.history_jsonl.is_none());
+        assert!(options.import_history_jsonl.is_none());
+    }
+
+    #[test]
+    fn startup_options_parse_history_import_flag_beside_the_mirror() {
+        let options = StartupOptions::from_args(os_args(&[
+            "--copy-stack-import-history-jsonl",
+            "/tmp/old.jsonl",
+            "--copy-stack-history-jsonl=/tmp/out.jsonl",
+        ]))
+        .expect("options should parse");
+
+        assert_eq!(
+            options.import_history_jsonl,
+            Some(PathBuf::from("/tmp/old.jsonl"))
+        );
+        assert_eq!(
+            options.history_jsonl.expect("JSONL should be enabled").path,
+            PathBuf::from("/tmp/out.jsonl")
+        );
+        assert_eq!(
+            StartupOptions::from_args(os_args(&["--copy-stack-import-history-jsonl=/tmp/a.jsonl"]))
+                .expect("options should parse")
+                .import_history_jsonl,
+            Some(PathBuf::from("/tmp/a.jsonl"))
+        );
+        assert!(
+            StartupOptions::from_args(os_args(&["--copy-stack-import-history-jsonl"])).is_err()
+        );
+    }
+
+    #[test]
+    fn startup_options_parse_the_opt_in_control_socket_directory() {
+        assert!(StartupOptions::from_args(os_args(&[]))
+            .expect("options should parse")
+            .control_socket_directory
+            .is_none());
+        for args in [
+            &["--copy-stack-control-socket", "/tmp/copy-stack-control"][..],
+            &["--copy-stack-control-socket=/tmp/copy-stack-control"],
+        ] {
+            assert_eq!(
+                StartupOptions::from_args(os_args(args))
+                    .expect("options should parse")
+                    .control_socket_directory,
+                Some(PathBuf::from("/tmp/copy-stack-control"))
+            );
+        }
+        assert!(StartupOptions::from_args(os_args(&["--copy-stack-control-socket"])).is_err());
+    }
+
+    #[test]
+    fn startup_options_parse_remote_actions_in_argument_order() {
+        let options = StartupOptions::from_args(os_args(&[
+            "/Applications/Copy Stack.app/Contents/MacOS/copy_stack",
+            "--delete",
+            "aa",
+            "--compact-mode=on",
+            "--restore=bb",
+            "--clear-history",
+            "--open",
+            "settings",
+            "--yes",
+            "--compact-mode",
+            "off",
+        ]))
+        .expect("options should parse");
+
+        assert_eq!(
+            options.remote_actions,
+            [
+                RemoteAction::Delete("aa".to_string()),
+                RemoteAction::SetCompactMode(true),
+                RemoteAction::Restore("bb".to_string()),
+                RemoteAction::ClearHistory,
+                RemoteAction::OpenSettings,
+                RemoteAction::SetCompactMode(false),
+            ]
+        );
+        assert!(StartupOptions::from_args(os_args(&["--yes"]))
+            .expect("options should parse")
+            .remote_actions
+            .is_empty());
+    }
+
+    #[test]
+    fn startup_options_reject_incomplete_remote_actions() {
+        for args in [
+            &["--clear-history"][..],
+            &["--restore"],
+            &["--delete="],
+            &["--compact-mode", "maybe"],
+            &["--compact-mode"],
+            &["--open", "history"],
+        ] {
+            assert!(
+                StartupOptions::from_args(os_args(args)).is_err(),
+                "{args:?}"
+            );
+        }
     }
 }
@@ -0,0 +1,490 @@
+//! Online snapshots of the history database.
+//!
+//! Snapshots live in a private `backups/` directory beside `copy_stack.db`.
+//! A file name records when the snapshot was taken and the schema version it
+//! holds, so listing never opens a snapshot.
+
+use crate::private_fs::{ensure_private_directory, harden_sqlite_files, prepare_sqlite_database};
+use crate::store::models::DatabaseBackup;
+use crate::store::schema::{drop_plaintext_search_index, CURRENT_SCHEMA_VERSION};
+use rusqlite::{Connection, DatabaseName, OpenFlags, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const BACKUP_DIR_NAME: &str = "backups";
+const BACKUP_FILE_PREFIX: &str = "copy_stack-";
+const BACKUP_FILE_SUFFIX: &str = ".db";
+const PARTIAL_BACKUP_SUFFIX: &str = ".partial";
+/// Snapshots kept after rotation.
+pub(super) const MAX_DATABASE_BACKUPS: usize = 5;
+/// The first schema version whose payload columns are sealed.
+const SEALED_PAYLOADS_SCHEMA_VERSION: i64 = 8;
+/// The first schema version whose search text is sealed.
+const SEALED_SEARCH_SCHEMA_VERSION: i64 = 13;
+
+/// Copies the open database into a new snapshot and returns its path.
+///
+/// The copy is written under a partial name and renamed once it is complete
+/// and hardened, so an interrupted copy is never listed.
+pub(super) fn snapshot_database(
+    connection: &Connection,
+    database_path: &Path,
+    schema_version: i64,
+    created_at: i64,
+) -> Result<PathBuf> {
+    let directory = ensure_private_directory(&backup_directory(database_path))
+        .map_err(|_| backup_error("prepare"))?;
+    let mut created_at = created_at;
+    let backup_path = loop {
+        let candidate = directory.join(backup_file_name(created_at, schema_version));
+        if !entry_exists(&candidate)? {
+            break candidate;
+        }
+        created_at += 1;
+    };
+    let partial_path = partial_backup_path(&backup_path);
+    let partial_path =
+        prepare_sqlite_database(&partial_path).map_err(|_| backup_error("prepare"))?;
+
+    let copied = connection
+        .backup(DatabaseName::Main, &partial_path, None)
+        .and_then(|()| harden_sqlite_files(&partial_path).map_err(|_| backup_error("harden")))
+        .and_then(|()| {
+            std::fs::rename(&partial_path, &backup_path).map_err(|_| backup_error("commit"))
+        });
+    if let Err(error) = copied {
+        let _ = remove_sqlite_files(&partial_path);
+        return Err(error);
+    }
+    Ok(backup_path)
+}
+
+/// Lists snapshots, newest first. Files this version did not write, and
+/// snapshots of a newer schema, are left out.
+pub(super) fn list_backups(database_path: &Path) -> Result<Vec<DatabaseBackup>> {
+    let mut backups = Vec::new();
+    for name in backup_directory_entries(database_path)? {
+        let Some((created_at, schema_version)) = parse_backup_file_name(&name) else {
+            continue;
+        };
+        if schema_version > CURRENT_SCHEMA_VERSION {
+            continue;
+        }
+        let path = backup_directory(database_path).join(&name);
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(_) => return Err(backup_error("list")),
+        };
+        if !metadata.file_type().is_file() {
+            continue;
+        }
+        backups.push(DatabaseBackup {
+            name,
+            created_at,
+            schema_version,
+            size_bytes: metadata.len(),
+            plaintext: schema_version < SEALED_PAYLOADS_SCHEMA_VERSION,
+        });
+    }
+    backups.sort_by(|left, right| {
+        right
+            .created_at
+            .cmp(&left.created_at)
+            .then_with(|| right.name.cmp(&left.name))
+    });
+    Ok(backups)
+}
+
+/// Resolves a listed snapshot name to its hardened file, or `None` when no
+/// such snapshot can be restored.
+pub(super) fn backup_path(database_path: &Path, name: &str) -> Result<Option<PathBuf>> {
+    match parse_backup_file_name(name) {
+        Some((_, schema_version)) if schema_version <= CURRENT_SCHEMA_VERSION => {}
+        _ => return Ok(None),
+    }
+    let path = backup_directory(database_path).join(name);
+    match std::fs::symlink_metadata(&path) {
+        Ok(metadata) if metadata.file_type().is_file() => {}
+        Ok(_) => return Ok(None),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(_) => return Err(backup_error("open")),
+    }
+    harden_sqlite_files(&path).map_err(|_| backup_error("harden"))?;
+    Ok(Some(path))
+}
+
+/// Removes all but the newest `MAX_DATABASE_BACKUPS` snapshots, and any
+/// partial copy an interrupted snapshot left behind.
+///
+/// The newest snapshot from before payload encryption is the rollback point
+/// of that migration, so it neither counts nor rotates out; only
+/// `remove_backup` deletes it.
+pub(super) fn rotate_backups(database_path: &Path) -> Result<()> {
+    let directory = backup_directory(database_path);
+    for name in backup_directory_entries(database_path)? {
+        let is_partial = name
+            .strip_suffix(PARTIAL_BACKUP_SUFFIX)
+            .is_some_and(|name| parse_backup_file_name(name).is_some());
+        if is_partial {
+            remove_sqlite_files(&directory.join(name))?;
+        }
+    }
+    let mut backups = list_backups(database_path)?;
+    if let Some(index) = backups.iter().position(|backup| backup.plaintext) {
+        backups.remove(index);
+    }
+    for backup in backups.into_iter().skip(MAX_DATABASE_BACKUPS) {
+        remove_sqlite_files(&directory.join(backup.name))?;
+    }
+    Ok(())
+}
+
+/// Deletes a listed snapshot. A name that resolves to no snapshot is left
+/// alone, so deleting twice is not an error.
+pub(super) fn remove_backup(database_path: &Path, name: &str) -> Result<()> {
+    match backup_path(database_path, name)? {
+        Some(path) => remove_sqlite_files(&path),
+        None => Ok(()),
+    }
+}
+
+/// Removes the plaintext that snapshots of older schemas hold, once the live
+/// database has been sealed and verified.
+///
+/// Only a migration could seal a snapshot from before payload encryption, so
+/// the newest one is kept as the rollback point of that migration until the
+/// user deletes it, and older ones are deleted. The rest lose their on-disk
+/// search index, which the schema initialization of a restore rebuilds from
+/// the sealed rows.
+pub(super) fn scrub_plaintext_backups(database_path: &Path) -> Result<()> {
+    let mut kept_plaintext = false;
+    for backup in list_backups(database_path)? {
+        if backup.schema_version >= SEALED_SEARCH_SCHEMA_VERSION {
+            continue;
+        }
+        let Some(path) = backup_path(database_path, &backup.name)? else {
+            continue;
+        };
+        if backup.plaintext {
+            if kept_plaintext {
+                remove_sqlite_files(&path)?;
+            }
+            kept_plaintext = true;
+            continue;
+        }
+        let connection = Connection::open(&path)?;
+        let indexed = connection
+            .prepare("SELECT 1 FROM sqlite_master WHERE name = 'clipboard_events_fts'")?
+            .exists([])?;
+        if indexed {
+            connection.pragma_update(None, "secure_delete", true)?;
+            drop_plaintext_search_index(&connection)?;
+            connection.execute_batch("VACUUM")?;
+        }
+        drop(connection);
+        harden_sqlite_files(&path).map_err(|_| backup_error("harden"))?;
+    }
+    Ok(())
+}
+
+/// Returns the digests of the flavor blob files the listed snapshots
+/// reference. Snapshots hold only the digests, so the live blob directory
+/// keeps these files for as long as the snapshots are listed.
+pub(super) fn blob_file_digests(database_path: &Path) -> Result<HashSet<String>> {
+    let mut digests = HashSet::new();
+    for backup in list_backups(database_path)? {
+        let Some(path) = backup_path(database_path, &backup.name)? else {
+            continue;
+        };
+        let connection = Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        let has_files = connection
+            .prepare("SELECT 1 FROM sqlite_master WHERE name = 'flavor_blob_files'")?
+            .exists([])?;
+        if !has_files {
+            continue;
+        }
+        let mut statement = connection.prepare("SELECT digest FROM flavor_blob_files")?;
+        for digest in statement.query_map([], |row| row.get::<_, String>(0))? {
+            digests.insert(digest?);
+        }
+    }
+    Ok(digests)
+}
+
+fn backup_directory(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(BACKUP_DIR_NAME)
+}
+
+fn backup_file_name(created_at: i64, schema_version: i64) -> String {
+    format!("{BACKUP_FILE_PREFIX}{created_at}-v{schema_version}{BACKUP_FILE_SUFFIX}")
+}
+
+fn partial_backup_path(backup_path: &Path) -> PathBuf {
+    let mut path = backup_path.as_os_str().to_owned();
+    path.push(PARTIAL_BACKUP_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Parses `copy_stack-<created_at>-v<schema_version>.db`, accepting only the
+/// exact names `backup_file_name` writes.
+fn parse_backup_file_name(name: &str) -> Option<(i64, i64)> {
+    let stem = name
+        .strip_prefix(BACKUP_FILE_PREFIX)?
+        .strip_suffix(BACKUP_FILE_SUFFIX)?;
+    let (created_at, schema_version) = stem.split_once("-v")?;
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    if !is_number(created_at) || !is_number(schema_version) {
+        return None;
+    }
+    let created_at = created_at.parse().ok()?;
+    let schema_version = schema_version.parse().ok()?;
+    (backup_file_name(created_at, schema_version) == name).then_some((created_at, schema_version))
+}
+
+fn backup_directory_entries(database_path: &Path) -> Result<Vec<String>> {
+    let entries = match std::fs::read_dir(backup_directory(database_path)) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(_) => return Err(backup_error("list")),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|_| backup_error("list"))?;
+        if let Ok(name) = entry.file_name().into_string() {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+fn entry_exists(path: &Path) -> Result<bool> {
+    match std::fs::symlink_metadata(path) {
+        Ok(_) => Ok(true),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(_) => Err(backup_error("prepare")),
+    }
+}
+
+fn remove_sqlite_files(path: &Path) -> Result<()> {
+    let mut paths = vec![path.to_path_buf()];
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        paths.push(PathBuf::from(sidecar));
+    }
+    for path in paths {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(_) => return Err(backup_error("remove")),
+        }
+    }
+    Ok(())
+}
+
+fn backup_error(operation: &str) -> rusqlite::Error {
+    rusqlite::Error::InvalidParameterName(format!("database backup {operation} operation failed"))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    struct TestDirectory {
+        root: PathBuf,
+    }
+
+    impl TestDirectory {
+        fn new(label: &str) -> Self {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system time should be after epoch")
+                .as_nanos();
+            let root = std::env::temp_dir().join(format!(
+                "copy_stack_{label}_{}_{}",
+                std::process::id(),
+                now
+            ));
+            std::fs::create_dir(&root).expect("test root should be created");
+            std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o700))
+                .expect("test root should be private");
+            Self { root }
+        }
+
+        fn database_path(&self) -> PathBuf {
+            self.root.join("copy_stack.db")
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn database_with_note(path: &Path, note: &str) -> Connection {
+        let connection = Connection::open(path).expect("database should open");
+        connection
+            .execute_batch("CREATE TABLE IF NOT EXISTS notes (body TEXT NOT NULL)")
+            .expect("table should be created");
+        connection
+            .execute("INSERT INTO notes (body) VALUES (?1)", [note])
+            .expect("note should be inserted");
+        connection
+    }
+
+    #[test]
+    fn snapshots_are_private_copies_listed_newest_first_and_rotated() {
+        let directory = TestDirectory::new("database_backup_rotation");
+        let database_path = directory.database_path();
+        let connection = database_with_note(&database_path, "first");
+
+        let first = snapshot_database(&connection, &database_path, 11, 1_000)
+            .expect("snapshot should be written");
+        let second = snapshot_database(&connection, &database_path, 11, 1_000)
+            .expect("a snapshot in the same millisecond should get its own name");
+        assert_ne!(first, second);
+        for path in [&first, &second, &directory.root.join(BACKUP_DIR_NAME)] {
+            let mode = std::fs::metadata(path)
+                .expect("backup entry should exist")
+                .permissions()
+                .mode()
+                & 0o777;
+            assert!(mode == 0o600 || mode == 0o700, "{mode:o}");
+        }
+        let copied: String = Connection::open(&first)
+            .expect("snapshot should open")
+            .query_row("SELECT body FROM notes", [], |row| row.get(0))
+            .expect("snapshot should hold the note");
+        assert_eq!(copied, "first");
+
+        for created_at in 2_000..2_000 + MAX_DATABASE_BACKUPS as i64 {
+            snapshot_database(&connection, &database_path, 12, created_at)
+                .expect("snapshot should be written");
+        }
+        let stale_partial = partial_backup_path(
+            &backup_directory(&database_path).join(backup_file_name(9_000, 12)),
+        );
+        std::fs::write(&stale_partial, b"interrupted").expect("partial should be written");
+        let newer = backup_directory(&database_path)
+            .join(backup_file_name(10_000, CURRENT_SCHEMA_VERSION + 1));
+        std::fs::write(&newer, b"newer").expect("newer snapshot should be written");
+        std::fs::write(
+            backup_directory(&database_path).join("notes.txt"),
+            b"unrelated",
+        )
+        .expect("unrelated file should be written");
+
+        rotate_backups(&database_path).expect("rotation should succeed");
+
+        let backups = list_backups(&database_path).expect("backups should list");
+        assert_eq!(backups.len(), MAX_DATABASE_BACKUPS);
+        assert_eq!(
+            backups[0].created_at,
+            2_000 + MAX_DATABASE_BACKUPS as i64 - 1
+        );
+        assert!(backups.iter().all(|backup| backup.schema_version == 12));
+        assert!(backups.iter().all(|backup| backup.size_bytes > 0));
+        assert!(!first.exists());
+        assert!(!stale_partial.exists());
+        assert!(newer.exists(), "a newer version's snapshot is left alone");
+        assert!(backup_directory(&database_path).join("notes.txt").exists());
+    }
+
+    #[test]
+    fn the_newest_plaintext_snapshot_is_kept_until_removed() {
+        let directory = TestDirectory::new("database_backup_plaintext");
+        let database_path = directory.database_path();
+        let connection = database_with_note(&database_path, "note");
+        let older = snapshot_database(&connection, &database_path, 6, 1_000)
+            .expect("snapshot should be written");
+        let newest = snapshot_database(&connection, &database_path, 7, 2_000)
+            .expect("snapshot should be written");
+        for created_at in 3_000..3_000 + MAX_DATABASE_BACKUPS as i64 {
+            snapshot_database(&connection, &database_path, 12, created_at)
+                .expect("snapshot should be written");
+        }
+
+        scrub_plaintext_backups(&database_path).expect("scrubbing should succeed");
+        rotate_backups(&database_path).expect("rotation should succeed");
+
+        assert!(!older.exists());
+        assert!(newest.exists());
+        let backups = list_backups(&database_path).expect("backups should list");
+        assert_eq!(backups.len(), MAX_DATABASE_BACKUPS + 1);
+        assert_eq!(
+            backups
+                .iter()
+                .filter(|backup| backup.plaintext)
+                .map(|backup| backup.schema_version)
+                .collect::<Vec<_>>(),
+            vec![7]
+        );
+
+        let name = newest
+            .file_name()
+            .and_then(|name| name.to_str())
+            .expect("snapshot name should be text");
+        remove_backup(&database_path, name).expect("the snapshot should be removed");
+        remove_backup(&database_path, name).expect("removing twice should succeed");
+        assert!(!newest.exists());
+        assert_eq!(
+            list_backups(&database_path)
+                .expect("backups should list")
+                .len(),
+            MAX_DATABASE_BACKUPS
+        );
+    }
+
+    #[test]
+    fn only_listed_snapshot_names_resolve() {
+        let directory = TestDirectory::new("database_backup_names");
+        let database_path = directory.database_path();
+        let connection = database_with_note(&database_path, "note");
+        let snapshot = snapshot_database(&connection, &database_path, 2, 5_000)
+            .expect("snapshot should be written");
+        let name = snapshot
+            .file_name()
+            .and_then(|name| name.to_str())
+            .expect("snapshot name should be text");
+
+        assert_eq!(
+            backup_path(&database_path, name).expect("lookup should succeed"),
+            Some(snapshot.clone())
+        );
+        for name in [
+            "../copy_stack.db",
+            "copy_stack-5000-v2.db.partial",
+            "copy_stack-05000-v2.db",
+            "copy_stack--5000-v2.db",
+            "copy_stack-5000-v2.db/..",
+            "copy_stack-6000-v2.db",
+            &backup_file_name(7_000, CURRENT_SCHEMA_VERSION + 1),
+        ] {
+            assert_eq!(
+                backup_path(&database_path, name).expect("lookup should succeed"),
+                None,
+                "{name}"
+            );
+        }
+
+        let link = backup_directory(&database_path).join(backup_file_name(8_000, 2));
+        std::os::unix::fs::symlink(&snapshot, &link).expect("symlink should be created");
+        assert_eq!(
+            backup_path(&database_path, &backup_file_name(8_000, 2))
+                .expect("lookup should succeed"),
+            None
+        );
+        assert_eq!(
+            list_backups(&database_path)
+                .expect("backups should list")
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Sealed flavor payloads kept as files instead of SQLite BLOBs.
+//!
+//! Files live in a private `blobs/` directory beside `copy_stack.db`, one per
+//! payload, named by the payload's lowercase hex SHA-256 digest. A file holds
+//! the same sealed bytes `flavor_blobs.data` would, and is installed through
+//! a same-directory temporary file, so a reader never sees a partial write.
+//! Which files the history still needs is recorded in the database; see
+//! `store::flavor_blobs`.
+
+use crate::event::MAX_EVENT_BLOB_BYTES;
+use crate::private_fs::{create_private_temp_file, ensure_private_directory, read_private_file};
+use rusqlite::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BLOB_DIR_NAME: &str = "blobs";
+/// Room for the sealing header and tag around the largest payload.
+const MAX_BLOB_FILE_BYTES: u64 = MAX_EVENT_BLOB_BYTES as u64 + 1024;
+
+/// Atomically writes the sealed payload `digest`, replacing any file a write
+/// that never committed left under the same name.
+pub(super) fn write_blob_file(database_path: &Path, digest: &str, sealed: &[u8]) -> Result<()> {
+    let directory = ensure_private_directory(&blob_directory(database_path))
+        .map_err(|_| blob_file_error("prepare"))?;
+    let path = directory.join(digest);
+    let mut temp = create_private_temp_file(&path, 0).map_err(|_| blob_file_error("create"))?;
+    temp.file_mut()
+        .write_all(sealed)
+        .map_err(|_| blob_file_error("write"))?;
+    temp.sync_all().map_err(|_| blob_file_error("sync"))?;
+    temp.commit(&path).map_err(|_| blob_file_error("commit"))
+}
+
+/// Reads the sealed payload `digest`, or `None` when its file is missing.
+pub(super) fn read_blob_file(database_path: &Path, digest: &str) -> Result<Option<Vec<u8>>> {
+    read_private_file(
+        &blob_directory(database_path).join(digest),
+        MAX_BLOB_FILE_BYTES,
+    )
+    .map_err(|_| blob_file_error("read"))
+}
+
+/// Lists the names of every file in the blob directory, including ones no
+/// payload is stored under.
+pub(super) fn blob_file_names(database_path: &Path) -> Result<Vec<String>> {
+    let entries = match std::fs::read_dir(blob_directory(database_path)) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(_) => return Err(blob_file_error("list")),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|_| blob_file_error("list"))?;
+        let is_directory = entry
+            .file_type()
+            .map_err(|_| blob_file_error("list"))?
+            .is_dir();
+        if is_directory {
+            continue;
+        }
+        if let Ok(name) = entry.file_name().into_string() {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Removes the blob directory entry `name`. A missing entry is not an error.
+pub(super) fn remove_blob_file(database_path: &Path, name: &str) -> Result<()> {
+    match std::fs::remove_file(blob_directory(database_path).join(name)) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(_) => Err(blob_file_error("remove")),
+    }
+}
+
+fn blob_directory(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(BLOB_DIR_NAME)
+}
+
+fn blob_file_error(operation: &'static str) -> rusqlite::Error {
+    rusqlite::Error::InvalidParameterName(format!("flavor blob file {operation} operation failed"))
+}
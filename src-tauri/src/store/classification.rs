@@ -3,6 +3,7 @@
 //! This module intentionally has no SQLite or filesystem dependencies. Storage
 //! owns persistence and delegates representation selection to these functions.
 
+use crate::event::EventBlobIndex;
 use crate::pasteboard_protocol::{REMOTE_CLIPBOARD_TYPE, SOURCE_TYPE};
 use copy_event_listener::event::{Data, Event, Item};
 use sha2::{Digest, Sha256};
@@ -163,6 +164,31 @@ pub(super) fn compact_text_event(event: &Event) -> Option<Event> {
     })
 }
 
+/// `compact_text_event` read straight from an event blob. Attachment flavors
+/// are ruled out by type, so images and files are never decoded.
+pub(super) fn compact_text_event_from_blob(event_data: &[u8]) -> Result<Option<Event>, String> {
+    let index = EventBlobIndex::parse(event_data)?;
+    if index.item_count() != 1
+        || index
+            .flavors()
+            .iter()
+            .any(|flavor| is_attachment_type(&flavor.data_type().to_ascii_lowercase()))
+    {
+        return Ok(None);
+    }
+
+    let event = index.decode_flavors(|data_type| {
+        matches!(
+            data_type,
+            "public.utf8-plain-text" | SOURCE_TYPE | REMOTE_CLIPBOARD_TYPE
+        ) || matches!(
+            data_type.to_ascii_lowercase().as_str(),
+            "public.html" | "public.rtf"
+        )
+    })?;
+    Ok(compact_text_event(&event))
+}
+
 pub(super) fn label_for_data_type(data_type: &str) -> String {
     match data_type {
         "files" => "Files".to_string(),
@@ -453,25 +479,31 @@ fn event_contains_attachment(event: &Event) -> bool {
         .flat_map(|item| &item.data_list)
         .any(|data| {
             let data_type = data.r#type.to_ascii_lowercase();
-            matches!(
-                data_type.as_str(),
-                "public.file-url"
-                    | "public.image"
-                    | "public.png"
-                    | "public.tiff"
-                    | "public.jpeg"
-                    | "public.jpg"
-                    | "public.gif"
-                    | "public.heic"
-                    | "public.webp"
-                    | "public.bmp"
-                    | "public.movie"
-                    | "public.video"
-            ) || (data_type == "public.html" && html_contains_attachment(&data.data))
+            is_attachment_type(&data_type)
+                || (data_type == "public.html" && html_contains_attachment(&data.data))
                 || (data_type == "public.rtf" && rtf_contains_attachment(&data.data))
         })
 }
 
+/// Expects a lowercased flavor type.
+fn is_attachment_type(data_type: &str) -> bool {
+    matches!(
+        data_type,
+        "public.file-url"
+            | "public.image"
+            | "public.png"
+            | "public.tiff"
+            | "public.jpeg"
+            | "public.jpg"
+            | "public.gif"
+            | "public.heic"
+            | "public.webp"
+            | "public.bmp"
+            | "public.movie"
+            | "public.video"
+    )
+}
+
 fn html_contains_attachment(data: &[u8]) -> bool {
     let html = String::from_utf8_lossy(data).to_ascii_lowercase();
     ["<img", "<picture", "<video", "<object", "<embed"]
@@ -573,6 +605,66 @@ mod tests {
         .is_none());
     }
 
+    #[test]
+    fn compact_projection_from_blob_matches_the_decoded_event() {
+        let text = || Data {
+            r#type: "public.utf8-plain-text".to_string(),
+            data: b"hello".to_vec(),
+        };
+        let events = [
+            event(vec![
+                text(),
+                Data {
+                    r#type: SOURCE_TYPE.to_string(),
+                    data: b"com.example.source".to_vec(),
+                },
+                Data {
+                    r#type: "public.rtf".to_string(),
+                    data: b"{\\rtf1 hello}".to_vec(),
+                },
+            ]),
+            event(vec![
+                text(),
+                Data {
+                    r#type: "public.TIFF".to_string(),
+                    data: vec![0; 64 * 1024],
+                },
+            ]),
+            event(vec![
+                text(),
+                Data {
+                    r#type: "public.html".to_string(),
+                    data: b"<p>hello</p><IMG src=x>".to_vec(),
+                },
+            ]),
+            Event {
+                items: vec![
+                    Item {
+                        data_list: vec![text()],
+                    };
+                    2
+                ],
+            },
+        ];
+
+        for event in events {
+            let blob = crate::event::encode_event_blob(&event).unwrap();
+            let projected = compact_text_event_from_blob(&blob).unwrap();
+            let expected = compact_text_event(&event);
+            assert_eq!(projected.is_some(), expected.is_some());
+            if let (Some(projected), Some(expected)) = (projected, expected) {
+                let flavors = |event: &Event| {
+                    event.items[0]
+                        .data_list
+                        .iter()
+                        .map(|data| (data.r#type.clone(), data.data.clone()))
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(flavors(&projected), flavors(&expected));
+            }
+        }
+    }
+
     #[test]
     fn file_display_parser_rejects_unknown_format() {
         let encoded = serde_json::to_vec(&FileDisplay {
@@ -1,35 +1,68 @@
 use crate::event::{
-    decode_event_blob, encode_event_blob, event_from_legacy_json, MAX_EVENT_BLOB_BYTES,
+    decode_event_blob, encode_event_blob, encode_plain_event_blob, event_from_legacy_json,
+    share_large_flavors, SharedFlavor, MAX_EVENT_BLOB_BYTES,
 };
+use crate::history_backup::{HistoryBackup, HistoryBackupRow};
+use crate::history_import::HistoryImport;
 use crate::i18n::LanguagePreference;
 use crate::pasteboard_protocol::{
-    assess_event, PasteboardMetadata, REMOTE_CLIPBOARD_TYPE, SOURCE_TYPE,
+    apply_source_policy, assess_event, HistoryDisposition, PasteboardAssessment,
+    PasteboardMetadata, SkipReason, SourceAppPolicy, MAX_SOURCE_BUNDLE_ID_BYTES,
+    REMOTE_CLIPBOARD_TYPE, SOURCE_TYPE,
+};
+use crate::resource_policy::{
+    prepare_capture_event, CaptureResourceRejection, MAX_DISPLAY_BYTES, MAX_PINNED_BYTES,
+    MAX_PINNED_ITEMS, MAX_TRAY_PREVIEW_BYTES,
 };
 #[cfg(test)]
 use crate::resource_policy::{
     MAX_DETAIL_IPC_BYTES, MAX_HTML_BYTES, MAX_PREVIEW_IMAGE_BYTES, MAX_PREVIEW_SEGMENTS,
 };
-use crate::resource_policy::{MAX_DISPLAY_BYTES, MAX_TRAY_PREVIEW_BYTES};
+use crate::secret_detection::{
+    screen_event, SecretAction, SecretKind, SecretPolicy, SECRET_EXPIRY,
+};
+use crate::store::backups;
 use crate::store::classification::{
     self, ClassifiedEvent, FileDisplay, FileDisplayItem, FILE_DISPLAY_FORMAT,
 };
+#[cfg(test)]
+use crate::store::encryption::MemoryKeyProvider;
+use crate::store::encryption::{
+    FileKeyProvider, KeyBytes, KeyProvider, PayloadCipher, PayloadColumn, PayloadKeyError,
+    INTEGRITY_KEY_FILE_NAME, KEY_FILE_NAME,
+};
+use crate::store::flavor_blobs;
+use crate::store::integrity::{self, ChainPosition, HistoryIntegrityKey, IntegrityFields};
+use crate::store::labels;
 use crate::store::models::{
-    AppSettings, HistoryCursor, HistoryDetail, HistoryDetailSeed, HistoryPage, HistoryStats,
-    HistorySummary, TrayEvent, TrayPreview, DEFAULT_HISTORY_PAGE_SIZE, MAX_HISTORY_PAGE_SIZE,
-    MAX_MENU_BAR_ITEM_LIMIT, MAX_SUMMARY_DISPLAY_BYTES,
+    AppSettings, BackupResult, BlobFileReport, CorruptDatabase, DataTypeBudget, DatabaseBackup,
+    DatabaseBackupNotFound, DatabaseBackupResult, HistoryCheckReport, HistoryCheckResult,
+    HistoryCollection, HistoryCursor, HistoryDetail, HistoryDetailSeed, HistoryExportEntry,
+    HistoryImportReport, HistoryIntegrityReport, HistoryLabelFilter, HistoryLabels, HistoryPage,
+    HistorySearchPage, HistorySearchResult, HistoryStats, HistorySummary, HistoryTag,
+    IntegrityResult, IntegrityViolation, InvalidBackup, LabelResult, PinOutcome,
+    SecretPolicySetting, SourceAppRule, TrayEvent, TrayPreview, DEFAULT_HISTORY_PAGE_SIZE,
+    MAX_HISTORY_PAGE_SIZE, MAX_MENU_BAR_ITEM_LIMIT, MAX_SUMMARY_DISPLAY_BYTES,
 };
 use crate::store::preview;
 #[cfg(test)]
 use crate::store::preview::StoredPreviewSegment;
+use crate::store::query::HistoryQuery;
 use crate::store::schema::{
-    self, CLASSIFIER_METADATA_KEY, CLASSIFIER_METADATA_VERSION, CURRENT_SCHEMA_VERSION,
-    REQUIRED_EVENT_COLUMNS,
+    self, MigrationStep, CLASSIFIER_METADATA_KEY, CLASSIFIER_METADATA_VERSION,
+    CURRENT_SCHEMA_VERSION, INTEGRITY_KEY_METADATA_KEY, INTEGRITY_KEY_METADATA_VERSION,
+    PAYLOAD_ENCRYPTION_KEY, PAYLOAD_ENCRYPTION_VERSION, REQUIRED_EVENT_COLUMNS,
+    SEARCH_INDEX_TRIGGERS,
 };
+use crate::store::search;
 use crate::store::settings;
 use chrono::Utc;
 use copy_event_listener::event::{Data, Event, Item};
 use rusqlite::{
-    params, types::ValueRef, Connection, OpenFlags, OptionalExtension, Result, Transaction,
+    backup::Progress,
+    params, params_from_iter,
+    types::{Value, ValueRef},
+    Connection, DatabaseName, OpenFlags, OptionalExtension, Result, Transaction,
 };
 #[cfg(test)]
 use serde::Serialize;
@@ -41,11 +74,13 @@ use std::fs::File;
 #[cfg(test)]
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tauri::AppHandle;
 
 const APP_DATA_DIR: &str = ".copy_stack";
 const DB_FILE_NAME: &str = "copy_stack.db";
-const MAX_SOURCE_BUNDLE_ID_BYTES: usize = 255;
+const PRE_ENCRYPTION_BACKUP_FILE_NAME: &str = "copy_stack.db.pre-encryption";
+const MILLIS_PER_DAY: i64 = 86_400_000;
 #[cfg(test)]
 const INLINE_ATTACHMENT_PLACEHOLDER: char = '\u{fffc}';
 
@@ -84,6 +119,20 @@ impl StoredEvent {
 struct DbRow {
     event_data: Vec<u8>,
     timestamp: i64,
+    pinned: bool,
+    first_seen_at: i64,
+    last_seen_at: i64,
+    copy_count: i64,
+    expires_at: Option<i64>,
+}
+
+/// One existing row that shares the incoming event's compact text.
+struct CompactMatch {
+    content_hash: String,
+    timestamp: i64,
+    pinned: bool,
+    first_seen_at: i64,
+    copy_count: i64,
 }
 
 struct PersistedMetadata {
@@ -94,13 +143,27 @@ struct PersistedMetadata {
     compact_content_hash: Option<String>,
     compact_display: Option<Vec<u8>>,
     byte_count: u64,
+    /// Unix milliseconds after which the row is deleted regardless of the
+    /// retention limits; `None` for ordinary captures.
+    expires_at: Option<i64>,
+}
+
+/// The payload columns of one row, sealed for its `content_hash`.
+struct SealedPayloads {
+    event_data: Vec<u8>,
+    display: Vec<u8>,
+    summary_display: Vec<u8>,
+    compact_display: Option<Vec<u8>>,
 }
 
 pub(crate) struct PreparedHistoryEvent {
     event: Event,
+    /// The event blob as stored, with `shared_flavors` referenced by digest.
     event_data: Vec<u8>,
+    shared_flavors: Vec<SharedFlavor>,
     classified: ClassifiedEvent,
     metadata: PersistedMetadata,
+    search_text: String,
     compact_mode: bool,
 }
 
@@ -108,6 +171,40 @@ impl PreparedHistoryEvent {
     pub(crate) fn content_hash(&self) -> &str {
         &self.classified.content_hash
     }
+
+    /// Marks the capture for deletion once `lifetime` has passed, even while
+    /// it would otherwise stay within the retention limits.
+    pub(crate) fn expire_after(&mut self, lifetime: Duration) {
+        let lifetime = i64::try_from(lifetime.as_millis()).unwrap_or(i64::MAX);
+        self.metadata.expires_at =
+            Some(Database::current_unix_timestamp().saturating_add(lifetime));
+    }
+
+    pub(crate) fn expires(&self) -> bool {
+        self.metadata.expires_at.is_some()
+    }
+}
+
+/// The persisted settings that decide whether and how a capture is recorded,
+/// read together so the event can be prepared after the lock is released.
+#[derive(Clone, Debug)]
+pub(crate) struct CapturePolicy {
+    source_app_policy: SourceAppPolicy,
+    secret_policy: SecretPolicy,
+    compact_mode: bool,
+}
+
+/// Where the capture pipeline left an event.
+pub(crate) enum CaptureOutcome {
+    Prepared(PreparedHistoryEvent),
+    /// The pasteboard protocol or the source application policy excludes it.
+    Skipped(SkipReason),
+    /// A flavor or the whole event exceeds its capture budget.
+    Rejected(CaptureResourceRejection),
+    /// A secret detector whose action is `skip` matched.
+    Secret(SecretKind),
+    /// Nothing in the event classifies as a history row.
+    Unsupported,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -123,15 +220,41 @@ impl RestoreSeed {
     /// Decodes and applies the current compact projection after the database
     /// lock has been released.
     pub fn into_event(self) -> Result<Option<Event>> {
-        let event = Database::event_from_blob(&self.event_data)?;
         if self.compact_mode {
-            Ok(Database::compact_text_event(&event))
+            Database::compact_text_event_from_blob(&self.event_data)
         } else {
-            Ok(Some(event))
+            Database::event_from_blob(&self.event_data).map(Some)
         }
     }
 }
 
+const CHAINED_ROW_COLUMNS: &str =
+    "event_data, timestamp, source_bundle_id, is_remote_clipboard, integrity_mac, integrity_link";
+
+/// The columns a lazy lookup verifies before handing out a row's payload,
+/// read in `CHAINED_ROW_COLUMNS` order. `event_data` is still sealed.
+struct ChainedRow {
+    event_data: Vec<u8>,
+    timestamp: i64,
+    source_bundle_id: Option<String>,
+    is_remote_clipboard: bool,
+    integrity_mac: Vec<u8>,
+    integrity_link: Vec<u8>,
+}
+
+impl ChainedRow {
+    fn from_row(row: &rusqlite::Row<'_>) -> Result<Self> {
+        Ok(Self {
+            event_data: row.get(0)?,
+            timestamp: row.get(1)?,
+            source_bundle_id: row.get(2)?,
+            is_remote_clipboard: row.get(3)?,
+            integrity_mac: row.get(4)?,
+            integrity_link: row.get(5)?,
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 struct MigrationStats {
     source_rows: u64,
@@ -140,6 +263,25 @@ struct MigrationStats {
     policy_dropped_rows: u64,
 }
 
+/// Why `check_history_integrity` moved a row out of history, stored in
+/// `quarantined_events.reason`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum QuarantineReason {
+    Undecryptable,
+    Undecodable,
+    Mismatched,
+}
+
+impl QuarantineReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Undecryptable => "undecryptable",
+            Self::Undecodable => "undecodable",
+            Self::Mismatched => "content_hash_mismatch",
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum MigrationFailpoint {
@@ -147,6 +289,9 @@ enum MigrationFailpoint {
     AfterCopy,
     AfterValidation,
     AfterDropOriginal,
+    /// After the registered step that reaches this `user_version`, before
+    /// its savepoint is released.
+    AfterStep(i64),
 }
 
 #[derive(Clone, Debug)]
@@ -197,6 +342,8 @@ struct HistoryJsonlBytes {
 pub struct Database {
     conn: Connection,
     path: Option<PathBuf>,
+    cipher: PayloadCipher,
+    integrity: HistoryIntegrityKey,
 }
 
 #[cfg(test)]
@@ -262,6 +409,30 @@ impl Database {
         Self::open_private_database(path)
     }
 
+    /// Opens the app's existing database for a client running beside the
+    /// app, such as `copy-stack-cli`. Neither open creates, migrates, or
+    /// encrypts: a database the app has not brought up to date is refused.
+    /// A writable open then goes through the app's own open; a read-only one
+    /// also leaves every file as it finds it.
+    pub fn open_existing(writable: bool) -> Result<Self> {
+        let db_path = Self::database_path()?;
+        if writable {
+            Self::open_private_database_writable(&db_path)
+        } else {
+            Self::open_private_database_read_only(&db_path)
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_path_read_only(path: &std::path::Path) -> Result<Self> {
+        Self::open_private_database_read_only(path)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn open_path_writable(path: &std::path::Path) -> Result<Self> {
+        Self::open_private_database_writable(path)
+    }
+
     fn database_path() -> Result<PathBuf> {
         #[cfg(debug_assertions)]
         if let Some(qa_data_dir) = std::env::var_os("COPY_STACK_QA_DATA_DIR") {
@@ -289,19 +460,467 @@ impl Database {
     fn open_private_database(path: &Path) -> Result<Self> {
         let path = crate::private_fs::prepare_sqlite_database(path)
             .map_err(|_| Self::private_database_error("prepare"))?;
+        Self::open_private_database_with(
+            &path,
+            &FileKeyProvider::beside_database(&path),
+            &FileKeyProvider::integrity_key_beside_database(&path),
+        )
+    }
+
+    /// Opens an existing database through `open_private_database`, once a
+    /// read-only look shows the app has brought it up to date, so a client
+    /// never runs a migration or takes its snapshot.
+    fn open_private_database_writable(path: &Path) -> Result<Self> {
+        let path = crate::private_fs::resolve_private_path(path)
+            .map_err(|_| Self::private_database_error("resolve"))?;
+        if std::fs::symlink_metadata(&path).is_err() {
+            return Err(Self::private_database_error("find"));
+        }
+        let conn = Self::open_read_only_connection(&path)?;
+        Self::require_app_initialized_in(&conn)?;
+        drop(conn);
+        Self::open_private_database(&path)
+    }
+
+    /// Opens a database over a read-only connection. Since nothing can be
+    /// migrated or sealed, a database the app has not brought up to date, or
+    /// whose keys are missing, is refused. The database and key files are
+    /// checked like the app's own open but never tightened: a file with the
+    /// wrong owner or mode is refused instead.
+    fn open_private_database_read_only(path: &Path) -> Result<Self> {
+        let path = crate::private_fs::resolve_private_path(path)
+            .map_err(|_| Self::private_database_error("resolve"))?;
+        if std::fs::symlink_metadata(&path).is_err() {
+            return Err(Self::private_database_error("find"));
+        }
+        crate::private_fs::verify_sqlite_files(&path)
+            .map_err(|_| Self::private_database_error("read-only validation"))?;
+        for key_file in [KEY_FILE_NAME, INTEGRITY_KEY_FILE_NAME] {
+            crate::private_fs::verify_private_file_if_exists(&path.with_file_name(key_file))
+                .map_err(|_| Self::private_database_error("read-only validation"))?;
+        }
+        let conn = Self::open_read_only_connection(&path)?;
+        Self::require_app_initialized_in(&conn)?;
+
+        let key_encryption_key = FileKeyProvider::beside_database(&path)
+            .load_key()
+            .and_then(|key| key.ok_or(PayloadKeyError::Missing))
+            .map_err(Self::payload_key_error)?;
+        let wrapped_key = Self::wrapped_payload_key_in(&conn)?
+            .ok_or_else(|| Self::payload_key_error(PayloadKeyError::Missing))?;
+        let cipher = PayloadCipher::unwrap(&key_encryption_key, &wrapped_key)
+            .map_err(Self::payload_key_error)?;
+        let integrity_key = FileKeyProvider::integrity_key_beside_database(&path)
+            .load_key()
+            .and_then(|key| key.ok_or(PayloadKeyError::Missing))
+            .map_err(Self::payload_key_error)?;
+        let integrity =
+            HistoryIntegrityKey::derive(&integrity_key).map_err(Self::payload_key_error)?;
+        schema::create_memory_search_index(&conn)?;
+        Self::load_search_index_in(&conn, &cipher)?;
+        Ok(Self {
+            conn,
+            path: Some(path),
+            cipher,
+            integrity,
+        })
+    }
+
+    fn open_read_only_connection(path: &Path) -> Result<Connection> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX
+                | OpenFlags::SQLITE_OPEN_NOFOLLOW,
+        )?;
+        conn.busy_timeout(Duration::from_secs(2))?;
+        Self::configure_connection(&conn)?;
+        Ok(conn)
+    }
+
+    /// Refuses a database that the app's own open would still create,
+    /// migrate, or rebuild.
+    fn require_app_initialized_in(connection: &Connection) -> Result<()> {
+        if !Self::table_exists_in(connection, "clipboard_events")?
+            || schema::user_version(connection)? != CURRENT_SCHEMA_VERSION
+            || Self::history_migration_pending_in(connection)?
+        {
+            return Err(rusqlite::Error::InvalidParameterName(
+                "database must be opened by the app before it can be read".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Opens a prepared database file and encrypts any plaintext history.
+    ///
+    /// Plaintext history is first copied to a private backup. The backup is
+    /// removed once the encrypted rows open with a key freshly reloaded from
+    /// `key_provider`; if they do not, it replaces the database again.
+    fn open_private_database_with(
+        path: &Path,
+        key_provider: &dyn KeyProvider,
+        integrity_key_provider: &dyn KeyProvider,
+    ) -> Result<Self> {
+        Self::open_prepared_database(path, key_provider, integrity_key_provider, true)
+    }
+
+    /// Opens a prepared database file, first snapshotting history that the
+    /// schema initialization is about to rebuild when `snapshot_migrations`
+    /// is set.
+    fn open_prepared_database(
+        path: &Path,
+        key_provider: &dyn KeyProvider,
+        integrity_key_provider: &dyn KeyProvider,
+        snapshot_migrations: bool,
+    ) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        Self::configure_connection(&conn)?;
+        if snapshot_migrations && Self::history_migration_pending_in(&conn)? {
+            backups::snapshot_database(
+                &conn,
+                path,
+                schema::user_version(&conn)?,
+                Self::current_unix_timestamp(),
+            )?;
+            backups::rotate_backups(path)?;
+        }
+        let (cipher, integrity, legacy_integrity) =
+            Self::load_history_keys_in(&conn, key_provider, integrity_key_provider)?;
+        let backup_path = Self::back_up_plaintext_history_in(&conn, path)?;
         let db = Self {
-            conn: Connection::open(&path)?,
-            path: Some(path.clone()),
+            conn,
+            path: Some(path.to_path_buf()),
+            cipher,
+            integrity,
         };
 
         let schema_result = db.initialize_schema();
-        let hardening_result = crate::private_fs::harden_sqlite_files(&path)
+        let hardening_result = crate::private_fs::harden_sqlite_files(path)
             .map_err(|_| Self::private_database_error("harden"));
+        if let Some(backup_path) = backup_path {
+            if schema_result.is_ok() && db.verify_sealed_payloads(key_provider).is_err() {
+                drop(db);
+                std::fs::rename(&backup_path, path)
+                    .map_err(|_| Self::private_database_error("restore"))?;
+                return Err(Self::private_database_error("encryption verification"));
+            }
+            Self::remove_file_if_exists(&backup_path)?;
+        }
         schema_result?;
         hardening_result?;
+        db.adopt_integrity_key(&legacy_integrity)?;
+        backups::scrub_plaintext_backups(path)?;
         Ok(db)
     }
 
+    /// Loads the payload cipher and the history integrity key, creating and
+    /// storing keys a database does not have yet. Also returns the integrity
+    /// key that versions before the dedicated key file derived from the
+    /// key-encryption key, for `adopt_integrity_key`.
+    fn load_history_keys_in(
+        connection: &Connection,
+        key_provider: &dyn KeyProvider,
+        integrity_key_provider: &dyn KeyProvider,
+    ) -> Result<(PayloadCipher, HistoryIntegrityKey, HistoryIntegrityKey)> {
+        let (cipher, key_encryption_key) = Self::load_payload_key_in(connection, key_provider)?;
+        let integrity = Self::load_integrity_key_in(connection, integrity_key_provider)?;
+        let legacy_integrity =
+            HistoryIntegrityKey::derive(&key_encryption_key).map_err(Self::payload_key_error)?;
+        Ok((cipher, integrity, legacy_integrity))
+    }
+
+    /// Unwraps the database's payload key, creating and storing one for a
+    /// database that has none yet.
+    fn load_payload_key_in(
+        connection: &Connection,
+        key_provider: &dyn KeyProvider,
+    ) -> Result<(PayloadCipher, KeyBytes)> {
+        schema::create_payload_key_table(connection)?;
+        let key_encryption_key = key_provider.load_key().map_err(Self::payload_key_error)?;
+        if let Some(wrapped_key) = Self::wrapped_payload_key_in(connection)? {
+            let unwrapped =
+                key_encryption_key
+                    .ok_or(PayloadKeyError::Missing)
+                    .and_then(|key_encryption_key| {
+                        PayloadCipher::unwrap(&key_encryption_key, &wrapped_key)
+                            .map(|cipher| (cipher, key_encryption_key))
+                    });
+            match unwrapped {
+                Ok(keys) => return Ok(keys),
+                // A key left by an encryption that was rolled back has sealed
+                // nothing, so it is replaced rather than locking the history.
+                Err(_) if !Self::payloads_sealed_in(connection)? => {
+                    connection.execute("DELETE FROM payload_key", [])?;
+                }
+                Err(error) => return Err(Self::payload_key_error(error)),
+            }
+        }
+
+        let key_encryption_key = match key_encryption_key {
+            Some(key_encryption_key) => key_encryption_key,
+            None => key_provider.create_key().map_err(Self::payload_key_error)?,
+        };
+        let (cipher, wrapped_key) =
+            PayloadCipher::generate(&key_encryption_key).map_err(Self::payload_key_error)?;
+        connection.execute(
+            "INSERT INTO payload_key (key_id, wrapped_key) VALUES (1, ?1)",
+            [wrapped_key],
+        )?;
+        Ok((cipher, key_encryption_key))
+    }
+
+    /// Loads the history integrity key, creating one unless the history has
+    /// already been signed with a key that is now missing.
+    fn load_integrity_key_in(
+        connection: &Connection,
+        integrity_key_provider: &dyn KeyProvider,
+    ) -> Result<HistoryIntegrityKey> {
+        let integrity_key = match integrity_key_provider
+            .load_key()
+            .map_err(Self::payload_key_error)?
+        {
+            Some(integrity_key) => integrity_key,
+            None if Self::integrity_key_adopted_in(connection)? => {
+                return Err(Self::payload_key_error(PayloadKeyError::Missing));
+            }
+            None => integrity_key_provider
+                .create_key()
+                .map_err(Self::payload_key_error)?,
+        };
+        HistoryIntegrityKey::derive(&integrity_key).map_err(Self::payload_key_error)
+    }
+
+    /// Moves a history chain that an older version signed with the key
+    /// derived from the key-encryption key over to the integrity key file.
+    ///
+    /// The chain is re-signed only if it verifies under the old key, so
+    /// tampering from before the move still fails verification afterwards.
+    fn adopt_integrity_key(&self, legacy_integrity: &HistoryIntegrityKey) -> Result<()> {
+        let transaction = self.conn.unchecked_transaction()?;
+        if Self::integrity_key_adopted_in(&transaction)? {
+            return Ok(());
+        }
+        if integrity::verify_all_in(&transaction, &self.cipher, legacy_integrity)?.is_ok() {
+            integrity::reseal_all_in(&transaction, &self.cipher, &self.integrity)?;
+        }
+        Self::set_metadata_version_in(
+            &transaction,
+            INTEGRITY_KEY_METADATA_KEY,
+            INTEGRITY_KEY_METADATA_VERSION,
+        )?;
+        transaction.commit()
+    }
+
+    fn wrapped_payload_key_in(connection: &Connection) -> Result<Option<Vec<u8>>> {
+        connection
+            .query_row(
+                "SELECT wrapped_key FROM payload_key WHERE key_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Reports whether `initialize_schema` will migrate or rebuild an existing
+    /// history table, reading only; a database without one has nothing to
+    /// lose.
+    fn history_migration_pending_in(connection: &Connection) -> Result<bool> {
+        if !Self::table_exists_in(connection, "clipboard_events")? {
+            return Ok(false);
+        }
+        if schema::user_version(connection)? < CURRENT_SCHEMA_VERSION
+            || !Self::table_exists_in(connection, "app_metadata")?
+        {
+            return Ok(true);
+        }
+        let columns = Self::table_columns_in(connection, "clipboard_events")?;
+        Ok(
+            !Self::clipboard_events_schema_is_current(connection, &columns)?
+                || Self::metadata_version_in(connection, CLASSIFIER_METADATA_KEY)?
+                    < CLASSIFIER_METADATA_VERSION
+                || Self::metadata_version_in(connection, PAYLOAD_ENCRYPTION_KEY)?
+                    < PAYLOAD_ENCRYPTION_VERSION
+                || !Self::integrity_key_adopted_in(connection)?,
+        )
+    }
+
+    /// Copies history that has not been encrypted yet to a private sibling
+    /// file, returning its path. Databases without plaintext rows need no
+    /// backup; a backup left behind by an interrupted start is replaced.
+    fn back_up_plaintext_history_in(
+        connection: &Connection,
+        path: &Path,
+    ) -> Result<Option<PathBuf>> {
+        let backup_path = path.with_file_name(PRE_ENCRYPTION_BACKUP_FILE_NAME);
+        Self::remove_file_if_exists(&backup_path)?;
+        if Self::payloads_sealed_in(connection)?
+            || !Self::table_exists_in(connection, "clipboard_events")?
+        {
+            return Ok(None);
+        }
+        let has_rows = connection
+            .query_row("SELECT 1 FROM clipboard_events LIMIT 1", [], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !has_rows {
+            return Ok(None);
+        }
+
+        let backup_path = crate::private_fs::prepare_sqlite_database(&backup_path)
+            .map_err(|_| Self::private_database_error("prepare backup"))?;
+        let backup_target = backup_path
+            .to_str()
+            .ok_or_else(|| Self::private_database_error("prepare backup"))?;
+        if let Err(error) = connection.execute("VACUUM INTO ?1", [backup_target]) {
+            let _ = Self::remove_file_if_exists(&backup_path);
+            return Err(error);
+        }
+        crate::private_fs::harden_sqlite_files(&backup_path)
+            .map_err(|_| Self::private_database_error("harden backup"))?;
+        Ok(Some(backup_path))
+    }
+
+    /// Opens every sealed payload with a payload key unwrapped from a freshly
+    /// loaded key-encryption key.
+    fn verify_sealed_payloads(&self, key_provider: &dyn KeyProvider) -> Result<()> {
+        let key_encryption_key = key_provider
+            .load_key()
+            .map_err(Self::payload_key_error)?
+            .ok_or_else(|| Self::payload_key_error(PayloadKeyError::Missing))?;
+        let wrapped_key = Self::wrapped_payload_key_in(&self.conn)?
+            .ok_or_else(|| Self::payload_key_error(PayloadKeyError::Missing))?;
+        let cipher = PayloadCipher::unwrap(&key_encryption_key, &wrapped_key)
+            .map_err(Self::payload_key_error)?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT content_hash, event_data, display, summary_display, compact_display
+             FROM clipboard_events",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let content_hash = row.get::<_, String>(0)?;
+            Self::open_payload(&cipher, &content_hash, PayloadColumn::EventData, row, 1)?;
+            Self::open_payload(&cipher, &content_hash, PayloadColumn::Display, row, 2)?;
+            Self::open_payload(
+                &cipher,
+                &content_hash,
+                PayloadColumn::SummaryDisplay,
+                row,
+                3,
+            )?;
+            Self::open_optional_payload(
+                &cipher,
+                &content_hash,
+                PayloadColumn::CompactDisplay,
+                row,
+                4,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn integrity_key_adopted_in(connection: &Connection) -> Result<bool> {
+        Ok(Self::table_exists_in(connection, "app_metadata")?
+            && Self::metadata_version_in(connection, INTEGRITY_KEY_METADATA_KEY)?
+                >= INTEGRITY_KEY_METADATA_VERSION)
+    }
+
+    fn payloads_sealed_in(connection: &Connection) -> Result<bool> {
+        Ok(Self::table_exists_in(connection, "app_metadata")?
+            && Self::metadata_version_in(connection, PAYLOAD_ENCRYPTION_KEY)?
+                >= PAYLOAD_ENCRYPTION_VERSION)
+    }
+
+    fn remove_file_if_exists(path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(_) => Err(Self::private_database_error("remove backup")),
+        }
+    }
+
+    fn payload_key_error(error: PayloadKeyError) -> rusqlite::Error {
+        rusqlite::Error::InvalidParameterName(format!("clipboard payload key failed: {error}"))
+    }
+
+    /// Reads and decrypts the payload `column` of the row keyed by
+    /// `content_hash`.
+    fn open_payload(
+        cipher: &PayloadCipher,
+        content_hash: &str,
+        column: PayloadColumn,
+        row: &rusqlite::Row<'_>,
+        index: usize,
+    ) -> Result<Vec<u8>> {
+        cipher
+            .open(content_hash, column, &row.get::<_, Vec<u8>>(index)?)
+            .map_err(|_| {
+                rusqlite::Error::InvalidParameterName(
+                    "clipboard payload could not be decrypted".to_string(),
+                )
+            })
+    }
+
+    /// Opens a row's `event_data` with its shared flavors put back.
+    fn open_event_data(
+        connection: &Connection,
+        cipher: &PayloadCipher,
+        content_hash: &str,
+        row: &rusqlite::Row<'_>,
+        index: usize,
+    ) -> Result<Vec<u8>> {
+        Self::open_payload(cipher, content_hash, PayloadColumn::EventData, row, index)
+            .and_then(|event_data| flavor_blobs::inline_in(connection, cipher, event_data))
+    }
+
+    fn open_optional_payload(
+        cipher: &PayloadCipher,
+        content_hash: &str,
+        column: PayloadColumn,
+        row: &rusqlite::Row<'_>,
+        index: usize,
+    ) -> Result<Option<Vec<u8>>> {
+        if row.get_ref(index)? == ValueRef::Null {
+            return Ok(None);
+        }
+        Self::open_payload(cipher, content_hash, column, row, index).map(Some)
+    }
+
+    fn seal_payloads(
+        cipher: &PayloadCipher,
+        content_hash: &str,
+        event_data: &[u8],
+        display: &[u8],
+        metadata: &PersistedMetadata,
+    ) -> Result<SealedPayloads> {
+        let seal = |column, plaintext: &[u8]| {
+            cipher.seal(content_hash, column, plaintext).map_err(|_| {
+                rusqlite::Error::InvalidParameterName(
+                    "clipboard payload could not be encrypted".to_string(),
+                )
+            })
+        };
+        Ok(SealedPayloads {
+            event_data: seal(PayloadColumn::EventData, event_data)?,
+            display: seal(PayloadColumn::Display, display)?,
+            summary_display: seal(PayloadColumn::SummaryDisplay, &metadata.summary_display)?,
+            compact_display: metadata
+                .compact_display
+                .as_deref()
+                .map(|compact_display| seal(PayloadColumn::CompactDisplay, compact_display))
+                .transpose()?,
+        })
+    }
+
+    /// Keeps temporary tables, which hold the plaintext search index, in
+    /// memory, and zeroes deleted content so that dropped rows do not linger
+    /// in the file's free pages.
+    fn configure_connection(connection: &Connection) -> Result<()> {
+        connection.pragma_update(None, "temp_store", "MEMORY")?;
+        connection.pragma_update(None, "secure_delete", true)
+    }
+
     fn private_database_error(operation: &'static str) -> rusqlite::Error {
         rusqlite::Error::InvalidParameterName(format!(
             "private database {operation} operation failed"
@@ -316,7 +935,7 @@ impl Database {
         &self,
         failpoint: Option<MigrationFailpoint>,
     ) -> Result<()> {
-        let transaction = self.conn.unchecked_transaction()?;
+        let mut transaction = self.conn.unchecked_transaction()?;
         let schema_version = schema::user_version(&transaction)?;
         if schema_version > CURRENT_SCHEMA_VERSION {
             return Err(rusqlite::Error::InvalidParameterName(format!(
@@ -329,48 +948,212 @@ impl Database {
         Self::insert_default_settings(&transaction)?;
 
         let table_exists = Self::table_exists_in(&transaction, "clipboard_events")?;
-        let columns = if table_exists {
-            Self::table_columns_in(&transaction, "clipboard_events")?
-        } else {
-            Vec::new()
-        };
         let classifier_version = Self::metadata_version_in(&transaction, CLASSIFIER_METADATA_KEY)?;
         if classifier_version > CLASSIFIER_METADATA_VERSION {
             return Err(rusqlite::Error::InvalidParameterName(format!(
                 "classifier metadata version {classifier_version} is newer than supported version {CLASSIFIER_METADATA_VERSION}"
             )));
         }
-        let current_shape =
-            table_exists && Self::clipboard_events_schema_is_current(&transaction, &columns)?;
+        if Self::metadata_version_in(&transaction, PAYLOAD_ENCRYPTION_KEY)?
+            > PAYLOAD_ENCRYPTION_VERSION
+        {
+            return Err(rusqlite::Error::InvalidParameterName(format!(
+                "payload encryption version is newer than supported version {PAYLOAD_ENCRYPTION_VERSION}"
+            )));
+        }
+        if Self::metadata_version_in(&transaction, INTEGRITY_KEY_METADATA_KEY)?
+            > INTEGRITY_KEY_METADATA_VERSION
+        {
+            return Err(rusqlite::Error::InvalidParameterName(format!(
+                "integrity key version is newer than supported version {INTEGRITY_KEY_METADATA_VERSION}"
+            )));
+        }
 
-        let rebuilt_history = if !table_exists {
+        let migrated_history = table_exists && schema_version < CURRENT_SCHEMA_VERSION;
+        let mut rebuilt_history = if !table_exists {
             schema::create_clipboard_events_table(&transaction, "clipboard_events")?;
             true
-        } else if schema_version < CURRENT_SCHEMA_VERSION
-            || !current_shape
-            || classifier_version < CLASSIFIER_METADATA_VERSION
-        {
-            Self::rebuild_clipboard_events_table_in(&transaction, &columns, failpoint)?;
-            true
+        } else if migrated_history {
+            self.run_migrations_in(&mut transaction, schema_version, failpoint)?
         } else {
             false
         };
+        schema::create_sealed_search_table(&transaction)?;
+        schema::create_memory_search_index(&transaction)?;
+        schema::create_label_tables(&transaction)?;
+        schema::create_history_integrity_table(&transaction)?;
+        schema::create_quarantine_table(&transaction)?;
+        schema::create_flavor_blob_tables(&transaction)?;
+        schema::create_flavor_blob_file_table(&transaction)?;
+
+        // A table that still differs from the current shape or classifier
+        // after its migrations is rebuilt. Rows written before payload
+        // encryption are read as plaintext and sealed by the rebuild.
+        if !rebuilt_history {
+            let columns = Self::table_columns_in(&transaction, "clipboard_events")?;
+            let payload_version = Self::metadata_version_in(&transaction, PAYLOAD_ENCRYPTION_KEY)?;
+            if !Self::clipboard_events_schema_is_current(&transaction, &columns)?
+                || classifier_version < CLASSIFIER_METADATA_VERSION
+                || payload_version < PAYLOAD_ENCRYPTION_VERSION
+            {
+                Self::rebuild_clipboard_events_table_in(
+                    &transaction,
+                    &columns,
+                    &self.cipher,
+                    payload_version == PAYLOAD_ENCRYPTION_VERSION,
+                    failpoint,
+                )?;
+                rebuilt_history = true;
+            }
+        }
 
         if rebuilt_history {
             schema::drop_clipboard_event_indexes(&transaction)?;
         }
         schema::create_clipboard_event_indexes(&transaction)?;
         Self::validate_clipboard_event_indexes(&transaction)?;
-        Self::validate_clipboard_events_table(&transaction, "clipboard_events", rebuilt_history)?;
+        Self::validate_clipboard_events_table(
+            &transaction,
+            "clipboard_events",
+            rebuilt_history || migrated_history,
+        )?;
+        if rebuilt_history
+            || !Self::search_index_rows_match_in(&transaction)?
+            || !Self::load_search_index_in(&transaction, &self.cipher)?
+        {
+            Self::rebuild_search_index_in(&transaction, &self.cipher)?;
+        }
+        if rebuilt_history {
+            labels::prune_orphaned_memberships_in(&transaction)?;
+            integrity::reseal_all_in(&transaction, &self.cipher, &self.integrity)?;
+        }
+        Self::validate_search_index_in(&transaction)?;
         Self::set_metadata_version_in(
             &transaction,
             CLASSIFIER_METADATA_KEY,
             CLASSIFIER_METADATA_VERSION,
         )?;
+        Self::set_metadata_version_in(
+            &transaction,
+            PAYLOAD_ENCRYPTION_KEY,
+            PAYLOAD_ENCRYPTION_VERSION,
+        )?;
         schema::set_user_version(&transaction, CURRENT_SCHEMA_VERSION)?;
         transaction.commit()
     }
 
+    /// Runs the registered steps from `schema_version`, each in a savepoint
+    /// that also records the version it reaches. Returns whether a step
+    /// rebuilt the table.
+    fn run_migrations_in(
+        &self,
+        transaction: &mut Transaction<'_>,
+        schema_version: i64,
+        failpoint: Option<MigrationFailpoint>,
+    ) -> Result<bool> {
+        let path = schema::migration_path(schema_version).ok_or_else(|| {
+            rusqlite::Error::InvalidParameterName(format!(
+                "database schema version {schema_version} has no migration"
+            ))
+        })?;
+        for migration in path {
+            let savepoint = transaction.savepoint()?;
+            let rebuilt = match migration.step {
+                MigrationStep::Rebuild => {
+                    let columns = Self::table_columns_in(&savepoint, "clipboard_events")?;
+                    Self::rebuild_clipboard_events_table_in(
+                        &savepoint,
+                        &columns,
+                        &self.cipher,
+                        false,
+                        failpoint,
+                    )?;
+                    true
+                }
+                MigrationStep::Alter(apply) => {
+                    apply(&savepoint)?;
+                    false
+                }
+                MigrationStep::SealPayloads => {
+                    Self::seal_plaintext_payloads_in(&savepoint, &self.cipher)?;
+                    false
+                }
+                MigrationStep::AlterAndReseal(apply) => {
+                    apply(&savepoint)?;
+                    integrity::reseal_all_in(&savepoint, &self.cipher, &self.integrity)?;
+                    false
+                }
+            };
+            let reached = if rebuilt {
+                CURRENT_SCHEMA_VERSION
+            } else {
+                migration.to
+            };
+            schema::set_user_version(&savepoint, reached)?;
+            Self::maybe_fail_migration(failpoint, MigrationFailpoint::AfterStep(reached))?;
+            savepoint.commit()?;
+            if rebuilt {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Seals the payload columns of every row in place, keeping each row's
+    /// identity, position, and statistics.
+    fn seal_plaintext_payloads_in(connection: &Connection, cipher: &PayloadCipher) -> Result<()> {
+        let content_hashes = {
+            let mut statement = connection.prepare("SELECT content_hash FROM clipboard_events")?;
+            let content_hashes = statement
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>>>()?;
+            content_hashes
+        };
+        for content_hash in content_hashes {
+            let (event_data, display, summary_display, compact_display) = connection.query_row(
+                "SELECT event_data, display, summary_display, compact_display
+                 FROM clipboard_events
+                 WHERE content_hash = ?1",
+                [&content_hash],
+                |row| {
+                    Ok((
+                        row.get::<_, Vec<u8>>(0)?,
+                        row.get::<_, Vec<u8>>(1)?,
+                        row.get::<_, Vec<u8>>(2)?,
+                        row.get::<_, Option<Vec<u8>>>(3)?,
+                    ))
+                },
+            )?;
+            let seal = |column, plaintext: &[u8]| {
+                cipher.seal(&content_hash, column, plaintext).map_err(|_| {
+                    rusqlite::Error::InvalidParameterName(
+                        "clipboard payload could not be encrypted".to_string(),
+                    )
+                })
+            };
+            connection.execute(
+                "UPDATE clipboard_events
+                 SET event_data = ?1, display = ?2, summary_display = ?3, compact_display = ?4
+                 WHERE content_hash = ?5",
+                params![
+                    seal(PayloadColumn::EventData, &event_data)?,
+                    seal(PayloadColumn::Display, &display)?,
+                    seal(PayloadColumn::SummaryDisplay, &summary_display)?,
+                    compact_display
+                        .as_deref()
+                        .map(|compact_display| seal(PayloadColumn::CompactDisplay, compact_display))
+                        .transpose()?,
+                    &content_hash,
+                ],
+            )?;
+        }
+        Self::set_metadata_version_in(
+            connection,
+            PAYLOAD_ENCRYPTION_KEY,
+            PAYLOAD_ENCRYPTION_VERSION,
+        )
+    }
+
     fn insert_default_settings(connection: &Connection) -> Result<()> {
         for (key, value) in settings::default_entries() {
             connection.execute(
@@ -469,23 +1252,44 @@ impl Database {
     #[cfg(test)]
     fn rebuild_clipboard_events_table(&self, columns: &[String]) -> Result<()> {
         let transaction = self.conn.unchecked_transaction()?;
-        Self::rebuild_clipboard_events_table_in(&transaction, columns, None)?;
+        let source_sealed = Self::metadata_version_in(&transaction, PAYLOAD_ENCRYPTION_KEY)?
+            == PAYLOAD_ENCRYPTION_VERSION;
+        Self::rebuild_clipboard_events_table_in(
+            &transaction,
+            columns,
+            &self.cipher,
+            source_sealed,
+            None,
+        )?;
         schema::drop_clipboard_event_indexes(&transaction)?;
         schema::create_clipboard_event_indexes(&transaction)?;
         Self::validate_clipboard_event_indexes(&transaction)?;
         Self::validate_clipboard_events_table(&transaction, "clipboard_events", true)?;
+        Self::rebuild_search_index_in(&transaction, &self.cipher)?;
+        Self::validate_search_index_in(&transaction)?;
+        labels::prune_orphaned_memberships_in(&transaction)?;
+        integrity::reseal_all_in(&transaction, &self.cipher, &self.integrity)?;
         Self::set_metadata_version_in(
             &transaction,
             CLASSIFIER_METADATA_KEY,
             CLASSIFIER_METADATA_VERSION,
         )?;
+        Self::set_metadata_version_in(
+            &transaction,
+            PAYLOAD_ENCRYPTION_KEY,
+            PAYLOAD_ENCRYPTION_VERSION,
+        )?;
         schema::set_user_version(&transaction, CURRENT_SCHEMA_VERSION)?;
         transaction.commit()
     }
 
+    /// Reads every row of the current table for a rebuild. `source_cipher`
+    /// opens `event_data` when the rows were already sealed, and puts back
+    /// any shared flavors it references.
     fn read_clipboard_event_rows_in(
         connection: &Connection,
         columns: &[String],
+        source_cipher: Option<&PayloadCipher>,
     ) -> Result<Vec<DbRow>> {
         let has_sort_order = columns.iter().any(|column| column == "sort_order");
         let order_clause = if has_sort_order {
@@ -493,16 +1297,61 @@ impl Database {
         } else {
             "ORDER BY timestamp DESC, rowid DESC"
         };
+        // Schemas before version 4 have no pinned column. Later rebuilds carry
+        // pins through so a classifier or shape change never unpins a row.
+        let pinned = if columns.iter().any(|column| column == "pinned") {
+            "pinned != 0"
+        } else {
+            "0"
+        };
+        // Schemas before version 6 have no copy tracking. Their rows start as
+        // copied once, first and last seen at their ordering timestamp.
+        let copy_tracking = if columns.iter().any(|column| column == "copy_count") {
+            "first_seen_at, last_seen_at, copy_count"
+        } else {
+            "NULL, NULL, 1"
+        };
+        // Schemas before version 7 have no auto-expiring rows.
+        let expires_at = if columns.iter().any(|column| column == "expires_at") {
+            "expires_at"
+        } else {
+            "NULL"
+        };
+        let content_hash = if source_cipher.is_some() {
+            "content_hash"
+        } else {
+            "NULL"
+        };
         let query = format!(
-            "SELECT event_data, timestamp FROM clipboard_events {}",
-            order_clause
+            "SELECT event_data, timestamp, {}, {}, {}, {} FROM clipboard_events {}",
+            pinned, copy_tracking, expires_at, content_hash, order_clause
         );
 
         let mut stmt = connection.prepare(&query)?;
         let rows = stmt.query_map([], |row| {
+            let timestamp = Self::timestamp_from_row(row, 1)?;
+            let event_data = match source_cipher {
+                Some(cipher) => flavor_blobs::inline_in(
+                    connection,
+                    cipher,
+                    Self::open_payload(
+                        cipher,
+                        &row.get::<_, String>(7)?,
+                        PayloadColumn::EventData,
+                        row,
+                        0,
+                    )?,
+                )?,
+                None => Self::event_blob_from_row(row, 0)?,
+            };
             Ok(DbRow {
-                event_data: Self::event_blob_from_row(row, 0)?,
-                timestamp: Self::timestamp_from_row(row, 1)?,
+                event_data,
+                timestamp,
+                pinned: row.get(2)?,
+                first_seen_at: row.get::<_, Option<i64>>(3)?.unwrap_or(timestamp),
+                last_seen_at: row.get::<_, Option<i64>>(4)?.unwrap_or(timestamp),
+                copy_count: row.get::<_, i64>(5)?.max(1),
+                expires_at: row.get(6)?,
             })
         })?;
 
@@ -609,16 +1458,27 @@ impl Database {
     }
 
     fn rebuild_clipboard_events_table_in(
-        transaction: &Transaction<'_>,
+        transaction: &Connection,
         columns: &[String],
+        cipher: &PayloadCipher,
+        source_sealed: bool,
         failpoint: Option<MigrationFailpoint>,
     ) -> Result<MigrationStats> {
         transaction.execute("DROP TABLE IF EXISTS clipboard_events_next", [])?;
         schema::create_clipboard_events_table(transaction, "clipboard_events_next")?;
         Self::maybe_fail_migration(failpoint, MigrationFailpoint::AfterCreateReplacement)?;
 
-        let rows = Self::read_clipboard_event_rows_in(transaction, columns)?;
-        let stats = Self::insert_deduped_rows_in(transaction, "clipboard_events_next", rows)?;
+        let rows = Self::read_clipboard_event_rows_in(
+            transaction,
+            columns,
+            source_sealed.then_some(cipher),
+        )?;
+        // Rebuilt rows store every flavor inline again.
+        if Self::table_exists_in(transaction, "flavor_blobs")? {
+            flavor_blobs::clear_in(transaction)?;
+        }
+        let stats =
+            Self::insert_deduped_rows_in(transaction, "clipboard_events_next", rows, cipher)?;
         Self::maybe_fail_migration(failpoint, MigrationFailpoint::AfterCopy)?;
 
         Self::validate_clipboard_events_table(transaction, "clipboard_events_next", true)?;
@@ -650,6 +1510,7 @@ impl Database {
         connection: &Connection,
         table: &str,
         rows: Vec<DbRow>,
+        cipher: &PayloadCipher,
     ) -> Result<MigrationStats> {
         let mut stats = MigrationStats {
             source_rows: rows.len() as u64,
@@ -671,9 +1532,33 @@ impl Database {
             if classified.content_hash.is_empty()
                 || !seen_hashes.insert(classified.content_hash.clone())
             {
-                stats.duplicate_rows += 1;
-                continue;
-            }
+                // The surviving row absorbs the duplicate's pin and copy
+                // history, and only expires if both copies were due to.
+                connection.execute(
+                    &format!(
+                        "UPDATE {table}
+                         SET pinned = MAX(pinned, ?1),
+                             first_seen_at = MIN(first_seen_at, ?2),
+                             last_seen_at = MAX(last_seen_at, ?3),
+                             copy_count = copy_count + ?4,
+                             expires_at = CASE
+                                 WHEN expires_at IS NULL OR ?5 IS NULL THEN NULL
+                                 ELSE MAX(expires_at, ?5)
+                             END
+                         WHERE content_hash = ?6"
+                    ),
+                    params![
+                        row.pinned,
+                        row.first_seen_at,
+                        row.last_seen_at,
+                        row.copy_count,
+                        row.expires_at,
+                        &classified.content_hash,
+                    ],
+                )?;
+                stats.duplicate_rows += 1;
+                continue;
+            }
             let compact_classified = Self::compact_text_event(&event)
                 .as_ref()
                 .and_then(Self::classify_event);
@@ -683,6 +1568,13 @@ impl Database {
                 assessment.metadata,
                 compact_classified,
             );
+            let sealed = Self::seal_payloads(
+                cipher,
+                &classified.content_hash,
+                &row.event_data,
+                &classified.display,
+                &metadata,
+            )?;
 
             connection.execute(
                 &format!(
@@ -699,25 +1591,38 @@ impl Database {
                         is_remote_clipboard,
                         byte_count,
                         timestamp,
-                        metadata_version
+                        metadata_version,
+                        pinned,
+                        first_seen_at,
+                        last_seen_at,
+                        copy_count,
+                        expires_at,
+                        integrity_mac,
+                        integrity_link
                      ) VALUES (
-                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
+                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                        ?16, ?17, ?18, x'', x''
                      )"
                 ),
                 params![
                     classified.content_hash,
-                    row.event_data,
+                    sealed.event_data,
                     classified.data_type,
-                    classified.display,
-                    metadata.summary_display,
+                    sealed.display,
+                    sealed.summary_display,
                     metadata.summary_truncated,
                     metadata.compact_content_hash,
-                    metadata.compact_display,
+                    sealed.compact_display,
                     metadata.source_bundle_id,
                     metadata.is_remote_clipboard,
                     metadata.byte_count,
                     row.timestamp,
                     CLASSIFIER_METADATA_VERSION,
+                    row.pinned,
+                    row.first_seen_at,
+                    row.last_seen_at,
+                    row.copy_count,
+                    row.expires_at,
                 ],
             )?;
             stats.inserted_rows += 1;
@@ -753,7 +1658,10 @@ impl Database {
                  WHERE length(content_hash) != 64
                     OR summary_display IS NULL
                     OR byte_count < 0
-                    OR metadata_version != ?1"
+                    OR metadata_version != ?1
+                    OR pinned NOT IN (0, 1)
+                    OR copy_count < 1
+                    OR first_seen_at > last_seen_at"
             ),
             [CLASSIFIER_METADATA_VERSION],
             |row| row.get(0),
@@ -770,6 +1678,7 @@ impl Database {
         for index in [
             "idx_clipboard_events_timestamp",
             "idx_clipboard_events_compact",
+            "idx_clipboard_events_pinned",
         ] {
             let exists = connection
                 .query_row(
@@ -790,6 +1699,178 @@ impl Database {
         Ok(())
     }
 
+    /// Rewrites the search text of every stored row.
+    ///
+    /// Runs after a history rebuild, when the sealed text no longer covers
+    /// exactly the stored rows, or when some of it does not open. A row whose
+    /// payload cannot be decoded is still indexed by its persisted display
+    /// columns.
+    fn rebuild_search_index_in(connection: &Connection, cipher: &PayloadCipher) -> Result<()> {
+        connection.execute("DELETE FROM clipboard_events_search", [])?;
+        let mut statement = connection.prepare(
+            "SELECT content_hash, event_data, display, summary_display, compact_display
+             FROM clipboard_events",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let content_hash = row.get::<_, String>(0)?;
+            let event = Self::open_event_data(connection, cipher, &content_hash, row, 1)
+                .and_then(|event_data| Self::event_from_blob(&event_data))
+                .ok();
+            let search_text = search::search_text(
+                event.as_ref(),
+                &Self::open_payload(cipher, &content_hash, PayloadColumn::Display, row, 2)?,
+                &Self::open_payload(cipher, &content_hash, PayloadColumn::SummaryDisplay, row, 3)?,
+                Self::open_optional_payload(
+                    cipher,
+                    &content_hash,
+                    PayloadColumn::CompactDisplay,
+                    row,
+                    4,
+                )?
+                .as_deref(),
+            );
+            Self::upsert_search_text_in(connection, cipher, &content_hash, &search_text)?;
+        }
+        Ok(())
+    }
+
+    /// Fills this connection's in-memory trigram index from the sealed search
+    /// text. Returns `false` when some text does not open; that row is left
+    /// out of the index.
+    fn load_search_index_in(connection: &Connection, cipher: &PayloadCipher) -> Result<bool> {
+        connection.execute("DELETE FROM clipboard_events_search_text", [])?;
+        let mut statement =
+            connection.prepare("SELECT content_hash, body FROM clipboard_events_search")?;
+        let mut rows = statement.query([])?;
+        let mut complete = true;
+        while let Some(row) = rows.next()? {
+            let content_hash = row.get::<_, String>(0)?;
+            let search_text = cipher
+                .open(
+                    &content_hash,
+                    PayloadColumn::SearchText,
+                    &row.get::<_, Vec<u8>>(1)?,
+                )
+                .ok()
+                .and_then(|search_text| String::from_utf8(search_text).ok());
+            match search_text {
+                Some(search_text) => {
+                    Self::index_search_text_in(connection, &content_hash, &search_text)?;
+                }
+                None => complete = false,
+            }
+        }
+        Ok(complete)
+    }
+
+    fn search_index_rows_match_in(connection: &Connection) -> Result<bool> {
+        connection.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM clipboard_events_search)
+                    = (SELECT COUNT(*) FROM clipboard_events)
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM clipboard_events_search AS search
+                    WHERE NOT EXISTS (
+                        SELECT 1
+                        FROM clipboard_events
+                        WHERE clipboard_events.content_hash = search.content_hash
+                    )
+                )",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    fn validate_search_index_in(connection: &Connection) -> Result<()> {
+        let mut valid = Self::search_index_rows_match_in(connection)?
+            && connection.query_row(
+                "SELECT
+                    (SELECT COUNT(*) FROM clipboard_events_search_text)
+                        = (SELECT COUNT(*) FROM clipboard_events_search)",
+                [],
+                |row| row.get::<_, bool>(0),
+            )?;
+        for (object_type, name) in std::iter::once(("table", "clipboard_events_fts"))
+            .chain(SEARCH_INDEX_TRIGGERS.map(|trigger| ("trigger", trigger)))
+        {
+            valid = valid
+                && connection
+                    .query_row(
+                        "SELECT 1 FROM sqlite_temp_master WHERE type = ?1 AND name = ?2",
+                        [object_type, name],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+        }
+        if !valid {
+            return Err(rusqlite::Error::InvalidParameterName(
+                "clipboard history search index validation failed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Stores a row's sealed search text and indexes its plaintext in memory.
+    fn upsert_search_text_in(
+        connection: &Connection,
+        cipher: &PayloadCipher,
+        content_hash: &str,
+        search_text: &str,
+    ) -> Result<()> {
+        let sealed = cipher
+            .seal(
+                content_hash,
+                PayloadColumn::SearchText,
+                search_text.as_bytes(),
+            )
+            .map_err(|_| {
+                rusqlite::Error::InvalidParameterName(
+                    "clipboard search text could not be encrypted".to_string(),
+                )
+            })?;
+        connection.execute(
+            "INSERT INTO clipboard_events_search (content_hash, body) VALUES (?1, ?2)
+             ON CONFLICT(content_hash) DO UPDATE SET body = excluded.body",
+            params![content_hash, sealed],
+        )?;
+        Self::index_search_text_in(connection, content_hash, search_text)
+    }
+
+    fn index_search_text_in(
+        connection: &Connection,
+        content_hash: &str,
+        search_text: &str,
+    ) -> Result<()> {
+        connection.execute(
+            "INSERT INTO clipboard_events_search_text (content_hash, body) VALUES (?1, ?2)
+             ON CONFLICT(content_hash) DO UPDATE SET body = excluded.body",
+            params![content_hash, search_text],
+        )?;
+        Ok(())
+    }
+
+    fn delete_event_row_in(
+        connection: &Connection,
+        integrity: &HistoryIntegrityKey,
+        content_hash: &str,
+    ) -> Result<()> {
+        let position = integrity::position_in(connection, content_hash)?;
+        labels::delete_memberships_in(connection, content_hash)?;
+        flavor_blobs::release_in(connection, content_hash)?;
+        connection.execute(
+            "DELETE FROM clipboard_events_search WHERE content_hash = ?1",
+            [content_hash],
+        )?;
+        connection.execute(
+            "DELETE FROM clipboard_events WHERE content_hash = ?1",
+            [content_hash],
+        )?;
+        integrity::relink_in(connection, integrity, position.as_slice())
+    }
+
     fn maybe_fail_migration(
         actual: Option<MigrationFailpoint>,
         expected: MigrationFailpoint,
@@ -806,13 +1887,28 @@ impl Database {
     pub fn get_settings(&self) -> Result<AppSettings> {
         let language = self.get_language()?;
         let max_history_bytes = self.get_max_history_bytes()?;
+        let source_policy = self.get_source_app_policy()?;
+        let secret_policy = self.get_secret_policy()?;
         let history = self.get_history_stats()?;
         Ok(AppSettings {
             max_items: self.get_max_items()?,
             max_history_bytes,
+            max_age_days: self.get_max_age_days()?,
+            data_type_budgets: self.get_data_type_budgets()?,
+            denied_source_apps: source_policy.denied,
+            allowed_source_apps: source_policy.allowed,
+            source_allow_only: source_policy.allow_only,
+            secret_policies: SecretKind::ALL
+                .into_iter()
+                .map(|kind| SecretPolicySetting {
+                    kind,
+                    action: secret_policy.action(kind),
+                })
+                .collect(),
             show_in_menu_bar: self.get_show_in_menu_bar()?,
             menu_bar_item_limit: self.get_menu_bar_item_limit()?,
             move_restored_item_to_top: self.get_move_restored_item_to_top()?,
+            move_recopied_item_to_top: self.get_move_recopied_item_to_top()?,
             compact_mode: self.get_compact_mode()?,
             language: language.code().to_string(),
             resolved_language: language.resolve().code().to_string(),
@@ -831,6 +1927,62 @@ impl Database {
         settings::set_max_items(&self.conn, max_items)
     }
 
+    pub fn get_data_type_budgets(&self) -> Result<Vec<DataTypeBudget>> {
+        settings::get_data_type_budgets(&self.conn)
+    }
+
+    pub fn set_data_type_budget(
+        &self,
+        data_type: &str,
+        max_items: Option<u32>,
+        max_bytes: Option<u64>,
+    ) -> Result<()> {
+        settings::set_data_type_budget(&self.conn, data_type, max_items, max_bytes)
+    }
+
+    pub fn get_source_app_policy(&self) -> Result<SourceAppPolicy> {
+        settings::get_source_app_policy(&self.conn)
+    }
+
+    /// Returns `false` when the rule was rejected; see
+    /// `settings::set_source_app_rule`.
+    pub fn set_source_app_rule(
+        &self,
+        bundle_id: &str,
+        rule: Option<SourceAppRule>,
+    ) -> Result<bool> {
+        settings::set_source_app_rule(&self.conn, bundle_id, rule)
+    }
+
+    pub fn set_source_allow_only(&self, allow_only: bool) -> Result<()> {
+        settings::set_source_allow_only(&self.conn, allow_only)
+    }
+
+    pub fn get_secret_policy(&self) -> Result<SecretPolicy> {
+        settings::get_secret_policy(&self.conn)
+    }
+
+    pub fn set_secret_policy(&self, kind: SecretKind, action: SecretAction) -> Result<()> {
+        settings::set_secret_policy(&self.conn, kind, action)
+    }
+
+    /// Runs the protocol assessment followed by the persisted source
+    /// application policy, the same screening the capture pipeline applies.
+    pub fn assess_capture(&self, event: &Event) -> Result<PasteboardAssessment> {
+        Ok(apply_source_policy(
+            assess_event(event),
+            &self.get_source_app_policy()?,
+        ))
+    }
+
+    pub fn get_max_age_days(&self) -> Result<u32> {
+        settings::get_max_age_days(&self.conn)
+    }
+
+    pub fn set_max_age_days(&self, max_age_days: u32) -> Result<()> {
+        settings::set_max_age_days(&self.conn, max_age_days)
+    }
+
     pub fn get_max_history_bytes(&self) -> Result<u64> {
         settings::get_max_history_bytes(&self.conn)
     }
@@ -863,6 +2015,14 @@ impl Database {
         settings::set_move_restored_item_to_top(&self.conn, move_restored_item_to_top)
     }
 
+    pub fn get_move_recopied_item_to_top(&self) -> Result<bool> {
+        settings::get_move_recopied_item_to_top(&self.conn)
+    }
+
+    pub fn set_move_recopied_item_to_top(&self, move_recopied_item_to_top: bool) -> Result<()> {
+        settings::set_move_recopied_item_to_top(&self.conn, move_recopied_item_to_top)
+    }
+
     pub fn get_compact_mode(&self) -> Result<bool> {
         settings::get_compact_mode(&self.conn)
     }
@@ -879,6 +2039,53 @@ impl Database {
         settings::set_language(&self.conn, language)
     }
 
+    pub(crate) fn capture_policy(&self) -> Result<CapturePolicy> {
+        Ok(CapturePolicy {
+            source_app_policy: self.get_source_app_policy()?,
+            secret_policy: self.get_secret_policy()?,
+            compact_mode: self.get_compact_mode()?,
+        })
+    }
+
+    /// Runs a capture through every policy in pipeline order: the pasteboard
+    /// protocol, the source application policy, the resource budgets, and
+    /// secret screening, then classifies what remains for storage. A matched
+    /// `expire` detector marks the prepared event to expire.
+    pub(crate) fn prepare_capture(event: Event, policy: &CapturePolicy) -> Result<CaptureOutcome> {
+        let assessment = assess_event(&event);
+        Self::prepare_assessed_capture(event, assessment, policy)
+    }
+
+    /// Runs `prepare_capture` on an event whose pasteboard assessment the
+    /// caller made, such as an imported entry whose source comes from what
+    /// its format recorded rather than from the event's markers.
+    fn prepare_assessed_capture(
+        event: Event,
+        assessment: PasteboardAssessment,
+        policy: &CapturePolicy,
+    ) -> Result<CaptureOutcome> {
+        let assessment = apply_source_policy(assessment, &policy.source_app_policy);
+        if let HistoryDisposition::Skip(reason) = assessment.disposition {
+            return Ok(CaptureOutcome::Skipped(reason));
+        }
+        let event = match prepare_capture_event(event) {
+            Ok(capture) => capture.event,
+            Err(rejection) => return Ok(CaptureOutcome::Rejected(rejection)),
+        };
+        let screened = match screen_event(event, &policy.secret_policy) {
+            Ok(screened) => screened,
+            Err(kind) => return Ok(CaptureOutcome::Secret(kind)),
+        };
+        let Some(mut prepared) = Self::prepare_history_event(&screened.event, policy.compact_mode)?
+        else {
+            return Ok(CaptureOutcome::Unsupported);
+        };
+        if screened.expires {
+            prepared.expire_after(SECRET_EXPIRY);
+        }
+        Ok(CaptureOutcome::Prepared(prepared))
+    }
+
     pub(crate) fn prepare_history_event(
         event: &Event,
         compact_mode: bool,
@@ -901,11 +2108,13 @@ impl Database {
         };
         classified.display =
             Self::bounded_persisted_display(&classified.data_type, &classified.display);
-        let event_data = encode_event_blob(&prepared_event).map_err(|_| {
-            rusqlite::Error::InvalidParameterName(
-                "clipboard event could not be prepared for storage".to_string(),
-            )
-        })?;
+        let (event_data, shared_flavors) = encode_event_blob(&prepared_event)
+            .and_then(share_large_flavors)
+            .map_err(|_| {
+                rusqlite::Error::InvalidParameterName(
+                    "clipboard event could not be prepared for storage".to_string(),
+                )
+            })?;
         let mut compact_classified = if compact_mode {
             Some(classified.clone())
         } else {
@@ -922,23 +2131,30 @@ impl Database {
             assessment.metadata,
             compact_classified,
         );
+        let search_text = search::search_text(
+            Some(&prepared_event),
+            &classified.display,
+            &metadata.summary_display,
+            metadata.compact_display.as_deref(),
+        );
 
         Ok(Some(PreparedHistoryEvent {
             event: prepared_event,
             event_data,
+            shared_flavors,
             classified,
             metadata,
+            search_text,
             compact_mode,
         }))
     }
 
     #[cfg(test)]
     pub fn insert_event(&self, event: &Event) -> Result<bool> {
-        let compact_mode = self.get_compact_mode()?;
-        let Some(prepared) = Self::prepare_history_event(event, compact_mode)? else {
-            return Ok(false);
-        };
-        self.insert_prepared_event(prepared)
+        match Self::prepare_capture(event.clone(), &self.capture_policy()?)? {
+            CaptureOutcome::Prepared(prepared) => self.insert_prepared_event(prepared),
+            _ => Ok(false),
+        }
     }
 
     pub(crate) fn insert_prepared_event(&self, prepared: PreparedHistoryEvent) -> Result<bool> {
@@ -962,16 +2178,39 @@ impl Database {
         let PreparedHistoryEvent {
             event: _,
             event_data,
+            shared_flavors,
             classified,
             metadata,
+            search_text,
             compact_mode,
         } = prepared;
+        let sealed = Self::seal_payloads(
+            &self.cipher,
+            &classified.content_hash,
+            &event_data,
+            &classified.display,
+            &metadata,
+        )?;
 
         if compact_mode {
-            return self.upsert_compact_event(event_data, classified, metadata);
+            return self.upsert_compact_event(
+                sealed,
+                &shared_flavors,
+                classified,
+                metadata,
+                &search_text,
+            );
         }
 
         let transaction = self.conn.unchecked_transaction()?;
+        let previous_position = integrity::position_in(&transaction, &classified.content_hash)?;
+        let seen_at = Self::current_unix_timestamp();
+        let recopied_timestamp = if settings::get_move_recopied_item_to_top(&transaction)? {
+            Some(Self::next_history_timestamp_in(&transaction)?)
+        } else {
+            None
+        };
+        flavor_blobs::release_in(&transaction, &classified.content_hash)?;
         let updated = transaction.execute(
             "UPDATE clipboard_events
              SET event_data = ?1,
@@ -984,49 +2223,78 @@ impl Database {
                  source_bundle_id = ?8,
                  is_remote_clipboard = ?9,
                  byte_count = ?10,
-                 metadata_version = ?11
-             WHERE content_hash = ?12",
+                 metadata_version = ?11,
+                 last_seen_at = MAX(last_seen_at, ?12),
+                 copy_count = copy_count + 1,
+                 timestamp = COALESCE(?13, timestamp),
+                 expires_at = ?14
+             WHERE content_hash = ?15",
             params![
-                &event_data,
+                &sealed.event_data,
                 &classified.data_type,
-                &classified.display,
-                &metadata.summary_display,
+                &sealed.display,
+                &sealed.summary_display,
                 metadata.summary_truncated,
                 &metadata.compact_content_hash,
-                &metadata.compact_display,
+                &sealed.compact_display,
                 &metadata.source_bundle_id,
                 metadata.is_remote_clipboard,
                 metadata.byte_count,
                 CLASSIFIER_METADATA_VERSION,
+                seen_at,
+                recopied_timestamp,
+                metadata.expires_at,
                 &classified.content_hash,
             ],
         )?;
 
         if updated == 0 {
             let timestamp = Self::next_history_timestamp_in(&transaction)?;
-            Self::insert_current_row(&transaction, &classified, &event_data, &metadata, timestamp)?;
+            Self::insert_current_row(&transaction, &classified, &sealed, &metadata, timestamp)?;
         }
+        flavor_blobs::retain_in(
+            &transaction,
+            &self.cipher,
+            &classified.content_hash,
+            &shared_flavors,
+        )?;
+        self.seal_row_integrity_in(&transaction, &classified.content_hash, previous_position)?;
+        Self::upsert_search_text_in(
+            &transaction,
+            &self.cipher,
+            &classified.content_hash,
+            &search_text,
+        )?;
 
-        Self::cleanup_old_events_in(&transaction)?;
+        Self::cleanup_old_events_in(&transaction, &self.integrity)?;
         transaction.commit()?;
+        self.collect_blob_files();
         Ok(true)
     }
 
     fn upsert_compact_event(
         &self,
-        event_data: Vec<u8>,
+        sealed: SealedPayloads,
+        shared_flavors: &[SharedFlavor],
         classified: ClassifiedEvent,
         metadata: PersistedMetadata,
+        search_text: &str,
     ) -> Result<bool> {
         let transaction = self.conn.unchecked_transaction()?;
         let mut stmt = transaction.prepare(
-            "SELECT content_hash, timestamp
+            "SELECT content_hash, timestamp, pinned, first_seen_at, copy_count
              FROM clipboard_events
              WHERE compact_content_hash = ?1
              ORDER BY timestamp DESC, content_hash ASC",
         )?;
         let rows = stmt.query_map([&classified.content_hash], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            Ok(CompactMatch {
+                content_hash: row.get(0)?,
+                timestamp: row.get(1)?,
+                pinned: row.get(2)?,
+                first_seen_at: row.get(3)?,
+                copy_count: row.get(4)?,
+            })
         })?;
         let mut matching_rows = Vec::new();
         for row in rows {
@@ -1034,20 +2302,45 @@ impl Database {
         }
         drop(stmt);
 
-        if let Some((newest_content_hash, newest_timestamp)) = matching_rows.first() {
+        if let Some(newest) = matching_rows.first() {
             let existing_target_hash = matching_rows
                 .iter()
-                .find(|(content_hash, _)| content_hash == &classified.content_hash)
-                .map(|(content_hash, _)| content_hash.as_str());
-            let row_to_update = existing_target_hash.unwrap_or(newest_content_hash);
-            for (content_hash, _) in &matching_rows {
-                if content_hash != row_to_update {
-                    transaction.execute(
-                        "DELETE FROM clipboard_events WHERE content_hash = ?1",
-                        [content_hash],
+                .find(|row| row.content_hash == classified.content_hash)
+                .map(|row| row.content_hash.as_str());
+            let row_to_update = existing_target_hash.unwrap_or(&newest.content_hash);
+            let previous_position = integrity::position_in(&transaction, row_to_update)?;
+            let pinned = matching_rows.iter().any(|row| row.pinned);
+            let first_seen_at = matching_rows
+                .iter()
+                .map(|row| row.first_seen_at)
+                .min()
+                .unwrap_or(newest.timestamp);
+            let copy_count = matching_rows
+                .iter()
+                .fold(1_i64, |count, row| count.saturating_add(row.copy_count));
+            let timestamp = if settings::get_move_recopied_item_to_top(&transaction)? {
+                Self::next_history_timestamp_in(&transaction)?
+            } else {
+                newest.timestamp
+            };
+            let seen_at = Self::current_unix_timestamp().max(first_seen_at);
+            for CompactMatch { content_hash, .. } in &matching_rows {
+                if content_hash != &classified.content_hash {
+                    labels::rekey_memberships_in(
+                        &transaction,
+                        content_hash,
+                        &classified.content_hash,
                     )?;
                 }
+                if content_hash != row_to_update {
+                    Self::delete_event_row_in(&transaction, &self.integrity, content_hash)?;
+                }
             }
+            transaction.execute(
+                "DELETE FROM clipboard_events_search WHERE content_hash = ?1",
+                [row_to_update],
+            )?;
+            flavor_blobs::release_in(&transaction, row_to_update)?;
             transaction.execute(
                 "UPDATE clipboard_events
                  SET content_hash = ?1,
@@ -1062,41 +2355,79 @@ impl Database {
                      is_remote_clipboard = ?10,
                      byte_count = ?11,
                      timestamp = ?12,
-                     metadata_version = ?13
-                 WHERE content_hash = ?14",
+                     metadata_version = ?13,
+                     pinned = ?14,
+                     first_seen_at = ?15,
+                     last_seen_at = ?16,
+                     copy_count = ?17,
+                     expires_at = ?18
+                 WHERE content_hash = ?19",
                 params![
                     &classified.content_hash,
-                    &event_data,
+                    &sealed.event_data,
                     &classified.data_type,
-                    &classified.display,
-                    &metadata.summary_display,
+                    &sealed.display,
+                    &sealed.summary_display,
                     metadata.summary_truncated,
                     &metadata.compact_content_hash,
-                    &metadata.compact_display,
+                    &sealed.compact_display,
                     &metadata.source_bundle_id,
                     metadata.is_remote_clipboard,
                     metadata.byte_count,
-                    newest_timestamp,
+                    timestamp,
                     CLASSIFIER_METADATA_VERSION,
+                    pinned,
+                    first_seen_at,
+                    seen_at,
+                    copy_count,
+                    metadata.expires_at,
                     row_to_update,
                 ],
             )?;
-            Self::cleanup_old_events_in(&transaction)?;
+            flavor_blobs::retain_in(
+                &transaction,
+                &self.cipher,
+                &classified.content_hash,
+                shared_flavors,
+            )?;
+            self.seal_row_integrity_in(&transaction, &classified.content_hash, previous_position)?;
+            Self::upsert_search_text_in(
+                &transaction,
+                &self.cipher,
+                &classified.content_hash,
+                search_text,
+            )?;
+            Self::cleanup_old_events_in(&transaction, &self.integrity)?;
             transaction.commit()?;
+            self.collect_blob_files();
             return Ok(true);
         }
 
         let timestamp = Self::next_history_timestamp_in(&transaction)?;
-        Self::insert_current_row(&transaction, &classified, &event_data, &metadata, timestamp)?;
-        Self::cleanup_old_events_in(&transaction)?;
+        Self::insert_current_row(&transaction, &classified, &sealed, &metadata, timestamp)?;
+        flavor_blobs::retain_in(
+            &transaction,
+            &self.cipher,
+            &classified.content_hash,
+            shared_flavors,
+        )?;
+        self.seal_row_integrity_in(&transaction, &classified.content_hash, None)?;
+        Self::upsert_search_text_in(
+            &transaction,
+            &self.cipher,
+            &classified.content_hash,
+            search_text,
+        )?;
+        Self::cleanup_old_events_in(&transaction, &self.integrity)?;
         transaction.commit()?;
+        self.collect_blob_files();
         Ok(true)
     }
 
     fn insert_current_row(
         connection: &Connection,
         classified: &ClassifiedEvent,
-        event_data: &[u8],
+        sealed: &SealedPayloads,
         metadata: &PersistedMetadata,
         timestamp: i64,
     ) -> Result<()> {
@@ -1114,44 +2445,211 @@ impl Database {
                 is_remote_clipboard,
                 byte_count,
                 timestamp,
-                metadata_version
+                metadata_version,
+                first_seen_at,
+                last_seen_at,
+                expires_at,
+                integrity_mac,
+                integrity_link
              ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?12, ?12, ?14, x'', x''
              )",
             params![
                 &classified.content_hash,
-                event_data,
+                &sealed.event_data,
                 &classified.data_type,
-                &classified.display,
-                &metadata.summary_display,
+                &sealed.display,
+                &sealed.summary_display,
                 metadata.summary_truncated,
                 &metadata.compact_content_hash,
-                &metadata.compact_display,
+                &sealed.compact_display,
                 &metadata.source_bundle_id,
                 metadata.is_remote_clipboard,
                 metadata.byte_count,
                 timestamp,
                 CLASSIFIER_METADATA_VERSION,
+                metadata.expires_at,
             ],
         )?;
         Ok(())
     }
 
+    /// Re-MACs a row written in the current transaction and relinks the
+    /// chain where it sat before, if anywhere, and where it sits now.
+    fn seal_row_integrity_in(
+        &self,
+        connection: &Connection,
+        content_hash: &str,
+        previous_position: Option<ChainPosition>,
+    ) -> Result<()> {
+        integrity::seal_row_in(connection, &self.cipher, &self.integrity, content_hash)?;
+        let mut positions: Vec<ChainPosition> = previous_position.into_iter().collect();
+        positions.extend(integrity::position_in(connection, content_hash)?);
+        integrity::relink_in(connection, &self.integrity, &positions)
+    }
+
     pub fn move_event_to_top(&self, content_hash: &str) -> Result<()> {
-        let updated = self.conn.execute(
+        let transaction = self.conn.unchecked_transaction()?;
+        let previous_position = integrity::position_in(&transaction, content_hash)?;
+        let updated = transaction.execute(
             "UPDATE clipboard_events
              SET timestamp = ?1
              WHERE content_hash = ?2",
-            (self.next_history_timestamp()?, content_hash),
+            (Self::next_history_timestamp_in(&transaction)?, content_hash),
         )?;
 
         if updated == 0 {
             return Err(rusqlite::Error::QueryReturnedNoRows);
         }
 
-        Ok(())
+        self.seal_row_integrity_in(&transaction, content_hash, previous_position)?;
+        transaction.commit()
+    }
+
+    /// Pins one row, keeping it out of count and byte eviction.
+    ///
+    /// Pinned rows have their own budget of `MAX_PINNED_ITEMS` rows and
+    /// `MAX_PINNED_BYTES`; a pin that would exceed it leaves the row unchanged.
+    /// Pinning an already pinned row succeeds without counting it twice.
+    pub fn pin_event(&self, content_hash: &str) -> Result<PinOutcome> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let Some((pinned, byte_count)) = transaction
+            .query_row(
+                "SELECT pinned, byte_count FROM clipboard_events WHERE content_hash = ?1",
+                [content_hash],
+                |row| Ok((row.get::<_, bool>(0)?, row.get::<_, i64>(1)?.max(0) as u64)),
+            )
+            .optional()?
+        else {
+            return Ok(PinOutcome::NotFound);
+        };
+        if pinned {
+            return Ok(PinOutcome::Updated);
+        }
+        if !Self::pinned_budget_allows_in(&transaction, byte_count)? {
+            return Ok(PinOutcome::LimitReached);
+        }
+
+        transaction.execute(
+            "UPDATE clipboard_events SET pinned = 1 WHERE content_hash = ?1",
+            [content_hash],
+        )?;
+        transaction.commit()?;
+        Ok(PinOutcome::Updated)
+    }
+
+    /// Whether one more pinned row of `byte_count` bytes fits the pinned budget.
+    fn pinned_budget_allows_in(connection: &Connection, byte_count: u64) -> Result<bool> {
+        let (pinned_count, pinned_bytes): (i64, i64) = connection.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(byte_count), 0)
+             FROM clipboard_events
+             WHERE pinned = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((pinned_count.max(0) as u64) < MAX_PINNED_ITEMS
+            && (pinned_bytes.max(0) as u64).saturating_add(byte_count) <= MAX_PINNED_BYTES)
+    }
+
+    /// Returns one row to ordinary retention. It keeps its timestamp, so the next
+    /// cleanup may evict it if it now falls outside the history budget.
+    pub fn unpin_event(&self, content_hash: &str) -> Result<PinOutcome> {
+        let updated = self.conn.execute(
+            "UPDATE clipboard_events SET pinned = 0 WHERE content_hash = ?1",
+            [content_hash],
+        )?;
+        Ok(if updated == 0 {
+            PinOutcome::NotFound
+        } else {
+            PinOutcome::Updated
+        })
+    }
+
+    pub fn list_collections(&self) -> Result<Vec<HistoryCollection>> {
+        labels::list_collections(&self.conn)
+    }
+
+    pub fn create_collection(&self, name: &str) -> Result<LabelResult<HistoryCollection>> {
+        self.with_label_transaction(|connection| labels::create_collection(connection, name))
+    }
+
+    pub fn rename_collection(
+        &self,
+        collection_id: i64,
+        name: &str,
+    ) -> Result<LabelResult<HistoryCollection>> {
+        self.with_label_transaction(|connection| {
+            labels::rename_collection(connection, collection_id, name)
+        })
+    }
+
+    /// Deletes a collection and its memberships. The history rows stay.
+    pub fn delete_collection(&self, collection_id: i64) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| {
+            labels::delete_collection(connection, collection_id)
+        })
+    }
+
+    pub fn add_to_collection(
+        &self,
+        collection_id: i64,
+        content_hash: &str,
+    ) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| {
+            labels::add_to_collection(connection, collection_id, content_hash)
+        })
+    }
+
+    pub fn remove_from_collection(
+        &self,
+        collection_id: i64,
+        content_hash: &str,
+    ) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| {
+            labels::remove_from_collection(connection, collection_id, content_hash)
+        })
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<HistoryTag>> {
+        labels::list_tags(&self.conn)
+    }
+
+    pub fn tag_event(&self, content_hash: &str, tag: &str) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| labels::tag_item(connection, content_hash, tag))
+    }
+
+    pub fn untag_event(&self, content_hash: &str, tag: &str) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| labels::untag_item(connection, content_hash, tag))
+    }
+
+    pub fn rename_tag(&self, tag: &str, new_name: &str) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| labels::rename_tag(connection, tag, new_name))
+    }
+
+    /// Removes a tag from every row that carries it.
+    pub fn delete_tag(&self, tag: &str) -> Result<LabelResult<()>> {
+        self.with_label_transaction(|connection| labels::delete_tag(connection, tag))
+    }
+
+    pub fn get_event_labels(&self, content_hash: &str) -> Result<LabelResult<HistoryLabels>> {
+        labels::item_labels(&self.conn, content_hash)
+    }
+
+    /// Commits only when the change was accepted, so a rejected request leaves
+    /// no partial writes behind.
+    fn with_label_transaction<T>(
+        &self,
+        change: impl FnOnce(&Connection) -> Result<LabelResult<T>>,
+    ) -> Result<LabelResult<T>> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let outcome = change(&transaction)?;
+        if outcome.is_ok() {
+            transaction.commit()?;
+        }
+        Ok(outcome)
     }
 
+    #[cfg(test)]
     fn next_history_timestamp(&self) -> Result<i64> {
         Self::next_history_timestamp_in(&self.conn)
     }
@@ -1234,6 +2732,7 @@ impl Database {
             compact_content_hash,
             compact_display,
             byte_count,
+            expires_at: None,
         }
     }
 
@@ -1351,9 +2850,12 @@ impl Database {
 
         while let Some(row) = rows.next().map_err(|error| error.to_string())? {
             let content_hash: String = row.get(0).map_err(|error| error.to_string())?;
-            let event_data: Vec<u8> = row.get(1).map_err(|error| error.to_string())?;
+            let event_data = Self::open_event_data(&self.conn, &self.cipher, &content_hash, row, 1)
+                .map_err(|error| error.to_string())?;
             let data_type: String = row.get(2).map_err(|error| error.to_string())?;
-            let display: Vec<u8> = row.get(3).map_err(|error| error.to_string())?;
+            let display =
+                Self::open_payload(&self.cipher, &content_hash, PayloadColumn::Display, row, 3)
+                    .map_err(|error| error.to_string())?;
             let timestamp: i64 = row.get(4).map_err(|error| error.to_string())?;
             let event = Self::event_from_blob(&event_data).map_err(|error| error.to_string())?;
             let record = Self::history_jsonl_record(
@@ -1422,13 +2924,49 @@ impl Database {
         preview::build_history_detail(seed, compact_mode)
     }
 
-    #[cfg(test)]
-    fn rich_preview_from_event_data(event_data: &[u8]) -> Vec<StoredPreviewSegment> {
-        preview::rich_preview_from_event_data(event_data)
+    /// Copies the seed of every visible row matching `query`, in history
+    /// order, for `build_history_export_entry`. An empty query matches all.
+    pub fn history_export_seeds(
+        &self,
+        query: &HistoryQuery,
+    ) -> Result<IntegrityResult<Vec<HistoryDetailSeed>>> {
+        let compact_mode = self.get_compact_mode()?;
+        let expression = search::match_expression(&query.terms);
+        if !query.terms.is_empty() && expression.is_none() {
+            return Ok(Ok(Vec::new()));
+        }
+
+        // SQLite reads every row for a negative LIMIT.
+        let results =
+            self.query_search_results(query, expression.as_deref(), None, -1, compact_mode)?;
+        let mut seeds = Vec::with_capacity(results.len());
+        for result in results {
+            match self.get_history_detail_seed(&result.summary.content_hash)? {
+                Ok(Some(seed)) => seeds.push(seed),
+                Ok(None) => {}
+                Err(violation) => return Ok(Err(violation)),
+            }
+        }
+        Ok(Ok(seeds))
     }
 
-    #[cfg(test)]
-    fn rich_preview_segments(event: &Event) -> Vec<StoredPreviewSegment> {
+    /// Builds one export report entry from an owned seed, like
+    /// `build_history_detail`, outside the database lock.
+    pub fn build_history_export_entry(
+        seed: HistoryDetailSeed,
+        compact_mode: bool,
+        with_images: bool,
+    ) -> Result<HistoryExportEntry> {
+        preview::build_history_export_entry(seed, compact_mode, with_images)
+    }
+
+    #[cfg(test)]
+    fn rich_preview_from_event_data(event_data: &[u8]) -> Vec<StoredPreviewSegment> {
+        preview::rich_preview_from_event_data(event_data)
+    }
+
+    #[cfg(test)]
+    fn rich_preview_segments(event: &Event) -> Vec<StoredPreviewSegment> {
         preview::rich_preview_segments(event)
     }
 
@@ -1458,15 +2996,27 @@ impl Database {
         &self,
         cursor: Option<&str>,
         page_size: Option<usize>,
+    ) -> Result<HistoryPage> {
+        self.get_filtered_history_page(cursor, page_size, &HistoryLabelFilter::default())
+    }
+
+    /// Pages history restricted to one collection and/or tag. `total_count`
+    /// counts the matching rows rather than the whole history.
+    pub fn get_filtered_history_page(
+        &self,
+        cursor: Option<&str>,
+        page_size: Option<usize>,
+        filter: &HistoryLabelFilter,
     ) -> Result<HistoryPage> {
         let compact_mode = self.get_compact_mode()?;
-        self.get_history_page_for_mode(cursor, page_size, compact_mode)
+        self.get_history_page_for_mode(cursor, page_size, filter, compact_mode)
     }
 
     fn get_history_page_for_mode(
         &self,
         cursor: Option<&str>,
         page_size: Option<usize>,
+        filter: &HistoryLabelFilter,
         compact_mode: bool,
     ) -> Result<HistoryPage> {
         let page_size = page_size
@@ -1477,20 +3027,24 @@ impl Database {
             .map(HistoryCursor::decode)
             .transpose()
             .map_err(rusqlite::Error::InvalidParameterName)?;
-        let mut items = self.query_history_summaries(cursor.as_ref(), fetch_limit, compact_mode)?;
+        let mut items =
+            self.query_history_summaries(cursor.as_ref(), fetch_limit, filter, compact_mode)?;
         let has_more = items.len() > page_size;
         if has_more {
             items.truncate(page_size);
         }
         let next_cursor = has_more.then(|| items.last()).flatten().map(|item| {
             HistoryCursor {
+                pinned: item.pinned,
                 timestamp: item.timestamp,
                 content_hash: item.content_hash.clone(),
             }
             .encode()
         });
         let stats = self.get_history_stats()?;
-        let total_count = if compact_mode {
+        let total_count = if !filter.is_empty() {
+            self.count_filtered_history(filter, compact_mode)?
+        } else if compact_mode {
             stats.compact_visible_items
         } else {
             stats.total_items
@@ -1505,19 +3059,35 @@ impl Database {
         })
     }
 
-    fn query_history_summaries(
+    fn count_filtered_history(
         &self,
-        cursor: Option<&HistoryCursor>,
-        limit: i64,
+        filter: &HistoryLabelFilter,
         compact_mode: bool,
-    ) -> Result<Vec<HistorySummary>> {
-        let alias = if compact_mode {
-            "event"
+    ) -> Result<u64> {
+        let (from, alias) = Self::history_page_source(compact_mode);
+        let mut parameters = Vec::new();
+        let label_filters =
+            labels::sql_filters(filter, &format!("{alias}.content_hash"), &mut parameters);
+        let query = format!(
+            "SELECT COUNT(*) FROM {from} WHERE {}{label_filters}",
+            Self::visible_history_filter(compact_mode)
+        );
+        let count: i64 = self
+            .conn
+            .query_row(&query, params_from_iter(parameters), |row| row.get(0))?;
+        Ok(count.max(0) as u64)
+    }
+
+    fn history_page_source(compact_mode: bool) -> (&'static str, &'static str) {
+        if compact_mode {
+            ("clipboard_events AS event", "event")
         } else {
-            "clipboard_events"
-        };
-        let data_type = if compact_mode { "'text'" } else { "data_type" };
-        let compact_filter = if compact_mode {
+            ("clipboard_events", "clipboard_events")
+        }
+    }
+
+    fn visible_history_filter(compact_mode: bool) -> &'static str {
+        if compact_mode {
             "compact_content_hash IS NOT NULL
              AND NOT EXISTS (
                  SELECT 1
@@ -1533,21 +3103,46 @@ impl Database {
              )"
         } else {
             "1 = 1"
-        };
-        let cursor_filter = if cursor.is_some() {
-            "AND (
-                timestamp < ?1
-                OR (timestamp = ?1 AND content_hash > ?2)
-             )"
-        } else {
-            ""
-        };
-        let limit_parameter = if cursor.is_some() { "?3" } else { "?1" };
-        let from = if compact_mode {
-            "clipboard_events AS event"
+        }
+    }
+
+    fn query_history_summaries(
+        &self,
+        cursor: Option<&HistoryCursor>,
+        limit: i64,
+        filter: &HistoryLabelFilter,
+        compact_mode: bool,
+    ) -> Result<Vec<HistorySummary>> {
+        let (from, alias) = Self::history_page_source(compact_mode);
+        let data_type = if compact_mode { "'text'" } else { "data_type" };
+        let compact_filter = Self::visible_history_filter(compact_mode);
+        let mut parameters = Vec::new();
+        let label_filters =
+            labels::sql_filters(filter, &format!("{alias}.content_hash"), &mut parameters);
+        let cursor_filter = if let Some(cursor) = cursor {
+            parameters.push(Value::Integer(i64::from(cursor.pinned)));
+            let pinned = parameters.len();
+            parameters.push(Value::Integer(cursor.timestamp));
+            let timestamp = parameters.len();
+            parameters.push(Value::Text(cursor.content_hash.clone()));
+            let content_hash = parameters.len();
+            format!(
+                "AND (
+                    {alias}.pinned < ?{pinned}
+                    OR (
+                        {alias}.pinned = ?{pinned}
+                        AND (
+                            {alias}.timestamp < ?{timestamp}
+                            OR ({alias}.timestamp = ?{timestamp} AND {alias}.content_hash > ?{content_hash})
+                        )
+                    )
+                 )"
+            )
         } else {
-            "clipboard_events"
+            String::new()
         };
+        parameters.push(Value::Integer(limit));
+        let limit_parameter = parameters.len();
         let query = format!(
             "SELECT
                 content_hash,
@@ -1557,36 +3152,218 @@ impl Database {
                 timestamp,
                 source_bundle_id,
                 is_remote_clipboard,
-                byte_count
+                byte_count,
+                pinned,
+                first_seen_at,
+                last_seen_at,
+                copy_count
              FROM {from}
-             WHERE {compact_filter}
+             WHERE {compact_filter}{label_filters}
              {cursor_filter}
-             ORDER BY {alias}.timestamp DESC, {alias}.content_hash ASC
-             LIMIT {limit_parameter}"
+             ORDER BY {alias}.pinned DESC, {alias}.timestamp DESC, {alias}.content_hash ASC
+             LIMIT ?{limit_parameter}"
         );
         let mut statement = self.conn.prepare(&query)?;
-        let map_row = |row: &rusqlite::Row<'_>| {
-            let data_type = row.get::<_, String>(1)?;
-            Ok(HistorySummary {
-                content_hash: row.get(0)?,
-                has_detail: Self::data_type_has_detail(&data_type),
-                data_type,
-                display: row.get(2)?,
-                display_truncated: row.get(3)?,
-                timestamp: row.get(4)?,
-                source_bundle_id: row.get(5)?,
-                is_remote_clipboard: row.get(6)?,
-                byte_count: row.get::<_, i64>(7)?.max(0) as u64,
-            })
+        let rows = statement.query_map(params_from_iter(parameters), |row| {
+            self.history_summary_from_row(row)
+        })?;
+        rows.collect()
+    }
+
+    /// Maps the first twelve summary columns shared by list and search queries.
+    fn history_summary_from_row(&self, row: &rusqlite::Row<'_>) -> Result<HistorySummary> {
+        let content_hash = row.get::<_, String>(0)?;
+        let data_type = row.get::<_, String>(1)?;
+        Ok(HistorySummary {
+            display: Self::open_payload(
+                &self.cipher,
+                &content_hash,
+                PayloadColumn::SummaryDisplay,
+                row,
+                2,
+            )?,
+            content_hash,
+            has_detail: Self::data_type_has_detail(&data_type),
+            data_type,
+            display_truncated: row.get(3)?,
+            timestamp: row.get(4)?,
+            source_bundle_id: row.get(5)?,
+            is_remote_clipboard: row.get(6)?,
+            byte_count: row.get::<_, i64>(7)?.max(0) as u64,
+            pinned: row.get(8)?,
+            first_seen_at: row.get(9)?,
+            last_seen_at: row.get(10)?,
+            copy_count: row.get::<_, i64>(11)?.max(1) as u64,
+        })
+    }
+
+    /// Returns one cursor page of rows that match every query term and filter.
+    ///
+    /// Results use the same pinned-first `timestamp DESC, content_hash ASC` order
+    /// and cursor as `get_history_page`, not relevance ranking, so paging stays
+    /// stable while new rows are captured. Filter-only queries skip the full-text index and
+    /// carry no snippet. Empty queries, and queries whose terms are all too short
+    /// to match, return an empty page.
+    pub fn search_history_page(
+        &self,
+        query: &HistoryQuery,
+        cursor: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<HistorySearchPage> {
+        let compact_mode = self.get_compact_mode()?;
+        let page_size = page_size
+            .unwrap_or(DEFAULT_HISTORY_PAGE_SIZE)
+            .clamp(1, MAX_HISTORY_PAGE_SIZE);
+        let fetch_limit = (page_size + 1) as i64;
+        let cursor = cursor
+            .map(HistoryCursor::decode)
+            .transpose()
+            .map_err(rusqlite::Error::InvalidParameterName)?;
+        let expression = search::match_expression(&query.terms);
+        if query.is_empty() || (!query.terms.is_empty() && expression.is_none()) {
+            return Ok(HistorySearchPage {
+                items: Vec::new(),
+                next_cursor: None,
+                has_more: false,
+            });
+        }
+
+        let mut items = self.query_search_results(
+            query,
+            expression.as_deref(),
+            cursor.as_ref(),
+            fetch_limit,
+            compact_mode,
+        )?;
+        let has_more = items.len() > page_size;
+        if has_more {
+            items.truncate(page_size);
+        }
+        let next_cursor = has_more.then(|| items.last()).flatten().map(|item| {
+            HistoryCursor {
+                pinned: item.summary.pinned,
+                timestamp: item.summary.timestamp,
+                content_hash: item.summary.content_hash.clone(),
+            }
+            .encode()
+        });
+
+        Ok(HistorySearchPage {
+            items,
+            next_cursor,
+            has_more,
+        })
+    }
+
+    fn query_search_results(
+        &self,
+        query: &HistoryQuery,
+        expression: Option<&str>,
+        cursor: Option<&HistoryCursor>,
+        limit: i64,
+        compact_mode: bool,
+    ) -> Result<Vec<HistorySearchResult>> {
+        let data_type = if compact_mode {
+            "'text'"
+        } else {
+            "event.data_type"
+        };
+        let compact_filter = if compact_mode {
+            "event.compact_content_hash IS NOT NULL
+             AND NOT EXISTS (
+                 SELECT 1
+                 FROM clipboard_events AS newer
+                 WHERE newer.compact_content_hash = event.compact_content_hash
+                   AND (
+                       newer.timestamp > event.timestamp
+                       OR (
+                           newer.timestamp = event.timestamp
+                           AND newer.content_hash < event.content_hash
+                       )
+                   )
+             )"
+        } else {
+            "1 = 1"
+        };
+        let mut parameters = Vec::new();
+        let (snippet, source, match_filter) = if let Some(expression) = expression {
+            parameters.push(Value::Text(expression.to_string()));
+            parameters.push(Value::Text(search::SNIPPET_HIGHLIGHT_START.to_string()));
+            parameters.push(Value::Text(search::SNIPPET_HIGHLIGHT_END.to_string()));
+            (
+                "snippet(clipboard_events_fts, 0, ?2, ?3, '…', 64)",
+                "clipboard_events_fts
+                 JOIN clipboard_events_search_text AS search
+                   ON search.search_id = clipboard_events_fts.rowid
+                 JOIN clipboard_events AS event
+                   ON event.content_hash = search.content_hash",
+                "clipboard_events_fts MATCH ?1",
+            )
+        } else {
+            ("NULL", "clipboard_events AS event", "1 = 1")
         };
-        let rows = if let Some(cursor) = cursor {
-            statement.query_map(
-                params![cursor.timestamp, &cursor.content_hash, limit],
-                map_row,
-            )?
+        let query_filter = query.sql_filters(data_type, &mut parameters);
+        let cursor_filter = if let Some(cursor) = cursor {
+            parameters.push(Value::Integer(i64::from(cursor.pinned)));
+            parameters.push(Value::Integer(cursor.timestamp));
+            parameters.push(Value::Text(cursor.content_hash.clone()));
+            let pinned_parameter = parameters.len() - 2;
+            let timestamp_parameter = parameters.len() - 1;
+            let hash_parameter = parameters.len();
+            format!(
+                "AND (
+                    event.pinned < ?{pinned_parameter}
+                    OR (
+                        event.pinned = ?{pinned_parameter}
+                        AND (
+                            event.timestamp < ?{timestamp_parameter}
+                            OR (
+                                event.timestamp = ?{timestamp_parameter}
+                                AND event.content_hash > ?{hash_parameter}
+                            )
+                        )
+                    )
+                 )"
+            )
         } else {
-            statement.query_map([limit], map_row)?
+            String::new()
         };
+        parameters.push(Value::Integer(limit));
+        let limit_parameter = parameters.len();
+        let sql = format!(
+            "SELECT
+                event.content_hash,
+                {data_type},
+                event.summary_display,
+                event.summary_truncated,
+                event.timestamp,
+                event.source_bundle_id,
+                event.is_remote_clipboard,
+                event.byte_count,
+                event.pinned,
+                event.first_seen_at,
+                event.last_seen_at,
+                event.copy_count,
+                {snippet}
+             FROM {source}
+             WHERE {match_filter}
+               AND {compact_filter}
+               {query_filter}
+             {cursor_filter}
+             ORDER BY event.pinned DESC, event.timestamp DESC, event.content_hash ASC
+             LIMIT ?{limit_parameter}"
+        );
+        let mut statement = self.conn.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(parameters), |row| {
+            Ok(HistorySearchResult {
+                summary: self.history_summary_from_row(row)?,
+                snippet: row
+                    .get::<_, Option<String>>(12)?
+                    .as_deref()
+                    .map(search::snippet_segments)
+                    .unwrap_or_default(),
+            })
+        })?;
         rows.collect()
     }
 
@@ -1609,166 +3386,822 @@ impl Database {
         )
     }
 
-    pub fn get_history_detail_seed(&self, content_hash: &str) -> Result<Option<HistoryDetailSeed>> {
-        self.conn
+    pub fn get_history_detail_seed(
+        &self,
+        content_hash: &str,
+    ) -> Result<IntegrityResult<Option<HistoryDetailSeed>>> {
+        let stored = self
+            .conn
             .query_row(
-                "SELECT
-                    content_hash,
-                    event_data,
-                    data_type,
-                    display,
-                    compact_display,
-                    timestamp,
-                    source_bundle_id,
-                    is_remote_clipboard,
-                    byte_count
-                 FROM clipboard_events
-                 WHERE content_hash = ?1",
+                &format!(
+                    "SELECT {CHAINED_ROW_COLUMNS}, data_type, display, compact_display, byte_count
+                     FROM clipboard_events
+                     WHERE content_hash = ?1"
+                ),
                 [content_hash],
                 |row| {
-                    Ok(HistoryDetailSeed {
-                        content_hash: row.get(0)?,
-                        event_data: row.get(1)?,
-                        data_type: row.get(2)?,
-                        display: row.get(3)?,
-                        compact_display: row.get(4)?,
-                        timestamp: row.get(5)?,
-                        source_bundle_id: row.get(6)?,
-                        is_remote_clipboard: row.get(7)?,
-                        byte_count: row.get::<_, i64>(8)?.max(0) as u64,
-                    })
+                    Ok((
+                        ChainedRow::from_row(row)?,
+                        row.get::<_, String>(6)?,
+                        row.get::<_, Vec<u8>>(7)?,
+                        row.get::<_, Option<Vec<u8>>>(8)?,
+                        row.get::<_, i64>(9)?,
+                    ))
                 },
             )
-            .optional()
+            .optional()?;
+        let Some((chained, data_type, display, compact_display, byte_count)) = stored else {
+            return Ok(Ok(None));
+        };
+        let Ok(event_data) = self.open_verified_event_data(content_hash, &chained)? else {
+            return Ok(Err(IntegrityViolation));
+        };
+        let (Ok(display), Ok(compact_display)) = (
+            self.cipher
+                .open(content_hash, PayloadColumn::Display, &display),
+            compact_display
+                .map(|compact_display| {
+                    self.cipher.open(
+                        content_hash,
+                        PayloadColumn::CompactDisplay,
+                        &compact_display,
+                    )
+                })
+                .transpose(),
+        ) else {
+            return Ok(Err(IntegrityViolation));
+        };
+
+        Ok(Ok(Some(HistoryDetailSeed {
+            content_hash: content_hash.to_string(),
+            event_data,
+            data_type,
+            display,
+            compact_display,
+            timestamp: chained.timestamp,
+            source_bundle_id: chained.source_bundle_id,
+            is_remote_clipboard: chained.is_remote_clipboard,
+            byte_count: byte_count.max(0) as u64,
+        })))
     }
 
-    pub fn get_restore_seed(&self, content_hash: &str) -> Result<Option<RestoreSeed>> {
+    pub fn get_restore_seed(
+        &self,
+        content_hash: &str,
+    ) -> Result<IntegrityResult<Option<RestoreSeed>>> {
         let compact_mode = self.get_compact_mode()?;
-        let seed = self
+        let stored = self
             .conn
             .query_row(
-                "SELECT
-                    content_hash,
-                    compact_content_hash,
-                    event_data,
-                    source_bundle_id,
-                    is_remote_clipboard
-                 FROM clipboard_events
-                 WHERE content_hash = ?1",
+                &format!(
+                    "SELECT {CHAINED_ROW_COLUMNS}, compact_content_hash
+                     FROM clipboard_events
+                     WHERE content_hash = ?1"
+                ),
                 [content_hash],
-                |row| {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, Option<String>>(1)?,
-                        row.get::<_, Vec<u8>>(2)?,
-                        row.get::<_, Option<String>>(3)?,
-                        row.get::<_, bool>(4)?,
-                    ))
-                },
+                |row| Ok((ChainedRow::from_row(row)?, row.get::<_, Option<String>>(6)?)),
             )
             .optional()?;
-        let Some((
-            stored_content_hash,
-            compact_content_hash,
-            event_data,
-            source_bundle_id,
-            is_remote_clipboard,
-        )) = seed
-        else {
-            return Ok(None);
+        let Some((chained, compact_content_hash)) = stored else {
+            return Ok(Ok(None));
+        };
+        let Ok(event_data) = self.open_verified_event_data(content_hash, &chained)? else {
+            return Ok(Err(IntegrityViolation));
         };
         let content_hash = if compact_mode {
             let Some(compact_content_hash) = compact_content_hash else {
-                return Ok(None);
+                return Ok(Ok(None));
             };
             compact_content_hash
         } else {
-            stored_content_hash
+            content_hash.to_string()
         };
 
-        Ok(Some(RestoreSeed {
+        Ok(Ok(Some(RestoreSeed {
             content_hash,
             event_data,
-            source_bundle_id,
-            is_remote_clipboard,
+            source_bundle_id: chained.source_bundle_id,
+            is_remote_clipboard: chained.is_remote_clipboard,
             compact_mode,
-        }))
+        })))
     }
 
-    pub(crate) fn history_mirror_database_path(&self) -> Result<PathBuf> {
-        self.path.clone().ok_or_else(|| {
-            rusqlite::Error::InvalidParameterName(
-                "history mirror requires an on-disk database".to_string(),
-            )
-        })
-    }
-
-    /// Loads a mirror snapshot through an independent read-only connection.
-    ///
-    /// The history-mirror worker calls this after debounce, so the application's
-    /// shared database mutex is never held while every persisted event BLOB is
-    /// cloned. Reading the latest committed database also makes delayed or
-    /// reordered refresh signals harmless: a signal never carries stale rows.
-    pub(crate) fn visit_history_snapshot_rows_from_path<F>(path: &Path, visitor: F) -> Result<()>
-    where
-        F: FnMut(crate::history_mirror::HistorySnapshotRow) -> bool,
-    {
-        crate::private_fs::harden_private_file_if_exists(path)
-            .map_err(|_| Self::private_database_error("mirror read validation"))?;
-        let connection = Connection::open_with_flags(
-            path,
-            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    /// Opens a row's `event_data` only if it, the row's MAC, and the row's
+    /// place in the chain all verify.
+    fn open_verified_event_data(
+        &self,
+        content_hash: &str,
+        chained: &ChainedRow,
+    ) -> Result<IntegrityResult<Vec<u8>>> {
+        let Ok(event_data) =
+            self.cipher
+                .open(content_hash, PayloadColumn::EventData, &chained.event_data)
+        else {
+            return Ok(Err(IntegrityViolation));
+        };
+        let verified = integrity::verify_row_in(
+            &self.conn,
+            &self.integrity,
+            &IntegrityFields {
+                content_hash,
+                event_data: &event_data,
+                timestamp: chained.timestamp,
+                source_bundle_id: chained.source_bundle_id.as_deref(),
+                is_remote_clipboard: chained.is_remote_clipboard,
+            },
+            &chained.integrity_mac,
+            &chained.integrity_link,
         )?;
-        connection.busy_timeout(std::time::Duration::from_secs(2))?;
-        Self::visit_history_snapshot_rows_in(&connection, visitor)
+        if verified.is_err() {
+            return Ok(Err(IntegrityViolation));
+        }
+        // The verified blob binds each shared flavor's digest, and inlining
+        // checks every payload against its digest.
+        match flavor_blobs::inline_in(&self.conn, &self.cipher, event_data) {
+            Ok(event_data) => Ok(Ok(event_data)),
+            Err(_) => Ok(Err(IntegrityViolation)),
+        }
     }
 
-    #[cfg(test)]
-    pub fn history_snapshot_rows(&self) -> Result<Vec<crate::history_mirror::HistorySnapshotRow>> {
-        Self::history_snapshot_rows_in(&self.conn)
+    /// Checks every row's MAC and chain link and the chain head.
+    pub fn verify_history_integrity(&self) -> Result<IntegrityResult<HistoryIntegrityReport>> {
+        Ok(
+            integrity::verify_all_in(&self.conn, &self.cipher, &self.integrity)?
+                .map(|verified_items| HistoryIntegrityReport { verified_items }),
+        )
     }
 
-    #[cfg(test)]
-    fn history_snapshot_rows_in(
-        connection: &Connection,
-    ) -> Result<Vec<crate::history_mirror::HistorySnapshotRow>> {
-        let mut rows = Vec::new();
-        Self::visit_history_snapshot_rows_in(connection, |row| {
-            rows.push(row);
-            true
-        })?;
-        Ok(rows)
-    }
+    /// Runs SQLite's `integrity_check`, then opens every payload column,
+    /// decodes `event_data`, and checks that the event still classifies to the
+    /// row's `content_hash`. Rows that fail move to `quarantined_events` with
+    /// their stored columns, and search, labels, and the chain are updated as
+    /// for a delete.
+    pub fn check_history_integrity(&self) -> Result<HistoryCheckResult<HistoryCheckReport>> {
+        let transaction = self.conn.unchecked_transaction()?;
+        let integrity_check =
+            transaction.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0));
+        match integrity_check {
+            Ok(result) if result == "ok" => {}
+            Ok(_) => return Ok(Err(CorruptDatabase)),
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == rusqlite::ErrorCode::DatabaseCorrupt =>
+            {
+                return Ok(Err(CorruptDatabase));
+            }
+            Err(error) => return Err(error),
+        }
 
-    fn visit_history_snapshot_rows_in<F>(connection: &Connection, mut visitor: F) -> Result<()>
-    where
-        F: FnMut(crate::history_mirror::HistorySnapshotRow) -> bool,
-    {
-        if settings::get_compact_mode(connection)? {
-            return Self::visit_compact_history_snapshot_rows_in(connection, visitor);
+        let mut report = HistoryCheckReport::default();
+        let mut failed_rows = Vec::new();
+        {
+            let mut statement = transaction.prepare(
+                "SELECT content_hash, event_data, display, summary_display, compact_display
+                 FROM clipboard_events",
+            )?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let content_hash = row.get::<_, String>(0)?;
+                report.checked_items += 1;
+                if let Some(reason) = self.quarantine_reason(&content_hash, row)? {
+                    failed_rows.push((content_hash, reason));
+                }
+            }
         }
 
-        let mut statement = connection.prepare(
-            "SELECT
-                content_hash,
-                event_data,
-                data_type,
-                display,
-                timestamp,
-                source_bundle_id,
-                is_remote_clipboard
-             FROM clipboard_events
-             ORDER BY timestamp DESC, content_hash ASC",
+        let quarantined_at = Self::current_unix_timestamp();
+        for (content_hash, reason) in failed_rows {
+            Self::quarantine_row_in(
+                &transaction,
+                &self.cipher,
+                &self.integrity,
+                &content_hash,
+                reason,
+                quarantined_at,
+            )?;
+            report.quarantined_items += 1;
+            match reason {
+                QuarantineReason::Undecryptable => report.undecryptable_items += 1,
+                QuarantineReason::Undecodable => report.undecodable_items += 1,
+                QuarantineReason::Mismatched => report.mismatched_items += 1,
+            }
+        }
+        transaction.commit()?;
+        self.collect_blob_files();
+        Ok(Ok(report))
+    }
+
+    /// Moves the row `content_hash` to `quarantined_events` with its stored
+    /// columns, then deletes it from history like a user delete.
+    ///
+    /// The delete releases the row's shared flavors, so `event_data` is
+    /// quarantined with them inlined. A payload that does not open, or whose
+    /// shared flavors are gone, is quarantined as stored.
+    fn quarantine_row_in(
+        connection: &Connection,
+        cipher: &PayloadCipher,
+        integrity: &HistoryIntegrityKey,
+        content_hash: &str,
+        reason: QuarantineReason,
+        quarantined_at: i64,
+    ) -> Result<()> {
+        let stored: Vec<u8> = connection.query_row(
+            "SELECT event_data FROM clipboard_events WHERE content_hash = ?1",
+            [content_hash],
+            |row| row.get(0),
+        )?;
+        let event_data = cipher
+            .open(content_hash, PayloadColumn::EventData, &stored)
+            .ok()
+            .and_then(|opened| {
+                let inlined = flavor_blobs::inline_in(connection, cipher, opened.clone()).ok()?;
+                if inlined == opened {
+                    return None;
+                }
+                cipher
+                    .seal(content_hash, PayloadColumn::EventData, &inlined)
+                    .ok()
+            })
+            .unwrap_or(stored);
+        connection.execute(
+            "INSERT INTO quarantined_events (
+                reason,
+                quarantined_at,
+                content_hash,
+                event_data,
+                data_type,
+                display,
+                summary_display,
+                summary_truncated,
+                compact_content_hash,
+                compact_display,
+                source_bundle_id,
+                is_remote_clipboard,
+                byte_count,
+                timestamp,
+                metadata_version,
+                pinned,
+                first_seen_at,
+                last_seen_at,
+                copy_count,
+                expires_at
+             )
+             SELECT
+                ?1,
+                ?2,
+                content_hash,
+                ?4,
+                data_type,
+                display,
+                summary_display,
+                summary_truncated,
+                compact_content_hash,
+                compact_display,
+                source_bundle_id,
+                is_remote_clipboard,
+                byte_count,
+                timestamp,
+                metadata_version,
+                pinned,
+                first_seen_at,
+                last_seen_at,
+                copy_count,
+                expires_at
+             FROM clipboard_events
+             WHERE content_hash = ?3",
+            params![reason.as_str(), quarantined_at, content_hash, event_data],
+        )?;
+        Self::delete_event_row_in(connection, integrity, content_hash)
+    }
+
+    /// Brings the flavor blob directory in line with the database: files no
+    /// payload needs are removed, and rows referencing a payload whose file
+    /// is gone are quarantined as undecodable.
+    pub fn reconcile_blob_files(&self) -> Result<BlobFileReport> {
+        let mut report = BlobFileReport::default();
+        let transaction = self.conn.unchecked_transaction()?;
+        let missing = flavor_blobs::missing_files_in(&transaction)?;
+        if !missing.is_empty() {
+            let quarantined_at = Self::current_unix_timestamp();
+            for content_hash in flavor_blobs::referencing_rows_in(&transaction, &missing)? {
+                Self::quarantine_row_in(
+                    &transaction,
+                    &self.cipher,
+                    &self.integrity,
+                    &content_hash,
+                    QuarantineReason::Undecodable,
+                    quarantined_at,
+                )?;
+                report.quarantined_items += 1;
+            }
+            flavor_blobs::forget_in(&transaction, &missing)?;
+            report.missing_files = missing.len() as u64;
+        }
+        transaction.commit()?;
+        report.orphaned_files = flavor_blobs::remove_unreferenced_files_in(&self.conn)?;
+        Ok(report)
+    }
+
+    /// Removes the flavor blob files a committed write or delete left
+    /// unreferenced. A failure only leaves them for `reconcile_blob_files`.
+    fn collect_blob_files(&self) {
+        let _ = flavor_blobs::remove_unreferenced_files_in(&self.conn);
+    }
+
+    /// Returns the first check a row read by `check_history_integrity` fails.
+    fn quarantine_reason(
+        &self,
+        content_hash: &str,
+        row: &rusqlite::Row<'_>,
+    ) -> Result<Option<QuarantineReason>> {
+        let mut event_data = None;
+        for (index, column) in [
+            (1, PayloadColumn::EventData),
+            (2, PayloadColumn::Display),
+            (3, PayloadColumn::SummaryDisplay),
+            (4, PayloadColumn::CompactDisplay),
+        ] {
+            let Some(sealed) = row.get::<_, Option<Vec<u8>>>(index)? else {
+                continue;
+            };
+            let Ok(opened) = self.cipher.open(content_hash, column, &sealed) else {
+                return Ok(Some(QuarantineReason::Undecryptable));
+            };
+            if column == PayloadColumn::EventData {
+                event_data = Some(opened);
+            }
+        }
+        let Some(event) = event_data
+            .and_then(|event_data| {
+                flavor_blobs::inline_in(&self.conn, &self.cipher, event_data).ok()
+            })
+            .and_then(|event_data| decode_event_blob(&event_data).ok())
+        else {
+            return Ok(Some(QuarantineReason::Undecodable));
+        };
+        Ok(Self::classify_event(&event)
+            .filter(|classified| classified.content_hash == content_hash)
+            .is_none()
+            .then_some(QuarantineReason::Mismatched))
+    }
+
+    /// Lists the database snapshots, newest first. An in-memory database has
+    /// none.
+    pub fn list_database_backups(&self) -> Result<Vec<DatabaseBackup>> {
+        match &self.path {
+            Some(path) => backups::list_backups(path),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Deletes a listed snapshot. This is how the snapshot kept from before
+    /// payload encryption is dismissed.
+    pub fn delete_database_backup(&self, name: &str) -> Result<()> {
+        match &self.path {
+            Some(path) => backups::remove_backup(path, name),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the database with a listed snapshot and migrates it to the
+    /// current schema.
+    ///
+    /// The current database is snapshotted first, so a restore can itself be
+    /// undone. If the restored copy does not open, that snapshot is copied
+    /// back and this database stays usable.
+    pub fn restore_database_backup(&mut self, name: &str) -> Result<DatabaseBackupResult<()>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(Err(DatabaseBackupNotFound));
+        };
+        let Some(backup_path) = backups::backup_path(&path, name)? else {
+            return Ok(Err(DatabaseBackupNotFound));
+        };
+        let current_path = backups::snapshot_database(
+            &self.conn,
+            &path,
+            schema::user_version(&self.conn)?,
+            Self::current_unix_timestamp(),
+        )?;
+
+        self.conn
+            .restore(DatabaseName::Main, &backup_path, None::<fn(Progress)>)?;
+        let reopened = Self::open_prepared_database(
+            &path,
+            &FileKeyProvider::beside_database(&path),
+            &FileKeyProvider::integrity_key_beside_database(&path),
+            false,
+        );
+        match reopened {
+            Ok(reopened) => *self = reopened,
+            Err(error) => {
+                self.conn
+                    .restore(DatabaseName::Main, &current_path, None::<fn(Progress)>)?;
+                crate::private_fs::harden_sqlite_files(&path)
+                    .map_err(|_| Self::private_database_error("harden"))?;
+                return Err(error);
+            }
+        }
+        backups::rotate_backups(&path)?;
+        Ok(Ok(()))
+    }
+
+    /// Copies every setting and every row, oldest first, for a backup archive.
+    ///
+    /// Only a history whose whole chain verifies is exported, so an archive
+    /// never launders rows that were edited outside the application.
+    pub fn history_backup(&self) -> Result<IntegrityResult<HistoryBackup>> {
+        if integrity::verify_all_in(&self.conn, &self.cipher, &self.integrity)?.is_err() {
+            return Ok(Err(IntegrityViolation));
+        }
+
+        let mut statement = self.conn.prepare(
+            "SELECT content_hash, event_data, timestamp, pinned, first_seen_at, last_seen_at,
+                    copy_count, expires_at
+             FROM clipboard_events
+             ORDER BY timestamp ASC, content_hash DESC",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut backup_rows = Vec::new();
+        while let Some(row) = rows.next()? {
+            let content_hash: String = row.get(0)?;
+            let sealed: Vec<u8> = row.get(1)?;
+            let Ok(event_data) = self
+                .cipher
+                .open(&content_hash, PayloadColumn::EventData, &sealed)
+                .map_err(|_| ())
+                .and_then(|event_data| {
+                    flavor_blobs::inline_in(&self.conn, &self.cipher, event_data).map_err(|_| ())
+                })
+                .and_then(|event_data| {
+                    decode_event_blob(&event_data)
+                        .and_then(|event| encode_plain_event_blob(&event))
+                        .map_err(|_| ())
+                })
+            else {
+                return Ok(Err(IntegrityViolation));
+            };
+            backup_rows.push(HistoryBackupRow {
+                content_hash,
+                event_data,
+                timestamp: row.get(2)?,
+                pinned: row.get(3)?,
+                first_seen_at: row.get(4)?,
+                last_seen_at: row.get(5)?,
+                copy_count: row.get(6)?,
+                expires_at: row.get(7)?,
+            });
+        }
+
+        Ok(Ok(HistoryBackup {
+            settings: settings::all_entries(&self.conn)?,
+            rows: backup_rows,
+        }))
+    }
+
+    /// Replaces the settings with a backup's and merges its rows into history
+    /// by `content_hash`, in one transaction.
+    ///
+    /// Each event is decoded and runs through the capture pipeline in full
+    /// mode, so source metadata, displays, and search text come from this
+    /// version's classifier, and the source application and secret policies
+    /// in force before the import apply, not the archive's. Rows the pasteboard policy no longer records are
+    /// skipped, rows those policies refuse are denied, and rows over the
+    /// capture budgets are rejected. A masked secret is imported under its
+    /// masked `content_hash`, and an expiring one expires at the earlier of
+    /// its archived and its new expiry. An event that does not decode, or that
+    /// as archived no longer hashes to its row's `content_hash`, rejects the
+    /// whole archive. A row already in history keeps its payload and place and
+    /// only merges pin and seen statistics; a new row keeps its archived
+    /// timestamp. Pins beyond the pinned budget are dropped, and retention
+    /// runs before commit.
+    pub fn import_history_backup(
+        &self,
+        backup: &HistoryBackup,
+    ) -> Result<BackupResult<HistoryImportReport>> {
+        // Read before the archive's settings replace the current ones, so an
+        // archive cannot loosen the policies its own rows are screened by.
+        let policy = CapturePolicy {
+            compact_mode: false,
+            ..self.capture_policy()?
+        };
+        let transaction = self.conn.unchecked_transaction()?;
+        if !settings::restore_entries(&transaction, &backup.settings)? {
+            return Ok(Err(InvalidBackup));
+        }
+
+        let mut report = HistoryImportReport::default();
+        for row in &backup.rows {
+            let Ok(event) = decode_event_blob(&row.event_data) else {
+                return Ok(Err(InvalidBackup));
+            };
+            let mut prepared = match Self::prepare_capture(event.clone(), &policy)? {
+                CaptureOutcome::Prepared(prepared) => prepared,
+                outcome => {
+                    Self::count_unprepared_import(&mut report, &outcome);
+                    continue;
+                }
+            };
+            if prepared.content_hash() != row.content_hash
+                && !Self::records_content_hash(&event, &row.content_hash)?
+            {
+                return Ok(Err(InvalidBackup));
+            }
+            let content_hash = prepared.content_hash().to_string();
+            let expires_at = match (prepared.metadata.expires_at, row.expires_at) {
+                (Some(screened), Some(archived)) => Some(screened.min(archived)),
+                (screened, archived) => screened.or(archived),
+            };
+
+            let first_seen_at = row.first_seen_at.min(row.last_seen_at);
+            let copy_count = row.copy_count.max(1);
+            let merged = transaction.execute(
+                "UPDATE clipboard_events
+                 SET first_seen_at = MIN(first_seen_at, ?1),
+                     last_seen_at = MAX(last_seen_at, ?2),
+                     copy_count = MAX(copy_count, ?3),
+                     expires_at = CASE
+                         WHEN expires_at IS NULL OR ?4 IS NULL THEN NULL
+                         ELSE MAX(expires_at, ?4)
+                     END
+                 WHERE content_hash = ?5",
+                params![
+                    first_seen_at,
+                    row.last_seen_at,
+                    copy_count,
+                    expires_at,
+                    &content_hash,
+                ],
+            )?;
+            if merged > 0 {
+                report.merged_items += 1;
+            } else {
+                prepared.metadata.expires_at = expires_at;
+                self.insert_imported_row_in(
+                    &transaction,
+                    &prepared,
+                    row.timestamp,
+                    (first_seen_at, row.last_seen_at),
+                    copy_count,
+                )?;
+                report.imported_items += 1;
+            }
+
+            if row.pinned
+                && Self::pinned_budget_allows_in(&transaction, prepared.metadata.byte_count)?
+            {
+                transaction.execute(
+                    "UPDATE clipboard_events SET pinned = 1 WHERE content_hash = ?1",
+                    [&content_hash],
+                )?;
+            }
+        }
+
+        Self::cleanup_old_events_in(&transaction, &self.integrity)?;
+        transaction.commit()?;
+        self.collect_blob_files();
+        Ok(Ok(report))
+    }
+
+    /// Merges entries read from another history format by `content_hash`.
+    ///
+    /// Each entry runs through the capture pipeline in full mode, with the
+    /// source its format recorded standing in for the event's markers, so the
+    /// source application and secret policies apply as they would to a
+    /// capture. An entry that as read no longer classifies to the hash its
+    /// format recorded is rejected. New rows keep the entry's timestamp and
+    /// source metadata, and existing rows only widen their seen range.
+    pub fn import_history(&self, import: &HistoryImport) -> Result<HistoryImportReport> {
+        let policy = CapturePolicy {
+            compact_mode: false,
+            ..self.capture_policy()?
+        };
+        let transaction = self.conn.unchecked_transaction()?;
+        let mut report = HistoryImportReport {
+            rejected_items: import.rejected_items,
+            ..HistoryImportReport::default()
+        };
+        for row in &import.rows {
+            let assessment = PasteboardAssessment {
+                metadata: PasteboardMetadata {
+                    source_bundle_id: row.source_bundle_id.clone(),
+                    is_remote_clipboard: row.is_remote_clipboard,
+                },
+                ..assess_event(&row.event)
+            };
+            let mut prepared =
+                match Self::prepare_assessed_capture(row.event.clone(), assessment, &policy)? {
+                    CaptureOutcome::Prepared(prepared) => prepared,
+                    outcome => {
+                        Self::count_unprepared_import(&mut report, &outcome);
+                        continue;
+                    }
+                };
+            if let Some(content_hash) = row.content_hash.as_deref() {
+                if content_hash != prepared.content_hash()
+                    && !Self::records_content_hash(&row.event, content_hash)?
+                {
+                    report.rejected_items += 1;
+                    continue;
+                }
+            }
+
+            let content_hash = prepared.content_hash().to_string();
+            let merged = transaction.execute(
+                "UPDATE clipboard_events
+                 SET first_seen_at = MIN(first_seen_at, ?1),
+                     last_seen_at = MAX(last_seen_at, ?1)
+                 WHERE content_hash = ?2",
+                params![row.timestamp, &content_hash],
+            )?;
+            if merged > 0 {
+                report.merged_items += 1;
+                continue;
+            }
+
+            prepared.metadata.source_bundle_id = row
+                .source_bundle_id
+                .clone()
+                .filter(|source| source.len() <= MAX_SOURCE_BUNDLE_ID_BYTES);
+            prepared.metadata.is_remote_clipboard = row.is_remote_clipboard;
+            self.insert_imported_row_in(
+                &transaction,
+                &prepared,
+                row.timestamp,
+                (row.timestamp, row.timestamp),
+                1,
+            )?;
+            report.imported_items += 1;
+        }
+
+        Self::cleanup_old_events_in(&transaction, &self.integrity)?;
+        transaction.commit()?;
+        self.collect_blob_files();
+        Ok(report)
+    }
+
+    /// Counts an imported entry the capture pipeline did not prepare.
+    fn count_unprepared_import(report: &mut HistoryImportReport, outcome: &CaptureOutcome) {
+        match outcome {
+            CaptureOutcome::Prepared(_) => {}
+            CaptureOutcome::Skipped(SkipReason::SourceApplication) | CaptureOutcome::Secret(_) => {
+                report.denied_items += 1;
+            }
+            CaptureOutcome::Skipped(_) | CaptureOutcome::Unsupported => report.skipped_items += 1,
+            CaptureOutcome::Rejected(_) => report.rejected_items += 1,
+        }
+    }
+
+    /// Whether `event`, as an import read it, classifies to `content_hash`.
+    /// Checked when the screened event does not, since masking a secret
+    /// changes the hash its source recorded.
+    fn records_content_hash(event: &Event, content_hash: &str) -> Result<bool> {
+        let Ok(capture) = prepare_capture_event(event.clone()) else {
+            return Ok(false);
+        };
+        Ok(Self::prepare_history_event(&capture.event, false)?
+            .is_some_and(|prepared| prepared.content_hash() == content_hash))
+    }
+
+    /// Inserts a row an import found no match for, with the seen statistics
+    /// its source recorded rather than the current time.
+    fn insert_imported_row_in(
+        &self,
+        transaction: &Connection,
+        prepared: &PreparedHistoryEvent,
+        timestamp: i64,
+        (first_seen_at, last_seen_at): (i64, i64),
+        copy_count: i64,
+    ) -> Result<()> {
+        let content_hash = prepared.content_hash();
+        let sealed = Self::seal_payloads(
+            &self.cipher,
+            content_hash,
+            &prepared.event_data,
+            &prepared.classified.display,
+            &prepared.metadata,
+        )?;
+        Self::insert_current_row(
+            transaction,
+            &prepared.classified,
+            &sealed,
+            &prepared.metadata,
+            timestamp,
+        )?;
+        transaction.execute(
+            "UPDATE clipboard_events
+             SET first_seen_at = ?1, last_seen_at = ?2, copy_count = ?3
+             WHERE content_hash = ?4",
+            params![first_seen_at, last_seen_at, copy_count, content_hash],
+        )?;
+        flavor_blobs::retain_in(
+            transaction,
+            &self.cipher,
+            content_hash,
+            &prepared.shared_flavors,
+        )?;
+        self.seal_row_integrity_in(transaction, content_hash, None)?;
+        Self::upsert_search_text_in(
+            transaction,
+            &self.cipher,
+            content_hash,
+            &prepared.search_text,
+        )
+    }
+
+    pub(crate) fn history_mirror_database_path(&self) -> Result<PathBuf> {
+        self.path.clone().ok_or_else(|| {
+            rusqlite::Error::InvalidParameterName(
+                "history mirror requires an on-disk database".to_string(),
+            )
+        })
+    }
+
+    /// Loads a mirror snapshot through an independent read-only connection.
+    ///
+    /// The history-mirror worker calls this after debounce, so the application's
+    /// shared database mutex is never held while every persisted event BLOB is
+    /// cloned. Reading the latest committed database also makes delayed or
+    /// reordered refresh signals harmless: a signal never carries stale rows.
+    pub(crate) fn visit_history_snapshot_rows_from_path<F>(
+        path: &Path,
+        cipher: &PayloadCipher,
+        visitor: F,
+    ) -> Result<()>
+    where
+        F: FnMut(crate::history_mirror::HistorySnapshotRow) -> bool,
+    {
+        crate::private_fs::harden_private_file_if_exists(path)
+            .map_err(|_| Self::private_database_error("mirror read validation"))?;
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        connection.busy_timeout(std::time::Duration::from_secs(2))?;
+        Self::visit_history_snapshot_rows_in(&connection, cipher, visitor)
+    }
+
+    /// Returns the payload cipher for readers that open their own connection.
+    pub(crate) fn payload_cipher(&self) -> PayloadCipher {
+        self.cipher.clone()
+    }
+
+    #[cfg(test)]
+    pub fn history_snapshot_rows(&self) -> Result<Vec<crate::history_mirror::HistorySnapshotRow>> {
+        Self::history_snapshot_rows_in(&self.conn, &self.cipher)
+    }
+
+    #[cfg(test)]
+    fn history_snapshot_rows_in(
+        connection: &Connection,
+        cipher: &PayloadCipher,
+    ) -> Result<Vec<crate::history_mirror::HistorySnapshotRow>> {
+        let mut rows = Vec::new();
+        Self::visit_history_snapshot_rows_in(connection, cipher, |row| {
+            rows.push(row);
+            true
+        })?;
+        Ok(rows)
+    }
+
+    fn visit_history_snapshot_rows_in<F>(
+        connection: &Connection,
+        cipher: &PayloadCipher,
+        mut visitor: F,
+    ) -> Result<()>
+    where
+        F: FnMut(crate::history_mirror::HistorySnapshotRow) -> bool,
+    {
+        if settings::get_compact_mode(connection)? {
+            return Self::visit_compact_history_snapshot_rows_in(connection, cipher, visitor);
+        }
+
+        let mut statement = connection.prepare(&format!(
+            "SELECT
+                content_hash,
+                event_data,
+                data_type,
+                display,
+                timestamp,
+                source_bundle_id,
+                is_remote_clipboard,
+                {},
+                {}
+             FROM clipboard_events
+             ORDER BY timestamp DESC, content_hash ASC",
+            labels::collection_names_sql("clipboard_events.content_hash"),
+            labels::tag_names_sql("clipboard_events.content_hash"),
+        ))?;
         let mapped = statement.query_map([], |row| {
+            let content_hash = row.get::<_, String>(0)?;
             Ok(crate::history_mirror::HistorySnapshotRow {
-                content_hash: row.get(0)?,
-                event_data: row.get(1)?,
+                event_data: Self::open_event_data(connection, cipher, &content_hash, row, 1)?,
                 data_type: row.get(2)?,
-                display: row.get(3)?,
+                display: Self::open_payload(cipher, &content_hash, PayloadColumn::Display, row, 3)?,
+                content_hash,
                 timestamp: row.get(4)?,
                 source_bundle_id: row.get(5)?,
                 is_remote_clipboard: row.get(6)?,
+                collections: labels::split_names(row.get(7)?),
+                tags: labels::split_names(row.get(8)?),
             })
         })?;
         for row in mapped {
@@ -1781,18 +4214,22 @@ impl Database {
 
     fn visit_compact_history_snapshot_rows_in<F>(
         connection: &Connection,
+        cipher: &PayloadCipher,
         mut visitor: F,
     ) -> Result<()>
     where
         F: FnMut(crate::history_mirror::HistorySnapshotRow) -> bool,
     {
-        let mut statement = connection.prepare(
+        let mut statement = connection.prepare(&format!(
             "SELECT
                 compact_content_hash,
                 compact_display,
                 timestamp,
                 source_bundle_id,
-                is_remote_clipboard
+                is_remote_clipboard,
+                {},
+                {},
+                content_hash
              FROM clipboard_events AS event
              WHERE compact_content_hash IS NOT NULL
                AND compact_display IS NOT NULL
@@ -1809,20 +4246,37 @@ impl Database {
                      )
                )
              ORDER BY timestamp DESC, content_hash ASC",
-        )?;
+            labels::collection_names_sql("event.content_hash"),
+            labels::tag_names_sql("event.content_hash"),
+        ))?;
         let mapped = statement.query_map([], |row| {
             Ok((
                 row.get::<_, String>(0)?,
-                row.get::<_, Vec<u8>>(1)?,
+                Self::open_payload(
+                    cipher,
+                    &row.get::<_, String>(7)?,
+                    PayloadColumn::CompactDisplay,
+                    row,
+                    1,
+                )?,
                 row.get::<_, i64>(2)?,
                 row.get::<_, Option<String>>(3)?,
                 row.get::<_, bool>(4)?,
+                labels::split_names(row.get(5)?),
+                labels::split_names(row.get(6)?),
             ))
         })?;
 
         for mapped_row in mapped {
-            let (content_hash, display, timestamp, source_bundle_id, is_remote_clipboard) =
-                mapped_row?;
+            let (
+                content_hash,
+                display,
+                timestamp,
+                source_bundle_id,
+                is_remote_clipboard,
+                collections,
+                tags,
+            ) = mapped_row?;
             let mut data_list = vec![Data {
                 r#type: "public.utf8-plain-text".to_string(),
                 data: display.clone(),
@@ -1855,6 +4309,8 @@ impl Database {
                 timestamp,
                 source_bundle_id,
                 is_remote_clipboard,
+                collections,
+                tags,
             };
             if !visitor(row) {
                 break;
@@ -1907,79 +4363,110 @@ impl Database {
             "SELECT
                 content_hash,
                 {data_type},
-                summary_display
+                summary_display,
+                pinned
              FROM {from}
              WHERE {compact_filter}
+               AND pinned = ?1
              ORDER BY timestamp DESC, content_hash ASC
-             LIMIT ?1"
+             LIMIT ?2"
         );
         let mut statement = self.conn.prepare(&query)?;
-        let rows = statement.query_map([limit], |row| {
-            Ok(TrayEvent {
-                content_hash: row.get(0)?,
-                data_type: row.get(1)?,
-                display: row.get(2)?,
-            })
-        })?;
-        rows.collect()
-    }
-
-    pub(crate) fn get_tray_preview(&self, content_hash: &str) -> Result<Option<TrayPreview>> {
-        let compact_mode = self.get_compact_mode()?;
-        let query = if compact_mode {
-            "SELECT
-                'text',
-                substr(compact_display, 1, ?2),
-                length(compact_display) > ?2
-             FROM clipboard_events
-             WHERE content_hash = ?1
-               AND compact_content_hash IS NOT NULL"
+        let mut events = Vec::new();
+        // Pinned rows form their own section and do not use up the configured
+        // recent-item limit.
+        for (pinned, limit) in [(true, MAX_PINNED_ITEMS as i64), (false, limit)] {
+            let rows = statement.query_map(params![pinned, limit], |row| {
+                let content_hash = row.get::<_, String>(0)?;
+                Ok(TrayEvent {
+                    display: Self::open_payload(
+                        &self.cipher,
+                        &content_hash,
+                        PayloadColumn::SummaryDisplay,
+                        row,
+                        2,
+                    )?,
+                    content_hash,
+                    data_type: row.get(1)?,
+                    pinned: row.get(3)?,
+                })
+            })?;
+            for row in rows {
+                events.push(row?);
+            }
+        }
+        Ok(events)
+    }
+
+    pub(crate) fn get_tray_preview(&self, content_hash: &str) -> Result<Option<TrayPreview>> {
+        let compact_mode = self.get_compact_mode()?;
+        let compact_filter = if compact_mode {
+            "AND compact_content_hash IS NOT NULL"
         } else {
-            "SELECT
-                data_type,
-                substr(
-                    CASE
-                        WHEN data_type IN ('text', 'rtf', 'html')
-                            THEN COALESCE(compact_display, display)
-                        ELSE summary_display
-                    END,
-                    1,
-                    ?2
-                ),
-                length(
-                    CASE
-                        WHEN data_type IN ('text', 'rtf', 'html')
-                            THEN COALESCE(compact_display, display)
-                        ELSE summary_display
-                    END
-                ) > ?2
-             FROM clipboard_events
-             WHERE content_hash = ?1"
+            ""
         };
-        let mut preview = self
+        // Payloads are sealed, so the preview column is chosen and cut to the
+        // byte bound after decryption rather than in SQL.
+        let preview = self
             .conn
             .query_row(
-                query,
-                params![content_hash, MAX_TRAY_PREVIEW_BYTES as i64],
+                &format!(
+                    "SELECT data_type, display, summary_display, compact_display
+                     FROM clipboard_events
+                     WHERE content_hash = ?1 {compact_filter}"
+                ),
+                [content_hash],
                 |row| {
-                    Ok(TrayPreview {
-                        data_type: row.get(0)?,
-                        display: row.get(1)?,
-                        truncated: row.get(2)?,
-                    })
+                    let data_type = row.get::<_, String>(0)?;
+                    let compact_display = Self::open_optional_payload(
+                        &self.cipher,
+                        content_hash,
+                        PayloadColumn::CompactDisplay,
+                        row,
+                        3,
+                    )?;
+                    if compact_mode {
+                        return Ok(("text".to_string(), compact_display.unwrap_or_default()));
+                    }
+                    let display = if matches!(data_type.as_str(), "text" | "rtf" | "html") {
+                        match compact_display {
+                            Some(compact_display) => compact_display,
+                            None => Self::open_payload(
+                                &self.cipher,
+                                content_hash,
+                                PayloadColumn::Display,
+                                row,
+                                1,
+                            )?,
+                        }
+                    } else {
+                        Self::open_payload(
+                            &self.cipher,
+                            content_hash,
+                            PayloadColumn::SummaryDisplay,
+                            row,
+                            2,
+                        )?
+                    };
+                    Ok((data_type, display))
                 },
             )
             .optional()?;
 
-        if let Some(preview) = preview.as_mut() {
-            if let Err(error) = std::str::from_utf8(&preview.display) {
+        Ok(preview.map(|(data_type, mut display)| {
+            let truncated = display.len() > MAX_TRAY_PREVIEW_BYTES;
+            display.truncate(MAX_TRAY_PREVIEW_BYTES);
+            if let Err(error) = std::str::from_utf8(&display) {
                 if error.error_len().is_none() {
-                    preview.display.truncate(error.valid_up_to());
+                    display.truncate(error.valid_up_to());
                 }
             }
-        }
-
-        Ok(preview)
+            TrayPreview {
+                data_type,
+                display,
+                truncated,
+            }
+        }))
     }
 
     pub fn get_history_stats(&self) -> Result<HistoryStats> {
@@ -2023,12 +4510,13 @@ impl Database {
         )?;
 
         let event_iter = stmt.query_map([], |row| {
+            let content_hash = row.get::<_, String>(0)?;
             Ok((
-                row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
-                row.get::<_, Vec<u8>>(2)?,
-                row.get::<_, Vec<u8>>(3)?,
+                Self::open_payload(&self.cipher, &content_hash, PayloadColumn::Display, row, 2)?,
+                Self::open_event_data(&self.conn, &self.cipher, &content_hash, row, 3)?,
                 row.get::<_, i64>(4)?,
+                content_hash,
             ))
         })?;
 
@@ -2037,7 +4525,7 @@ impl Database {
         let mut compact_hashes = std::collections::HashSet::new();
 
         for event in event_iter {
-            let (content_hash, data_type, display, event_data, timestamp) = event?;
+            let (data_type, display, event_data, timestamp, content_hash) = event?;
             if compact_mode {
                 let Ok(event) = Self::event_from_blob(&event_data) else {
                     continue;
@@ -2081,12 +4569,11 @@ impl Database {
 
         let mut rows = stmt.query([content_hash])?;
         if let Some(row) = rows.next()? {
-            let event_data: Vec<u8> = row.get(0)?;
-            let event = Self::event_from_blob(&event_data)?;
+            let event_data = Self::open_event_data(&self.conn, &self.cipher, content_hash, row, 0)?;
             if self.get_compact_mode()? {
-                Ok(Self::compact_text_event(&event))
+                Self::compact_text_event_from_blob(&event_data)
             } else {
-                Ok(Some(event))
+                Self::event_from_blob(&event_data).map(Some)
             }
         } else {
             Ok(None)
@@ -2098,59 +4585,199 @@ impl Database {
             .map_err(|error| rusqlite::Error::InvalidParameterName(error.to_string()))
     }
 
+    /// Decodes a seed's event as history shows it: the stored event, or its
+    /// compact text projection in compact mode. `None` means the row has no
+    /// compact projection.
+    pub fn history_event_from_seed(
+        seed: &HistoryDetailSeed,
+        compact_mode: bool,
+    ) -> Result<Option<Event>> {
+        if compact_mode {
+            Self::compact_text_event_from_blob(&seed.event_data)
+        } else {
+            Self::event_from_blob(&seed.event_data).map(Some)
+        }
+    }
+
+    /// Decodes only the flavors the compact projection reads.
+    fn compact_text_event_from_blob(event_data: &[u8]) -> Result<Option<Event>> {
+        classification::compact_text_event_from_blob(event_data)
+            .map_err(|error| rusqlite::Error::InvalidParameterName(error.to_string()))
+    }
+
     pub fn delete_event(&self, content_hash: &str) -> Result<()> {
-        self.conn.execute(
-            "DELETE FROM clipboard_events WHERE content_hash = ?1",
-            [content_hash],
-        )?;
+        let transaction = self.conn.unchecked_transaction()?;
+        Self::delete_event_row_in(&transaction, &self.integrity, content_hash)?;
+        transaction.commit()?;
+        self.collect_blob_files();
         Ok(())
     }
 
     pub fn clear_all_events(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM clipboard_events", [])?;
+        let transaction = self.conn.unchecked_transaction()?;
+        transaction.execute("DELETE FROM clipboard_events_search", [])?;
+        labels::clear_memberships_in(&transaction)?;
+        flavor_blobs::clear_in(&transaction)?;
+        transaction.execute("DELETE FROM clipboard_events", [])?;
+        integrity::relink_in(&transaction, &self.integrity, &[])?;
+        transaction.commit()?;
+        self.collect_blob_files();
         Ok(())
     }
 
-    pub fn cleanup_old_events(&self) -> Result<()> {
+    /// Deletes due auto-expiring rows, then enforces the age, per-type, item,
+    /// and byte limits and returns how many rows were deleted, so a periodic
+    /// sweep can skip notifying when nothing expired.
+    pub fn cleanup_old_events(&self) -> Result<u64> {
         let transaction = self.conn.unchecked_transaction()?;
-        Self::cleanup_old_events_in(&transaction)?;
-        transaction.commit()
+        let deleted = Self::cleanup_old_events_in(&transaction, &self.integrity)?;
+        transaction.commit()?;
+        self.collect_blob_files();
+        Ok(deleted)
+    }
+
+    /// Returns when the earliest auto-expiring row is due, in Unix
+    /// milliseconds.
+    pub fn next_expiry_at(&self) -> Result<Option<i64>> {
+        self.conn
+            .query_row("SELECT MIN(expires_at) FROM clipboard_events", [], |row| {
+                row.get(0)
+            })
     }
 
-    fn cleanup_old_events_in(connection: &Connection) -> Result<()> {
+    fn cleanup_old_events_in(
+        connection: &Connection,
+        integrity: &HistoryIntegrityKey,
+    ) -> Result<u64> {
         let max_items = settings::get_max_items(connection)?;
         let max_history_bytes = settings::get_max_history_bytes(connection)?;
-        let count: i64 =
-            connection.query_row("SELECT COUNT(*) FROM clipboard_events", [], |row| {
-                row.get(0)
-            })?;
+        let max_age_days = settings::get_max_age_days(connection)?;
+        let mut deleted = 0;
+
+        // Expiry only comes from a secret policy, so a pin does not keep
+        // the row past it.
+        let mut statement = connection
+            .prepare("SELECT content_hash FROM clipboard_events WHERE expires_at <= ?1")?;
+        let expired_hashes = statement
+            .query_map([Self::current_unix_timestamp()], |row| {
+                row.get::<_, String>(0)
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        drop(statement);
+        for content_hash in expired_hashes {
+            Self::delete_event_row_in(connection, integrity, &content_hash)?;
+            deleted += 1;
+        }
 
-        if count > max_items as i64 {
-            let excess = count - max_items as i64;
-            connection.execute(
-                "DELETE FROM clipboard_events WHERE content_hash IN (
-                    SELECT content_hash FROM clipboard_events
-                    ORDER BY timestamp ASC, content_hash DESC
-                    LIMIT ?1
-                )",
-                [excess],
-            )?;
+        // Age is measured from the last capture, so content copied again
+        // recently stays even if it first appeared long ago. The age limit is
+        // a purge policy rather than a budget, so pinning does not exempt a
+        // row from it.
+        if max_age_days > 0 {
+            let cutoff = Self::current_unix_timestamp()
+                .saturating_sub(i64::from(max_age_days).saturating_mul(MILLIS_PER_DAY));
+            let mut statement = connection
+                .prepare("SELECT content_hash FROM clipboard_events WHERE last_seen_at < ?1")?;
+            let hashes_to_delete = statement
+                .query_map([cutoff], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>>>()?;
+            drop(statement);
+            for content_hash in hashes_to_delete {
+                Self::delete_event_row_in(connection, integrity, &content_hash)?;
+                deleted += 1;
+            }
         }
 
-        let total_bytes: i64 = connection.query_row(
-            "SELECT COALESCE(SUM(byte_count), 0) FROM clipboard_events",
-            [],
+        for budget in settings::get_data_type_budgets(connection)? {
+            if let Some(max_items) = budget.max_items {
+                deleted += Self::evict_beyond_count_in(
+                    connection,
+                    integrity,
+                    Some(&budget.data_type),
+                    max_items,
+                )?;
+            }
+            if let Some(max_bytes) = budget.max_bytes {
+                deleted += Self::evict_beyond_bytes_in(
+                    connection,
+                    integrity,
+                    Some(&budget.data_type),
+                    max_bytes,
+                )?;
+            }
+        }
+        deleted += Self::evict_beyond_count_in(connection, integrity, None, max_items)?;
+        deleted += Self::evict_beyond_bytes_in(connection, integrity, None, max_history_bytes)?;
+
+        Ok(deleted)
+    }
+
+    /// Deletes the oldest unpinned rows, limited to `data_type` when given,
+    /// until at most `max_items` of them remain.
+    fn evict_beyond_count_in(
+        connection: &Connection,
+        integrity: &HistoryIntegrityKey,
+        data_type: Option<&str>,
+        max_items: u32,
+    ) -> Result<u64> {
+        let count: i64 = connection.query_row(
+            "SELECT COUNT(*) FROM clipboard_events
+             WHERE pinned = 0 AND (?1 IS NULL OR data_type = ?1)",
+            [data_type],
             |row| row.get(0),
         )?;
-        let total_bytes = total_bytes.max(0) as u64;
-        if total_bytes > max_history_bytes {
-            let mut bytes_to_reclaim = total_bytes - max_history_bytes;
+        if count <= i64::from(max_items) {
+            return Ok(0);
+        }
+
+        let excess = count - i64::from(max_items);
+        let mut statement = connection.prepare(
+            "SELECT content_hash FROM clipboard_events
+             WHERE pinned = 0 AND (?1 IS NULL OR data_type = ?1)
+             ORDER BY timestamp ASC, content_hash DESC
+             LIMIT ?2",
+        )?;
+        let hashes_to_delete = statement
+            .query_map(params![data_type, excess], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+        drop(statement);
+        for content_hash in &hashes_to_delete {
+            Self::delete_event_row_in(connection, integrity, content_hash)?;
+        }
+        Ok(hashes_to_delete.len() as u64)
+    }
+
+    /// Deletes the oldest unpinned rows, limited to `data_type` when given,
+    /// until their accounted bytes fit in `max_bytes`. Deleting a row that
+    /// shares a flavor payload moves its share onto the rows still
+    /// referencing it, so the total is measured again after each pass.
+    fn evict_beyond_bytes_in(
+        connection: &Connection,
+        integrity: &HistoryIntegrityKey,
+        data_type: Option<&str>,
+        max_bytes: u64,
+    ) -> Result<u64> {
+        let mut deleted = 0;
+        loop {
+            let total_bytes: i64 = connection.query_row(
+                "SELECT COALESCE(SUM(byte_count), 0) FROM clipboard_events
+                 WHERE pinned = 0 AND (?1 IS NULL OR data_type = ?1)",
+                [data_type],
+                |row| row.get(0),
+            )?;
+            let total_bytes = total_bytes.max(0) as u64;
+            if total_bytes <= max_bytes {
+                return Ok(deleted);
+            }
+
+            let mut bytes_to_reclaim = total_bytes - max_bytes;
             let mut statement = connection.prepare(
                 "SELECT content_hash, byte_count
                  FROM clipboard_events
+                 WHERE pinned = 0 AND (?1 IS NULL OR data_type = ?1)
                  ORDER BY timestamp ASC, content_hash DESC",
             )?;
-            let rows = statement.query_map([], |row| {
+            let rows = statement.query_map([data_type], |row| {
                 Ok((
                     row.get::<_, String>(0)?,
                     row.get::<_, i64>(1)?.max(0) as u64,
@@ -2166,26 +4793,30 @@ impl Database {
                 }
             }
             drop(statement);
-            for content_hash in hashes_to_delete {
-                connection.execute(
-                    "DELETE FROM clipboard_events WHERE content_hash = ?1",
-                    [content_hash],
-                )?;
+            if hashes_to_delete.is_empty() {
+                return Ok(deleted);
+            }
+            for content_hash in &hashes_to_delete {
+                Self::delete_event_row_in(connection, integrity, content_hash)?;
             }
+            deleted += hashes_to_delete.len() as u64;
         }
-
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::history_import::ImportedHistoryRow;
     use crate::history_mirror::{HistoryMirror, HistoryMirrorConfig};
     use crate::pasteboard_protocol::{
-        prepare_event_for_restore, AUTO_GENERATED_TYPE, CONCEALED_TYPE, LEGACY_TRANSIENT_TYPE,
-        ONEPASSWORD_TYPE, PASTEBOARD_GENERATOR_TYPE, TRANSIENT_TYPE, TYPEIT4ME_CLIPPING_TYPE,
+        prepare_event_for_restore, HistoryDisposition, SkipReason, AUTO_GENERATED_TYPE,
+        CONCEALED_TYPE, LEGACY_TRANSIENT_TYPE, ONEPASSWORD_TYPE, PASTEBOARD_GENERATOR_TYPE,
+        TRANSIENT_TYPE, TYPEIT4ME_CLIPPING_TYPE,
     };
+    use crate::store::blob_files;
+    use crate::store::labels::MAX_TAGS_PER_ITEM;
+    use crate::store::models::LabelRejection;
     use std::time::Duration;
 
     const PROTOCOL_SKIP_CASES: [(&str, &str, &[u8]); 7] = [
@@ -2257,6 +4888,7 @@ mod tests {
         assert!(
             db.get_history_detail_seed(body_hash)
                 .expect("history detail lookup should succeed")
+                .expect("stored history should verify")
                 .is_none(),
             "{case} leaked into the detail path"
         );
@@ -2289,7 +4921,7 @@ mod tests {
         assert_eq!(stats.total_bytes, 0, "{case} consumed retained bytes");
     }
 
-    fn assert_database_mirror_is_empty(database_path: &Path, label: &str) {
+    fn assert_database_mirror_is_empty(db: &Database, database_path: &Path, label: &str) {
         let output = database_path
             .parent()
             .expect("database should have a parent")
@@ -2297,6 +4929,7 @@ mod tests {
         let mirror = HistoryMirror::start_database(
             HistoryMirrorConfig::new(output.clone(), 4_096).with_debounce(Duration::ZERO),
             database_path.to_path_buf(),
+            db.payload_cipher(),
         )
         .expect("database-backed mirror should start");
         mirror
@@ -2338,11 +4971,29 @@ mod tests {
         }
     }
 
+    /// Wraps a connection whose schema has not been initialized yet, keeping
+    /// its payload key in memory.
+    fn uninitialized_database(conn: Connection, path: Option<PathBuf>) -> Database {
+        Database::configure_connection(&conn).expect("connection should configure");
+        let (cipher, integrity, _) = Database::load_history_keys_in(
+            &conn,
+            &MemoryKeyProvider::default(),
+            &MemoryKeyProvider::default(),
+        )
+        .expect("history keys should be created");
+        Database {
+            conn,
+            path,
+            cipher,
+            integrity,
+        }
+    }
+
     fn in_memory_database() -> Database {
-        let db = Database {
-            conn: Connection::open_in_memory().expect("in-memory database should open"),
-            path: None,
-        };
+        let db = uninitialized_database(
+            Connection::open_in_memory().expect("in-memory database should open"),
+            None,
+        );
         db.initialize_schema()
             .expect("in-memory schema should initialize");
         db
@@ -2466,10 +5117,10 @@ mod tests {
         ] {
             let path = temp_database_path("migration_rollback");
             create_version_one_database(&path, &clipboard_event);
-            let db = Database {
-                conn: Connection::open(&path).expect("database should reopen"),
-                path: Some(path.clone()),
-            };
+            let db = uninitialized_database(
+                Connection::open(&path).expect("database should reopen"),
+                Some(path.clone()),
+            );
             assert!(
                 db.initialize_schema_with_failpoint(Some(failpoint))
                     .is_err(),
@@ -2499,6 +5150,182 @@ mod tests {
         }
     }
 
+    /// Databases as each past schema version wrote them, holding the same
+    /// three rows. Versions eight and up wrap their payload key under
+    /// `[0x42; 32]`.
+    const SCHEMA_FIXTURES: [(i64, &str); 14] = [
+        (0, include_str!("fixtures/schema_v0.sql")),
+        (1, include_str!("fixtures/schema_v1.sql")),
+        (2, include_str!("fixtures/schema_v2.sql")),
+        (3, include_str!("fixtures/schema_v3.sql")),
+        (4, include_str!("fixtures/schema_v4.sql")),
+        (5, include_str!("fixtures/schema_v5.sql")),
+        (6, include_str!("fixtures/schema_v6.sql")),
+        (7, include_str!("fixtures/schema_v7.sql")),
+        (8, include_str!("fixtures/schema_v8.sql")),
+        (9, include_str!("fixtures/schema_v9.sql")),
+        (10, include_str!("fixtures/schema_v10.sql")),
+        (11, include_str!("fixtures/schema_v11.sql")),
+        (12, include_str!("fixtures/schema_v12.sql")),
+        (13, include_str!("fixtures/schema_v13.sql")),
+    ];
+
+    fn load_schema_fixture(path: &Path, sql: &str) {
+        Connection::open(path)
+            .expect("fixture database should open")
+            .execute_batch(sql)
+            .expect("fixture should load");
+    }
+
+    fn fixture_rows(connection: &Connection) -> Vec<(i64, String)> {
+        let mut statement = connection
+            .prepare("SELECT rowid, content_hash FROM clipboard_events ORDER BY timestamp")
+            .expect("fixture rows should prepare");
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .expect("fixture rows should query")
+            .collect::<Result<Vec<_>>>()
+            .expect("fixture rows should load");
+        rows
+    }
+
+    #[test]
+    fn every_schema_fixture_migrates_to_the_current_schema_intact() {
+        assert_eq!(
+            SCHEMA_FIXTURES.last().map(|(version, _)| *version),
+            Some(CURRENT_SCHEMA_VERSION)
+        );
+        for (version, sql) in SCHEMA_FIXTURES {
+            let path = temp_database_path("schema_fixture");
+            load_schema_fixture(&path, sql);
+            let original_rows = (version >= 2)
+                .then(|| fixture_rows(&Connection::open(&path).expect("fixture should reopen")));
+
+            let db = Database::open_private_database_with(
+                &path,
+                &MemoryKeyProvider::with_key([0x42; 32]),
+                &MemoryKeyProvider::default(),
+            )
+            .unwrap_or_else(|error| panic!("version {version} should migrate: {error}"));
+            assert_eq!(
+                schema::user_version(&db.conn).expect("version should load"),
+                CURRENT_SCHEMA_VERSION,
+                "version {version}"
+            );
+            assert_eq!(
+                db.table_columns("clipboard_events")
+                    .expect("columns should load"),
+                REQUIRED_EVENT_COLUMNS,
+                "version {version}"
+            );
+            if let Some(original_rows) = original_rows {
+                assert_eq!(fixture_rows(&db.conn), original_rows, "version {version}");
+            }
+
+            let rows = db
+                .conn
+                .prepare(
+                    "SELECT pinned, first_seen_at, last_seen_at, copy_count, expires_at
+                     FROM clipboard_events
+                     ORDER BY timestamp",
+                )
+                .expect("row state should prepare")
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, bool>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, u32>(3)?,
+                        row.get::<_, Option<i64>>(4)?,
+                    ))
+                })
+                .expect("row state should query")
+                .collect::<Result<Vec<_>>>()
+                .expect("row state should load");
+            assert_eq!(
+                rows,
+                vec![
+                    (false, 1_700_000_000_000, 1_700_000_000_000, 1, None),
+                    (
+                        version >= 4,
+                        if version >= 6 {
+                            1_699_990_000_000
+                        } else {
+                            1_700_000_100_000
+                        },
+                        1_700_000_100_000,
+                        if version >= 6 { 3 } else { 1 },
+                        None,
+                    ),
+                    (
+                        false,
+                        1_700_000_200_000,
+                        1_700_000_200_000,
+                        1,
+                        (version >= 7).then_some(4_102_444_800_000),
+                    ),
+                ],
+                "version {version}"
+            );
+            let labelled = db
+                .list_collections()
+                .expect("collections should load")
+                .into_iter()
+                .map(|collection| (collection.name, collection.item_count))
+                .chain(
+                    db.list_tags()
+                        .expect("tags should load")
+                        .into_iter()
+                        .map(|tag| (tag.name, tag.item_count)),
+                )
+                .collect::<Vec<_>>();
+            if version >= 5 {
+                assert_eq!(
+                    labelled,
+                    vec![("Golden".to_string(), 1), ("fixture".to_string(), 1)]
+                );
+            } else {
+                assert!(labelled.is_empty(), "version {version}");
+            }
+            assert_eq!(db.get_max_items().expect("settings should load"), 50);
+            assert_eq!(search_hashes(&db, "golden").len(), 3, "version {version}");
+            assert_eq!(
+                db.verify_history_integrity()
+                    .expect("integrity should verify"),
+                Ok(HistoryIntegrityReport { verified_items: 3 }),
+                "version {version}"
+            );
+            drop(db);
+            remove_database_files(&path);
+        }
+    }
+
+    #[test]
+    fn failed_migration_step_rolls_back_every_step_before_it() {
+        let path = temp_database_path("migration_step_rollback");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[6].1);
+        let db = uninitialized_database(
+            Connection::open(&path).expect("database should reopen"),
+            Some(path.clone()),
+        );
+        assert!(db
+            .initialize_schema_with_failpoint(Some(MigrationFailpoint::AfterStep(8)))
+            .is_err());
+        drop(db);
+
+        let connection = Connection::open(&path).expect("rolled-back database should reopen");
+        assert_eq!(
+            schema::user_version(&connection).expect("version should load"),
+            6
+        );
+        assert!(!Database::table_columns_in(&connection, "clipboard_events")
+            .expect("columns should load")
+            .contains(&"expires_at".to_string()));
+        assert_eq!(fixture_rows(&connection).len(), 3);
+        drop(connection);
+        remove_database_files(&path);
+    }
+
     #[test]
     fn corrupt_legacy_row_aborts_migration_without_replacing_original_table() {
         let path = temp_database_path("migration_corrupt");
@@ -2603,524 +5430,2292 @@ mod tests {
     }
 
     #[test]
-    fn legacy_json_schema_migrates_metadata_and_versions_transactionally() {
-        let path = temp_database_path("migration_legacy_json");
-        let connection = Connection::open(&path).expect("legacy database should open");
-        connection
-            .execute_batch(
-                "CREATE TABLE clipboard_events (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    event_data TEXT NOT NULL,
-                    data_type TEXT NOT NULL,
-                    display TEXT NOT NULL,
-                    sort_order INTEGER NOT NULL,
-                    timestamp TEXT NOT NULL
-                 );",
-            )
-            .expect("legacy JSON schema should initialize");
-        let legacy_json = r#"{"items":[{"data_list":[{"type":"public.utf8-plain-text","data":[108,101,103,97,99,121]},{"type":"org.nspasteboard.source","data":[99,111,109,46,101,120,97,109,112,108,101,46,97,112,112]},{"type":"com.apple.is-remote-clipboard","data":[]}]}]}"#;
-        connection
-            .execute(
-                "INSERT INTO clipboard_events
-                 (event_data, data_type, display, sort_order, timestamp)
-                 VALUES (?1, 'text', 'legacy', 7, '2024-03-09T16:00:00Z')",
-                [legacy_json],
-            )
-            .expect("legacy JSON row should insert");
-        drop(connection);
+    fn plaintext_history_is_encrypted_in_place_and_needs_its_key_to_reopen() {
+        use std::os::unix::fs::PermissionsExt;
 
-        let db = Database::open_path(&path).expect("legacy JSON database should migrate");
+        let plaintext = b"synthetic plaintext payload";
+        let path = temp_database_path("payload_encryption");
+        create_version_one_database(
+            &path,
+            &event(vec![data("public.utf8-plain-text", plaintext)]),
+        );
+
+        let db = Database::open_path(&path).expect("plaintext history should be encrypted");
         let page = db
-            .get_history_page(None, Some(50))
-            .expect("migrated page should load");
+            .get_history_page(None, None)
+            .expect("encrypted history should page");
         assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].display, plaintext);
         assert_eq!(
-            page.items[0].source_bundle_id.as_deref(),
-            Some("com.example.app")
+            db.verify_history_integrity()
+                .expect("migrated history should be checked"),
+            Ok(HistoryIntegrityReport { verified_items: 1 })
         );
-        assert!(page.items[0].is_remote_clipboard);
-        assert_eq!(page.items[0].timestamp, 1_710_000_000_000);
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"synthetic new capture",
+        )]))
+        .expect("new capture should insert");
+        let content_hash = page.items[0].content_hash.clone();
+        drop(db);
+
+        let connection = Connection::open(&path).expect("encrypted database should reopen");
+        let mut statement = connection
+            .prepare(
+                "SELECT event_data, display, summary_display, compact_display
+                 FROM clipboard_events",
+            )
+            .expect("payload query should prepare");
+        let mut rows = statement.query([]).expect("payload rows should load");
+        while let Some(row) = rows.next().expect("payload row should load") {
+            for index in 0..4 {
+                let Some(payload) = row
+                    .get::<_, Option<Vec<u8>>>(index)
+                    .expect("payload should load")
+                else {
+                    continue;
+                };
+                assert!(!payload
+                    .windows(b"synthetic".len())
+                    .any(|window| window == b"synthetic"));
+            }
+        }
+        drop(rows);
+        drop(statement);
+        drop(connection);
+
+        let directory = path.parent().expect("database should have a parent");
+        assert!(!directory.join(PRE_ENCRYPTION_BACKUP_FILE_NAME).exists());
+        let key_path = directory.join(crate::store::encryption::KEY_FILE_NAME);
         assert_eq!(
-            db.table_columns("clipboard_events")
-                .expect("columns should load"),
-            REQUIRED_EVENT_COLUMNS
+            std::fs::metadata(&key_path)
+                .expect("key file should exist")
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
         );
+
+        let reopened = Database::open_path(&path).expect("encrypted history should reopen");
         assert_eq!(
-            schema::user_version(&db.conn).expect("version should load"),
-            CURRENT_SCHEMA_VERSION
+            reopened
+                .get_event_by_content_hash(&content_hash)
+                .expect("encrypted event should load")
+                .expect("encrypted event should exist")
+                .items[0]
+                .data_list[0]
+                .data,
+            plaintext
+        );
+        drop(reopened);
+
+        std::fs::remove_file(&key_path).expect("key file should be removed");
+        assert!(Database::open_path(&path).is_err());
+        assert!(
+            !key_path.exists(),
+            "a lost key must not be silently replaced"
         );
-        drop(db);
         remove_database_files(&path);
     }
 
+    /// Hands out a new key on every call, like a provider that never persists.
+    struct ForgetfulKeyProvider;
+
+    impl KeyProvider for ForgetfulKeyProvider {
+        fn load_key(&self) -> std::result::Result<Option<[u8; 32]>, PayloadKeyError> {
+            Ok(None)
+        }
+
+        fn create_key(&self) -> std::result::Result<[u8; 32], PayloadKeyError> {
+            Ok([9; 32])
+        }
+    }
+
     #[test]
-    fn legacy_sort_order_remains_authoritative_after_cursor_migration() {
-        let path = temp_database_path("migration_sort_order");
-        let connection = Connection::open(&path).expect("legacy database should open");
-        connection
-            .execute_batch(
-                "CREATE TABLE clipboard_events (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    event_data BLOB NOT NULL,
-                    data_type TEXT NOT NULL,
-                    display BLOB NOT NULL,
-                    sort_order INTEGER NOT NULL,
-                    timestamp INTEGER NOT NULL
-                 );",
-            )
-            .expect("legacy ordered schema should initialize");
+    fn unverifiable_encryption_restores_the_plaintext_backup() {
+        let path = temp_database_path("payload_encryption_rollback");
+        create_version_one_database(
+            &path,
+            &event(vec![data("public.utf8-plain-text", b"rollback payload")]),
+        );
 
-        let high_order = event(vec![data(
-            "public.utf8-plain-text",
-            b"synthetic high order",
-        )]);
-        let low_order = event(vec![data("public.utf8-plain-text", b"synthetic low order")]);
-        for (clipboard_event, display, sort_order, timestamp) in [
-            (
-                &high_order,
-                b"synthetic high order".as_slice(),
-                10_i64,
-                1_i64,
-            ),
-            (&low_order, b"synthetic low order".as_slice(), 5_i64, 9_i64),
-        ] {
-            connection
-                .execute(
-                    "INSERT INTO clipboard_events
-                     (event_data, data_type, display, sort_order, timestamp)
-                     VALUES (?1, 'text', ?2, ?3, ?4)",
-                    params![
-                        encode_event_blob(clipboard_event).expect("legacy event should encode"),
-                        display,
-                        sort_order,
-                        timestamp,
-                    ],
-                )
-                .expect("legacy ordered row should insert");
-        }
-        drop(connection);
+        assert!(Database::open_private_database_with(
+            &path,
+            &ForgetfulKeyProvider,
+            &MemoryKeyProvider::default()
+        )
+        .is_err());
 
-        let db = Database::open_path(&path).expect("ordered legacy database should migrate");
-        let page = db
-            .get_history_page(None, Some(50))
-            .expect("migrated history should load");
-        assert_eq!(page.items.len(), 2);
-        assert_eq!(page.items[0].display, b"synthetic high order");
-        assert_eq!(page.items[1].display, b"synthetic low order");
-        assert!(page.items[0].timestamp > page.items[1].timestamp);
+        let directory = path.parent().expect("database should have a parent");
+        assert!(!directory.join(PRE_ENCRYPTION_BACKUP_FILE_NAME).exists());
+        let connection = Connection::open(&path).expect("restored database should reopen");
+        assert_eq!(
+            schema::user_version(&connection).expect("version should load"),
+            1
+        );
+        let event_data: Vec<u8> = connection
+            .query_row("SELECT event_data FROM clipboard_events", [], |row| {
+                row.get(0)
+            })
+            .expect("plaintext row should remain");
+        assert_eq!(
+            decode_event_blob(&event_data)
+                .expect("restored row should decode")
+                .items[0]
+                .data_list[0]
+                .data,
+            b"rollback payload"
+        );
+        drop(connection);
 
+        let db = Database::open_private_database_with(
+            &path,
+            &MemoryKeyProvider::default(),
+            &MemoryKeyProvider::default(),
+        )
+        .expect("a working key provider should encrypt the restored history");
+        assert_eq!(
+            db.get_history_stats()
+                .expect("history stats should load")
+                .total_items,
+            1
+        );
         drop(db);
         remove_database_files(&path);
     }
 
     #[test]
-    fn corrupt_timestamp_extremes_return_stable_results_without_panicking() {
-        assert_eq!(Database::normalize_unix_timestamp(i64::MIN), i64::MIN);
-        assert_eq!(Database::normalize_unix_timestamp(i64::MAX), i64::MAX);
+    fn migrations_snapshot_history_that_a_restore_brings_back() {
+        let path = temp_database_path("database_snapshot_restore");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[12].1);
+        write_fixture_key(&path);
+
+        let db = Database::open_path(&path).expect("version twelve should migrate");
+        let backups = db.list_database_backups().expect("backups should list");
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].schema_version, 12);
+        assert!(!file_contains(
+            &path.with_file_name("backups").join(&backups[0].name),
+            "golden fixture"
+        ));
+        let original_hashes = history_hashes(&db);
+        drop(db);
 
-        let db = in_memory_database();
+        let mut db = Database::open_path(&path).expect("current history should reopen");
+        assert_eq!(
+            db.list_database_backups().expect("backups should list"),
+            backups,
+            "a current schema is not snapshotted again"
+        );
         db.insert_event(&event(vec![data(
             "public.utf8-plain-text",
-            b"synthetic timestamp edge",
+            b"after migration",
         )]))
-        .expect("fixture should insert");
-        db.conn
-            .execute("UPDATE clipboard_events SET timestamp = ?1", [i64::MAX])
-            .expect("fixture timestamp should update");
-
-        let error = db
-            .next_history_timestamp()
-            .expect_err("exhausted timestamp range should be rejected");
-        assert!(matches!(error, rusqlite::Error::InvalidParameterName(_)));
-    }
-
-    #[cfg(unix)]
-    #[test]
-    fn on_disk_open_prepares_private_database_files_and_redacts_path_failures() {
-        use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
-
-        let path = temp_database_path("private_open");
-        let db = Database::open_path(&path).expect("private database should open");
-        assert_eq!(
-            std::fs::symlink_metadata(path.parent().expect("database should have a parent"))
-                .expect("private parent metadata should load")
-                .permissions()
-                .mode()
-                & 0o777,
-            0o700
-        );
+        .expect("new capture should insert");
         assert_eq!(
-            std::fs::symlink_metadata(&path)
-                .expect("database metadata should load")
-                .permissions()
-                .mode()
-                & 0o777,
-            0o600
+            db.restore_database_backup("copy_stack.db")
+                .expect("an unknown name should be reported"),
+            Err(DatabaseBackupNotFound)
         );
+        assert_eq!(history_hashes(&db).len(), 4);
+
+        db.restore_database_backup(&backups[0].name)
+            .expect("restore should succeed")
+            .expect("the listed snapshot should restore");
+        assert_eq!(history_hashes(&db), original_hashes);
+        assert_eq!(search_hashes(&db, "golden").len(), 3);
         assert_eq!(
-            std::fs::symlink_metadata(&path)
-                .expect("database owner should load")
-                .uid(),
-            std::fs::symlink_metadata(path.parent().expect("database should have a parent"))
-                .expect("private parent owner should load")
-                .uid()
+            db.verify_history_integrity()
+                .expect("restored history should be checked"),
+            Ok(HistoryIntegrityReport { verified_items: 3 })
         );
+        let after_restore = db.list_database_backups().expect("backups should list");
+        assert_eq!(after_restore.len(), 2);
+        assert_eq!(after_restore[0].schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(after_restore[1], backups[0]);
+
+        db.restore_database_backup(&after_restore[0].name)
+            .expect("restore should succeed")
+            .expect("the pre-restore snapshot should restore");
+        assert_eq!(history_hashes(&db).len(), 4);
         drop(db);
         remove_database_files(&path);
-
-        let symlink_path = temp_database_path("private_symlink");
-        let target = symlink_path
-            .parent()
-            .expect("symlink fixture should have a parent")
-            .join("target.db");
-        std::fs::write(&target, b"not sqlite").expect("symlink target should be created");
-        symlink(&target, &symlink_path).expect("database symlink should be created");
-        let error = match Database::open_path(&symlink_path) {
-            Ok(_) => panic!("database symlinks must be rejected"),
-            Err(error) => error.to_string(),
-        };
-        assert!(!error.contains(&symlink_path.to_string_lossy().into_owned()));
-        assert!(!error.contains(&target.to_string_lossy().into_owned()));
-        remove_database_files(&symlink_path);
     }
 
     #[test]
-    fn qa_database_override_accepts_only_an_absolute_directory() {
-        let absolute = std::env::temp_dir().join("copy-stack-qa-data");
-        assert_eq!(
-            Database::qa_database_path(&absolute).unwrap(),
-            absolute.join(DB_FILE_NAME)
-        );
-        let error = Database::qa_database_path(Path::new("relative/qa-data"))
-            .expect_err("relative QA directory must be rejected");
-        assert!(error.to_string().contains("must be absolute"));
-        assert!(!error.to_string().contains("relative/qa-data"));
+    fn upgrades_from_before_payload_encryption_keep_their_snapshot_until_deleted() {
+        let path = temp_database_path("plaintext_snapshot");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[7].1);
+
+        let db = Database::open_private_database_with(
+            &path,
+            &MemoryKeyProvider::default(),
+            &MemoryKeyProvider::default(),
+        )
+        .expect("version seven should migrate");
+        let backups = db.list_database_backups().expect("backups should list");
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].schema_version, 7);
+        assert!(backups[0].plaintext);
+        assert_eq!(search_hashes(&db, "golden").len(), 3);
+
+        db.delete_database_backup(&backups[0].name)
+            .expect("the snapshot should be deleted");
+        assert!(db
+            .list_database_backups()
+            .expect("backups should list")
+            .is_empty());
+        drop(db);
+
+        let leftovers = std::fs::read_dir(path.with_file_name("backups"))
+            .expect("backup directory should list")
+            .map(|entry| entry.expect("backup entry should read").path())
+            .collect::<Vec<_>>();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+        remove_database_files(&path);
     }
 
-    #[test]
-    fn protocol_skips_are_absent_from_every_storage_downstream_in_both_modes() {
-        for compact_mode in [false, true] {
-            let mode = if compact_mode { "compact" } else { "full" };
-            let path = temp_database_path(&format!("protocol_downstream_{mode}"));
-            let db = Database::open_path(&path).expect("private database should open");
-            db.set_compact_mode(compact_mode)
-                .expect("history mode should update");
+    /// Writes the key-encryption key that fixtures from version eight on are
+    /// sealed under beside `path`.
+    fn write_fixture_key(path: &Path) {
+        #[cfg(unix)]
+        use std::os::unix::fs::PermissionsExt;
 
-            for (label, marker_type, marker_payload) in PROTOCOL_SKIP_CASES {
-                let clipboard_event = protocol_skipped_event(label, marker_type, marker_payload);
-                let body_hash =
-                    Database::hash_bytes(format!("synthetic skipped {label}").as_bytes());
-                assert!(
-                    Database::prepare_history_event(&clipboard_event, compact_mode)
-                        .expect("protocol assessment should succeed")
-                        .is_none(),
-                    "{label} was prepared in {mode} mode"
-                );
-                assert_eq!(
-                    db.event_content_hash(&clipboard_event)
-                        .expect("event hash lookup should succeed"),
-                    None,
-                    "{label} produced a content hash in {mode} mode"
-                );
-                assert!(
-                    !db.insert_event(&clipboard_event)
-                        .expect("skipped event insertion should succeed"),
-                    "{label} was inserted in {mode} mode"
-                );
-                assert_history_downstreams_are_empty(
-                    &db,
-                    &body_hash,
-                    &format!("{label} in {mode} mode"),
-                );
-            }
+        let key_path = path.with_file_name(crate::store::encryption::KEY_FILE_NAME);
+        std::fs::write(&key_path, [0x42; 32]).expect("fixture key should be written");
+        #[cfg(unix)]
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o600))
+            .expect("fixture key should be private");
+    }
 
-            assert_database_mirror_is_empty(&path, &format!("protocol-{mode}"));
-            drop(db);
-            remove_database_files(&path);
+    fn file_contains(path: &Path, text: &str) -> bool {
+        std::fs::read(path)
+            .expect("file should read")
+            .windows(text.len())
+            .any(|window| window == text.as_bytes())
+    }
+
+    fn history_hashes(db: &Database) -> Vec<String> {
+        db.get_history_page(None, None)
+            .expect("history should page")
+            .items
+            .into_iter()
+            .map(|item| item.content_hash)
+            .collect()
+    }
+
+    #[test]
+    fn history_chain_verifies_across_every_mutation() {
+        let db = in_memory_database();
+        let verified_items = |db: &Database| {
+            db.verify_history_integrity()
+                .expect("history should be checked")
+                .expect("history should verify")
+                .verified_items
+        };
+        assert_eq!(verified_items(&db), 0);
+
+        for index in 0..5 {
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                format!("chained {index}").as_bytes(),
+            )]))
+            .expect("event should insert");
+        }
+        assert_eq!(verified_items(&db), 5);
+
+        let hashes = history_hashes(&db);
+        db.delete_event(&hashes[2])
+            .expect("middle row should delete");
+        db.move_event_to_top(&hashes[4])
+            .expect("oldest row should move");
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"chained 1")]))
+            .expect("duplicate should update");
+        assert_eq!(verified_items(&db), 4);
+
+        db.set_max_items(2).expect("max items should save");
+        db.cleanup_old_events().expect("retention should run");
+        assert_eq!(verified_items(&db), 2);
+        for content_hash in history_hashes(&db) {
+            assert!(db
+                .get_restore_seed(&content_hash)
+                .expect("restore seed lookup should work")
+                .expect("stored history should verify")
+                .is_some());
         }
+
+        db.clear_all_events().expect("history should clear");
+        assert_eq!(verified_items(&db), 0);
     }
 
     #[test]
-    fn protocol_metadata_is_hash_independent_and_round_trips_in_both_modes() {
-        for compact_mode in [false, true] {
+    fn tampered_history_fails_verification_and_is_never_restored() {
+        let tampered = |tamper: &dyn Fn(&Database, &[String]), victim: usize| {
             let db = in_memory_database();
-            db.set_compact_mode(compact_mode)
-                .expect("history mode should update");
-            let plain = event(vec![data(
-                "public.utf8-plain-text",
-                b"synthetic protocol identity",
-            )]);
-            let with_metadata = event(vec![
-                data("public.utf8-plain-text", b"synthetic protocol identity"),
-                data(SOURCE_TYPE, b"com.example.synthetic"),
-                data(REMOTE_CLIPBOARD_TYPE, b"payload does not affect presence"),
-            ]);
-            let plain_hash = Database::prepare_history_event(&plain, compact_mode)
-                .expect("plain event should prepare")
-                .expect("plain event should be recordable")
-                .content_hash()
-                .to_string();
-            let metadata_hash = Database::prepare_history_event(&with_metadata, compact_mode)
-                .expect("metadata event should prepare")
-                .expect("metadata event should be recordable")
-                .content_hash()
-                .to_string();
+            for index in 0..3 {
+                db.insert_event(&event(vec![data(
+                    "public.utf8-plain-text",
+                    format!("audited {index}").as_bytes(),
+                )]))
+                .expect("event should insert");
+            }
+            let hashes = history_hashes(&db);
+            tamper(&db, &hashes);
             assert_eq!(
-                plain_hash, metadata_hash,
-                "protocol metadata changed the content hash"
+                db.verify_history_integrity()
+                    .expect("history should be checked"),
+                Err(IntegrityViolation)
             );
-
-            assert!(db.insert_event(&plain).expect("plain event should insert"));
-            assert!(db
-                .insert_event(&with_metadata)
-                .expect("metadata event should update the same row"));
-            let page = db
-                .get_history_page(None, Some(50))
-                .expect("history should load");
-            assert_eq!(page.items.len(), 1);
-            assert_eq!(page.items[0].content_hash, metadata_hash);
             assert_eq!(
-                page.items[0].source_bundle_id.as_deref(),
-                Some("com.example.synthetic")
+                db.get_restore_seed(&hashes[victim])
+                    .expect("restore seed lookup should work"),
+                Err(IntegrityViolation)
             );
-            assert!(page.items[0].is_remote_clipboard);
-
-            let seed = db
-                .get_restore_seed(&metadata_hash)
-                .expect("restore seed lookup should succeed")
-                .expect("restore seed should exist");
             assert_eq!(
-                seed.source_bundle_id.as_deref(),
-                Some("com.example.synthetic")
+                db.get_history_detail_seed(&hashes[victim])
+                    .expect("detail seed lookup should work"),
+                Err(IntegrityViolation)
             );
-            assert!(seed.is_remote_clipboard);
-            let restored = prepare_event_for_restore(
-                seed.into_event()
-                    .expect("restore event should decode")
-                    .expect("restore event should exist"),
-                Some("com.example.synthetic"),
-                true,
-            )
-            .expect("restore metadata should canonicalize");
-            let source_count = restored
-                .items
-                .iter()
-                .flat_map(|item| item.data_list.iter())
-                .filter(|data| data.r#type == SOURCE_TYPE)
-                .count();
-            let remote_count = restored
-                .items
-                .iter()
-                .flat_map(|item| item.data_list.iter())
-                .filter(|data| data.r#type == REMOTE_CLIPBOARD_TYPE)
-                .count();
-            assert_eq!(source_count, 1);
-            assert_eq!(remote_count, 1);
-        }
-    }
+        };
 
-    #[test]
-    fn legacy_migration_drops_every_protocol_skipped_marker() {
-        let path = temp_database_path("protocol_migration");
-        let first_event = protocol_skipped_event(
-            PROTOCOL_SKIP_CASES[0].0,
-            PROTOCOL_SKIP_CASES[0].1,
-            PROTOCOL_SKIP_CASES[0].2,
+        // An edited timestamp or source breaks the row's own MAC.
+        tampered(
+            &|db, hashes| {
+                db.conn
+                    .execute(
+                        "UPDATE clipboard_events SET timestamp = timestamp - 1
+                         WHERE content_hash = ?1",
+                        [&hashes[1]],
+                    )
+                    .expect("timestamp should be edited");
+            },
+            1,
         );
-        create_version_one_database(&path, &first_event);
-        let connection = Connection::open(&path).expect("legacy database should reopen");
-        for (index, (label, marker_type, marker_payload)) in
-            PROTOCOL_SKIP_CASES.iter().enumerate().skip(1)
-        {
-            let clipboard_event = protocol_skipped_event(label, marker_type, marker_payload);
-            let event_blob =
-                encode_event_blob(&clipboard_event).expect("legacy protocol event should encode");
+        tampered(
+            &|db, hashes| {
+                db.conn
+                    .execute(
+                        "UPDATE clipboard_events SET source_bundle_id = 'com.example.other'
+                         WHERE content_hash = ?1",
+                        [&hashes[0]],
+                    )
+                    .expect("source should be edited");
+            },
+            0,
+        );
+        // A sealed payload moved from another row no longer opens.
+        tampered(
+            &|db, hashes| {
+                db.conn
+                    .execute(
+                        "UPDATE clipboard_events
+                         SET event_data = (
+                             SELECT event_data FROM clipboard_events WHERE content_hash = ?1
+                         )
+                         WHERE content_hash = ?2",
+                        [&hashes[1], &hashes[2]],
+                    )
+                    .expect("payload should be swapped");
+            },
+            2,
+        );
+        // A removed row breaks the link of the row after it, and a removed
+        // newest row breaks the head.
+        tampered(
+            &|db, hashes| {
+                db.conn
+                    .execute(
+                        "DELETE FROM clipboard_events WHERE content_hash = ?1",
+                        [&hashes[1]],
+                    )
+                    .expect("row should be removed");
+            },
+            0,
+        );
+        tampered(
+            &|db, hashes| {
+                db.conn
+                    .execute(
+                        "DELETE FROM clipboard_events WHERE content_hash = ?1",
+                        [&hashes[0]],
+                    )
+                    .expect("row should be removed");
+            },
+            1,
+        );
+    }
+
+    fn text_event(text: &str) -> Event {
+        event(vec![data("public.utf8-plain-text", text.as_bytes())])
+    }
+
+    #[test]
+    fn history_check_quarantines_rows_that_no_longer_open_decode_or_match() {
+        let db = in_memory_database();
+        for index in 0..4 {
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                format!("checked {index}").as_bytes(),
+            )]))
+            .expect("event should insert");
+        }
+        let hashes = history_hashes(&db);
+        db.tag_event(&hashes[2], "suspect")
+            .expect("tag should apply")
+            .expect("tag should be accepted");
+        let reseal = |content_hash: &str, event_data: &[u8]| {
+            let sealed = db
+                .cipher
+                .seal(content_hash, PayloadColumn::EventData, event_data)
+                .expect("payload should seal");
+            db.conn
+                .execute(
+                    "UPDATE clipboard_events SET event_data = ?1 WHERE content_hash = ?2",
+                    params![sealed, content_hash],
+                )
+                .expect("payload should be replaced");
+        };
+        db.conn
+            .execute(
+                "UPDATE clipboard_events SET display = x'00' WHERE content_hash = ?1",
+                [&hashes[0]],
+            )
+            .expect("display should be corrupted");
+        reseal(&hashes[1], b"not an event blob");
+        reseal(
+            &hashes[2],
+            &encode_event_blob(&event(vec![data("public.utf8-plain-text", b"other")]))
+                .expect("event should encode"),
+        );
+
+        assert_eq!(
+            db.check_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryCheckReport {
+                checked_items: 4,
+                quarantined_items: 3,
+                undecryptable_items: 1,
+                undecodable_items: 1,
+                mismatched_items: 1,
+            })
+        );
+        assert_eq!(history_hashes(&db), vec![hashes[3].clone()]);
+        let quarantined = db
+            .conn
+            .prepare("SELECT content_hash, reason FROM quarantined_events ORDER BY timestamp DESC")
+            .expect("quarantine should prepare")
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .expect("quarantine should query")
+            .collect::<Result<Vec<_>>>()
+            .expect("quarantine should load");
+        assert_eq!(
+            quarantined,
+            vec![
+                (hashes[0].clone(), "undecryptable".to_string()),
+                (hashes[1].clone(), "undecodable".to_string()),
+                (hashes[2].clone(), "content_hash_mismatch".to_string()),
+            ]
+        );
+        assert!(db
+            .list_tags()
+            .expect("tags should load")
+            .iter()
+            .all(|tag| tag.item_count == 0));
+        Database::validate_search_index_in(&db.conn).expect("index should match rows");
+        assert_eq!(
+            db.verify_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryIntegrityReport { verified_items: 1 })
+        );
+        assert_eq!(
+            db.check_history_integrity()
+                .expect("history should be rechecked"),
+            Ok(HistoryCheckReport {
+                checked_items: 1,
+                ..HistoryCheckReport::default()
+            })
+        );
+
+        let quarantined = |db: &Database| {
+            db.conn
+                .query_row("SELECT COUNT(*) FROM quarantined_events", [], |row| {
+                    row.get::<_, u64>(0)
+                })
+                .expect("quarantine should count")
+        };
+        let before_clear = quarantined(&db);
+        assert!(before_clear > 0);
+        db.clear_all_events().expect("history should clear");
+        assert!(history_hashes(&db).is_empty());
+        assert_eq!(quarantined(&db), before_clear);
+    }
+
+    #[test]
+    fn history_backups_merge_by_content_hash_without_duplicating_rows() {
+        let source = in_memory_database();
+        for text in ["backed up a", "backed up b"] {
+            source
+                .insert_event(&text_event(text))
+                .expect("event should insert");
+        }
+        let source_hashes = history_hashes(&source);
+        source
+            .pin_event(&source_hashes[1])
+            .expect("oldest row should pin");
+        source.set_max_items(42).expect("max items should save");
+        let backup = source
+            .history_backup()
+            .expect("history should export")
+            .expect("history should verify");
+        assert_eq!(backup.rows.len(), 2);
+        assert_eq!(backup.rows[0].content_hash, source_hashes[1]);
+
+        let target = in_memory_database();
+        for text in ["backed up b", "local only"] {
+            target
+                .insert_event(&text_event(text))
+                .expect("event should insert");
+        }
+        let report = target
+            .import_history_backup(&backup)
+            .expect("import should run")
+            .expect("backup should be accepted");
+        assert_eq!(
+            report,
+            HistoryImportReport {
+                imported_items: 1,
+                merged_items: 1,
+                skipped_items: 0,
+                denied_items: 0,
+                rejected_items: 0,
+            }
+        );
+        assert_eq!(target.get_max_items().expect("max items should load"), 42);
+        assert_eq!(history_hashes(&target).len(), 3);
+        let imported = target
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items
+            .into_iter()
+            .find(|item| item.content_hash == source_hashes[1])
+            .expect("imported row should be listed");
+        assert!(imported.pinned);
+
+        let again = target
+            .import_history_backup(&backup)
+            .expect("import should run")
+            .expect("backup should be accepted");
+        assert_eq!((again.imported_items, again.merged_items), (0, 2));
+        assert_eq!(history_hashes(&target).len(), 3);
+        assert_eq!(
+            target
+                .verify_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryIntegrityReport { verified_items: 3 })
+        );
+    }
+
+    #[test]
+    fn history_backup_rows_are_revalidated_before_anything_is_imported() {
+        let source = in_memory_database();
+        source
+            .insert_event(&text_event("revalidated"))
+            .expect("event should insert");
+        let backup = source
+            .history_backup()
+            .expect("history should export")
+            .expect("history should verify");
+
+        let target = in_memory_database();
+        let rejected = |edit: &dyn Fn(&mut HistoryBackupRow)| {
+            let mut edited = backup.clone();
+            edit(&mut edited.rows[0]);
+            edited
+                .settings
+                .push(("max_items".to_string(), "7".to_string()));
+            assert_eq!(
+                target
+                    .import_history_backup(&edited)
+                    .expect("import should run"),
+                Err(InvalidBackup)
+            );
+            assert!(history_hashes(&target).is_empty());
+            assert_eq!(
+                target.get_max_items().expect("max items should load"),
+                settings::DEFAULT_MAX_ITEMS
+            );
+        };
+        rejected(&|row| row.event_data.truncate(3));
+        rejected(&|row| row.content_hash = "0".repeat(64));
+
+        let mut concealed = backup.clone();
+        let (label, marker_type, marker_payload) = PROTOCOL_SKIP_CASES[5];
+        concealed.rows[0].event_data =
+            encode_event_blob(&protocol_skipped_event(label, marker_type, marker_payload))
+                .expect("event should encode");
+        assert_eq!(
+            target
+                .import_history_backup(&concealed)
+                .expect("import should run")
+                .expect("backup should be accepted")
+                .skipped_items,
+            1
+        );
+        assert!(history_hashes(&target).is_empty());
+
+        source
+            .conn
+            .execute("UPDATE clipboard_events SET timestamp = timestamp + 1", [])
+            .expect("timestamp should be edited");
+        assert_eq!(
+            source.history_backup().expect("history should be checked"),
+            Err(IntegrityViolation)
+        );
+    }
+
+    #[test]
+    fn imported_entries_keep_their_timestamps_and_sources_and_merge_by_content_hash() {
+        let db = in_memory_database();
+        db.insert_event(&text_event("already here"))
+            .expect("event should insert");
+        let local = db
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items
+            .remove(0);
+        let row = |text: &str, timestamp: i64| {
+            let event = text_event(text);
+            let content_hash = Database::prepare_history_event(&event, false)
+                .expect("event should prepare")
+                .expect("event should be recorded")
+                .content_hash()
+                .to_string();
+            ImportedHistoryRow {
+                event,
+                timestamp,
+                source_bundle_id: Some("com.example.notes".to_string()),
+                is_remote_clipboard: true,
+                content_hash: Some(content_hash),
+            }
+        };
+        let mut mismatched = row("mismatched", 30);
+        mismatched.content_hash = Some("0".repeat(64));
+        let import = HistoryImport {
+            rows: vec![
+                row("imported", 20),
+                row("already here", 10),
+                mismatched,
+                ImportedHistoryRow {
+                    content_hash: None,
+                    ..row("unhashed", 40)
+                },
+            ],
+            rejected_items: 2,
+        };
+
+        assert_eq!(
+            db.import_history(&import).expect("import should run"),
+            HistoryImportReport {
+                imported_items: 2,
+                merged_items: 1,
+                skipped_items: 0,
+                denied_items: 0,
+                rejected_items: 3,
+            }
+        );
+        let items = db
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items;
+        assert_eq!(items.len(), 3);
+        let imported = items
+            .iter()
+            .find(|item| item.display == b"imported")
+            .expect("imported row should be listed");
+        assert_eq!(imported.timestamp, 20);
+        assert_eq!((imported.first_seen_at, imported.last_seen_at), (20, 20));
+        assert_eq!(
+            imported.source_bundle_id.as_deref(),
+            Some("com.example.notes")
+        );
+        assert!(imported.is_remote_clipboard);
+        let merged = items
+            .iter()
+            .find(|item| item.content_hash == local.content_hash)
+            .expect("local row should stay listed");
+        assert_eq!(merged.first_seen_at, 10);
+        assert_eq!(merged.last_seen_at, local.last_seen_at);
+        assert_eq!(merged.copy_count, local.copy_count);
+        assert_eq!(merged.source_bundle_id, None);
+
+        let again = db.import_history(&import).expect("import should run");
+        assert_eq!((again.imported_items, again.merged_items), (0, 3));
+        assert_eq!(
+            db.verify_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryIntegrityReport { verified_items: 3 })
+        );
+    }
+
+    #[test]
+    fn imports_apply_the_source_and_secret_policies() {
+        // Assembled at runtime so the source holds no scanner-flagged literal.
+        let token = format!("ghp_{}", ('a'..='z').chain('0'..='9').collect::<String>());
+        let jwt = [
+            "eyJhbGciOiJIUzI1NiJ9",
+            "eyJzdWIiOiJzeW50aGV0aWMifQ",
+            "kP3xQ9mZ2vR7tW1yB5nC8dF4gH6jL0sA",
+        ]
+        .join(".");
+        let db = in_memory_database();
+        assert_eq!(
+            db.set_source_app_rule("com.example.vault", Some(SourceAppRule::Deny)),
+            Ok(true)
+        );
+        let row = |text: &str, source: &str| {
+            let event = text_event(text);
+            let content_hash = Database::prepare_history_event(&event, false)
+                .expect("event should prepare")
+                .expect("event should be recorded")
+                .content_hash()
+                .to_string();
+            ImportedHistoryRow {
+                event,
+                timestamp: 10,
+                source_bundle_id: Some(source.to_string()),
+                is_remote_clipboard: false,
+                content_hash: Some(content_hash),
+            }
+        };
+        let import = HistoryImport {
+            rows: vec![
+                row("vault note", "com.example.vault"),
+                row(&token, "com.example.notes"),
+                row("card 4111 1111 1111 1111", "com.example.notes"),
+                row(&jwt, "com.example.notes"),
+            ],
+            rejected_items: 0,
+        };
+
+        assert_eq!(
+            db.import_history(&import).expect("import should run"),
+            HistoryImportReport {
+                imported_items: 2,
+                merged_items: 0,
+                skipped_items: 0,
+                denied_items: 2,
+                rejected_items: 0,
+            }
+        );
+        let displays = db
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items
+            .into_iter()
+            .map(|item| item.display)
+            .collect::<Vec<_>>();
+        assert_eq!(displays.len(), 2);
+        assert!(displays.contains(&"card ••••••••1111".as_bytes().to_vec()));
+        assert!(db.next_expiry_at().expect("expiry should load").is_some());
+
+        let source = in_memory_database();
+        for kind in [SecretKind::GithubToken, SecretKind::PaymentCard] {
+            source
+                .set_secret_policy(kind, SecretAction::Expire)
+                .expect("secret policy should persist");
+        }
+        for event in [
+            text_event(&token),
+            text_event("card 4111 1111 1111 1111"),
+            event(vec![
+                data("public.utf8-plain-text", b"vault note"),
+                data(SOURCE_TYPE, b"com.example.vault"),
+            ]),
+        ] {
+            assert_eq!(source.insert_event(&event), Ok(true));
+        }
+        let backup = source
+            .history_backup()
+            .expect("history should export")
+            .expect("history should verify");
+
+        // The archive's own settings would let all three rows in, but they
+        // only take effect after its rows were screened.
+        let target = in_memory_database();
+        assert_eq!(
+            target.set_source_app_rule("com.example.vault", Some(SourceAppRule::Deny)),
+            Ok(true)
+        );
+        assert_eq!(
+            target
+                .import_history_backup(&backup)
+                .expect("import should run")
+                .expect("backup should be accepted"),
+            HistoryImportReport {
+                imported_items: 1,
+                merged_items: 0,
+                skipped_items: 0,
+                denied_items: 2,
+                rejected_items: 0,
+            }
+        );
+        let items = target
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].display, "card ••••••••1111".as_bytes());
+        assert_eq!(
+            target.verify_history_integrity(),
+            Ok(Ok(HistoryIntegrityReport { verified_items: 1 }))
+        );
+        let settings = target.get_settings().expect("settings should load");
+        assert!(settings.secret_policies.contains(&SecretPolicySetting {
+            kind: SecretKind::GithubToken,
+            action: SecretAction::Expire,
+        }));
+        assert!(settings.denied_source_apps.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clipboard_manager_history_imports_through_the_capture_policy() {
+        use crate::history_import::{read_clipboard_manager_history, ClipboardManager};
+
+        let path = temp_database_path("maccy_import");
+        let store_path = path.with_file_name("Storage.sqlite");
+        Connection::open(&store_path)
+            .expect("Maccy store should open")
+            .execute_batch(include_str!("../history_import/fixtures/maccy.sql"))
+            .expect("Maccy store should load");
+        let import = read_clipboard_manager_history(ClipboardManager::Maccy, &store_path)
+            .expect("Maccy store should read");
+        let db = in_memory_database();
+
+        assert_eq!(
+            db.import_history(&import).expect("import should run"),
+            HistoryImportReport {
+                imported_items: 2,
+                merged_items: 0,
+                skipped_items: 1,
+                denied_items: 0,
+                rejected_items: 2,
+            }
+        );
+        let items = db
+            .get_history_page(None, None)
+            .expect("history should page")
+            .items;
+        assert_eq!(items.len(), 2);
+        let files = &items[0];
+        assert_eq!(files.data_type, "files");
+        assert_eq!(files.timestamp, 1_678_307_300_000);
+        assert_eq!(files.source_bundle_id.as_deref(), Some("com.apple.finder"));
+        let text = &items[1];
+        assert_eq!(text.data_type, "html");
+        assert_eq!(text.timestamp, 1_678_307_200_500);
+        assert_eq!(text.source_bundle_id.as_deref(), Some("com.apple.Safari"));
+
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn legacy_json_schema_migrates_metadata_and_versions_transactionally() {
+        let path = temp_database_path("migration_legacy_json");
+        let connection = Connection::open(&path).expect("legacy database should open");
+        connection
+            .execute_batch(
+                "CREATE TABLE clipboard_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    event_data TEXT NOT NULL,
+                    data_type TEXT NOT NULL,
+                    display TEXT NOT NULL,
+                    sort_order INTEGER NOT NULL,
+                    timestamp TEXT NOT NULL
+                 );",
+            )
+            .expect("legacy JSON schema should initialize");
+        let legacy_json = r#"{"items":[{"data_list":[{"type":"public.utf8-plain-text","data":[108,101,103,97,99,121]},{"type":"org.nspasteboard.source","data":[99,111,109,46,101,120,97,109,112,108,101,46,97,112,112]},{"type":"com.apple.is-remote-clipboard","data":[]}]}]}"#;
+        connection
+            .execute(
+                "INSERT INTO clipboard_events
+                 (event_data, data_type, display, sort_order, timestamp)
+                 VALUES (?1, 'text', 'legacy', 7, '2024-03-09T16:00:00Z')",
+                [legacy_json],
+            )
+            .expect("legacy JSON row should insert");
+        drop(connection);
+
+        let db = Database::open_path(&path).expect("legacy JSON database should migrate");
+        let page = db
+            .get_history_page(None, Some(50))
+            .expect("migrated page should load");
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(
+            page.items[0].source_bundle_id.as_deref(),
+            Some("com.example.app")
+        );
+        assert!(page.items[0].is_remote_clipboard);
+        assert_eq!(page.items[0].timestamp, 1_710_000_000_000);
+        assert_eq!(page.items[0].first_seen_at, 1_710_000_000_000);
+        assert_eq!(page.items[0].last_seen_at, 1_710_000_000_000);
+        assert_eq!(page.items[0].copy_count, 1);
+        assert_eq!(
+            db.table_columns("clipboard_events")
+                .expect("columns should load"),
+            REQUIRED_EVENT_COLUMNS
+        );
+        assert_eq!(
+            schema::user_version(&db.conn).expect("version should load"),
+            CURRENT_SCHEMA_VERSION
+        );
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn legacy_sort_order_remains_authoritative_after_cursor_migration() {
+        let path = temp_database_path("migration_sort_order");
+        let connection = Connection::open(&path).expect("legacy database should open");
+        connection
+            .execute_batch(
+                "CREATE TABLE clipboard_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    event_data BLOB NOT NULL,
+                    data_type TEXT NOT NULL,
+                    display BLOB NOT NULL,
+                    sort_order INTEGER NOT NULL,
+                    timestamp INTEGER NOT NULL
+                 );",
+            )
+            .expect("legacy ordered schema should initialize");
+
+        let high_order = event(vec![data(
+            "public.utf8-plain-text",
+            b"synthetic high order",
+        )]);
+        let low_order = event(vec![data("public.utf8-plain-text", b"synthetic low order")]);
+        for (clipboard_event, display, sort_order, timestamp) in [
+            (
+                &high_order,
+                b"synthetic high order".as_slice(),
+                10_i64,
+                1_i64,
+            ),
+            (&low_order, b"synthetic low order".as_slice(), 5_i64, 9_i64),
+        ] {
             connection
                 .execute(
                     "INSERT INTO clipboard_events
-                     (content_hash, event_data, data_type, display, timestamp)
-                     VALUES (?1, ?2, 'text', ?3, ?4)",
+                     (event_data, data_type, display, sort_order, timestamp)
+                     VALUES (?1, 'text', ?2, ?3, ?4)",
                     params![
-                        format!("{index:064x}"),
-                        event_blob,
-                        format!("synthetic skipped {label}").into_bytes(),
-                        index as i64 + 1,
+                        encode_event_blob(clipboard_event).expect("legacy event should encode"),
+                        display,
+                        sort_order,
+                        timestamp,
                     ],
                 )
-                .expect("legacy protocol row should insert");
+                .expect("legacy ordered row should insert");
         }
         drop(connection);
 
-        let db = Database::open_path(&path).expect("legacy database should migrate");
-        let row_count: u64 = db
-            .conn
-            .query_row("SELECT COUNT(*) FROM clipboard_events", [], |row| {
-                row.get(0)
+        let db = Database::open_path(&path).expect("ordered legacy database should migrate");
+        let page = db
+            .get_history_page(None, Some(50))
+            .expect("migrated history should load");
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].display, b"synthetic high order");
+        assert_eq!(page.items[1].display, b"synthetic low order");
+        assert!(page.items[0].timestamp > page.items[1].timestamp);
+
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn corrupt_timestamp_extremes_return_stable_results_without_panicking() {
+        assert_eq!(Database::normalize_unix_timestamp(i64::MIN), i64::MIN);
+        assert_eq!(Database::normalize_unix_timestamp(i64::MAX), i64::MAX);
+
+        let db = in_memory_database();
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"synthetic timestamp edge",
+        )]))
+        .expect("fixture should insert");
+        db.conn
+            .execute("UPDATE clipboard_events SET timestamp = ?1", [i64::MAX])
+            .expect("fixture timestamp should update");
+
+        let error = db
+            .next_history_timestamp()
+            .expect_err("exhausted timestamp range should be rejected");
+        assert!(matches!(error, rusqlite::Error::InvalidParameterName(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn on_disk_open_prepares_private_database_files_and_redacts_path_failures() {
+        use std::os::unix::fs::{symlink, MetadataExt, PermissionsExt};
+
+        let path = temp_database_path("private_open");
+        let db = Database::open_path(&path).expect("private database should open");
+        assert_eq!(
+            std::fs::symlink_metadata(path.parent().expect("database should have a parent"))
+                .expect("private parent metadata should load")
+                .permissions()
+                .mode()
+                & 0o777,
+            0o700
+        );
+        assert_eq!(
+            std::fs::symlink_metadata(&path)
+                .expect("database metadata should load")
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+        assert_eq!(
+            std::fs::symlink_metadata(&path)
+                .expect("database owner should load")
+                .uid(),
+            std::fs::symlink_metadata(path.parent().expect("database should have a parent"))
+                .expect("private parent owner should load")
+                .uid()
+        );
+        drop(db);
+        remove_database_files(&path);
+
+        let symlink_path = temp_database_path("private_symlink");
+        let target = symlink_path
+            .parent()
+            .expect("symlink fixture should have a parent")
+            .join("target.db");
+        std::fs::write(&target, b"not sqlite").expect("symlink target should be created");
+        symlink(&target, &symlink_path).expect("database symlink should be created");
+        let error = match Database::open_path(&symlink_path) {
+            Ok(_) => panic!("database symlinks must be rejected"),
+            Err(error) => error.to_string(),
+        };
+        assert!(!error.contains(&symlink_path.to_string_lossy().into_owned()));
+        assert!(!error.contains(&target.to_string_lossy().into_owned()));
+        remove_database_files(&symlink_path);
+    }
+
+    #[test]
+    fn qa_database_override_accepts_only_an_absolute_directory() {
+        let absolute = std::env::temp_dir().join("copy-stack-qa-data");
+        assert_eq!(
+            Database::qa_database_path(&absolute).unwrap(),
+            absolute.join(DB_FILE_NAME)
+        );
+        let error = Database::qa_database_path(Path::new("relative/qa-data"))
+            .expect_err("relative QA directory must be rejected");
+        assert!(error.to_string().contains("must be absolute"));
+        assert!(!error.to_string().contains("relative/qa-data"));
+    }
+
+    #[test]
+    fn protocol_skips_are_absent_from_every_storage_downstream_in_both_modes() {
+        for compact_mode in [false, true] {
+            let mode = if compact_mode { "compact" } else { "full" };
+            let path = temp_database_path(&format!("protocol_downstream_{mode}"));
+            let db = Database::open_path(&path).expect("private database should open");
+            db.set_compact_mode(compact_mode)
+                .expect("history mode should update");
+
+            for (label, marker_type, marker_payload) in PROTOCOL_SKIP_CASES {
+                let clipboard_event = protocol_skipped_event(label, marker_type, marker_payload);
+                let body_hash =
+                    Database::hash_bytes(format!("synthetic skipped {label}").as_bytes());
+                assert!(
+                    Database::prepare_history_event(&clipboard_event, compact_mode)
+                        .expect("protocol assessment should succeed")
+                        .is_none(),
+                    "{label} was prepared in {mode} mode"
+                );
+                assert_eq!(
+                    db.event_content_hash(&clipboard_event)
+                        .expect("event hash lookup should succeed"),
+                    None,
+                    "{label} produced a content hash in {mode} mode"
+                );
+                assert!(
+                    !db.insert_event(&clipboard_event)
+                        .expect("skipped event insertion should succeed"),
+                    "{label} was inserted in {mode} mode"
+                );
+                assert_history_downstreams_are_empty(
+                    &db,
+                    &body_hash,
+                    &format!("{label} in {mode} mode"),
+                );
+            }
+
+            assert_database_mirror_is_empty(&db, &path, &format!("protocol-{mode}"));
+            drop(db);
+            remove_database_files(&path);
+        }
+    }
+
+    #[test]
+    fn protocol_metadata_is_hash_independent_and_round_trips_in_both_modes() {
+        for compact_mode in [false, true] {
+            let db = in_memory_database();
+            db.set_compact_mode(compact_mode)
+                .expect("history mode should update");
+            let plain = event(vec![data(
+                "public.utf8-plain-text",
+                b"synthetic protocol identity",
+            )]);
+            let with_metadata = event(vec![
+                data("public.utf8-plain-text", b"synthetic protocol identity"),
+                data(SOURCE_TYPE, b"com.example.synthetic"),
+                data(REMOTE_CLIPBOARD_TYPE, b"payload does not affect presence"),
+            ]);
+            let plain_hash = Database::prepare_history_event(&plain, compact_mode)
+                .expect("plain event should prepare")
+                .expect("plain event should be recordable")
+                .content_hash()
+                .to_string();
+            let metadata_hash = Database::prepare_history_event(&with_metadata, compact_mode)
+                .expect("metadata event should prepare")
+                .expect("metadata event should be recordable")
+                .content_hash()
+                .to_string();
+            assert_eq!(
+                plain_hash, metadata_hash,
+                "protocol metadata changed the content hash"
+            );
+
+            assert!(db.insert_event(&plain).expect("plain event should insert"));
+            assert!(db
+                .insert_event(&with_metadata)
+                .expect("metadata event should update the same row"));
+            let page = db
+                .get_history_page(None, Some(50))
+                .expect("history should load");
+            assert_eq!(page.items.len(), 1);
+            assert_eq!(page.items[0].content_hash, metadata_hash);
+            assert_eq!(
+                page.items[0].source_bundle_id.as_deref(),
+                Some("com.example.synthetic")
+            );
+            assert!(page.items[0].is_remote_clipboard);
+
+            let seed = db
+                .get_restore_seed(&metadata_hash)
+                .expect("restore seed lookup should succeed")
+                .expect("stored history should verify")
+                .expect("restore seed should exist");
+            assert_eq!(
+                seed.source_bundle_id.as_deref(),
+                Some("com.example.synthetic")
+            );
+            assert!(seed.is_remote_clipboard);
+            let restored = prepare_event_for_restore(
+                seed.into_event()
+                    .expect("restore event should decode")
+                    .expect("restore event should exist"),
+                Some("com.example.synthetic"),
+                true,
+            )
+            .expect("restore metadata should canonicalize");
+            let source_count = restored
+                .items
+                .iter()
+                .flat_map(|item| item.data_list.iter())
+                .filter(|data| data.r#type == SOURCE_TYPE)
+                .count();
+            let remote_count = restored
+                .items
+                .iter()
+                .flat_map(|item| item.data_list.iter())
+                .filter(|data| data.r#type == REMOTE_CLIPBOARD_TYPE)
+                .count();
+            assert_eq!(source_count, 1);
+            assert_eq!(remote_count, 1);
+        }
+    }
+
+    #[test]
+    fn legacy_migration_drops_every_protocol_skipped_marker() {
+        let path = temp_database_path("protocol_migration");
+        let first_event = protocol_skipped_event(
+            PROTOCOL_SKIP_CASES[0].0,
+            PROTOCOL_SKIP_CASES[0].1,
+            PROTOCOL_SKIP_CASES[0].2,
+        );
+        create_version_one_database(&path, &first_event);
+        let connection = Connection::open(&path).expect("legacy database should reopen");
+        for (index, (label, marker_type, marker_payload)) in
+            PROTOCOL_SKIP_CASES.iter().enumerate().skip(1)
+        {
+            let clipboard_event = protocol_skipped_event(label, marker_type, marker_payload);
+            let event_blob =
+                encode_event_blob(&clipboard_event).expect("legacy protocol event should encode");
+            connection
+                .execute(
+                    "INSERT INTO clipboard_events
+                     (content_hash, event_data, data_type, display, timestamp)
+                     VALUES (?1, ?2, 'text', ?3, ?4)",
+                    params![
+                        format!("{index:064x}"),
+                        event_blob,
+                        format!("synthetic skipped {label}").into_bytes(),
+                        index as i64 + 1,
+                    ],
+                )
+                .expect("legacy protocol row should insert");
+        }
+        drop(connection);
+
+        let db = Database::open_path(&path).expect("legacy database should migrate");
+        let row_count: u64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM clipboard_events", [], |row| {
+                row.get(0)
+            })
+            .expect("migrated row count should load");
+        assert_eq!(row_count, 0);
+        for compact_mode in [false, true] {
+            db.set_compact_mode(compact_mode)
+                .expect("history mode should update");
+            for (label, _, _) in PROTOCOL_SKIP_CASES {
+                let body_hash =
+                    Database::hash_bytes(format!("synthetic skipped {label}").as_bytes());
+                assert_history_downstreams_are_empty(&db, &body_hash, &format!("migrated {label}"));
+            }
+        }
+        assert_database_mirror_is_empty(&db, &path, "protocol-migration");
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn prepared_compact_events_keep_protocol_metadata_and_reuse_the_effective_hash() {
+        let db = in_memory_database();
+        let clipboard_event = event(vec![
+            data("public.utf8-plain-text", b"compact protocol metadata"),
+            data(SOURCE_TYPE, b"com.example.source"),
+            data(REMOTE_CLIPBOARD_TYPE, b"marker payload is ignored"),
+        ]);
+        let prepared = Database::prepare_history_event(&clipboard_event, true)
+            .expect("event preparation should succeed")
+            .expect("compact text should be accepted");
+        let expected_hash = Database::hash_bytes(b"compact protocol metadata");
+        assert_eq!(prepared.content_hash(), expected_hash);
+        assert!(db
+            .insert_prepared_event(prepared)
+            .expect("prepared event should insert"));
+
+        let page = db
+            .get_history_page_for_mode(None, Some(50), &HistoryLabelFilter::default(), true)
+            .expect("compact history should load");
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(
+            page.items[0].source_bundle_id.as_deref(),
+            Some("com.example.source")
+        );
+        assert!(page.items[0].is_remote_clipboard);
+        assert_eq!(page.items[0].data_type, "text");
+    }
+
+    #[test]
+    fn compact_restore_seed_uses_effective_hash_and_applies_projection_outside_storage() {
+        let db = in_memory_database();
+        let clipboard_event = event(vec![
+            data("public.utf8-plain-text", b"effective restore text"),
+            data("public.rtf", br"{\rtf1 effective restore text}"),
+            data(SOURCE_TYPE, b"com.example.restore"),
+            data(REMOTE_CLIPBOARD_TYPE, b""),
+        ]);
+        db.insert_event(&clipboard_event)
+            .expect("full event should insert");
+        let stored_hash = db
+            .get_history_page(None, Some(1))
+            .expect("history should load")
+            .items[0]
+            .content_hash
+            .clone();
+        let effective_hash = Database::hash_bytes(b"effective restore text");
+        assert_ne!(stored_hash, effective_hash);
+
+        db.set_compact_mode(true)
+            .expect("compact mode should enable");
+        let seed = db
+            .get_restore_seed(&stored_hash)
+            .expect("restore seed lookup should work")
+            .expect("stored history should verify")
+            .expect("restore seed should exist");
+        assert_eq!(seed.content_hash, effective_hash);
+        assert_eq!(
+            seed.source_bundle_id.as_deref(),
+            Some("com.example.restore")
+        );
+        assert!(seed.is_remote_clipboard);
+
+        let restored = seed
+            .into_event()
+            .expect("restore event should decode")
+            .expect("compact projection should exist");
+        assert!(Database::find_data(&restored, "public.rtf").is_none());
+        assert_eq!(
+            Database::find_data(&restored, "public.utf8-plain-text")
+                .expect("plain text should remain")
+                .data,
+            b"effective restore text"
+        );
+        assert!(Database::find_data(&restored, SOURCE_TYPE).is_some());
+        assert!(Database::find_data(&restored, REMOTE_CLIPBOARD_TYPE).is_some());
+    }
+
+    #[test]
+    fn history_summary_paging_is_bounded_and_uses_stable_cursors() {
+        let db = in_memory_database();
+        db.set_max_items(1_000).expect("retention should expand");
+        for index in 0..120 {
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                format!("summary row {index:03}").as_bytes(),
+            )]))
+            .expect("fixture should insert");
+        }
+
+        let first = db
+            .get_history_page(None, None)
+            .expect("default page should load");
+        assert_eq!(first.items.len(), DEFAULT_HISTORY_PAGE_SIZE);
+        assert!(first.has_more);
+        assert!(first.next_cursor.is_some());
+
+        let capped = db
+            .get_history_page(None, Some(usize::MAX))
+            .expect("capped page should load");
+        assert_eq!(capped.items.len(), MAX_HISTORY_PAGE_SIZE);
+
+        let mut hashes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = db
+                .get_history_page(cursor.as_deref(), Some(17))
+                .expect("cursor page should load");
+            hashes.extend(page.items.iter().map(|item| item.content_hash.clone()));
+            if !page.has_more {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        assert_eq!(hashes.len(), 120);
+        assert_eq!(hashes.iter().collect::<HashSet<_>>().len(), 120);
+        assert!(db.get_history_page(Some("malformed"), Some(50)).is_err());
+    }
+
+    #[test]
+    fn history_cursor_tie_breaker_does_not_repeat_or_skip_equal_timestamps() {
+        let db = in_memory_database();
+        for value in ["same timestamp a", "same timestamp b", "same timestamp c"] {
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                value.as_bytes(),
+            )]))
+            .expect("fixture should insert");
+        }
+        db.conn
+            .execute("UPDATE clipboard_events SET timestamp = 42", [])
+            .expect("timestamps should align");
+
+        let mut hashes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = db
+                .get_history_page(cursor.as_deref(), Some(1))
+                .expect("single-row page should load");
+            hashes.extend(page.items.iter().map(|item| item.content_hash.clone()));
+            if !page.has_more {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        let mut sorted = hashes.clone();
+        sorted.sort();
+        assert_eq!(hashes, sorted);
+        assert_eq!(hashes.len(), 3);
+    }
+
+    fn parsed(query: &str) -> HistoryQuery {
+        HistoryQuery::parse(query).expect("query should parse")
+    }
+
+    fn search_hashes(db: &Database, query: &str) -> Vec<String> {
+        db.search_history_page(&parsed(query), None, Some(MAX_HISTORY_PAGE_SIZE))
+            .expect("search should load")
+            .items
+            .into_iter()
+            .map(|item| item.summary.content_hash)
+            .collect()
+    }
+
+    #[test]
+    fn export_seeds_follow_the_search_filter_and_build_readable_entries() {
+        let db = in_memory_database();
+        db.insert_event(&event(vec![
+            data("public.utf8-plain-text", b"/Users/example/Documents"),
+            data("public.file-url", b"file:///Users/example/Documents/"),
+        ]))
+        .expect("folder should insert");
+        db.insert_event(&event(vec![
+            data("public.utf8-plain-text", b"exported note"),
+            data(SOURCE_TYPE, b"com.example.notes"),
+        ]))
+        .expect("text should insert");
+
+        let seeds = db
+            .history_export_seeds(&parsed(""))
+            .expect("seeds should load")
+            .expect("history should verify");
+        assert_eq!(seeds.len(), 2);
+        let folder = Database::build_history_export_entry(seeds[1].clone(), false, false)
+            .expect("entry should build");
+        assert_eq!(folder.data_type, "folder");
+        assert_eq!(folder.text, "Documents");
+        assert_eq!(folder.files[0].item_type, "folder");
+
+        let seeds = db
+            .history_export_seeds(&parsed("note app:com.example.notes"))
+            .expect("seeds should load")
+            .expect("history should verify");
+        assert_eq!(seeds.len(), 1);
+        let text = Database::build_history_export_entry(seeds[0].clone(), false, true)
+            .expect("entry should build");
+        assert_eq!(text.text, "exported note");
+        assert_eq!(text.source_bundle_id.as_deref(), Some("com.example.notes"));
+        assert!(text.files.is_empty() && text.images.is_empty());
+        assert!(db
+            .history_export_seeds(&parsed("type:png"))
+            .expect("seeds should load")
+            .expect("history should verify")
+            .is_empty());
+    }
+
+    #[test]
+    fn search_index_tracks_every_write_path_and_pages_with_history_cursors() {
+        let db = in_memory_database();
+        db.set_max_items(1_000).expect("retention should expand");
+        let plain = event(vec![data(
+            "public.utf8-plain-text",
+            b"docker compose up -d --build",
+        )]);
+        let html = event(vec![data(
+            "public.html",
+            b"<p>Run <b>docker</b>&nbsp;system prune</p>",
+        )]);
+        let rtf = event(vec![data(
+            "public.rtf",
+            b"{\\rtf1\\ansi{\\fonttbl\\f0 Menlo;}\\f0 docker ps -a\\par}",
+        )]);
+        for clipboard_event in [&plain, &html, &rtf] {
+            db.insert_event(clipboard_event)
+                .expect("fixture should insert");
+        }
+        for index in 0..5 {
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                format!("unrelated row {index}").as_bytes(),
+            )]))
+            .expect("filler should insert");
+        }
+        let plain_hash = db
+            .event_content_hash(&plain)
+            .expect("hash should load")
+            .expect("plain text should classify");
+        let html_hash = db
+            .event_content_hash(&html)
+            .expect("hash should load")
+            .expect("HTML should classify");
+
+        let first = db
+            .search_history_page(&parsed("DOCKER"), None, Some(2))
+            .expect("first search page should load");
+        assert_eq!(first.items.len(), 2);
+        assert!(first.has_more);
+        let second = db
+            .search_history_page(&parsed("DOCKER"), first.next_cursor.as_deref(), Some(2))
+            .expect("second search page should load");
+        assert_eq!(second.items.len(), 1);
+        assert!(!second.has_more);
+        let history = db
+            .get_history_page(None, Some(MAX_HISTORY_PAGE_SIZE))
+            .expect("history should load");
+        let expected_order = history
+            .items
+            .iter()
+            .map(|item| item.content_hash.clone())
+            .filter(|hash| {
+                [&plain, &html, &rtf].iter().any(|clipboard_event| {
+                    db.event_content_hash(clipboard_event).unwrap().as_ref() == Some(hash)
+                })
+            })
+            .collect::<Vec<_>>();
+        let searched_order = first
+            .items
+            .iter()
+            .chain(&second.items)
+            .map(|item| item.summary.content_hash.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(searched_order, expected_order);
+        assert_eq!(
+            first.items[0].summary,
+            history.items[history
+                .items
+                .iter()
+                .position(|item| item.content_hash == first.items[0].summary.content_hash)
+                .expect("search hit should be a history row")]
+        );
+
+        let html_hit = db
+            .search_history_page(&parsed("system prune"), None, None)
+            .expect("HTML text should be searchable");
+        assert_eq!(html_hit.items.len(), 1);
+        assert_eq!(html_hit.items[0].summary.content_hash, html_hash);
+        assert!(html_hit.items[0]
+            .snippet
+            .iter()
+            .any(|segment| segment.highlighted && segment.text.eq_ignore_ascii_case("prune")));
+        assert_eq!(search_hashes(&db, "menlo"), Vec::<String>::new());
+        assert_eq!(search_hashes(&db, "docker ps").len(), 3);
+        assert_eq!(search_hashes(&db, "\"docker\" OR NOT").len(), 0);
+        assert!(db
+            .search_history_page(&parsed("do"), None, None)
+            .expect("short query should not fail")
+            .items
+            .is_empty());
+        assert!(db
+            .search_history_page(&parsed("docker"), Some("malformed"), None)
+            .is_err());
+
+        db.delete_event(&plain_hash).expect("delete should succeed");
+        assert!(!search_hashes(&db, "docker").contains(&plain_hash));
+
+        db.set_max_items(3).expect("retention should shrink");
+        db.cleanup_old_events().expect("cleanup should succeed");
+        assert!(search_hashes(&db, "docker").is_empty());
+        assert_eq!(search_hashes(&db, "unrelated").len(), 3);
+        Database::validate_search_index_in(&db.conn).expect("index should match rows");
+
+        db.clear_all_events().expect("clear should succeed");
+        assert!(search_hashes(&db, "unrelated").is_empty());
+        let search_rows: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM clipboard_events_search", [], |row| {
+                row.get(0)
+            })
+            .expect("search rows should count");
+        assert_eq!(search_rows, 0);
+    }
+
+    #[test]
+    fn compact_upserts_move_search_text_to_the_canonical_row() {
+        let db = in_memory_database();
+        let rich = event(vec![
+            data("public.rtf", b"{\\rtf1 kubectl rollout status}"),
+            data("public.utf8-plain-text", b"kubectl rollout status"),
+        ]);
+        db.insert_event(&rich).expect("rich row should insert");
+        db.set_compact_mode(true)
+            .expect("compact mode should enable");
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"kubectl rollout status",
+        )]))
+        .expect("compact row should upsert");
+
+        let hits = search_hashes(&db, "rollout");
+        let compact_hash = db
+            .get_history_page(None, None)
+            .expect("compact page should load")
+            .items[0]
+            .content_hash
+            .clone();
+        assert_eq!(hits, vec![compact_hash]);
+        Database::validate_search_index_in(&db.conn).expect("index should match rows");
+    }
+
+    #[test]
+    fn search_index_is_backfilled_for_older_and_incomplete_databases() {
+        let path = temp_database_path("search_backfill");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[2].1);
+
+        let migrated = Database::open_path(&path).expect("version two should migrate");
+        assert_eq!(
+            schema::user_version(&migrated.conn).expect("version should load"),
+            CURRENT_SCHEMA_VERSION
+        );
+        assert_eq!(search_hashes(&migrated, "alpha").len(), 1);
+        migrated
+            .conn
+            .execute("DELETE FROM clipboard_events_search", [])
+            .expect("index rows should clear");
+        drop(migrated);
+
+        let repaired = Database::open_path(&path).expect("incomplete index should rebuild");
+        assert_eq!(search_hashes(&repaired, "golden").len(), 3);
+        drop(repaired);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn search_text_is_sealed_in_the_database_file() {
+        let path = temp_database_path("search_text_sealed");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[12].1);
+        let key_provider = MemoryKeyProvider::with_key([0x42; 32]);
+        let integrity_key_provider = MemoryKeyProvider::default();
+
+        let db =
+            Database::open_private_database_with(&path, &key_provider, &integrity_key_provider)
+                .expect("version twelve should migrate");
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"vault phrase correct horse battery",
+        )]))
+        .expect("row should insert");
+        assert_eq!(search_hashes(&db, "correct horse").len(), 1);
+        drop(db);
+
+        let mut file = std::fs::read(&path).expect("database file should read");
+        file.extend(std::fs::read(format!("{}-journal", path.display())).unwrap_or_default());
+        for plaintext in ["correct horse", "golden fixture"] {
+            assert!(
+                !file
+                    .windows(plaintext.len())
+                    .any(|window| window == plaintext.as_bytes()),
+                "{plaintext}"
+            );
+        }
+
+        let reopened =
+            Database::open_private_database_with(&path, &key_provider, &integrity_key_provider)
+                .expect("database should reopen");
+        assert_eq!(search_hashes(&reopened, "correct horse").len(), 1);
+        assert_eq!(search_hashes(&reopened, "golden").len(), 3);
+        drop(reopened);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn chains_signed_with_the_derived_key_move_to_the_integrity_key_once_verified() {
+        let path = temp_database_path("integrity_key_adoption");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[13].1);
+        let key_provider = MemoryKeyProvider::with_key([0x42; 32]);
+        let integrity_key_provider = MemoryKeyProvider::default();
+
+        let db =
+            Database::open_private_database_with(&path, &key_provider, &integrity_key_provider)
+                .expect("version thirteen should open");
+        assert_eq!(
+            db.verify_history_integrity()
+                .expect("history should be checked")
+                .expect("the re-signed chain should verify")
+                .verified_items,
+            3
+        );
+        assert!(integrity_key_provider
+            .load_key()
+            .expect("integrity key should load")
+            .is_some_and(|integrity_key| integrity_key != [0x42; 32]));
+        drop(db);
+        assert!(
+            Database::open_private_database_with(
+                &path,
+                &key_provider,
+                &MemoryKeyProvider::default()
+            )
+            .is_err(),
+            "a lost integrity key must not be silently replaced"
+        );
+        remove_database_files(&path);
+
+        let path = temp_database_path("integrity_key_adoption_tampered");
+        load_schema_fixture(&path, SCHEMA_FIXTURES[13].1);
+        Connection::open(&path)
+            .expect("fixture should reopen")
+            .execute(
+                "UPDATE clipboard_events SET integrity_link = zeroblob(32)
+                 WHERE rowid = (SELECT MIN(rowid) FROM clipboard_events)",
+                [],
+            )
+            .expect("link should be overwritten");
+        let db = Database::open_private_database_with(
+            &path,
+            &key_provider,
+            &MemoryKeyProvider::default(),
+        )
+        .expect("version thirteen should open");
+        assert!(db
+            .verify_history_integrity()
+            .expect("history should be checked")
+            .is_err());
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn search_filters_narrow_terms_and_page_without_the_index() {
+        let db = in_memory_database();
+        let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        png.extend([0x5a; 4 * 1024]);
+        let screenshot = event(vec![
+            data("public.png", &png),
+            data(SOURCE_TYPE, b"com.example.capture"),
+        ]);
+        let note = event(vec![
+            data("public.utf8-plain-text", b"release checklist notes"),
+            data(SOURCE_TYPE, b"com.example.notes"),
+        ]);
+        let remote = event(vec![
+            data("public.utf8-plain-text", b"release from another device"),
+            data(REMOTE_CLIPBOARD_TYPE, b""),
+        ]);
+        for clipboard_event in [&screenshot, &note, &remote] {
+            db.insert_event(clipboard_event)
+                .expect("fixture should insert");
+        }
+        let hash = |clipboard_event: &Event| {
+            db.event_content_hash(clipboard_event)
+                .expect("hash should load")
+                .expect("fixture should classify")
+        };
+        let (screenshot_hash, note_hash, remote_hash) =
+            (hash(&screenshot), hash(&note), hash(&remote));
+        db.conn
+            .execute(
+                "UPDATE clipboard_events SET timestamp = 1000 WHERE content_hash = ?1",
+                [&note_hash],
+            )
+            .expect("note timestamp should update");
+
+        assert_eq!(
+            search_hashes(&db, "type:image"),
+            vec![screenshot_hash.clone()]
+        );
+        assert_eq!(search_hashes(&db, "size:>=4kb"), vec![screenshot_hash]);
+        assert_eq!(
+            search_hashes(&db, "release app:com.example.notes"),
+            vec![note_hash.clone()]
+        );
+        assert_eq!(search_hashes(&db, "release remote:yes"), vec![remote_hash]);
+        assert_eq!(
+            search_hashes(&db, "type:text before:1970-01-02T00:00:00Z"),
+            vec![note_hash.clone()]
+        );
+        assert!(search_hashes(&db, "after:1970-01-02T00:00:00Z")
+            .iter()
+            .all(|hash| hash != &note_hash));
+        assert!(search_hashes(&db, "release type:png").is_empty());
+
+        let first = db
+            .search_history_page(&parsed("type:text"), None, Some(1))
+            .expect("filter-only page should load");
+        assert!(first.has_more);
+        assert!(first.items[0].snippet.is_empty());
+        let second = db
+            .search_history_page(&parsed("type:text"), first.next_cursor.as_deref(), Some(1))
+            .expect("second filter-only page should load");
+        assert!(!second.has_more);
+        assert_eq!(second.items[0].summary.content_hash, note_hash);
+
+        db.set_compact_mode(true)
+            .expect("compact mode should enable");
+        assert_eq!(search_hashes(&db, "type:text").len(), 2);
+        assert!(search_hashes(&db, "type:png").is_empty());
+    }
+
+    #[test]
+    fn pinned_rows_skip_retention_sort_first_and_page_across_sections() {
+        let db = in_memory_database();
+        db.set_max_items(1_000).expect("retention should expand");
+        let hashes = (0..5)
+            .map(|index| {
+                let clipboard_event = event(vec![data(
+                    "public.utf8-plain-text",
+                    format!("pinned fixture {index}").as_bytes(),
+                )]);
+                db.insert_event(&clipboard_event)
+                    .expect("fixture should insert");
+                db.event_content_hash(&clipboard_event)
+                    .expect("hash should load")
+                    .expect("fixture should classify")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(db.pin_event(&hashes[0]), Ok(PinOutcome::Updated));
+        assert_eq!(db.pin_event(&hashes[0]), Ok(PinOutcome::Updated));
+        assert_eq!(db.pin_event(&hashes[1]), Ok(PinOutcome::Updated));
+        assert_eq!(db.pin_event(&"f".repeat(64)), Ok(PinOutcome::NotFound));
+
+        db.set_max_items(1).expect("retention should shrink");
+        db.cleanup_old_events().expect("cleanup should succeed");
+        let mut cursor = None;
+        let mut paged = Vec::new();
+        loop {
+            let page = db
+                .get_history_page(cursor.as_deref(), Some(1))
+                .expect("single-row page should load");
+            paged.extend(
+                page.items
+                    .iter()
+                    .map(|item| (item.content_hash.clone(), item.pinned)),
+            );
+            if !page.has_more {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        assert_eq!(
+            paged,
+            vec![
+                (hashes[1].clone(), true),
+                (hashes[0].clone(), true),
+                (hashes[4].clone(), false),
+            ]
+        );
+        assert_eq!(
+            search_hashes(&db, "fixture"),
+            vec![hashes[1].clone(), hashes[0].clone(), hashes[4].clone()]
+        );
+
+        db.set_menu_bar_item_limit(1)
+            .expect("tray limit should update");
+        let tray = db.get_tray_events().expect("tray snapshot should load");
+        assert_eq!(
+            tray.iter()
+                .map(|event| (event.content_hash.as_str(), event.pinned))
+                .collect::<Vec<_>>(),
+            vec![
+                (hashes[1].as_str(), true),
+                (hashes[0].as_str(), true),
+                (hashes[4].as_str(), false),
+            ]
+        );
+
+        assert_eq!(db.unpin_event(&hashes[0]), Ok(PinOutcome::Updated));
+        assert_eq!(db.unpin_event(&"f".repeat(64)), Ok(PinOutcome::NotFound));
+        db.cleanup_old_events().expect("cleanup should succeed");
+        let remaining = db
+            .get_history_page(None, None)
+            .expect("history should load")
+            .items
+            .into_iter()
+            .map(|item| item.content_hash)
+            .collect::<Vec<_>>();
+        assert_eq!(remaining, vec![hashes[1].clone(), hashes[4].clone()]);
+    }
+
+    #[test]
+    fn pinned_budget_rejects_pins_beyond_its_row_and_byte_limits() {
+        let db = in_memory_database();
+        db.set_max_items(1_000).expect("retention should expand");
+        let hashes = (0..=MAX_PINNED_ITEMS)
+            .map(|index| {
+                let clipboard_event = event(vec![data(
+                    "public.utf8-plain-text",
+                    format!("budget fixture {index}").as_bytes(),
+                )]);
+                db.insert_event(&clipboard_event)
+                    .expect("fixture should insert");
+                db.event_content_hash(&clipboard_event)
+                    .expect("hash should load")
+                    .expect("fixture should classify")
             })
-            .expect("migrated row count should load");
-        assert_eq!(row_count, 0);
-        for compact_mode in [false, true] {
-            db.set_compact_mode(compact_mode)
-                .expect("history mode should update");
-            for (label, _, _) in PROTOCOL_SKIP_CASES {
-                let body_hash =
-                    Database::hash_bytes(format!("synthetic skipped {label}").as_bytes());
-                assert_history_downstreams_are_empty(&db, &body_hash, &format!("migrated {label}"));
-            }
+            .collect::<Vec<_>>();
+        let (last, pinned) = hashes.split_last().expect("fixtures should exist");
+        for content_hash in pinned {
+            assert_eq!(db.pin_event(content_hash), Ok(PinOutcome::Updated));
         }
-        assert_database_mirror_is_empty(&path, "protocol-migration");
-        drop(db);
-        remove_database_files(&path);
+        assert_eq!(db.pin_event(last), Ok(PinOutcome::LimitReached));
+
+        db.unpin_event(&pinned[0]).expect("unpin should succeed");
+        db.conn
+            .execute(
+                "UPDATE clipboard_events SET byte_count = ?1 WHERE content_hash = ?2",
+                params![MAX_PINNED_BYTES as i64, &pinned[1]],
+            )
+            .expect("pinned byte count should update");
+        assert_eq!(db.pin_event(last), Ok(PinOutcome::LimitReached));
+        let last_pinned: bool = db
+            .conn
+            .query_row(
+                "SELECT pinned FROM clipboard_events WHERE content_hash = ?1",
+                [last],
+                |row| row.get(0),
+            )
+            .expect("pin flag should load");
+        assert!(!last_pinned);
     }
 
     #[test]
-    fn prepared_compact_events_keep_protocol_metadata_and_reuse_the_effective_hash() {
+    fn pins_survive_history_rebuilds_and_compact_canonicalization() {
         let db = in_memory_database();
-        let clipboard_event = event(vec![
-            data("public.utf8-plain-text", b"compact protocol metadata"),
-            data(SOURCE_TYPE, b"com.example.source"),
-            data(REMOTE_CLIPBOARD_TYPE, b"marker payload is ignored"),
+        let rich = event(vec![
+            data("public.rtf", b"{\\rtf1 ssh deploy@example.test}"),
+            data("public.utf8-plain-text", b"ssh deploy@example.test"),
         ]);
-        let prepared = Database::prepare_history_event(&clipboard_event, true)
-            .expect("event preparation should succeed")
-            .expect("compact text should be accepted");
-        let expected_hash = Database::hash_bytes(b"compact protocol metadata");
-        assert_eq!(prepared.content_hash(), expected_hash);
-        assert!(db
-            .insert_prepared_event(prepared)
-            .expect("prepared event should insert"));
+        db.insert_event(&rich).expect("rich row should insert");
+        let rich_hash = db
+            .event_content_hash(&rich)
+            .expect("hash should load")
+            .expect("rich row should classify");
+        db.pin_event(&rich_hash).expect("pin should succeed");
+
+        db.rebuild_history_metadata()
+            .expect("history should rebuild");
+        let page = db
+            .get_history_page(None, None)
+            .expect("history should load");
+        assert_eq!(page.items[0].content_hash, rich_hash);
+        assert!(page.items[0].pinned);
 
+        db.set_compact_mode(true)
+            .expect("compact mode should enable");
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"ssh deploy@example.test",
+        )]))
+        .expect("compact row should upsert");
         let page = db
-            .get_history_page_for_mode(None, Some(50), true)
+            .get_history_page(None, None)
             .expect("compact history should load");
         assert_eq!(page.items.len(), 1);
+        assert_ne!(page.items[0].content_hash, rich_hash);
+        assert!(page.items[0].pinned);
+    }
+
+    #[test]
+    fn labels_filter_pages_export_to_the_mirror_and_follow_deletes() {
+        let db = in_memory_database();
+        let hashes = ["deploy script", "release notes", "lunch order"]
+            .iter()
+            .map(|text| {
+                let clipboard_event = event(vec![data("public.utf8-plain-text", text.as_bytes())]);
+                db.insert_event(&clipboard_event)
+                    .expect("fixture should insert");
+                db.event_content_hash(&clipboard_event)
+                    .expect("hash should load")
+                    .expect("fixture should classify")
+            })
+            .collect::<Vec<_>>();
+        let work = db
+            .create_collection("  Work \t items ")
+            .expect("collection should create")
+            .expect("collection name should be accepted");
+        assert_eq!(work.name, "Work items");
         assert_eq!(
-            page.items[0].source_bundle_id.as_deref(),
-            Some("com.example.source")
+            db.create_collection("work ITEMS"),
+            Ok(Err(LabelRejection::NameTaken))
+        );
+        assert_eq!(
+            db.create_collection("\u{7}"),
+            Ok(Err(LabelRejection::InvalidName))
+        );
+        for content_hash in &hashes[..2] {
+            assert_eq!(db.add_to_collection(work.id, content_hash), Ok(Ok(())));
+        }
+        assert_eq!(
+            db.add_to_collection(work.id, &"f".repeat(64)),
+            Ok(Err(LabelRejection::ItemNotFound))
+        );
+        assert_eq!(
+            db.add_to_collection(work.id + 1, &hashes[0]),
+            Ok(Err(LabelRejection::LabelNotFound))
+        );
+        assert_eq!(db.tag_event(&hashes[0], "ops"), Ok(Ok(())));
+        assert_eq!(db.tag_event(&hashes[0], "OPS"), Ok(Ok(())));
+        assert_eq!(db.tag_event(&hashes[2], "food"), Ok(Ok(())));
+
+        let filtered = db
+            .get_filtered_history_page(
+                None,
+                None,
+                &HistoryLabelFilter {
+                    collection_id: Some(work.id),
+                    tag: Some("Ops".to_string()),
+                },
+            )
+            .expect("filtered page should load");
+        assert_eq!(filtered.total_count, 1);
+        assert_eq!(filtered.items[0].content_hash, hashes[0]);
+        let first = db
+            .get_filtered_history_page(
+                None,
+                Some(1),
+                &HistoryLabelFilter {
+                    collection_id: Some(work.id),
+                    tag: None,
+                },
+            )
+            .expect("first collection page should load");
+        let second = db
+            .get_filtered_history_page(
+                first.next_cursor.as_deref(),
+                Some(1),
+                &HistoryLabelFilter {
+                    collection_id: Some(work.id),
+                    tag: None,
+                },
+            )
+            .expect("second collection page should load");
+        assert_eq!(first.total_count, 2);
+        assert_eq!(first.items[0].content_hash, hashes[1]);
+        assert_eq!(second.items[0].content_hash, hashes[0]);
+        assert!(!second.has_more);
+
+        let snapshot = db.history_snapshot_rows().expect("snapshot should load");
+        let deploy = snapshot
+            .iter()
+            .find(|row| row.content_hash == hashes[0])
+            .expect("deploy row should be mirrored");
+        assert_eq!(deploy.collections, ["Work items"]);
+        assert_eq!(deploy.tags, ["ops"]);
+
+        db.delete_event(&hashes[0]).expect("delete should succeed");
+        db.delete_event(&hashes[2]).expect("delete should succeed");
+        assert_eq!(
+            db.list_collections().expect("collections should load"),
+            vec![HistoryCollection {
+                id: work.id,
+                name: "Work items".to_string(),
+                item_count: 1,
+            }]
+        );
+        assert!(db.list_tags().expect("tags should load").is_empty());
+        let orphaned: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM item_tags WHERE content_hash IN (?1, ?2)",
+                params![&hashes[0], &hashes[2]],
+                |row| row.get(0),
+            )
+            .expect("memberships should count");
+        assert_eq!(orphaned, 0);
+
+        assert_eq!(db.delete_collection(work.id), Ok(Ok(())));
+        assert_eq!(
+            db.get_event_labels(&hashes[1]),
+            Ok(Ok(HistoryLabels {
+                collection_ids: Vec::new(),
+                tags: Vec::new(),
+            }))
         );
-        assert!(page.items[0].is_remote_clipboard);
-        assert_eq!(page.items[0].data_type, "text");
     }
 
     #[test]
-    fn compact_restore_seed_uses_effective_hash_and_applies_projection_outside_storage() {
+    fn labels_follow_compact_canonicalization_and_enforce_limits() {
         let db = in_memory_database();
-        let clipboard_event = event(vec![
-            data("public.utf8-plain-text", b"effective restore text"),
-            data("public.rtf", br"{\rtf1 effective restore text}"),
-            data(SOURCE_TYPE, b"com.example.restore"),
-            data(REMOTE_CLIPBOARD_TYPE, b""),
+        let rich = event(vec![
+            data("public.rtf", b"{\\rtf1 shared label text}"),
+            data("public.utf8-plain-text", b"shared label text"),
         ]);
-        db.insert_event(&clipboard_event)
-            .expect("full event should insert");
-        let stored_hash = db
-            .get_history_page(None, Some(1))
-            .expect("history should load")
-            .items[0]
-            .content_hash
-            .clone();
-        let effective_hash = Database::hash_bytes(b"effective restore text");
-        assert_ne!(stored_hash, effective_hash);
+        db.insert_event(&rich).expect("rich row should insert");
+        let rich_hash = db
+            .event_content_hash(&rich)
+            .expect("hash should load")
+            .expect("rich row should classify");
+        let saved = db
+            .create_collection("Saved")
+            .expect("collection should create")
+            .expect("collection name should be accepted");
+        db.add_to_collection(saved.id, &rich_hash)
+            .expect("membership should insert")
+            .expect("membership should be accepted");
+        for index in 0..MAX_TAGS_PER_ITEM {
+            assert_eq!(
+                db.tag_event(&rich_hash, &format!("tag {index}")),
+                Ok(Ok(()))
+            );
+        }
+        assert_eq!(
+            db.tag_event(&rich_hash, "one too many"),
+            Ok(Err(LabelRejection::LimitReached))
+        );
 
         db.set_compact_mode(true)
             .expect("compact mode should enable");
-        let seed = db
-            .get_restore_seed(&stored_hash)
-            .expect("restore seed lookup should work")
-            .expect("restore seed should exist");
-        assert_eq!(seed.content_hash, effective_hash);
+        db.insert_event(&event(vec![data(
+            "public.utf8-plain-text",
+            b"shared label text",
+        )]))
+        .expect("compact row should upsert");
+        let page = db
+            .get_history_page(None, None)
+            .expect("compact history should load");
+        let canonical_hash = &page.items[0].content_hash;
+        assert_ne!(canonical_hash, &rich_hash);
+        let labels = db
+            .get_event_labels(canonical_hash)
+            .expect("labels should load")
+            .expect("canonical row should exist");
+        assert_eq!(labels.collection_ids, vec![saved.id]);
+        assert_eq!(labels.tags.len(), MAX_TAGS_PER_ITEM as usize);
         assert_eq!(
-            seed.source_bundle_id.as_deref(),
-            Some("com.example.restore")
+            db.get_event_labels(&rich_hash),
+            Ok(Err(LabelRejection::ItemNotFound))
         );
-        assert!(seed.is_remote_clipboard);
 
-        let restored = seed
-            .into_event()
-            .expect("restore event should decode")
-            .expect("compact projection should exist");
-        assert!(Database::find_data(&restored, "public.rtf").is_none());
+        db.clear_all_events().expect("history should clear");
         assert_eq!(
-            Database::find_data(&restored, "public.utf8-plain-text")
-                .expect("plain text should remain")
-                .data,
-            b"effective restore text"
+            db.list_collections().expect("collections should load")[0].item_count,
+            0
         );
-        assert!(Database::find_data(&restored, SOURCE_TYPE).is_some());
-        assert!(Database::find_data(&restored, REMOTE_CLIPBOARD_TYPE).is_some());
+        assert!(db.list_tags().expect("tags should load").is_empty());
     }
 
     #[test]
-    fn history_summary_paging_is_bounded_and_uses_stable_cursors() {
+    fn duplicate_copies_count_and_move_to_top_only_when_enabled() {
         let db = in_memory_database();
-        db.set_max_items(1_000).expect("retention should expand");
-        for index in 0..120 {
-            db.insert_event(&event(vec![data(
-                "public.utf8-plain-text",
-                format!("summary row {index:03}").as_bytes(),
-            )]))
-            .expect("fixture should insert");
-        }
-
+        let repeated = event(vec![data("public.utf8-plain-text", b"repeated copy")]);
+        let other = event(vec![data("public.utf8-plain-text", b"other copy")]);
+        db.insert_event(&repeated)
+            .expect("first copy should insert");
+        db.insert_event(&other).expect("other copy should insert");
         let first = db
             .get_history_page(None, None)
-            .expect("default page should load");
-        assert_eq!(first.items.len(), DEFAULT_HISTORY_PAGE_SIZE);
-        assert!(first.has_more);
-        assert!(first.next_cursor.is_some());
-
-        let capped = db
-            .get_history_page(None, Some(usize::MAX))
-            .expect("capped page should load");
-        assert_eq!(capped.items.len(), MAX_HISTORY_PAGE_SIZE);
-
-        let mut hashes = Vec::new();
-        let mut cursor = None;
-        loop {
-            let page = db
-                .get_history_page(cursor.as_deref(), Some(17))
-                .expect("cursor page should load");
-            hashes.extend(page.items.iter().map(|item| item.content_hash.clone()));
-            if !page.has_more {
-                break;
-            }
-            cursor = page.next_cursor;
-        }
-        assert_eq!(hashes.len(), 120);
-        assert_eq!(hashes.iter().collect::<HashSet<_>>().len(), 120);
-        assert!(db.get_history_page(Some("malformed"), Some(50)).is_err());
+            .expect("history should load");
+        let original = first.items[1].clone();
+        assert_eq!(original.copy_count, 1);
+        assert_eq!(original.first_seen_at, original.timestamp);
+        assert_eq!(original.last_seen_at, original.timestamp);
+
+        db.insert_event(&repeated).expect("duplicate should update");
+        let second = db
+            .get_history_page(None, None)
+            .expect("history should load");
+        assert_eq!(second.items.len(), 2);
+        let recopied = &second.items[1];
+        assert_eq!(recopied.content_hash, original.content_hash);
+        assert_eq!(recopied.timestamp, original.timestamp);
+        assert_eq!(recopied.first_seen_at, original.first_seen_at);
+        assert!(recopied.last_seen_at >= original.last_seen_at);
+        assert_eq!(recopied.copy_count, 2);
+
+        db.set_move_recopied_item_to_top(true)
+            .expect("re-copy ordering should enable");
+        assert!(
+            db.get_settings()
+                .expect("settings should load")
+                .move_recopied_item_to_top
+        );
+        db.insert_event(&repeated).expect("duplicate should move");
+        let third = db
+            .get_history_page(None, None)
+            .expect("history should load");
+        assert_eq!(third.items[0].content_hash, original.content_hash);
+        assert!(third.items[0].timestamp > third.items[1].timestamp);
+        assert_eq!(third.items[0].first_seen_at, original.first_seen_at);
+        assert_eq!(third.items[0].copy_count, 3);
     }
 
     #[test]
-    fn history_cursor_tie_breaker_does_not_repeat_or_skip_equal_timestamps() {
+    fn max_age_days_expires_stale_rows_by_last_copy_even_when_pinned() {
         let db = in_memory_database();
-        for value in ["same timestamp a", "same timestamp b", "same timestamp c"] {
+        for value in [
+            "stale row",
+            "stale pinned row",
+            "recent pinned row",
+            "recent row",
+        ] {
             db.insert_event(&event(vec![data(
                 "public.utf8-plain-text",
                 value.as_bytes(),
             )]))
             .expect("fixture should insert");
         }
+        let rows = db
+            .history_snapshot_rows()
+            .expect("history snapshot should load");
+        let recent_hash = rows[0].content_hash.clone();
+        let recent_pinned_hash = rows[1].content_hash.clone();
+        let stale_pinned_hash = rows[2].content_hash.clone();
+        let stale_hash = rows[3].content_hash.clone();
+        for pinned in [&recent_pinned_hash, &stale_pinned_hash] {
+            assert_eq!(db.pin_event(pinned), Ok(PinOutcome::Updated));
+        }
+        let stale_at = Database::current_unix_timestamp() - 31 * MILLIS_PER_DAY;
         db.conn
-            .execute("UPDATE clipboard_events SET timestamp = 42", [])
-            .expect("timestamps should align");
+            .execute(
+                "UPDATE clipboard_events SET last_seen_at = ?1 WHERE content_hash IN (?2, ?3)",
+                params![stale_at, stale_hash, stale_pinned_hash],
+            )
+            .expect("fixture rows should age");
 
-        let mut hashes = Vec::new();
-        let mut cursor = None;
-        loop {
-            let page = db
-                .get_history_page(cursor.as_deref(), Some(1))
-                .expect("single-row page should load");
-            hashes.extend(page.items.iter().map(|item| item.content_hash.clone()));
-            if !page.has_more {
-                break;
-            }
-            cursor = page.next_cursor;
+        assert_eq!(
+            db.cleanup_old_events()
+                .expect("unlimited age should clean up"),
+            0
+        );
+        db.set_max_age_days(30).expect("age limit should persist");
+        assert_eq!(
+            db.get_settings()
+                .expect("settings should load")
+                .max_age_days,
+            30
+        );
+        assert_eq!(
+            db.cleanup_old_events()
+                .expect("age limit should drive cleanup"),
+            2
+        );
+        for expired in [&stale_hash, &stale_pinned_hash] {
+            assert!(db
+                .get_history_detail_seed(expired)
+                .expect("stale lookup should work")
+                .expect("stored history should verify")
+                .is_none());
         }
-        let mut sorted = hashes.clone();
-        sorted.sort();
-        assert_eq!(hashes, sorted);
-        assert_eq!(hashes.len(), 3);
+        for retained in [&recent_hash, &recent_pinned_hash] {
+            assert!(db
+                .get_history_detail_seed(retained)
+                .expect("retained lookup should work")
+                .expect("stored history should verify")
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn compact_duplicates_merge_copy_history_into_the_canonical_row() {
+        let db = in_memory_database();
+        let rich = event(vec![
+            data("public.html", b"<p>merged copy</p>"),
+            data("public.utf8-plain-text", b"merged copy"),
+        ]);
+        db.insert_event(&rich).expect("rich row should insert");
+        db.insert_event(&rich)
+            .expect("rich duplicate should update");
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"newer copy")]))
+            .expect("newer row should insert");
+        let rich_summary = db
+            .get_history_page(None, None)
+            .expect("history should load")
+            .items[1]
+            .clone();
+        assert_eq!(rich_summary.copy_count, 2);
+
+        db.set_compact_mode(true)
+            .expect("compact mode should enable");
+        db.set_move_recopied_item_to_top(true)
+            .expect("re-copy ordering should enable");
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"merged copy")]))
+            .expect("compact row should upsert");
+        let page = db
+            .get_history_page(None, None)
+            .expect("compact history should load");
+        assert_eq!(page.items.len(), 2);
+        assert_ne!(page.items[0].content_hash, rich_summary.content_hash);
+        assert_eq!(page.items[0].first_seen_at, rich_summary.first_seen_at);
+        assert!(page.items[0].last_seen_at >= rich_summary.last_seen_at);
+        assert_eq!(page.items[0].copy_count, 3);
     }
 
     #[test]
@@ -3207,6 +7802,7 @@ mod tests {
         let seed = db
             .get_history_detail_seed(&png_summary.content_hash)
             .expect("detail seed should load")
+            .expect("stored history should verify")
             .expect("detail seed should exist");
         assert!(seed.event_data.len() > 32 * 1024);
 
@@ -3267,31 +7863,61 @@ mod tests {
     #[test]
     fn persisted_display_is_bounded_without_truncating_restorable_event_data() {
         let db = in_memory_database();
-        let original = vec![b'x'; MAX_DISPLAY_BYTES + 4096];
-        let clipboard_event = event(vec![Data {
-            r#type: "public.utf8-plain-text".to_string(),
-            data: original.clone(),
-        }]);
+        let original = vec![b'x'; MAX_DISPLAY_BYTES + 4096];
+        let clipboard_event = event(vec![Data {
+            r#type: "public.utf8-plain-text".to_string(),
+            data: original.clone(),
+        }]);
+        db.insert_event(&clipboard_event)
+            .expect("bounded fixture should insert");
+        let content_hash = db
+            .event_content_hash(&clipboard_event)
+            .expect("hash should compute")
+            .expect("hash should exist");
+
+        let stored_display = db
+            .get_history_detail_seed(&content_hash)
+            .expect("detail seed should load")
+            .expect("stored history should verify")
+            .expect("detail seed should exist")
+            .display;
+        assert_eq!(stored_display.len(), MAX_DISPLAY_BYTES);
+
+        let restored = db
+            .get_event_by_content_hash(&content_hash)
+            .expect("stored event should load")
+            .expect("stored event should exist");
+        assert_eq!(restored.items[0].data_list[0].data, original);
+    }
+
+    #[test]
+    fn compressed_flavors_count_against_the_history_budget_by_stored_size() {
+        let db = in_memory_database();
+        let html = b"<tr><td>quarterly figures</td></tr>".repeat(8 * 1024);
+        let clipboard_event = event(vec![
+            data("public.html", &html),
+            data("public.utf8-plain-text", b"quarterly figures"),
+        ]);
         db.insert_event(&clipboard_event)
-            .expect("bounded fixture should insert");
-
-        let stored_display_bytes: i64 = db
-            .conn
-            .query_row("SELECT length(display) FROM clipboard_events", [], |row| {
-                row.get(0)
-            })
-            .expect("display length should load");
-        assert_eq!(stored_display_bytes as usize, MAX_DISPLAY_BYTES);
+            .expect("formatted fixture should insert");
 
+        let summary = db
+            .get_history_page(None, None)
+            .expect("summary page should load")
+            .items
+            .remove(0);
+        assert!(summary.byte_count < html.len() as u64 / 4);
         let restored = db
-            .get_event_by_content_hash(
-                &db.event_content_hash(&clipboard_event)
-                    .expect("hash should compute")
-                    .expect("hash should exist"),
-            )
+            .get_event_by_content_hash(&summary.content_hash)
             .expect("stored event should load")
             .expect("stored event should exist");
-        assert_eq!(restored.items[0].data_list[0].data, original);
+        assert_eq!(restored.items[0].data_list[0].data, html);
+        assert_eq!(
+            db.get_history_stats()
+                .expect("history stats should load")
+                .total_bytes,
+            summary.byte_count
+        );
     }
 
     #[test]
@@ -3556,6 +8182,7 @@ mod tests {
         assert!(db
             .get_history_detail_seed(&newest_hash)
             .expect("newest lookup should work")
+            .expect("stored history should verify")
             .is_some());
     }
 
@@ -3578,6 +8205,7 @@ mod tests {
         let newest_bytes = db
             .get_history_detail_seed(&newest_hash)
             .expect("newest detail lookup should work")
+            .expect("stored history should verify")
             .expect("newest detail should exist")
             .byte_count;
 
@@ -3608,6 +8236,684 @@ mod tests {
         );
     }
 
+    fn flavor_blob_counts(db: &Database) -> Vec<(i64, i64)> {
+        db.conn
+            .prepare("SELECT byte_count, ref_count FROM flavor_blobs ORDER BY digest")
+            .expect("flavor blobs should prepare")
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .expect("flavor blobs should query")
+            .collect::<Result<Vec<_>>>()
+            .expect("flavor blobs should load")
+    }
+
+    fn row_byte_count(db: &Database, content_hash: &str) -> u64 {
+        db.conn
+            .query_row(
+                "SELECT byte_count FROM clipboard_events WHERE content_hash = ?1",
+                [content_hash],
+                |row| row.get(0),
+            )
+            .expect("byte count should load")
+    }
+
+    #[test]
+    fn identical_large_flavors_are_stored_once_and_collected_with_their_last_row() {
+        let db = in_memory_database();
+        let png = valid_png(64, 64, 40 * 1024);
+        let image = event(vec![data("public.png", &png)]);
+        let document = event(vec![
+            data("public.rtf", br"{\rtf1 cat}"),
+            data("public.png", &png),
+        ]);
+        let stored_alone = |event: &Event| {
+            let prepared = Database::prepare_history_event(event, false)
+                .expect("event should prepare")
+                .expect("event should be recorded");
+            prepared.metadata.byte_count
+        };
+        let image_bytes = stored_alone(&image);
+        let document_bytes = stored_alone(&document);
+        db.insert_event(&image).expect("image should insert");
+        db.insert_event(&document).expect("document should insert");
+        let image_hash = db
+            .event_content_hash(&image)
+            .expect("image hash should load")
+            .expect("image should be stored");
+        let document_hash = db
+            .event_content_hash(&document)
+            .expect("document hash should load")
+            .expect("document should be stored");
+
+        let png_bytes = png.len() as i64;
+        assert_eq!(flavor_blob_counts(&db), vec![(png_bytes, 2)]);
+        let half = png.len() as u64 / 2;
+        assert_eq!(row_byte_count(&db, &image_hash), image_bytes + half);
+        assert_eq!(row_byte_count(&db, &document_hash), document_bytes + half);
+        assert_eq!(
+            db.get_history_stats()
+                .expect("stats should load")
+                .total_bytes,
+            image_bytes + document_bytes + png.len() as u64
+        );
+        let encoded = |event: &Event| encode_event_blob(event).expect("event should encode");
+        for (content_hash, expected) in [(&image_hash, &image), (&document_hash, &document)] {
+            let stored = db
+                .get_event_by_content_hash(content_hash)
+                .expect("event should load")
+                .expect("event should exist");
+            assert_eq!(encoded(&stored), encoded(expected));
+            let restored = db
+                .get_restore_seed(content_hash)
+                .expect("restore lookup should work")
+                .expect("stored history should verify")
+                .expect("restore seed should exist")
+                .into_event()
+                .expect("seed should decode")
+                .expect("seed should restore");
+            assert_eq!(encoded(&restored), encoded(expected));
+        }
+        let backup = db
+            .history_backup()
+            .expect("history should be checked")
+            .expect("history should verify");
+        assert!(backup.rows.iter().all(|row| {
+            row.event_data.starts_with(b"CSB1") && decode_event_blob(&row.event_data).is_ok()
+        }));
+
+        // Recopying keeps a single reference per row.
+        db.insert_event(&image).expect("image should be recopied");
+        assert_eq!(flavor_blob_counts(&db), vec![(png_bytes, 2)]);
+
+        db.delete_event(&image_hash).expect("image should delete");
+        assert_eq!(flavor_blob_counts(&db), vec![(png_bytes, 1)]);
+        assert_eq!(
+            row_byte_count(&db, &document_hash),
+            document_bytes + png.len() as u64
+        );
+        db.set_max_items(0).expect("item limit should update");
+        db.cleanup_old_events().expect("cleanup should run");
+        assert!(history_hashes(&db).is_empty());
+        assert!(flavor_blob_counts(&db).is_empty());
+        assert_eq!(
+            db.conn
+                .query_row("SELECT COUNT(*) FROM flavor_blob_refs", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .expect("references should count"),
+            0
+        );
+    }
+
+    #[test]
+    fn byte_budgets_count_shared_flavors_once() {
+        let db = in_memory_database();
+        db.set_max_items(1_000).expect("retention should expand");
+        let png = valid_png(64, 64, 40 * 1024);
+        for caption in ["first", "second", "third"] {
+            db.insert_event(&event(vec![
+                data("public.rtf", caption.as_bytes()),
+                data("public.png", &png),
+            ]))
+            .expect("shared image should insert");
+        }
+        let total_bytes = db
+            .get_history_stats()
+            .expect("stats should load")
+            .total_bytes;
+        assert!(total_bytes < 2 * png.len() as u64);
+
+        // Two rows fit once the oldest reference is gone, because the image
+        // itself is stored once.
+        db.set_max_history_bytes(total_bytes - 1)
+            .expect("byte budget should update");
+        db.cleanup_old_events().expect("cleanup should run");
+        assert_eq!(history_hashes(&db).len(), 2);
+        assert_eq!(flavor_blob_counts(&db), vec![(png.len() as i64, 2)]);
+
+        db.set_max_history_bytes(png.len() as u64 / 2)
+            .expect("byte budget should update");
+        db.cleanup_old_events().expect("cleanup should run");
+        assert!(history_hashes(&db).is_empty());
+        assert!(flavor_blob_counts(&db).is_empty());
+    }
+
+    #[test]
+    fn missing_shared_flavors_fail_verification_and_quarantine_the_row() {
+        let db = in_memory_database();
+        let image = event(vec![data("public.png", &valid_png(64, 64, 40 * 1024))]);
+        db.insert_event(&image).expect("image should insert");
+        let content_hash = history_hashes(&db).remove(0);
+        db.conn
+            .execute("UPDATE flavor_blobs SET data = x'00'", [])
+            .expect("shared payload should be corrupted");
+
+        assert_eq!(
+            db.get_history_detail_seed(&content_hash)
+                .expect("detail lookup should run"),
+            Err(IntegrityViolation)
+        );
+        assert_eq!(
+            db.check_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryCheckReport {
+                checked_items: 1,
+                quarantined_items: 1,
+                undecodable_items: 1,
+                ..HistoryCheckReport::default()
+            })
+        );
+        assert!(flavor_blob_counts(&db).is_empty());
+    }
+
+    #[test]
+    fn quarantined_rows_keep_their_shared_flavors_inline() {
+        let db = in_memory_database();
+        let document = event(vec![
+            data("public.rtf", br"{\rtf1 cat}"),
+            data("public.png", &valid_png(64, 64, 40 * 1024)),
+        ]);
+        db.insert_event(&document).expect("document should insert");
+        let content_hash = history_hashes(&db).remove(0);
+        db.conn
+            .execute(
+                "UPDATE clipboard_events SET display = x'00' WHERE content_hash = ?1",
+                [&content_hash],
+            )
+            .expect("display should be corrupted");
+
+        assert_eq!(
+            db.check_history_integrity()
+                .expect("history should be checked"),
+            Ok(HistoryCheckReport {
+                checked_items: 1,
+                quarantined_items: 1,
+                undecryptable_items: 1,
+                ..HistoryCheckReport::default()
+            })
+        );
+        assert!(flavor_blob_counts(&db).is_empty());
+        let sealed: Vec<u8> = db
+            .conn
+            .query_row(
+                "SELECT event_data FROM quarantined_events WHERE content_hash = ?1",
+                [&content_hash],
+                |row| row.get(0),
+            )
+            .expect("quarantined payload should load");
+        let event_data = db
+            .cipher
+            .open(&content_hash, PayloadColumn::EventData, &sealed)
+            .expect("quarantined payload should open");
+        let encoded = |event: &Event| encode_event_blob(event).expect("event should encode");
+        assert_eq!(
+            encoded(&decode_event_blob(&event_data).expect("quarantined payload should decode")),
+            encoded(&document)
+        );
+    }
+
+    fn blob_file_names(path: &Path) -> Vec<String> {
+        let mut names = std::fs::read_dir(path.with_file_name("blobs"))
+            .map(|entries| {
+                entries
+                    .map(|entry| {
+                        entry
+                            .expect("blob entry should list")
+                            .file_name()
+                            .into_string()
+                            .expect("blob name should be UTF-8")
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn large_shared_flavors_live_in_files_removed_with_their_last_row() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_database_path("blob_files");
+        let db = Database::open_path(&path).expect("database should open");
+        let png = valid_png(64, 64, 2 * 1024 * 1024);
+        let image = event(vec![data("public.png", &png)]);
+        let document = event(vec![
+            data("public.rtf", br"{\rtf1 cat}"),
+            data("public.png", &png),
+        ]);
+        db.insert_event(&image).expect("image should insert");
+        db.insert_event(&document).expect("document should insert");
+
+        let files = blob_file_names(&path);
+        assert_eq!(files.len(), 1);
+        let blob_path = path.with_file_name("blobs").join(&files[0]);
+        assert_eq!(
+            std::fs::symlink_metadata(&blob_path)
+                .expect("blob file metadata should load")
+                .permissions()
+                .mode()
+                & 0o777,
+            0o600
+        );
+        assert!(std::fs::read(&blob_path)
+            .expect("blob file should read")
+            .windows(8)
+            .all(|window| window != &png[..8]));
+        assert_eq!(flavor_blob_counts(&db), vec![(png.len() as i64, 2)]);
+        assert_eq!(
+            db.conn
+                .query_row("SELECT length(data) FROM flavor_blobs", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .expect("stored payload length should load"),
+            0
+        );
+        let encoded = |event: &Event| encode_event_blob(event).expect("event should encode");
+        let image_hash = db
+            .event_content_hash(&image)
+            .expect("image hash should load")
+            .expect("image should be stored");
+        let stored = db
+            .get_event_by_content_hash(&image_hash)
+            .expect("image should load")
+            .expect("image should exist");
+        assert_eq!(encoded(&stored), encoded(&image));
+        assert_eq!(
+            db.verify_history_integrity()
+                .expect("history should be verified"),
+            Ok(HistoryIntegrityReport { verified_items: 2 })
+        );
+
+        db.delete_event(&image_hash).expect("image should delete");
+        assert_eq!(blob_file_names(&path), files);
+        db.set_max_items(0).expect("item limit should update");
+        db.cleanup_old_events().expect("cleanup should run");
+        assert!(history_hashes(&db).is_empty());
+        assert!(blob_file_names(&path).is_empty());
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn reconciling_blob_files_removes_orphans_and_quarantines_rows_missing_theirs() {
+        let path = temp_database_path("blob_reconcile");
+        let db = Database::open_path(&path).expect("database should open");
+        let image = event(vec![data(
+            "public.png",
+            &valid_png(64, 64, 2 * 1024 * 1024),
+        )]);
+        db.insert_event(&image).expect("image should insert");
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"kept")]))
+            .expect("text should insert");
+        assert_eq!(
+            db.reconcile_blob_files()
+                .expect("blob files should reconcile"),
+            BlobFileReport::default()
+        );
+
+        let directory = path.with_file_name("blobs");
+        let files = blob_file_names(&path);
+        std::fs::remove_file(directory.join(&files[0])).expect("blob file should be removed");
+        std::fs::write(directory.join("leftover.tmp"), b"partial")
+            .expect("orphan file should be written");
+        assert_eq!(
+            db.reconcile_blob_files()
+                .expect("blob files should reconcile"),
+            BlobFileReport {
+                orphaned_files: 1,
+                missing_files: 1,
+                quarantined_items: 1,
+            }
+        );
+        assert!(blob_file_names(&path).is_empty());
+        assert_eq!(history_hashes(&db).len(), 1);
+        assert!(flavor_blob_counts(&db).is_empty());
+        assert_eq!(
+            db.verify_history_integrity()
+                .expect("history should be verified"),
+            Ok(HistoryIntegrityReport { verified_items: 1 })
+        );
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn blob_files_outlive_their_rows_while_a_snapshot_lists_them() {
+        let path = temp_database_path("blob_snapshot");
+        let mut db = Database::open_path(&path).expect("database should open");
+        let image = event(vec![data(
+            "public.png",
+            &valid_png(64, 64, 2 * 1024 * 1024),
+        )]);
+        db.insert_event(&image).expect("image should insert");
+        let image_hash = db
+            .event_content_hash(&image)
+            .expect("image hash should load")
+            .expect("image should be stored");
+        let files = blob_file_names(&path);
+        backups::snapshot_database(
+            &db.conn,
+            &path,
+            schema::user_version(&db.conn).expect("schema version should load"),
+            Database::current_unix_timestamp(),
+        )
+        .expect("snapshot should be written");
+        let snapshot = db.list_database_backups().expect("snapshots should list")[0]
+            .name
+            .clone();
+
+        db.delete_event(&image_hash).expect("image should delete");
+        assert_eq!(blob_file_names(&path), files);
+        assert_eq!(
+            db.reconcile_blob_files()
+                .expect("blob files should reconcile"),
+            BlobFileReport::default()
+        );
+
+        assert_eq!(
+            db.restore_database_backup(&snapshot)
+                .expect("restore should run"),
+            Ok(())
+        );
+        let encoded = |event: &Event| encode_event_blob(event).expect("event should encode");
+        let restored = db
+            .get_event_by_content_hash(&image_hash)
+            .expect("image should load")
+            .expect("image should be restored");
+        assert_eq!(encoded(&restored), encoded(&image));
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn collecting_blob_files_waits_for_uncommitted_writers() {
+        let path = temp_database_path("blob_writer");
+        let db = Database::open_path(&path).expect("database should open");
+        db.insert_event(&event(vec![data(
+            "public.png",
+            &valid_png(64, 64, 2 * 1024 * 1024),
+        )]))
+        .expect("image should insert");
+        let digest = "ab".repeat(32);
+        let writer = Connection::open(&path).expect("writer should open");
+        writer
+            .execute_batch("BEGIN IMMEDIATE")
+            .expect("writer should take the write lock");
+        writer
+            .execute(
+                "INSERT INTO flavor_blob_files (digest) VALUES (?1)",
+                [&digest],
+            )
+            .expect("file should be listed");
+        blob_files::write_blob_file(&path, &digest, b"sealed payload")
+            .expect("blob file should be written");
+
+        db.conn
+            .busy_timeout(Duration::from_millis(50))
+            .expect("busy timeout should update");
+        assert!(db.reconcile_blob_files().is_err());
+        assert!(blob_file_names(&path).contains(&digest));
+
+        writer
+            .execute_batch("COMMIT")
+            .expect("writer should commit");
+        assert_eq!(
+            flavor_blobs::remove_unreferenced_files_in(&db.conn)
+                .expect("blob files should be collected"),
+            0
+        );
+        assert!(blob_file_names(&path).contains(&digest));
+        drop(writer);
+        drop(db);
+        remove_database_files(&path);
+    }
+
+    #[test]
+    fn data_type_budgets_evict_within_their_type_before_global_limits() {
+        let db = in_memory_database();
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"oldest text")]))
+            .expect("text should insert");
+        for marker in 0..3u8 {
+            db.insert_event(&event(vec![data(
+                "public.png",
+                &[0x89, b'P', b'N', b'G', marker],
+            )]))
+            .expect("image should insert");
+        }
+        db.insert_event(&event(vec![data("public.utf8-plain-text", b"newest text")]))
+            .expect("text should insert");
+        let rows = db
+            .history_snapshot_rows()
+            .expect("history snapshot should load");
+        let newest_png = rows[1].content_hash.clone();
+        let newest_text_bytes = db
+            .get_history_detail_seed(&rows[0].content_hash)
+            .expect("newest text lookup should work")
+            .expect("stored history should verify")
+            .expect("newest text should exist")
+            .byte_count;
+
+        db.set_data_type_budget("png", Some(1), None)
+            .expect("image budget should persist");
+        db.set_data_type_budget("text", None, Some(newest_text_bytes))
+            .expect("text budget should persist");
+        assert_eq!(
+            db.get_settings()
+                .expect("settings should load")
+                .data_type_budgets,
+            [
+                DataTypeBudget {
+                    data_type: "png".to_string(),
+                    max_items: Some(1),
+                    max_bytes: None,
+                },
+                DataTypeBudget {
+                    data_type: "text".to_string(),
+                    max_items: None,
+                    max_bytes: Some(newest_text_bytes),
+                },
+            ]
+        );
+        assert_eq!(
+            db.cleanup_old_events()
+                .expect("per-type budgets should drive cleanup"),
+            3
+        );
+        let remaining = db
+            .history_snapshot_rows()
+            .expect("history snapshot should reload");
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining[1].content_hash, newest_png);
+        assert_eq!(
+            db.get_history_detail_seed(&remaining[0].content_hash)
+                .expect("newest text lookup should work")
+                .expect("stored history should verify")
+                .expect("newest text should remain")
+                .data_type,
+            "text"
+        );
+
+        db.set_data_type_budget("png", None, None)
+            .expect("image budget should clear");
+        db.set_data_type_budget("text", None, None)
+            .expect("text budget should clear");
+        assert!(db
+            .get_data_type_budgets()
+            .expect("budgets should reload")
+            .is_empty());
+    }
+
+    #[test]
+    fn source_app_rules_keep_denied_events_out_of_storage() {
+        let db = in_memory_database();
+        let from = |source: &str, text: &str| {
+            event(vec![
+                data("public.utf8-plain-text", text.as_bytes()),
+                data(SOURCE_TYPE, source.as_bytes()),
+            ])
+        };
+        assert_eq!(
+            db.set_source_app_rule(" com.example.Vault ", Some(SourceAppRule::Deny)),
+            Ok(true)
+        );
+        assert_eq!(
+            db.set_source_app_rule("com.example.Editor", Some(SourceAppRule::Allow)),
+            Ok(true)
+        );
+        for malformed in ["", "com example vault", "com.example.\u{1f}"] {
+            assert_eq!(
+                db.set_source_app_rule(malformed, Some(SourceAppRule::Deny)),
+                Ok(false),
+                "{malformed:?}"
+            );
+        }
+
+        let denied = from("COM.EXAMPLE.VAULT", "vault secret");
+        assert_eq!(
+            db.assess_capture(&denied)
+                .expect("policy should load")
+                .disposition,
+            HistoryDisposition::Skip(SkipReason::SourceApplication)
+        );
+        let policy = db.capture_policy().expect("policy should load");
+        assert!(matches!(
+            Database::prepare_capture(denied.clone(), &policy),
+            Ok(CaptureOutcome::Skipped(SkipReason::SourceApplication))
+        ));
+        assert_eq!(db.insert_event(&denied), Ok(false));
+        assert_eq!(
+            db.insert_event(&from("com.example.Other", "ordinary text")),
+            Ok(true)
+        );
+
+        db.set_source_allow_only(true)
+            .expect("allow-only mode should persist");
+        assert_eq!(
+            db.insert_event(&from("com.example.Other", "second ordinary text")),
+            Ok(false)
+        );
+        assert_eq!(
+            db.insert_event(&event(vec![data(
+                "public.utf8-plain-text",
+                b"unknown source"
+            )])),
+            Ok(false)
+        );
+        assert_eq!(
+            db.insert_event(&from("com.example.Editor", "allowed text")),
+            Ok(true)
+        );
+
+        let settings = db.get_settings().expect("settings should load");
+        assert_eq!(settings.denied_source_apps, ["com.example.vault"]);
+        assert_eq!(settings.allowed_source_apps, ["com.example.editor"]);
+        assert!(settings.source_allow_only);
+        assert_eq!(settings.history_count, 2);
+        let indexed_rows: i64 = db
+            .conn
+            .query_row("SELECT COUNT(*) FROM clipboard_events_search", [], |row| {
+                row.get(0)
+            })
+            .expect("search index should count");
+        assert_eq!(indexed_rows, 2);
+
+        assert_eq!(db.set_source_app_rule("com.example.vault", None), Ok(true));
+        assert!(db
+            .get_source_app_policy()
+            .expect("policy should reload")
+            .denied
+            .is_empty());
+    }
+
+    #[test]
+    fn secret_policies_skip_mask_or_expire_detected_captures() {
+        let db = in_memory_database();
+        let text = |value: &str| data("public.utf8-plain-text", value.as_bytes());
+        // Assembled at runtime so the source holds no scanner-flagged literal.
+        let token = format!("ghp_{}", ('a'..='z').chain('0'..='9').collect::<String>());
+        let jwt = [
+            "eyJhbGciOiJIUzI1NiJ9",
+            "eyJzdWIiOiJzeW50aGV0aWMifQ",
+            "kP3xQ9mZ2vR7tW1yB5nC8dF4gH6jL0sA",
+        ]
+        .join(".");
+
+        let policy = db.capture_policy().expect("policy should load");
+        assert!(matches!(
+            Database::prepare_capture(event(vec![text(&token)]), &policy),
+            Ok(CaptureOutcome::Secret(SecretKind::GithubToken))
+        ));
+        assert_eq!(db.insert_event(&event(vec![text(&token)])), Ok(false));
+        assert_eq!(
+            db.insert_event(&event(vec![
+                text("card 4111 1111 1111 1111"),
+                data("public.html", b"<p>card 4111 1111 1111 1111</p>"),
+            ])),
+            Ok(true)
+        );
+        let captured_at = Database::current_unix_timestamp();
+        assert_eq!(db.insert_event(&event(vec![text(&jwt)])), Ok(true));
+
+        let page = db
+            .get_history_page(None, Some(50))
+            .expect("history should load");
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[1].display, "card ••••••••1111".as_bytes());
+        assert_eq!(page.items[1].data_type, "text");
+        let unmasked_bodies: i64 = db
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM clipboard_events_search_text WHERE body LIKE '%1111 1111%'",
+                [],
+                |row| row.get(0),
+            )
+            .expect("search index should count");
+        assert_eq!(unmasked_bodies, 0);
+
+        let expires_at = db
+            .next_expiry_at()
+            .expect("expiry should load")
+            .expect("token should expire");
+        let lifetime = SECRET_EXPIRY.as_millis() as i64;
+        assert!(expires_at >= captured_at + lifetime);
+        assert!(expires_at <= Database::current_unix_timestamp() + lifetime);
+        assert_eq!(db.cleanup_old_events(), Ok(0));
+
+        // Pinning does not keep an expiring secret past its expiry.
+        let card_hash = page.items[1].content_hash.clone();
+        assert_eq!(
+            db.pin_event(&page.items[0].content_hash),
+            Ok(PinOutcome::Updated)
+        );
+        db.conn
+            .execute(
+                "UPDATE clipboard_events SET expires_at = 1 WHERE expires_at IS NOT NULL",
+                [],
+            )
+            .expect("expiry should be forced");
+        assert_eq!(db.cleanup_old_events(), Ok(1));
+        let remaining = db
+            .get_history_page(None, Some(50))
+            .expect("history should reload");
+        assert_eq!(remaining.items.len(), 1);
+        assert_eq!(remaining.items[0].content_hash, card_hash);
+        assert_eq!(db.next_expiry_at(), Ok(None));
+
+        db.set_secret_policy(SecretKind::GithubToken, SecretAction::Mask)
+            .expect("secret policy should persist");
+        assert_eq!(db.insert_event(&event(vec![text(&token)])), Ok(true));
+        let settings = db.get_settings().expect("settings should load");
+        assert!(settings.secret_policies.contains(&SecretPolicySetting {
+            kind: SecretKind::GithubToken,
+            action: SecretAction::Mask,
+        }));
+        assert!(settings.secret_policies.contains(&SecretPolicySetting {
+            kind: SecretKind::Jwt,
+            action: SecretAction::Expire,
+        }));
+    }
+
     #[test]
     fn app_settings_include_current_history_and_resource_limits() {
         let db = in_memory_database();
@@ -3671,10 +8977,10 @@ mod tests {
     fn schema_initialization_removes_legacy_source_app_column() {
         let clipboard_event = event(vec![data("public.utf8-plain-text", b"legacy row")]);
         let event_blob = encode_event_blob(&clipboard_event).expect("event should encode");
-        let db = Database {
-            conn: Connection::open_in_memory().expect("in-memory database should open"),
-            path: None,
-        };
+        let db = uninitialized_database(
+            Connection::open_in_memory().expect("in-memory database should open"),
+            None,
+        );
         db.conn
             .execute_batch(
                 "CREATE TABLE clipboard_events (
@@ -4504,7 +9810,16 @@ mod tests {
     fn metadata_rebuild_removes_legacy_unsupported_rows() {
         let db = in_memory_database();
         let event = event(vec![data("com.example.private", b"opaque payload")]);
-        let event_blob = encode_event_blob(&event).expect("private event should encode");
+        let content_hash = "0".repeat(64);
+        let seal = |column, plaintext: &[u8]| {
+            db.cipher
+                .seal(&content_hash, column, plaintext)
+                .expect("fixture payload should seal")
+        };
+        let event_blob = seal(
+            PayloadColumn::EventData,
+            &encode_event_blob(&event).expect("private event should encode"),
+        );
 
         db.conn
             .execute(
@@ -4522,15 +9837,19 @@ mod tests {
                     is_remote_clipboard,
                     byte_count,
                     timestamp,
-                    metadata_version
+                    metadata_version,
+                    first_seen_at,
+                    last_seen_at,
+                    integrity_mac,
+                    integrity_link
                  )
-                 VALUES (?1, ?2, ?3, ?4, ?5, 0, NULL, NULL, NULL, 0, ?6, ?7, ?8)",
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, NULL, NULL, NULL, 0, ?6, ?7, ?8, ?7, ?7, x'', x'')",
                 params![
-                    "0".repeat(64),
+                    &content_hash,
                     event_blob,
                     "unsupported",
-                    b"Unsupported clipboard data".to_vec(),
-                    b"Unsupported clipboard data".to_vec(),
+                    seal(PayloadColumn::Display, b"Unsupported clipboard data"),
+                    seal(PayloadColumn::SummaryDisplay, b"Unsupported clipboard data"),
                     64_i64,
                     1_i64,
                     CLASSIFIER_METADATA_VERSION,
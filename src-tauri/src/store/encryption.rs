@@ -0,0 +1,367 @@
+//! Envelope encryption for the clipboard payload columns.
+//!
+//! Each database has one random 256-bit payload key. It is stored in the
+//! `payload_key` table wrapped with AES-256-GCM under a key-encryption key that
+//! a [`KeyProvider`] keeps outside the database, so a copied database file
+//! alone does not reveal clipboard contents. Payload columns are sealed with
+//! the payload key and bound to their row's `content_hash` and column name, so
+//! a sealed value cannot be moved to another row or column undetected.
+
+use crate::private_fs::{create_private_temp_file, read_private_file, resolve_private_path};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub(crate) const KEY_BYTES: usize = 32;
+pub(crate) const KEY_FILE_NAME: &str = "copy_stack.key";
+pub(crate) const INTEGRITY_KEY_FILE_NAME: &str = "copy_stack_integrity.key";
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+const SEALED_FORMAT_VERSION: u8 = 1;
+const WRAPPED_KEY_AAD: &[u8] = b"copy_stack:payload_key:v1";
+
+pub type KeyBytes = [u8; KEY_BYTES];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PayloadKeyError {
+    /// The provider could not read or store its key, or no randomness was
+    /// available.
+    Unavailable,
+    /// The database has a wrapped payload key but the provider has no key to
+    /// unwrap it with.
+    Missing,
+    /// A key or sealed value is malformed, or failed authentication.
+    Rejected,
+}
+
+impl fmt::Display for PayloadKeyError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Unavailable => "the payload key storage is unavailable",
+            Self::Missing => "the payload key-encryption key is missing",
+            Self::Rejected => "a payload key or sealed value failed authentication",
+        })
+    }
+}
+
+impl std::error::Error for PayloadKeyError {}
+
+/// Stores a key outside the database: the key-encryption key that wraps its
+/// payload key, or the key its history integrity chain is signed with.
+///
+/// [`FileKeyProvider`] is the portable implementation. An OS keychain provider
+/// implements the same two calls and is chosen when the database is opened.
+pub trait KeyProvider {
+    /// Returns the stored key, or `None` if none has been created yet.
+    fn load_key(&self) -> Result<Option<KeyBytes>, PayloadKeyError>;
+
+    /// Creates and durably stores a new key. Callers only call this after
+    /// `load_key` returned `None`; an existing key is never replaced.
+    fn create_key(&self) -> Result<KeyBytes, PayloadKeyError>;
+}
+
+/// Keeps a key in a private `0600` file beside the database.
+#[derive(Clone, Debug)]
+pub struct FileKeyProvider {
+    path: PathBuf,
+}
+
+impl FileKeyProvider {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn beside_database(database_path: &Path) -> Self {
+        Self::new(database_path.with_file_name(KEY_FILE_NAME))
+    }
+
+    pub fn integrity_key_beside_database(database_path: &Path) -> Self {
+        Self::new(database_path.with_file_name(INTEGRITY_KEY_FILE_NAME))
+    }
+}
+
+impl KeyProvider for FileKeyProvider {
+    fn load_key(&self) -> Result<Option<KeyBytes>, PayloadKeyError> {
+        let Some(contents) = read_private_file(&self.path, KEY_BYTES as u64)
+            .map_err(|_| PayloadKeyError::Unavailable)?
+        else {
+            return Ok(None);
+        };
+        contents
+            .try_into()
+            .map(Some)
+            .map_err(|_| PayloadKeyError::Rejected)
+    }
+
+    fn create_key(&self) -> Result<KeyBytes, PayloadKeyError> {
+        let path = resolve_private_path(&self.path).map_err(|_| PayloadKeyError::Unavailable)?;
+        if self.load_key()?.is_some() {
+            return Err(PayloadKeyError::Unavailable);
+        }
+        let key = random_key()?;
+        let mut temp =
+            create_private_temp_file(&path, 0).map_err(|_| PayloadKeyError::Unavailable)?;
+        temp.file_mut()
+            .write_all(&key)
+            .map_err(|_| PayloadKeyError::Unavailable)?;
+        temp.sync_all().map_err(|_| PayloadKeyError::Unavailable)?;
+        temp.commit(&path)
+            .map_err(|_| PayloadKeyError::Unavailable)?;
+        Ok(key)
+    }
+}
+
+/// Holds a key in memory only, for databases that never touch the disk.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryKeyProvider {
+    key: std::cell::Cell<Option<KeyBytes>>,
+}
+
+#[cfg(test)]
+impl MemoryKeyProvider {
+    pub fn with_key(key: KeyBytes) -> Self {
+        Self {
+            key: std::cell::Cell::new(Some(key)),
+        }
+    }
+}
+
+#[cfg(test)]
+impl KeyProvider for MemoryKeyProvider {
+    fn load_key(&self) -> Result<Option<KeyBytes>, PayloadKeyError> {
+        Ok(self.key.get())
+    }
+
+    fn create_key(&self) -> Result<KeyBytes, PayloadKeyError> {
+        let key = random_key()?;
+        self.key.set(Some(key));
+        Ok(key)
+    }
+}
+
+/// The sealed columns of a `clipboard_events` row, of its
+/// `clipboard_events_search` text, and of a `flavor_blobs` row, which is
+/// sealed under its digest instead of a `content_hash`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum PayloadColumn {
+    EventData,
+    Display,
+    SummaryDisplay,
+    CompactDisplay,
+    FlavorData,
+    SearchText,
+}
+
+impl PayloadColumn {
+    fn name(self) -> &'static str {
+        match self {
+            Self::EventData => "event_data",
+            Self::Display => "display",
+            Self::SummaryDisplay => "summary_display",
+            Self::CompactDisplay => "compact_display",
+            Self::FlavorData => "flavor_data",
+            Self::SearchText => "search_text",
+        }
+    }
+}
+
+/// Seals and opens payload columns with one database's payload key.
+#[derive(Clone)]
+pub(crate) struct PayloadCipher {
+    cipher: Aes256Gcm,
+}
+
+impl fmt::Debug for PayloadCipher {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("PayloadCipher")
+            .finish_non_exhaustive()
+    }
+}
+
+impl PayloadCipher {
+    /// Creates a random payload key and returns it with its wrapped form.
+    pub(crate) fn generate(
+        key_encryption_key: &KeyBytes,
+    ) -> Result<(Self, Vec<u8>), PayloadKeyError> {
+        let payload_key = random_key()?;
+        let wrapped = seal_with(
+            &cipher_for(key_encryption_key)?,
+            WRAPPED_KEY_AAD,
+            &payload_key,
+        )?;
+        Ok((Self::from_key(&payload_key)?, wrapped))
+    }
+
+    pub(crate) fn unwrap(
+        key_encryption_key: &KeyBytes,
+        wrapped: &[u8],
+    ) -> Result<Self, PayloadKeyError> {
+        let payload_key = open_with(&cipher_for(key_encryption_key)?, WRAPPED_KEY_AAD, wrapped)?;
+        let payload_key: KeyBytes = payload_key
+            .try_into()
+            .map_err(|_| PayloadKeyError::Rejected)?;
+        Self::from_key(&payload_key)
+    }
+
+    fn from_key(payload_key: &KeyBytes) -> Result<Self, PayloadKeyError> {
+        Ok(Self {
+            cipher: cipher_for(payload_key)?,
+        })
+    }
+
+    pub(crate) fn seal(
+        &self,
+        content_hash: &str,
+        column: PayloadColumn,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, PayloadKeyError> {
+        seal_with(&self.cipher, &column_aad(content_hash, column), plaintext)
+    }
+
+    pub(crate) fn open(
+        &self,
+        content_hash: &str,
+        column: PayloadColumn,
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, PayloadKeyError> {
+        open_with(&self.cipher, &column_aad(content_hash, column), sealed)
+    }
+}
+
+fn cipher_for(key: &KeyBytes) -> Result<Aes256Gcm, PayloadKeyError> {
+    Aes256Gcm::new_from_slice(key).map_err(|_| PayloadKeyError::Rejected)
+}
+
+fn column_aad(content_hash: &str, column: PayloadColumn) -> Vec<u8> {
+    format!("copy_stack:{}:{content_hash}", column.name()).into_bytes()
+}
+
+fn random_key() -> Result<KeyBytes, PayloadKeyError> {
+    let mut key = [0; KEY_BYTES];
+    getrandom::getrandom(&mut key).map_err(|_| PayloadKeyError::Unavailable)?;
+    Ok(key)
+}
+
+/// Encodes `version || nonce || ciphertext || tag` under a fresh random nonce.
+fn seal_with(cipher: &Aes256Gcm, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, PayloadKeyError> {
+    let mut nonce = [0; NONCE_BYTES];
+    getrandom::getrandom(&mut nonce).map_err(|_| PayloadKeyError::Unavailable)?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| PayloadKeyError::Rejected)?;
+
+    let mut sealed = Vec::with_capacity(1 + NONCE_BYTES + ciphertext.len());
+    sealed.push(SEALED_FORMAT_VERSION);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open_with(cipher: &Aes256Gcm, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, PayloadKeyError> {
+    let Some((&SEALED_FORMAT_VERSION, rest)) = sealed.split_first() else {
+        return Err(PayloadKeyError::Rejected);
+    };
+    if rest.len() < NONCE_BYTES + TAG_BYTES {
+        return Err(PayloadKeyError::Rejected);
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_BYTES);
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| PayloadKeyError::Rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const OTHER_HASH: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+    #[test]
+    fn sealed_payloads_open_only_for_their_row_and_column() {
+        let (cipher, wrapped) = PayloadCipher::generate(&[7; KEY_BYTES]).expect("key generates");
+        let sealed = cipher
+            .seal(HASH, PayloadColumn::Display, b"clipboard text")
+            .expect("payload seals");
+        assert!(!sealed
+            .windows(b"clipboard text".len())
+            .any(|window| window == b"clipboard text"));
+        assert_ne!(
+            sealed,
+            cipher
+                .seal(HASH, PayloadColumn::Display, b"clipboard text")
+                .expect("payload seals again"),
+            "each seal uses a fresh nonce"
+        );
+
+        let reopened = PayloadCipher::unwrap(&[7; KEY_BYTES], &wrapped).expect("key unwraps");
+        assert_eq!(
+            reopened.open(HASH, PayloadColumn::Display, &sealed),
+            Ok(b"clipboard text".to_vec())
+        );
+        assert_eq!(
+            reopened.open(OTHER_HASH, PayloadColumn::Display, &sealed),
+            Err(PayloadKeyError::Rejected)
+        );
+        assert_eq!(
+            reopened.open(HASH, PayloadColumn::SummaryDisplay, &sealed),
+            Err(PayloadKeyError::Rejected)
+        );
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().expect("sealed value is not empty") ^= 1;
+        assert_eq!(
+            reopened.open(HASH, PayloadColumn::Display, &tampered),
+            Err(PayloadKeyError::Rejected)
+        );
+        assert_eq!(
+            reopened.open(HASH, PayloadColumn::Display, b"clipboard text"),
+            Err(PayloadKeyError::Rejected)
+        );
+        assert!(PayloadCipher::unwrap(&[8; KEY_BYTES], &wrapped).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_key_provider_creates_one_private_key_and_never_replaces_it() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = std::env::temp_dir().join(format!(
+            "copy-stack-key-provider-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir(&directory).expect("test directory should be created");
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o700))
+            .expect("test directory should be private");
+        let provider = FileKeyProvider::beside_database(&directory.join("copy_stack.db"));
+
+        assert_eq!(provider.load_key(), Ok(None));
+        let key = provider.create_key().expect("key should be created");
+        assert_eq!(provider.load_key(), Ok(Some(key)));
+        assert_eq!(provider.create_key(), Err(PayloadKeyError::Unavailable));
+        let metadata =
+            std::fs::metadata(directory.join(KEY_FILE_NAME)).expect("key file should exist");
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(metadata.len(), KEY_BYTES as u64);
+
+        std::fs::write(directory.join(KEY_FILE_NAME), b"short").expect("key should be replaced");
+        assert_eq!(provider.load_key(), Err(PayloadKeyError::Rejected));
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
@@ -0,0 +1,17 @@
+PRAGMA user_version = 0;
+CREATE TABLE clipboard_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_data TEXT NOT NULL,
+                data_type TEXT NOT NULL,
+                display TEXT NOT NULL,
+                sort_order INTEGER NOT NULL,
+                timestamp TEXT NOT NULL
+             );
+CREATE TABLE settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+             );
+INSERT INTO settings (key, value) VALUES ('max_items', '50');
+INSERT INTO clipboard_events (id, event_data, data_type, display, sort_order, timestamp) VALUES (1, '{"items":[{"data_list":[{"data":[103,111,108,100,101,110,32,102,105,120,116,117,114,101,32,97,108,112,104,97],"type":"public.utf8-plain-text"},{"data":[99,111,109,46,101,120,97,109,112,108,101,46,101,100,105,116,111,114],"type":"org.nspasteboard.source"}]}]}', 'text', 'golden fixture alpha', 1, '2023-11-14T22:13:20Z');
+INSERT INTO clipboard_events (id, event_data, data_type, display, sort_order, timestamp) VALUES (2, '{"items":[{"data_list":[{"data":[103,111,108,100,101,110,32,102,105,120,116,117,114,101,32,98,101,116,97],"type":"public.utf8-plain-text"}]}]}', 'text', 'golden fixture beta', 2, '2023-11-14T22:15:00Z');
+INSERT INTO clipboard_events (id, event_data, data_type, display, sort_order, timestamp) VALUES (3, '{"items":[{"data_list":[{"data":[103,111,108,100,101,110,32,102,105,120,116,117,114,101,32,103,97,109,109,97],"type":"public.utf8-plain-text"}]}]}', 'text', 'golden fixture gamma', 3, '2023-11-14T22:16:40Z');
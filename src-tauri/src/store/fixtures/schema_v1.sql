@@ -0,0 +1,16 @@
+PRAGMA user_version = 1;
+CREATE TABLE clipboard_events (
+                content_hash TEXT PRIMARY KEY,
+                event_data BLOB NOT NULL,
+                data_type TEXT NOT NULL,
+                display BLOB NOT NULL,
+                timestamp INTEGER NOT NULL
+             );
+CREATE TABLE settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+             );
+INSERT INTO settings (key, value) VALUES ('max_items', '50');
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, timestamp) VALUES ('bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', X'435342310100000002000000160000007075626C69632E757466382D706C61696E2D746578741400000000000000676F6C64656E206669787475726520616C706861170000006F72672E6E737061737465626F6172642E736F757263651200000000000000636F6D2E6578616D706C652E656469746F72', 'text', X'676F6C64656E206669787475726520616C706861', 1700000000000);
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, timestamp) VALUES ('0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', X'435342310100000001000000160000007075626C69632E757466382D706C61696E2D746578741300000000000000676F6C64656E20666978747572652062657461', 'text', X'676F6C64656E20666978747572652062657461', 1700000100000);
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, timestamp) VALUES ('7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', X'435342310100000001000000160000007075626C69632E757466382D706C61696E2D746578741400000000000000676F6C64656E20666978747572652067616D6D61', 'text', X'676F6C64656E20666978747572652067616D6D61', 1700000200000);
@@ -0,0 +1,160 @@
+PRAGMA user_version = 11;
+CREATE TABLE payload_key (
+            key_id INTEGER PRIMARY KEY CHECK (key_id = 1),
+            wrapped_key BLOB NOT NULL
+        );
+CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+CREATE TABLE app_metadata (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );
+CREATE TABLE clipboard_events (
+                content_hash TEXT PRIMARY KEY,
+                event_data BLOB NOT NULL,
+                data_type TEXT NOT NULL,
+                display BLOB NOT NULL,
+                summary_display BLOB NOT NULL,
+                summary_truncated INTEGER NOT NULL,
+                compact_content_hash TEXT,
+                compact_display BLOB,
+                source_bundle_id TEXT,
+                is_remote_clipboard INTEGER NOT NULL,
+                byte_count INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                metadata_version INTEGER NOT NULL,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                first_seen_at INTEGER NOT NULL,
+                last_seen_at INTEGER NOT NULL,
+                copy_count INTEGER NOT NULL DEFAULT 1,
+                expires_at INTEGER,
+                integrity_mac BLOB NOT NULL,
+                integrity_link BLOB NOT NULL
+            );
+CREATE TABLE clipboard_events_search (
+            search_id INTEGER PRIMARY KEY,
+            content_hash TEXT NOT NULL UNIQUE,
+            body TEXT NOT NULL
+        );
+CREATE VIRTUAL TABLE clipboard_events_fts USING fts5(
+            body,
+            content = 'clipboard_events_search',
+            content_rowid = 'search_id',
+            tokenize = 'trigram'
+        );
+CREATE TABLE collections (
+            collection_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+CREATE TABLE collection_items (
+            collection_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            PRIMARY KEY (collection_id, content_hash)
+        );
+CREATE TABLE tags (
+            tag_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+CREATE TABLE item_tags (
+            tag_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            PRIMARY KEY (tag_id, content_hash)
+        );
+CREATE TABLE history_integrity (
+            key_id INTEGER PRIMARY KEY CHECK (key_id = 1),
+            head BLOB NOT NULL
+        );
+CREATE TABLE quarantined_events (
+            quarantine_id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL,
+            quarantined_at INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            event_data BLOB NOT NULL,
+            data_type TEXT NOT NULL,
+            display BLOB NOT NULL,
+            summary_display BLOB NOT NULL,
+            summary_truncated INTEGER NOT NULL,
+            compact_content_hash TEXT,
+            compact_display BLOB,
+            source_bundle_id TEXT,
+            is_remote_clipboard INTEGER NOT NULL,
+            byte_count INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            metadata_version INTEGER NOT NULL,
+            pinned INTEGER NOT NULL,
+            first_seen_at INTEGER NOT NULL,
+            last_seen_at INTEGER NOT NULL,
+            copy_count INTEGER NOT NULL,
+            expires_at INTEGER
+        );
+CREATE TABLE flavor_blobs (
+            digest TEXT PRIMARY KEY,
+            data BLOB NOT NULL,
+            byte_count INTEGER NOT NULL,
+            ref_count INTEGER NOT NULL
+        );
+CREATE TABLE flavor_blob_refs (
+            content_hash TEXT NOT NULL,
+            digest TEXT NOT NULL,
+            PRIMARY KEY (content_hash, digest)
+        );
+CREATE TRIGGER clipboard_events_search_after_insert
+        AFTER INSERT ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;
+CREATE TRIGGER clipboard_events_search_after_delete
+        AFTER DELETE ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+        END;
+CREATE TRIGGER clipboard_events_search_after_update
+        AFTER UPDATE ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;
+INSERT INTO settings (key, value) VALUES ('max_history_bytes', '268435456');
+INSERT INTO settings (key, value) VALUES ('show_in_menu_bar', 'true');
+INSERT INTO settings (key, value) VALUES ('menu_bar_item_limit', '0');
+INSERT INTO settings (key, value) VALUES ('move_restored_item_to_top', 'false');
+INSERT INTO settings (key, value) VALUES ('move_recopied_item_to_top', 'false');
+INSERT INTO settings (key, value) VALUES ('compact_mode', 'false');
+INSERT INTO settings (key, value) VALUES ('language', 'system');
+INSERT INTO settings (key, value) VALUES ('max_age_days', '0');
+INSERT INTO settings (key, value) VALUES ('source_allow_only', 'false');
+INSERT INTO settings (key, value) VALUES ('max_items', '50');
+INSERT INTO app_metadata (key, value) VALUES ('classifier_metadata_version', 1);
+INSERT INTO app_metadata (key, value) VALUES ('payload_encryption_version', 1);
+INSERT INTO payload_key (key_id, wrapped_key) VALUES (1, X'01AFF7067420F2289EE80962609D3437536D504D83AA8DD28487732EC98AA7047576E4F2A0A26F7D0BA9F40A0BF2395863BEB70305B7B1043918DEF9AF');
+INSERT INTO history_integrity (key_id, head) VALUES (1, X'90AF5318C7B717506E2FE58D16B995B14E5B8FD6A6827DDD26966D71D363C00D');
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version, pinned, first_seen_at, last_seen_at, copy_count, expires_at, integrity_mac, integrity_link) VALUES ('bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', X'0173E94D490CD206E8CE473C2C328258959351D042659E9EA7AA72E595FC9C18C2F0090EE92396547B204027EF6C94E37CAF66BFA2D12CD186C496721DF36BDA92505AB5ECE33854BC620CBA3699254C1BD7939D1DF10080D24BCC65AA27C56C3FDA2794296F0BBB54A69E40C4A164233EB442E98D6DF6DCAF1EC53F571FC296EEB72277867012954EDF3240E2A40654F2C0E4C2', 'text', X'013F4D651BEF9825C2D397F3E6C324E06141474DD21B25A6171F0BF540C509FB9571FFD76FD15A5F3C09461E639129620B', X'01D1ED8241F78869B544C4BC8C539BA1E5E24D552BB0BBAF71406FAD451A5386FFC099BA30E4A8398D90C7B8F53148FEDE', 0, 'bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', X'0178DCBE91FC7E37B487C0B54E139840E6F88108F25AA10579DAFC88184A0A532E05501D30444576BE57CC399C4A7BD330', 'com.example.editor', 0, 197, 1700000000000, 1, 0, 1700000000000, 1700000000000, 1, NULL, X'AEC6DD611AC79CE3923A1024DCC282F5934EE41D2F1D385A49F9F265F20FDBAC', X'C36B9771AD4EBAAEC0551B05FCB08691BC4953B222485A2E2B4E8BAB706DE88A');
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version, pinned, first_seen_at, last_seen_at, copy_count, expires_at, integrity_mac, integrity_link) VALUES ('0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', X'01EB5C1BE08F221BA7907CC89535CD454E96FCA6190117AA707698B9AAC2D6A310CA314458D0ED9CA77ED001096968020DE977F915B99AF3D367C5D172405ADF172BA6A74171F698EF71460F1A1ECAE33725B92ACD2FE39B8EDA95B6F783', 'text', X'01C875FA957FEE0B52F54B8AB02F000D792EF33F6AF36AD5A0C94023D94721C8C70F48C79A81D141F54A8065EEAFEE0F', X'014B25433592299DFF0FAC02BE28804815A93E6474E6EBE5E8E29A4E61C8157DBA3BD124CCD01EBF60CB005BB370FCDF', 0, '0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', X'01243BD8996AC8E1B07DFB994C9DBA4A3CF6D278D4194A013795A2A1726C978302FEFE99F9E0CEE101181D35E127890B', NULL, 0, 122, 1700000100000, 1, 1, 1699990000000, 1700000100000, 3, NULL, X'85768BE90C57035787A6E48BC2D0873149E655A4CBD266A23995E75ED46FDE5D', X'9DC13B4ECE110FB3536E3514EE9EEA1954FB7E9BD4C14181F04F675DB323A7B8');
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version, pinned, first_seen_at, last_seen_at, copy_count, expires_at, integrity_mac, integrity_link) VALUES ('7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', X'0113E647E365378EA3E549A3C44340337416821EFDAD346D4423A75CDA66E0F632B81B82134420BEBEC296589CDAE896EAE01538057ABB1F4100962075179C5E6F10CF41EFFFE8BEBA6C35636DBD4D10A07468CD72315E27ABCCA2F55B0142', 'text', X'01726D95BD33E45066BE57C0CC796893FAD59FAED6FBE57917296FFA8CDD2C80C90CFE3EC8C0EB6B70065AEE2A304477FD', X'016B8E720758748029B541FEBF7040AA3BE982A3F356446680E70DD54967182EA20C71EFEA1999A1B7FA5C03505B2C474E', 0, '7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', X'012DC81F291AF3DBC5522A613C3A2B1AA52AA4A94214E7222F80F1A05A47B74F858CCB647368A02EC4A329631DBBCA47B5', NULL, 0, 126, 1700000200000, 1, 0, 1700000200000, 1700000200000, 1, 4102444800000, X'F039C8830FA3969DD05E8592E7C9BB6CFC42127BC7D1A0E0117BDAED26D90268', X'C033B44E52FDC49F08E335B3FAECE036BA38181767ABFE10EA9F18AF48A18A2D');
+INSERT INTO clipboard_events_search (search_id, content_hash, body) VALUES (1, 'bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', 'golden fixture alpha');
+INSERT INTO clipboard_events_search (search_id, content_hash, body) VALUES (2, '0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', 'golden fixture beta');
+INSERT INTO clipboard_events_search (search_id, content_hash, body) VALUES (3, '7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', 'golden fixture gamma');
+INSERT INTO collections (collection_id, name) VALUES (1, 'Golden');
+INSERT INTO collection_items (collection_id, content_hash) VALUES (1, 'bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4');
+INSERT INTO tags (tag_id, name) VALUES (1, 'fixture');
+INSERT INTO item_tags (tag_id, content_hash) VALUES (1, '0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797');
+CREATE INDEX idx_collection_items_content_hash
+            ON collection_items(content_hash);
+CREATE INDEX idx_item_tags_content_hash
+            ON item_tags(content_hash);
+CREATE INDEX idx_clipboard_events_timestamp
+         ON clipboard_events(timestamp DESC, content_hash ASC);
+CREATE INDEX idx_clipboard_events_compact
+         ON clipboard_events(
+             compact_content_hash,
+             timestamp DESC,
+             content_hash ASC
+         )
+         WHERE compact_content_hash IS NOT NULL;
+CREATE INDEX idx_clipboard_events_pinned
+         ON clipboard_events(pinned DESC, timestamp DESC, content_hash ASC);
+CREATE INDEX idx_flavor_blob_refs_digest
+            ON flavor_blob_refs(digest);
@@ -0,0 +1,46 @@
+PRAGMA user_version = 2;
+CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+CREATE TABLE app_metadata (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );
+CREATE TABLE clipboard_events (
+                content_hash TEXT PRIMARY KEY,
+                event_data BLOB NOT NULL,
+                data_type TEXT NOT NULL,
+                display BLOB NOT NULL,
+                summary_display BLOB NOT NULL,
+                summary_truncated INTEGER NOT NULL,
+                compact_content_hash TEXT,
+                compact_display BLOB,
+                source_bundle_id TEXT,
+                is_remote_clipboard INTEGER NOT NULL,
+                byte_count INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                metadata_version INTEGER NOT NULL);
+INSERT INTO settings (key, value) VALUES ('max_history_bytes', '268435456');
+INSERT INTO settings (key, value) VALUES ('show_in_menu_bar', 'true');
+INSERT INTO settings (key, value) VALUES ('menu_bar_item_limit', '0');
+INSERT INTO settings (key, value) VALUES ('move_restored_item_to_top', 'false');
+INSERT INTO settings (key, value) VALUES ('move_recopied_item_to_top', 'false');
+INSERT INTO settings (key, value) VALUES ('compact_mode', 'false');
+INSERT INTO settings (key, value) VALUES ('language', 'system');
+INSERT INTO settings (key, value) VALUES ('max_age_days', '0');
+INSERT INTO settings (key, value) VALUES ('source_allow_only', 'false');
+INSERT INTO settings (key, value) VALUES ('max_items', '50');
+INSERT INTO app_metadata (key, value) VALUES ('classifier_metadata_version', 1);
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version) VALUES ('bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', X'435342310100000002000000160000007075626C69632E757466382D706C61696E2D746578741400000000000000676F6C64656E206669787475726520616C706861170000006F72672E6E737061737465626F6172642E736F757263651200000000000000636F6D2E6578616D706C652E656469746F72', 'text', X'676F6C64656E206669787475726520616C706861', X'676F6C64656E206669787475726520616C706861', 0, 'bcb40c4e1ad7789622f7aef87bd9331b6768ef2f4391b81537a774a4dd841aa4', X'676F6C64656E206669787475726520616C706861', 'com.example.editor', 0, 197, 1700000000000, 1);
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version) VALUES ('0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', X'435342310100000001000000160000007075626C69632E757466382D706C61696E2D746578741300000000000000676F6C64656E20666978747572652062657461', 'text', X'676F6C64656E20666978747572652062657461', X'676F6C64656E20666978747572652062657461', 0, '0a5dab23f338e524863deb4a6db5e28ba515949548dd83c4ec193c06f8ae0797', X'676F6C64656E20666978747572652062657461', NULL, 0, 122, 1700000100000, 1);
+INSERT INTO clipboard_events (content_hash, event_data, data_type, display, summary_display, summary_truncated, compact_content_hash, compact_display, source_bundle_id, is_remote_clipboard, byte_count, timestamp, metadata_version) VALUES ('7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', X'435342310100000001000000160000007075626C69632E757466382D706C61696E2D746578741400000000000000676F6C64656E20666978747572652067616D6D61', 'text', X'676F6C64656E20666978747572652067616D6D61', X'676F6C64656E20666978747572652067616D6D61', 0, '7dce804764418147fe1d93d18cf53b7d7fc21dc7700b7122ef8c454240a37214', X'676F6C64656E20666978747572652067616D6D61', NULL, 0, 126, 1700000200000, 1);
+CREATE INDEX idx_clipboard_events_timestamp
+         ON clipboard_events(timestamp DESC, content_hash ASC);
+CREATE INDEX idx_clipboard_events_compact
+         ON clipboard_events(
+             compact_content_hash,
+             timestamp DESC,
+             content_hash ASC
+         )
+         WHERE compact_content_hash IS NOT NULL;
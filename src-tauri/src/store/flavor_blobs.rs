@@ -0,0 +1,335 @@
+//! Flavor payloads stored once and shared between history rows.
+//!
+//! `share_large_flavors` replaces each large binary flavor of a row's event
+//! blob with its SHA-256 digest. The payload is stored once in `flavor_blobs`,
+//! sealed under that digest, and `flavor_blob_refs` records which rows
+//! reference it. Every path that writes or deletes a history row updates its
+//! references in the same transaction, and a payload is deleted with its last
+//! reference.
+//!
+//! A row's `byte_count` includes an equal share of every payload it
+//! references, so summing `byte_count` over history still measures what is
+//! stored, and deleting a row reclaims only what it alone kept alive.
+//!
+//! Payloads of at least `MIN_FILE_BYTES` in an on-disk database are written
+//! to `store::blob_files` instead, leaving `flavor_blobs.data` empty, and
+//! `flavor_blob_files` lists their digests. Deleting such a payload only
+//! drops its rows; `remove_unreferenced_files_in` removes the file once the
+//! transaction has committed, so a rolled-back delete never loses a file.
+//! Files a database snapshot lists are kept until the snapshot is rotated
+//! away, so restoring it finds its payloads.
+
+use crate::event::{
+    inline_shared_flavors, EventBlobFlavor, EventBlobIndex, SharedFlavor, SHARED_DIGEST_BYTES,
+};
+use crate::store::backups;
+use crate::store::blob_files;
+use crate::store::encryption::{PayloadCipher, PayloadColumn};
+use rusqlite::{params, Connection, OptionalExtension, Result, Transaction, TransactionBehavior};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Payloads this large are kept in files rather than in the database.
+const MIN_FILE_BYTES: usize = 1024 * 1024;
+
+/// References every payload in `shared` from the row `content_hash`, storing
+/// the payloads not stored yet. The row must already be written.
+pub(super) fn retain_in(
+    connection: &Connection,
+    cipher: &PayloadCipher,
+    content_hash: &str,
+    shared: &[SharedFlavor],
+) -> Result<()> {
+    let mut retained = HashSet::new();
+    for flavor in shared {
+        let digest = digest_key(&flavor.digest);
+        if !retained.insert(digest.clone()) {
+            continue;
+        }
+        let stored = connection
+            .query_row(
+                "SELECT byte_count, ref_count FROM flavor_blobs WHERE digest = ?1",
+                [&digest],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+        let (byte_count, ref_count) = match stored {
+            Some(stored) => stored,
+            None => {
+                let byte_count = flavor.data.len() as i64;
+                let mut sealed = cipher
+                    .seal(&digest, PayloadColumn::FlavorData, &flavor.data)
+                    .map_err(|_| {
+                        rusqlite::Error::InvalidParameterName(
+                            "clipboard payload could not be encrypted".to_string(),
+                        )
+                    })?;
+                if let Some(database_path) =
+                    database_path_in(connection).filter(|_| flavor.data.len() >= MIN_FILE_BYTES)
+                {
+                    blob_files::write_blob_file(&database_path, &digest, &sealed)?;
+                    connection.execute(
+                        "INSERT INTO flavor_blob_files (digest) VALUES (?1)",
+                        [&digest],
+                    )?;
+                    sealed.clear();
+                }
+                connection.execute(
+                    "INSERT INTO flavor_blobs (digest, data, byte_count, ref_count)
+                     VALUES (?1, ?2, ?3, 0)",
+                    params![&digest, sealed, byte_count],
+                )?;
+                (byte_count, 0)
+            }
+        };
+
+        reshare_in(connection, &digest, byte_count, ref_count, ref_count + 1)?;
+        connection.execute(
+            "INSERT INTO flavor_blob_refs (content_hash, digest) VALUES (?1, ?2)",
+            params![content_hash, &digest],
+        )?;
+        connection.execute(
+            "UPDATE clipboard_events SET byte_count = byte_count + ?1 WHERE content_hash = ?2",
+            params![share(byte_count, ref_count + 1), content_hash],
+        )?;
+    }
+    Ok(())
+}
+
+/// Drops every reference the row `content_hash` holds, deleting payloads no
+/// other row references. Runs before the row is deleted or rewritten.
+pub(super) fn release_in(connection: &Connection, content_hash: &str) -> Result<()> {
+    let referenced = {
+        let mut statement = connection.prepare(
+            "SELECT flavor_blobs.digest, flavor_blobs.byte_count, flavor_blobs.ref_count
+             FROM flavor_blob_refs
+             JOIN flavor_blobs ON flavor_blobs.digest = flavor_blob_refs.digest
+             WHERE flavor_blob_refs.content_hash = ?1",
+        )?;
+        let referenced = statement
+            .query_map([content_hash], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        referenced
+    };
+
+    for (digest, byte_count, ref_count) in referenced {
+        connection.execute(
+            "UPDATE clipboard_events SET byte_count = byte_count - ?1 WHERE content_hash = ?2",
+            params![share(byte_count, ref_count), content_hash],
+        )?;
+        connection.execute(
+            "DELETE FROM flavor_blob_refs WHERE content_hash = ?1 AND digest = ?2",
+            params![content_hash, &digest],
+        )?;
+        reshare_in(connection, &digest, byte_count, ref_count, ref_count - 1)?;
+    }
+    connection.execute(
+        "DELETE FROM flavor_blob_refs WHERE content_hash = ?1",
+        [content_hash],
+    )?;
+    Ok(())
+}
+
+/// Deletes every payload and reference, for paths that clear or rebuild the
+/// whole history.
+pub(super) fn clear_in(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "DELETE FROM flavor_blob_refs;
+         DELETE FROM flavor_blobs;
+         DELETE FROM flavor_blob_files;",
+    )
+}
+
+/// Removes every file in the blob directory that neither `flavor_blob_files`
+/// nor a listed snapshot references, and returns how many were removed.
+///
+/// Call it outside a transaction. It holds the write lock while listing and
+/// removing, so a file another connection wrote for a transaction it has not
+/// committed yet is listed by the time it is checked.
+pub(super) fn remove_unreferenced_files_in(connection: &Connection) -> Result<u64> {
+    let Some(database_path) = database_path_in(connection) else {
+        return Ok(0);
+    };
+    if blob_files::blob_file_names(&database_path)?.is_empty() {
+        return Ok(0);
+    }
+    let transaction = Transaction::new_unchecked(connection, TransactionBehavior::Immediate)?;
+    let names = blob_files::blob_file_names(&database_path)?;
+    let mut kept = file_digests_in(&transaction)?;
+    if names.iter().any(|name| !kept.contains(name)) {
+        kept.extend(backups::blob_file_digests(&database_path)?);
+    }
+    let mut removed = 0;
+    for name in names {
+        if !kept.contains(&name) {
+            blob_files::remove_blob_file(&database_path, &name)?;
+            removed += 1;
+        }
+    }
+    transaction.commit()?;
+    Ok(removed)
+}
+
+/// Returns the digests `flavor_blob_files` lists whose file is missing.
+pub(super) fn missing_files_in(connection: &Connection) -> Result<Vec<String>> {
+    let listed = file_digests_in(connection)?;
+    if listed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let present = match database_path_in(connection) {
+        Some(database_path) => blob_files::blob_file_names(&database_path)?
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        None => HashSet::new(),
+    };
+    let mut missing = listed
+        .into_iter()
+        .filter(|digest| !present.contains(digest))
+        .collect::<Vec<_>>();
+    missing.sort();
+    Ok(missing)
+}
+
+/// Returns the rows referencing any payload in `digests`.
+pub(super) fn referencing_rows_in(
+    connection: &Connection,
+    digests: &[String],
+) -> Result<Vec<String>> {
+    let mut statement = connection.prepare(
+        "SELECT content_hash FROM flavor_blob_refs WHERE digest = ?1 ORDER BY content_hash",
+    )?;
+    let mut rows = Vec::new();
+    for digest in digests {
+        for content_hash in statement.query_map([digest], |row| row.get::<_, String>(0))? {
+            let content_hash = content_hash?;
+            if !rows.contains(&content_hash) {
+                rows.push(content_hash);
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Deletes the payloads in `digests` along with any reference left to them,
+/// once the rows that needed them are gone.
+pub(super) fn forget_in(connection: &Connection, digests: &[String]) -> Result<()> {
+    for digest in digests {
+        connection.execute("DELETE FROM flavor_blob_refs WHERE digest = ?1", [digest])?;
+        connection.execute("DELETE FROM flavor_blobs WHERE digest = ?1", [digest])?;
+        connection.execute("DELETE FROM flavor_blob_files WHERE digest = ?1", [digest])?;
+    }
+    Ok(())
+}
+
+/// Returns `event_data` with every shared flavor's payload back in place.
+/// Blobs without shared flavors, including ones that do not parse, are
+/// returned as-is for their decoder to judge.
+pub(super) fn inline_in(
+    connection: &Connection,
+    cipher: &PayloadCipher,
+    event_data: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let references = EventBlobIndex::parse(&event_data)
+        .is_ok_and(|index| index.flavors().iter().any(EventBlobFlavor::is_shared));
+    if !references {
+        return Ok(event_data);
+    }
+
+    inline_shared_flavors(event_data, |digest| {
+        let digest = digest_key(digest);
+        let (sealed, in_file) = connection
+            .query_row(
+                "SELECT data, digest IN (SELECT digest FROM flavor_blob_files)
+                 FROM flavor_blobs
+                 WHERE digest = ?1",
+                [&digest],
+                |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, bool>(1)?)),
+            )
+            .optional()
+            .map_err(|error| error.to_string())?
+            .ok_or_else(|| "shared flavor is missing".to_string())?;
+        let sealed = if in_file {
+            database_path_in(connection)
+                .map(|database_path| blob_files::read_blob_file(&database_path, &digest))
+                .transpose()
+                .map_err(|error| error.to_string())?
+                .flatten()
+                .ok_or_else(|| "shared flavor file is missing".to_string())?
+        } else {
+            sealed
+        };
+        cipher
+            .open(&digest, PayloadColumn::FlavorData, &sealed)
+            .map_err(|_| "shared flavor could not be decrypted".to_string())
+    })
+    .map_err(|_| {
+        rusqlite::Error::InvalidParameterName(
+            "clipboard payload shared flavor could not be loaded".to_string(),
+        )
+    })
+}
+
+/// Moves the payload `digest` from `from` to `to` references, adjusting the
+/// share every referencing row accounts for. A payload left unreferenced is
+/// deleted.
+fn reshare_in(
+    connection: &Connection,
+    digest: &str,
+    byte_count: i64,
+    from: i64,
+    to: i64,
+) -> Result<()> {
+    if to <= 0 {
+        connection.execute("DELETE FROM flavor_blobs WHERE digest = ?1", [digest])?;
+        connection.execute("DELETE FROM flavor_blob_files WHERE digest = ?1", [digest])?;
+        return Ok(());
+    }
+    connection.execute(
+        "UPDATE clipboard_events
+         SET byte_count = byte_count - ?1 + ?2
+         WHERE content_hash IN (
+             SELECT content_hash FROM flavor_blob_refs WHERE digest = ?3
+         )",
+        params![share(byte_count, from), share(byte_count, to), digest],
+    )?;
+    connection.execute(
+        "UPDATE flavor_blobs SET ref_count = ?1 WHERE digest = ?2",
+        params![to, digest],
+    )?;
+    Ok(())
+}
+
+fn file_digests_in(connection: &Connection) -> Result<HashSet<String>> {
+    let mut statement = connection.prepare("SELECT digest FROM flavor_blob_files")?;
+    let digests = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<HashSet<_>>>()?;
+    Ok(digests)
+}
+
+/// Returns the file of the connection's main database, or `None` for an
+/// in-memory database, whose payloads all stay in `flavor_blobs`.
+fn database_path_in(connection: &Connection) -> Option<PathBuf> {
+    connection
+        .path()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn share(byte_count: i64, ref_count: i64) -> i64 {
+    if ref_count <= 0 {
+        0
+    } else {
+        byte_count / ref_count
+    }
+}
+
+fn digest_key(digest: &[u8; SHARED_DIGEST_BYTES]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
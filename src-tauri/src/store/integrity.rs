@@ -0,0 +1,506 @@
+//! Tamper evidence for stored history.
+//!
+//! Every `clipboard_events` row carries an HMAC-SHA256 (`integrity_mac`) over
+//! its `content_hash`, decrypted `event_data`, ordering `timestamp`, and
+//! source metadata, and a second HMAC (`integrity_link`) binding that value to
+//! the MAC of the row before it in history order. `history_integrity.head`
+//! binds the newest row's MAC. Editing, inserting, reordering, or removing a
+//! row without the key therefore breaks a MAC, a link, or the head.
+//!
+//! The HMAC key is derived from a dedicated integrity key that a key provider
+//! keeps in its own private file, apart from the key-encryption key, and never
+//! in the database. Writes relink only the rows around the positions they
+//! touched, so a mutation never re-signs tampering elsewhere in the chain.
+
+use crate::store::encryption::{KeyBytes, PayloadCipher, PayloadColumn, PayloadKeyError};
+use crate::store::models::{IntegrityResult, IntegrityViolation};
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use sha2::Sha256;
+use std::fmt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const KEY_DOMAIN: &[u8] = b"copy_stack:history_integrity_key:v1";
+const ROW_DOMAIN: &[u8] = b"copy_stack:history_row:v1";
+const LINK_DOMAIN: &[u8] = b"copy_stack:history_link:v1";
+const HEAD_DOMAIN: &[u8] = b"copy_stack:history_head:v1";
+
+// History order is oldest first by timestamp, ties broken by descending hash:
+// the reverse of the paging order and the order eviction walks.
+const PREVIOUS_ROW_MAC: &str = "SELECT integrity_mac FROM clipboard_events
+     WHERE timestamp <= ?1 AND (timestamp < ?1 OR content_hash > ?2)
+     ORDER BY timestamp DESC, content_hash ASC
+     LIMIT 1";
+const NEXT_ROW: &str = "SELECT content_hash, timestamp, integrity_mac FROM clipboard_events
+     WHERE timestamp >= ?1 AND (timestamp > ?1 OR content_hash < ?2)
+     ORDER BY timestamp ASC, content_hash DESC
+     LIMIT 1";
+const NEWEST_ROW_MAC: &str = "SELECT integrity_mac FROM clipboard_events
+     ORDER BY timestamp DESC, content_hash ASC
+     LIMIT 1";
+
+#[derive(Clone)]
+pub(crate) struct HistoryIntegrityKey {
+    mac: HmacSha256,
+}
+
+impl fmt::Debug for HistoryIntegrityKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("HistoryIntegrityKey([redacted])")
+    }
+}
+
+/// The fields of a row that its MAC covers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct IntegrityFields<'a> {
+    pub content_hash: &'a str,
+    pub event_data: &'a [u8],
+    pub timestamp: i64,
+    pub source_bundle_id: Option<&'a str>,
+    pub is_remote_clipboard: bool,
+}
+
+/// Where a row sits, or sat before it moved or was deleted, in history order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ChainPosition {
+    pub timestamp: i64,
+    pub content_hash: String,
+}
+
+impl HistoryIntegrityKey {
+    pub(crate) fn derive(key_encryption_key: &KeyBytes) -> Result<Self, PayloadKeyError> {
+        let subkey = keyed(key_encryption_key)?
+            .chain_update(KEY_DOMAIN)
+            .finalize()
+            .into_bytes();
+        Ok(Self {
+            mac: keyed(&subkey)?,
+        })
+    }
+
+    pub(crate) fn row_mac(&self, row: &IntegrityFields<'_>) -> Vec<u8> {
+        self.row_tag(row).finalize().into_bytes().to_vec()
+    }
+
+    fn row_tag(&self, row: &IntegrityFields<'_>) -> HmacSha256 {
+        self.tag(
+            ROW_DOMAIN,
+            &[
+                row.content_hash.as_bytes(),
+                row.event_data,
+                &row.timestamp.to_le_bytes(),
+                &[u8::from(row.source_bundle_id.is_some())],
+                row.source_bundle_id.unwrap_or_default().as_bytes(),
+                &[u8::from(row.is_remote_clipboard)],
+            ],
+        )
+    }
+
+    fn link(&self, previous_mac: Option<&[u8]>, row_mac: &[u8]) -> Vec<u8> {
+        self.link_tag(previous_mac, row_mac)
+            .finalize()
+            .into_bytes()
+            .to_vec()
+    }
+
+    fn link_tag(&self, previous_mac: Option<&[u8]>, row_mac: &[u8]) -> HmacSha256 {
+        self.tag(
+            LINK_DOMAIN,
+            &[
+                &[u8::from(previous_mac.is_some())],
+                previous_mac.unwrap_or_default(),
+                row_mac,
+            ],
+        )
+    }
+
+    fn head(&self, newest_mac: Option<&[u8]>) -> Vec<u8> {
+        self.head_tag(newest_mac).finalize().into_bytes().to_vec()
+    }
+
+    fn head_tag(&self, newest_mac: Option<&[u8]>) -> HmacSha256 {
+        self.tag(
+            HEAD_DOMAIN,
+            &[
+                &[u8::from(newest_mac.is_some())],
+                newest_mac.unwrap_or_default(),
+            ],
+        )
+    }
+
+    /// Length-prefixes every part so no two field lists share an input.
+    fn tag(&self, domain: &[u8], parts: &[&[u8]]) -> HmacSha256 {
+        let mut mac = self.mac.clone();
+        mac.update(domain);
+        for part in parts {
+            mac.update(&(part.len() as u64).to_le_bytes());
+            mac.update(part);
+        }
+        mac
+    }
+}
+
+fn keyed(key: &[u8]) -> Result<HmacSha256, PayloadKeyError> {
+    HmacSha256::new_from_slice(key).map_err(|_| PayloadKeyError::Rejected)
+}
+
+pub(crate) fn position_in(
+    connection: &Connection,
+    content_hash: &str,
+) -> Result<Option<ChainPosition>> {
+    connection
+        .query_row(
+            "SELECT timestamp FROM clipboard_events WHERE content_hash = ?1",
+            [content_hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map(|timestamp| {
+            timestamp.map(|timestamp| ChainPosition {
+                timestamp,
+                content_hash: content_hash.to_string(),
+            })
+        })
+}
+
+/// Recomputes the MAC of one stored row from its current columns. Callers
+/// follow it with [`relink_in`] for the row's old and new positions.
+pub(crate) fn seal_row_in(
+    connection: &Connection,
+    cipher: &PayloadCipher,
+    key: &HistoryIntegrityKey,
+    content_hash: &str,
+) -> Result<()> {
+    let row_mac = connection.query_row(
+        "SELECT event_data, timestamp, source_bundle_id, is_remote_clipboard
+         FROM clipboard_events
+         WHERE content_hash = ?1",
+        [content_hash],
+        |row| {
+            let event_data = cipher
+                .open(
+                    content_hash,
+                    PayloadColumn::EventData,
+                    &row.get::<_, Vec<u8>>(0)?,
+                )
+                .map_err(|_| undecryptable_payload())?;
+            Ok(key.row_mac(&IntegrityFields {
+                content_hash,
+                event_data: &event_data,
+                timestamp: row.get(1)?,
+                source_bundle_id: row.get::<_, Option<String>>(2)?.as_deref(),
+                is_remote_clipboard: row.get(3)?,
+            }))
+        },
+    )?;
+    connection.execute(
+        "UPDATE clipboard_events SET integrity_mac = ?1 WHERE content_hash = ?2",
+        params![row_mac, content_hash],
+    )?;
+    Ok(())
+}
+
+/// Relinks the row at each position, if one is still there, and the row
+/// after it, then rebinds the head. Pass the old and new positions of every
+/// row that was inserted, moved, resealed, or deleted.
+pub(crate) fn relink_in(
+    connection: &Connection,
+    key: &HistoryIntegrityKey,
+    positions: &[ChainPosition],
+) -> Result<()> {
+    for position in positions {
+        let row_mac = connection
+            .query_row(
+                "SELECT integrity_mac FROM clipboard_events
+                 WHERE content_hash = ?1 AND timestamp = ?2",
+                params![position.content_hash, position.timestamp],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        if let Some(row_mac) = row_mac {
+            relink_row_in(connection, key, position, &row_mac)?;
+        }
+        let next = connection
+            .query_row(
+                NEXT_ROW,
+                params![position.timestamp, position.content_hash],
+                |row| {
+                    Ok((
+                        ChainPosition {
+                            content_hash: row.get(0)?,
+                            timestamp: row.get(1)?,
+                        },
+                        row.get::<_, Vec<u8>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        if let Some((next, next_mac)) = next {
+            relink_row_in(connection, key, &next, &next_mac)?;
+        }
+    }
+    write_head_in(connection, key)
+}
+
+fn relink_row_in(
+    connection: &Connection,
+    key: &HistoryIntegrityKey,
+    position: &ChainPosition,
+    row_mac: &[u8],
+) -> Result<()> {
+    let previous_mac = previous_mac_in(connection, position)?;
+    connection.execute(
+        "UPDATE clipboard_events SET integrity_link = ?1 WHERE content_hash = ?2",
+        params![
+            key.link(previous_mac.as_deref(), row_mac),
+            position.content_hash
+        ],
+    )?;
+    Ok(())
+}
+
+fn previous_mac_in(connection: &Connection, position: &ChainPosition) -> Result<Option<Vec<u8>>> {
+    connection
+        .query_row(
+            PREVIOUS_ROW_MAC,
+            params![position.timestamp, position.content_hash],
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+fn write_head_in(connection: &Connection, key: &HistoryIntegrityKey) -> Result<()> {
+    let newest_mac = connection
+        .query_row(NEWEST_ROW_MAC, [], |row| row.get::<_, Vec<u8>>(0))
+        .optional()?;
+    connection.execute(
+        "INSERT INTO history_integrity (key_id, head) VALUES (1, ?1)
+         ON CONFLICT(key_id) DO UPDATE SET head = excluded.head",
+        [key.head(newest_mac.as_deref())],
+    )?;
+    Ok(())
+}
+
+/// Recomputes every MAC and link from the stored rows. Only a history rebuild
+/// calls this, since it derives every row again anyway.
+pub(crate) fn reseal_all_in(
+    connection: &Connection,
+    cipher: &PayloadCipher,
+    key: &HistoryIntegrityKey,
+) -> Result<()> {
+    let mut statement = connection.prepare(
+        "SELECT content_hash, event_data, timestamp, source_bundle_id, is_remote_clipboard
+         FROM clipboard_events
+         ORDER BY timestamp ASC, content_hash DESC",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut sealed_rows = Vec::new();
+    let mut previous_mac: Option<Vec<u8>> = None;
+    while let Some(row) = rows.next()? {
+        let content_hash = row.get::<_, String>(0)?;
+        let event_data = cipher
+            .open(
+                &content_hash,
+                PayloadColumn::EventData,
+                &row.get::<_, Vec<u8>>(1)?,
+            )
+            .map_err(|_| undecryptable_payload())?;
+        let row_mac = key.row_mac(&IntegrityFields {
+            content_hash: &content_hash,
+            event_data: &event_data,
+            timestamp: row.get(2)?,
+            source_bundle_id: row.get::<_, Option<String>>(3)?.as_deref(),
+            is_remote_clipboard: row.get(4)?,
+        });
+        let link = key.link(previous_mac.as_deref(), &row_mac);
+        sealed_rows.push((content_hash, row_mac.clone(), link));
+        previous_mac = Some(row_mac);
+    }
+    drop(rows);
+    drop(statement);
+
+    for (content_hash, row_mac, link) in sealed_rows {
+        connection.execute(
+            "UPDATE clipboard_events
+             SET integrity_mac = ?1, integrity_link = ?2
+             WHERE content_hash = ?3",
+            params![row_mac, link, content_hash],
+        )?;
+    }
+    write_head_in(connection, key)
+}
+
+/// Checks one row's MAC, its link to the row before it, and the head when it
+/// is the newest row.
+pub(crate) fn verify_row_in(
+    connection: &Connection,
+    key: &HistoryIntegrityKey,
+    row: &IntegrityFields<'_>,
+    row_mac: &[u8],
+    link: &[u8],
+) -> Result<IntegrityResult<()>> {
+    let position = ChainPosition {
+        timestamp: row.timestamp,
+        content_hash: row.content_hash.to_string(),
+    };
+    let previous_mac = previous_mac_in(connection, &position)?;
+    let mut intact = key.row_tag(row).verify_slice(row_mac).is_ok()
+        && key
+            .link_tag(previous_mac.as_deref(), row_mac)
+            .verify_slice(link)
+            .is_ok();
+    let is_newest = connection
+        .query_row(
+            NEXT_ROW,
+            params![position.timestamp, position.content_hash],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_none();
+    if intact && is_newest {
+        intact = head_is_intact_in(connection, key, Some(row_mac))?;
+    }
+    Ok(if intact {
+        Ok(())
+    } else {
+        Err(IntegrityViolation)
+    })
+}
+
+/// Verifies every row and link in history order, and the head, returning
+/// how many rows were checked.
+pub(crate) fn verify_all_in(
+    connection: &Connection,
+    cipher: &PayloadCipher,
+    key: &HistoryIntegrityKey,
+) -> Result<IntegrityResult<u64>> {
+    let mut statement = connection.prepare(
+        "SELECT
+            content_hash,
+            event_data,
+            timestamp,
+            source_bundle_id,
+            is_remote_clipboard,
+            integrity_mac,
+            integrity_link
+         FROM clipboard_events
+         ORDER BY timestamp ASC, content_hash DESC",
+    )?;
+    let mut rows = statement.query([])?;
+    let mut previous_mac: Option<Vec<u8>> = None;
+    let mut verified_items = 0;
+    while let Some(row) = rows.next()? {
+        let content_hash = row.get::<_, String>(0)?;
+        let Ok(event_data) = cipher.open(
+            &content_hash,
+            PayloadColumn::EventData,
+            &row.get::<_, Vec<u8>>(1)?,
+        ) else {
+            return Ok(Err(IntegrityViolation));
+        };
+        let row_mac = row.get::<_, Vec<u8>>(5)?;
+        let source_bundle_id = row.get::<_, Option<String>>(3)?;
+        let fields = IntegrityFields {
+            content_hash: &content_hash,
+            event_data: &event_data,
+            timestamp: row.get(2)?,
+            source_bundle_id: source_bundle_id.as_deref(),
+            is_remote_clipboard: row.get(4)?,
+        };
+        let intact = key.row_tag(&fields).verify_slice(&row_mac).is_ok()
+            && key
+                .link_tag(previous_mac.as_deref(), &row_mac)
+                .verify_slice(&row.get::<_, Vec<u8>>(6)?)
+                .is_ok();
+        if !intact {
+            return Ok(Err(IntegrityViolation));
+        }
+        previous_mac = Some(row_mac);
+        verified_items += 1;
+    }
+
+    Ok(
+        if head_is_intact_in(connection, key, previous_mac.as_deref())? {
+            Ok(verified_items)
+        } else {
+            Err(IntegrityViolation)
+        },
+    )
+}
+
+fn head_is_intact_in(
+    connection: &Connection,
+    key: &HistoryIntegrityKey,
+    newest_mac: Option<&[u8]>,
+) -> Result<bool> {
+    let head = connection
+        .query_row(
+            "SELECT head FROM history_integrity WHERE key_id = 1",
+            [],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .optional()?;
+    Ok(head.is_some_and(|head| key.head_tag(newest_mac).verify_slice(&head).is_ok()))
+}
+
+fn undecryptable_payload() -> rusqlite::Error {
+    rusqlite::Error::InvalidParameterName("clipboard payload could not be decrypted".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(event_data: &[u8]) -> IntegrityFields<'_> {
+        IntegrityFields {
+            content_hash: "hash",
+            event_data,
+            timestamp: 1_000,
+            source_bundle_id: Some("com.example.editor"),
+            is_remote_clipboard: false,
+        }
+    }
+
+    #[test]
+    fn row_macs_cover_every_field_and_depend_on_the_key() {
+        let key = HistoryIntegrityKey::derive(&[7; 32]).expect("key should derive");
+        let row_mac = key.row_mac(&fields(b"payload"));
+        assert_eq!(row_mac.len(), 32);
+        assert!(key
+            .row_tag(&fields(b"payload"))
+            .verify_slice(&row_mac)
+            .is_ok());
+
+        for changed in [
+            IntegrityFields {
+                content_hash: "other",
+                ..fields(b"payload")
+            },
+            fields(b"payloae"),
+            IntegrityFields {
+                timestamp: 1_001,
+                ..fields(b"payload")
+            },
+            IntegrityFields {
+                source_bundle_id: None,
+                ..fields(b"payload")
+            },
+            IntegrityFields {
+                source_bundle_id: Some(""),
+                ..fields(b"payload")
+            },
+            IntegrityFields {
+                is_remote_clipboard: true,
+                ..fields(b"payload")
+            },
+        ] {
+            assert_ne!(key.row_mac(&changed), row_mac);
+        }
+
+        let other_key = HistoryIntegrityKey::derive(&[8; 32]).expect("key should derive");
+        assert_ne!(other_key.row_mac(&fields(b"payload")), row_mac);
+        assert_ne!(key.link(None, &row_mac), key.link(Some(&[]), &row_mac));
+        assert_ne!(key.head(None), key.link(None, &[]));
+    }
+}
@@ -0,0 +1,508 @@
+//! Named collections and free-form tags attached to history rows.
+//!
+//! Memberships are keyed by `content_hash`, like the search index. Every path
+//! that deletes, merges, or rebuilds history rows updates them in the same
+//! transaction, so a membership never outlives its row.
+
+use crate::store::models::{
+    HistoryCollection, HistoryLabelFilter, HistoryLabels, HistoryTag, LabelRejection, LabelResult,
+};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+pub(super) const MAX_LABEL_NAME_CHARS: usize = 64;
+pub(super) const MAX_COLLECTIONS: u64 = 256;
+pub(super) const MAX_TAGS_PER_ITEM: u64 = 32;
+/// Joins names in one SQL aggregate. Names cannot contain control characters,
+/// so the unit separator never appears inside one.
+const NAME_SEPARATOR: char = '\u{1f}';
+
+/// Trims and collapses whitespace, then accepts 1–64 characters without
+/// control characters.
+pub(super) fn normalize_name(name: &str) -> Option<String> {
+    let normalized = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let length = normalized.chars().count();
+    (length > 0 && length <= MAX_LABEL_NAME_CHARS && !normalized.chars().any(char::is_control))
+        .then_some(normalized)
+}
+
+pub(super) fn list_collections(connection: &Connection) -> Result<Vec<HistoryCollection>> {
+    let mut statement = connection.prepare(
+        "SELECT collections.collection_id, collections.name, COUNT(collection_items.content_hash)
+         FROM collections
+         LEFT JOIN collection_items
+           ON collection_items.collection_id = collections.collection_id
+         GROUP BY collections.collection_id
+         ORDER BY collections.name, collections.collection_id",
+    )?;
+    let rows = statement.query_map([], collection_from_row)?;
+    rows.collect()
+}
+
+pub(super) fn create_collection(
+    connection: &Connection,
+    name: &str,
+) -> Result<LabelResult<HistoryCollection>> {
+    let Some(name) = normalize_name(name) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    if collection_id_by_name(connection, &name)?.is_some() {
+        return Ok(Err(LabelRejection::NameTaken));
+    }
+    let count: i64 =
+        connection.query_row("SELECT COUNT(*) FROM collections", [], |row| row.get(0))?;
+    if count.max(0) as u64 >= MAX_COLLECTIONS {
+        return Ok(Err(LabelRejection::LimitReached));
+    }
+
+    connection.execute("INSERT INTO collections (name) VALUES (?1)", [&name])?;
+    Ok(Ok(HistoryCollection {
+        id: connection.last_insert_rowid(),
+        name,
+        item_count: 0,
+    }))
+}
+
+pub(super) fn rename_collection(
+    connection: &Connection,
+    collection_id: i64,
+    name: &str,
+) -> Result<LabelResult<HistoryCollection>> {
+    let Some(name) = normalize_name(name) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    if !collection_exists(connection, collection_id)? {
+        return Ok(Err(LabelRejection::LabelNotFound));
+    }
+    if collection_id_by_name(connection, &name)?.is_some_and(|existing| existing != collection_id) {
+        return Ok(Err(LabelRejection::NameTaken));
+    }
+
+    connection.execute(
+        "UPDATE collections SET name = ?1 WHERE collection_id = ?2",
+        params![&name, collection_id],
+    )?;
+    let collection = connection.query_row(
+        "SELECT collections.collection_id, collections.name, COUNT(collection_items.content_hash)
+         FROM collections
+         LEFT JOIN collection_items
+           ON collection_items.collection_id = collections.collection_id
+         WHERE collections.collection_id = ?1
+         GROUP BY collections.collection_id",
+        [collection_id],
+        collection_from_row,
+    )?;
+    Ok(Ok(collection))
+}
+
+pub(super) fn delete_collection(
+    connection: &Connection,
+    collection_id: i64,
+) -> Result<LabelResult<()>> {
+    connection.execute(
+        "DELETE FROM collection_items WHERE collection_id = ?1",
+        [collection_id],
+    )?;
+    let deleted = connection.execute(
+        "DELETE FROM collections WHERE collection_id = ?1",
+        [collection_id],
+    )?;
+    Ok(if deleted == 0 {
+        Err(LabelRejection::LabelNotFound)
+    } else {
+        Ok(())
+    })
+}
+
+/// Adds one row to a collection. Adding an existing member succeeds.
+pub(super) fn add_to_collection(
+    connection: &Connection,
+    collection_id: i64,
+    content_hash: &str,
+) -> Result<LabelResult<()>> {
+    if !collection_exists(connection, collection_id)? {
+        return Ok(Err(LabelRejection::LabelNotFound));
+    }
+    if !item_exists(connection, content_hash)? {
+        return Ok(Err(LabelRejection::ItemNotFound));
+    }
+    connection.execute(
+        "INSERT OR IGNORE INTO collection_items (collection_id, content_hash) VALUES (?1, ?2)",
+        params![collection_id, content_hash],
+    )?;
+    Ok(Ok(()))
+}
+
+/// Removes one row from a collection. Removing a non-member succeeds.
+pub(super) fn remove_from_collection(
+    connection: &Connection,
+    collection_id: i64,
+    content_hash: &str,
+) -> Result<LabelResult<()>> {
+    if !collection_exists(connection, collection_id)? {
+        return Ok(Err(LabelRejection::LabelNotFound));
+    }
+    connection.execute(
+        "DELETE FROM collection_items WHERE collection_id = ?1 AND content_hash = ?2",
+        params![collection_id, content_hash],
+    )?;
+    Ok(Ok(()))
+}
+
+/// Lists tags in use. A tag exists only while at least one row carries it.
+pub(super) fn list_tags(connection: &Connection) -> Result<Vec<HistoryTag>> {
+    let mut statement = connection.prepare(
+        "SELECT tags.name, COUNT(*)
+         FROM tags
+         JOIN item_tags ON item_tags.tag_id = tags.tag_id
+         GROUP BY tags.tag_id
+         ORDER BY tags.name",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok(HistoryTag {
+            name: row.get(0)?,
+            item_count: row.get::<_, i64>(1)?.max(0) as u64,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Attaches a tag to one row, creating the tag on first use. Tag names are
+/// matched case-insensitively for ASCII letters, and the first spelling wins.
+pub(super) fn tag_item(
+    connection: &Connection,
+    content_hash: &str,
+    name: &str,
+) -> Result<LabelResult<()>> {
+    let Some(name) = normalize_name(name) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    if !item_exists(connection, content_hash)? {
+        return Ok(Err(LabelRejection::ItemNotFound));
+    }
+    let tag_id = tag_id_by_name(connection, &name)?;
+    if let Some(tag_id) = tag_id {
+        let tagged = connection
+            .query_row(
+                "SELECT 1 FROM item_tags WHERE tag_id = ?1 AND content_hash = ?2",
+                params![tag_id, content_hash],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if tagged {
+            return Ok(Ok(()));
+        }
+    }
+    let tag_count: i64 = connection.query_row(
+        "SELECT COUNT(*) FROM item_tags WHERE content_hash = ?1",
+        [content_hash],
+        |row| row.get(0),
+    )?;
+    if tag_count.max(0) as u64 >= MAX_TAGS_PER_ITEM {
+        return Ok(Err(LabelRejection::LimitReached));
+    }
+
+    let tag_id = match tag_id {
+        Some(tag_id) => tag_id,
+        None => {
+            connection.execute("INSERT INTO tags (name) VALUES (?1)", [&name])?;
+            connection.last_insert_rowid()
+        }
+    };
+    connection.execute(
+        "INSERT INTO item_tags (tag_id, content_hash) VALUES (?1, ?2)",
+        params![tag_id, content_hash],
+    )?;
+    Ok(Ok(()))
+}
+
+/// Detaches a tag from one row. Detaching an absent tag succeeds.
+pub(super) fn untag_item(
+    connection: &Connection,
+    content_hash: &str,
+    name: &str,
+) -> Result<LabelResult<()>> {
+    let Some(name) = normalize_name(name) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    if !item_exists(connection, content_hash)? {
+        return Ok(Err(LabelRejection::ItemNotFound));
+    }
+    if let Some(tag_id) = tag_id_by_name(connection, &name)? {
+        connection.execute(
+            "DELETE FROM item_tags WHERE tag_id = ?1 AND content_hash = ?2",
+            params![tag_id, content_hash],
+        )?;
+        prune_unused_tags_in(connection)?;
+    }
+    Ok(Ok(()))
+}
+
+pub(super) fn rename_tag(
+    connection: &Connection,
+    name: &str,
+    new_name: &str,
+) -> Result<LabelResult<()>> {
+    let (Some(name), Some(new_name)) = (normalize_name(name), normalize_name(new_name)) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    let Some(tag_id) = tag_id_by_name(connection, &name)? else {
+        return Ok(Err(LabelRejection::LabelNotFound));
+    };
+    if tag_id_by_name(connection, &new_name)?.is_some_and(|existing| existing != tag_id) {
+        return Ok(Err(LabelRejection::NameTaken));
+    }
+    connection.execute(
+        "UPDATE tags SET name = ?1 WHERE tag_id = ?2",
+        params![&new_name, tag_id],
+    )?;
+    Ok(Ok(()))
+}
+
+pub(super) fn delete_tag(connection: &Connection, name: &str) -> Result<LabelResult<()>> {
+    let Some(name) = normalize_name(name) else {
+        return Ok(Err(LabelRejection::InvalidName));
+    };
+    let Some(tag_id) = tag_id_by_name(connection, &name)? else {
+        return Ok(Err(LabelRejection::LabelNotFound));
+    };
+    connection.execute("DELETE FROM item_tags WHERE tag_id = ?1", [tag_id])?;
+    connection.execute("DELETE FROM tags WHERE tag_id = ?1", [tag_id])?;
+    Ok(Ok(()))
+}
+
+pub(super) fn item_labels(
+    connection: &Connection,
+    content_hash: &str,
+) -> Result<LabelResult<HistoryLabels>> {
+    if !item_exists(connection, content_hash)? {
+        return Ok(Err(LabelRejection::ItemNotFound));
+    }
+    let mut statement = connection.prepare(
+        "SELECT collection_items.collection_id
+         FROM collection_items
+         JOIN collections ON collections.collection_id = collection_items.collection_id
+         WHERE collection_items.content_hash = ?1
+         ORDER BY collections.name, collections.collection_id",
+    )?;
+    let collection_ids = statement
+        .query_map([content_hash], |row| row.get(0))?
+        .collect::<Result<Vec<i64>>>()?;
+    let mut statement = connection.prepare(
+        "SELECT tags.name
+         FROM item_tags
+         JOIN tags ON tags.tag_id = item_tags.tag_id
+         WHERE item_tags.content_hash = ?1
+         ORDER BY tags.name",
+    )?;
+    let tags = statement
+        .query_map([content_hash], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(Ok(HistoryLabels {
+        collection_ids,
+        tags,
+    }))
+}
+
+/// Renders `AND EXISTS (...)` clauses restricting `content_hash` to members of
+/// the filter's collection and tag, binding values after existing parameters.
+pub(super) fn sql_filters(
+    filter: &HistoryLabelFilter,
+    content_hash: &str,
+    parameters: &mut Vec<Value>,
+) -> String {
+    let mut clauses = String::new();
+    if let Some(collection_id) = filter.collection_id {
+        parameters.push(Value::Integer(collection_id));
+        clauses.push_str(&format!(
+            " AND EXISTS (
+                SELECT 1 FROM collection_items
+                WHERE collection_items.collection_id = ?{}
+                  AND collection_items.content_hash = {content_hash}
+             )",
+            parameters.len()
+        ));
+    }
+    if let Some(tag) = filter.tag.as_deref() {
+        parameters.push(Value::Text(tag.to_string()));
+        clauses.push_str(&format!(
+            " AND EXISTS (
+                SELECT 1 FROM item_tags
+                JOIN tags ON tags.tag_id = item_tags.tag_id
+                WHERE tags.name = ?{}
+                  AND item_tags.content_hash = {content_hash}
+             )",
+            parameters.len()
+        ));
+    }
+    clauses
+}
+
+/// Selects one row's collection names, joined for `split_names`.
+pub(super) fn collection_names_sql(content_hash: &str) -> String {
+    format!(
+        "(SELECT group_concat(collections.name, char(31))
+          FROM collection_items
+          JOIN collections ON collections.collection_id = collection_items.collection_id
+          WHERE collection_items.content_hash = {content_hash})"
+    )
+}
+
+/// Selects one row's tag names, joined for `split_names`.
+pub(super) fn tag_names_sql(content_hash: &str) -> String {
+    format!(
+        "(SELECT group_concat(tags.name, char(31))
+          FROM item_tags
+          JOIN tags ON tags.tag_id = item_tags.tag_id
+          WHERE item_tags.content_hash = {content_hash})"
+    )
+}
+
+pub(super) fn split_names(value: Option<String>) -> Vec<String> {
+    let mut names: Vec<String> = value
+        .map(|value| value.split(NAME_SEPARATOR).map(str::to_string).collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+pub(super) fn delete_memberships_in(connection: &Connection, content_hash: &str) -> Result<()> {
+    connection.execute(
+        "DELETE FROM collection_items WHERE content_hash = ?1",
+        [content_hash],
+    )?;
+    connection.execute(
+        "DELETE FROM item_tags WHERE content_hash = ?1",
+        [content_hash],
+    )?;
+    prune_unused_tags_in(connection)
+}
+
+/// Moves every membership of `from` onto `to`, merging with any it already has.
+pub(super) fn rekey_memberships_in(connection: &Connection, from: &str, to: &str) -> Result<()> {
+    connection.execute(
+        "INSERT OR IGNORE INTO collection_items (collection_id, content_hash)
+         SELECT collection_id, ?2 FROM collection_items WHERE content_hash = ?1",
+        params![from, to],
+    )?;
+    connection.execute(
+        "INSERT OR IGNORE INTO item_tags (tag_id, content_hash)
+         SELECT tag_id, ?2 FROM item_tags WHERE content_hash = ?1",
+        params![from, to],
+    )?;
+    connection.execute(
+        "DELETE FROM collection_items WHERE content_hash = ?1",
+        [from],
+    )?;
+    connection.execute("DELETE FROM item_tags WHERE content_hash = ?1", [from])?;
+    Ok(())
+}
+
+/// Clears every membership and tag. Collections stay, now empty.
+pub(super) fn clear_memberships_in(connection: &Connection) -> Result<()> {
+    connection.execute("DELETE FROM collection_items", [])?;
+    connection.execute("DELETE FROM item_tags", [])?;
+    connection.execute("DELETE FROM tags", [])?;
+    Ok(())
+}
+
+/// Drops memberships whose row disappeared, e.g. when a history rebuild
+/// reclassified or dropped it.
+pub(super) fn prune_orphaned_memberships_in(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "DELETE FROM collection_items
+         WHERE content_hash NOT IN (SELECT content_hash FROM clipboard_events)",
+        [],
+    )?;
+    connection.execute(
+        "DELETE FROM item_tags
+         WHERE content_hash NOT IN (SELECT content_hash FROM clipboard_events)",
+        [],
+    )?;
+    prune_unused_tags_in(connection)
+}
+
+fn prune_unused_tags_in(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "DELETE FROM tags
+         WHERE NOT EXISTS (SELECT 1 FROM item_tags WHERE item_tags.tag_id = tags.tag_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn collection_from_row(row: &rusqlite::Row<'_>) -> Result<HistoryCollection> {
+    Ok(HistoryCollection {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        item_count: row.get::<_, i64>(2)?.max(0) as u64,
+    })
+}
+
+fn collection_exists(connection: &Connection, collection_id: i64) -> Result<bool> {
+    Ok(connection
+        .query_row(
+            "SELECT 1 FROM collections WHERE collection_id = ?1",
+            [collection_id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+fn collection_id_by_name(connection: &Connection, name: &str) -> Result<Option<i64>> {
+    connection
+        .query_row(
+            "SELECT collection_id FROM collections WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+fn tag_id_by_name(connection: &Connection, name: &str) -> Result<Option<i64>> {
+    connection
+        .query_row("SELECT tag_id FROM tags WHERE name = ?1", [name], |row| {
+            row.get(0)
+        })
+        .optional()
+}
+
+fn item_exists(connection: &Connection, content_hash: &str) -> Result<bool> {
+    Ok(connection
+        .query_row(
+            "SELECT 1 FROM clipboard_events WHERE content_hash = ?1",
+            [content_hash],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_trimmed_collapsed_and_bounded() {
+        assert_eq!(
+            normalize_name("  Release \t notes ").as_deref(),
+            Some("Release notes")
+        );
+        assert_eq!(normalize_name("SQL 片段").as_deref(), Some("SQL 片段"));
+        assert_eq!(normalize_name(" \n "), None);
+        assert_eq!(normalize_name("bad\u{1f}name"), None);
+        assert!(normalize_name(&"界".repeat(MAX_LABEL_NAME_CHARS)).is_some());
+        assert_eq!(normalize_name(&"界".repeat(MAX_LABEL_NAME_CHARS + 1)), None);
+    }
+
+    #[test]
+    fn joined_names_split_back_in_sorted_order() {
+        assert_eq!(
+            split_names(Some(format!("sql{NAME_SEPARATOR}Release notes"))),
+            ["Release notes", "sql"]
+        );
+        assert!(split_names(None).is_empty());
+    }
+}
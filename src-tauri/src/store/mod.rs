@@ -1,15 +1,25 @@
+mod backups;
+mod blob_files;
 mod classification;
 mod database;
+mod encryption;
+mod flavor_blobs;
+mod integrity;
+mod labels;
 mod models;
 #[cfg(test)]
 mod performance;
 mod preview;
+mod query;
 mod schema;
+mod search;
 mod settings;
 
 #[allow(unused_imports)]
 pub use classification::{FileDisplay, FileDisplayItem};
 pub use database::*;
+pub(crate) use encryption::PayloadCipher;
 #[allow(unused_imports)]
 pub use models::*;
 pub use preview::StoredPreviewSegment;
+pub(crate) use query::{is_known_data_type, HistoryQuery, HistoryQueryError};
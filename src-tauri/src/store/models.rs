@@ -1,18 +1,28 @@
+use crate::secret_detection::{SecretAction, SecretKind};
 use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
 pub const MAX_HISTORY_PAGE_SIZE: usize = 100;
 pub const MAX_MENU_BAR_ITEM_LIMIT: usize = 1_000;
 pub const MAX_SUMMARY_DISPLAY_BYTES: usize = 512;
+pub const MAX_SEARCH_SNIPPET_BYTES: usize = 512;
 pub const DEFAULT_MAX_HISTORY_BYTES: u64 = crate::resource_policy::MAX_HISTORY_BYTES;
+pub const MAX_SOURCE_APP_RULES: usize = 256;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AppSettings {
     pub max_items: u32,
     pub max_history_bytes: u64,
+    pub max_age_days: u32,
+    pub data_type_budgets: Vec<DataTypeBudget>,
+    pub denied_source_apps: Vec<String>,
+    pub allowed_source_apps: Vec<String>,
+    pub source_allow_only: bool,
+    pub secret_policies: Vec<SecretPolicySetting>,
     pub show_in_menu_bar: bool,
     pub menu_bar_item_limit: u32,
     pub move_restored_item_to_top: bool,
+    pub move_recopied_item_to_top: bool,
     pub compact_mode: bool,
     pub language: String,
     pub resolved_language: String,
@@ -22,6 +32,29 @@ pub struct AppSettings {
     pub max_event_bytes: u64,
 }
 
+/// Retention limits for one classified `data_type`, applied before the global
+/// limits. `None` leaves that dimension to the global limit alone.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataTypeBudget {
+    pub data_type: String,
+    pub max_items: Option<u32>,
+    pub max_bytes: Option<u64>,
+}
+
+/// The action taken when one secret detector matches a captured plain text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretPolicySetting {
+    pub kind: SecretKind,
+    pub action: SecretAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceAppRule {
+    Deny,
+    Allow,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HistorySummary {
     pub content_hash: String,
@@ -33,6 +66,10 @@ pub struct HistorySummary {
     pub is_remote_clipboard: bool,
     pub byte_count: u64,
     pub has_detail: bool,
+    pub pinned: bool,
+    pub first_seen_at: i64,
+    pub last_seen_at: i64,
+    pub copy_count: u64,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -44,6 +81,25 @@ pub struct HistoryPage {
     pub total_bytes: u64,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchSnippetSegment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistorySearchResult {
+    pub summary: HistorySummary,
+    pub snippet: Vec<SearchSnippetSegment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistorySearchPage {
+    pub items: Vec<HistorySearchResult>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HistoryDetailSeed {
     pub content_hash: String,
@@ -57,6 +113,34 @@ pub struct HistoryDetailSeed {
     pub byte_count: u64,
 }
 
+/// The readable parts of one row, as an `export_history` report shows them.
+/// Built from a `HistoryDetailSeed` after the database lock is released.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryExportEntry {
+    pub timestamp: i64,
+    pub data_type: String,
+    pub source_bundle_id: Option<String>,
+    pub byte_count: u64,
+    /// The full plain text, or the stored display for rows without any.
+    pub text: String,
+    /// The copied files and folders of a `file` row.
+    pub files: Vec<crate::store::FileDisplayItem>,
+    /// Images that pass the preview bounds, when requested.
+    pub images: Vec<HistoryExportImage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryExportImage {
+    pub label: String,
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryExportReport {
+    pub exported_items: u64,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct HistoryDetail {
     pub content_hash: String,
@@ -70,8 +154,149 @@ pub struct TrayEvent {
     pub content_hash: String,
     pub data_type: String,
     pub display: Vec<u8>,
+    pub pinned: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinOutcome {
+    Updated,
+    NotFound,
+    LimitReached,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryCollection {
+    pub id: i64,
+    pub name: String,
+    pub item_count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryTag {
+    pub name: String,
+    pub item_count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryLabels {
+    pub collection_ids: Vec<i64>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistoryLabelFilter {
+    pub collection_id: Option<i64>,
+    pub tag: Option<String>,
+}
+
+impl HistoryLabelFilter {
+    pub fn is_empty(&self) -> bool {
+        self.collection_id.is_none() && self.tag.is_none()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelRejection {
+    InvalidName,
+    NameTaken,
+    LabelNotFound,
+    ItemNotFound,
+    LimitReached,
+}
+
+pub type LabelResult<T> = std::result::Result<T, LabelRejection>;
+
+/// A stored row whose MAC, chain link, or payload no longer verifies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntegrityViolation;
+
+pub type IntegrityResult<T> = std::result::Result<T, IntegrityViolation>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryIntegrityReport {
+    pub verified_items: u64,
+}
+
+/// SQLite's own `integrity_check` found damage below the row level, so no
+/// row was checked or moved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CorruptDatabase;
+
+pub type HistoryCheckResult<T> = std::result::Result<T, CorruptDatabase>;
+
+/// What `check_history_integrity` found, as counts per failure kind. A row
+/// that failed more than one check counts under the first.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryCheckReport {
+    pub checked_items: u64,
+    pub quarantined_items: u64,
+    /// A payload column no longer opens with the payload key.
+    pub undecryptable_items: u64,
+    /// `event_data` opened but is not a valid event blob.
+    pub undecodable_items: u64,
+    /// The event no longer classifies to the row's `content_hash`.
+    pub mismatched_items: u64,
 }
 
+/// What `reconcile_blob_files` found in the flavor blob directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlobFileReport {
+    /// Files no stored payload needed, now removed.
+    pub orphaned_files: u64,
+    /// Payloads whose file was gone.
+    pub missing_files: u64,
+    /// Rows moved to quarantine because they referenced a missing file.
+    pub quarantined_items: u64,
+}
+
+/// A backup archive whose settings or rows this version will not import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidBackup;
+
+pub type BackupResult<T> = std::result::Result<T, InvalidBackup>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryBackupReport {
+    pub exported_items: u64,
+}
+
+/// How an import merged incoming rows into history by `content_hash`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryImportReport {
+    /// Rows that were not in history yet.
+    pub imported_items: u64,
+    /// Rows already in history, whose pin and seen statistics were merged.
+    pub merged_items: u64,
+    /// Rows the pasteboard policy no longer records.
+    pub skipped_items: u64,
+    /// Rows the source application policy or a secret detector set to `skip`
+    /// keeps out of history.
+    pub denied_items: u64,
+    /// Rows over the capture budgets, or that could not be restored as they
+    /// were recorded, such as truncated JSONL records. Backup imports reject
+    /// the whole file for a row that does not restore instead.
+    pub rejected_items: u64,
+}
+
+/// A database snapshot in the private `backups/` directory. Only the file
+/// name crosses IPC; it is the handle `restore_database_backup` accepts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatabaseBackup {
+    pub name: String,
+    pub created_at: i64,
+    pub schema_version: i64,
+    pub size_bytes: u64,
+    /// The snapshot predates payload encryption and holds history in
+    /// plaintext.
+    pub plaintext: bool,
+}
+
+/// A snapshot name that is not listed, or holds a newer schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DatabaseBackupNotFound;
+
+pub type DatabaseBackupResult<T> = std::result::Result<T, DatabaseBackupNotFound>;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TrayPreview {
     pub data_type: String,
@@ -88,27 +313,41 @@ pub struct HistoryStats {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub(crate) struct HistoryCursor {
+    pub pinned: bool,
     pub timestamp: i64,
     pub content_hash: String,
 }
 
 impl HistoryCursor {
-    const PREFIX: &'static str = "v1";
+    const PREFIX: &'static str = "v2";
+    const LEGACY_PREFIX: &'static str = "v1";
 
     pub(crate) fn encode(&self) -> String {
-        format!("{}:{}:{}", Self::PREFIX, self.timestamp, self.content_hash)
+        format!(
+            "{}:{}:{}:{}",
+            Self::PREFIX,
+            u8::from(self.pinned),
+            self.timestamp,
+            self.content_hash
+        )
     }
 
     pub(crate) fn decode(value: &str) -> Result<Self, String> {
-        let mut parts = value.splitn(3, ':');
-        let prefix = parts.next();
+        let (pinned, position) = match value.split_once(':') {
+            Some((Self::PREFIX, rest)) => match rest.split_once(':') {
+                Some(("0", position)) => (false, position),
+                Some(("1", position)) => (true, position),
+                _ => return Err("history cursor pin flag is invalid".to_string()),
+            },
+            // Version 1 cursors were issued before pins existed, so they always
+            // point into the unpinned section.
+            Some((Self::LEGACY_PREFIX, position)) => (false, position),
+            _ => return Err("unsupported history cursor version".to_string()),
+        };
+        let mut parts = position.splitn(2, ':');
         let timestamp = parts.next();
         let content_hash = parts.next();
 
-        if prefix != Some(Self::PREFIX) {
-            return Err("unsupported history cursor version".to_string());
-        }
-
         let timestamp = timestamp
             .ok_or_else(|| "history cursor is missing a timestamp".to_string())?
             .parse::<i64>()
@@ -124,6 +363,7 @@ impl HistoryCursor {
         }
 
         Ok(Self {
+            pinned,
             timestamp,
             content_hash: content_hash.to_string(),
         })
@@ -137,6 +377,7 @@ mod tests {
     #[test]
     fn history_cursor_round_trips_and_rejects_malformed_values() {
         let cursor = HistoryCursor {
+            pinned: true,
             timestamp: 1_725_000_000_123,
             content_hash: "a".repeat(64),
         };
@@ -144,10 +385,20 @@ mod tests {
             HistoryCursor::decode(&cursor.encode()).expect("cursor should decode"),
             cursor
         );
+        assert_eq!(
+            HistoryCursor::decode(&format!("v1:1725000000123:{}", "a".repeat(64)))
+                .expect("legacy cursor should decode"),
+            HistoryCursor {
+                pinned: false,
+                ..cursor
+            }
+        );
 
         for value in [
             "",
             "v2:1:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "v2:2:1:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "v3:1:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
             "v1:nope:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
             "v1:1:short",
             "v1:1:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
@@ -287,6 +287,7 @@ fn run_case(profile: FixtureProfile, item_count: usize) -> PerformanceRecord {
         let seed = db
             .get_history_detail_seed(&summary.content_hash)
             .expect("detail seed should load")
+            .expect("stored history should verify")
             .expect("detail seed should exist");
         Database::build_history_detail(seed, false).expect("detail should build");
     }
@@ -321,6 +322,7 @@ fn run_case(profile: FixtureProfile, item_count: usize) -> PerformanceRecord {
     let mirror = HistoryMirror::start_database(
         HistoryMirrorConfig::new(jsonl_path.clone(), 4_096).with_debounce(Duration::ZERO),
         database_path.clone(),
+        db.payload_cipher(),
     )
     .expect("database-backed JSONL mirror should start");
     let started = Instant::now();
@@ -4,16 +4,18 @@
 //! must copy a `HistoryDetailSeed` while holding the database lock and build the
 //! preview only after releasing it.
 
-use crate::event::decode_event_blob;
+use crate::event::EventBlobIndex;
 use crate::resource_policy::{
     allow_image_preview, MAX_DETAIL_IPC_BYTES, MAX_DISPLAY_BYTES, MAX_HTML_BYTES,
     MAX_PREVIEW_IMAGE_BYTES, MAX_PREVIEW_SEGMENTS,
 };
 use crate::store::classification::{
     file_url_display_name, file_url_extension, file_url_path, find_data, find_data_in_item,
-    find_raw_utf8_display,
+    find_raw_utf8_display, parse_file_display,
+};
+use crate::store::models::{
+    HistoryDetail, HistoryDetailSeed, HistoryExportEntry, HistoryExportImage,
 };
-use crate::store::models::{HistoryDetail, HistoryDetailSeed};
 use copy_event_listener::event::{Event, Item};
 use rusqlite::Result;
 use std::fs::File;
@@ -21,6 +23,12 @@ use std::io::Read;
 use std::path::Path;
 
 const INLINE_ATTACHMENT_PLACEHOLDER: char = '\u{fffc}';
+const PREVIEW_FLAVOR_TYPES: [&str; 4] = [
+    "public.utf8-plain-text",
+    "public.html",
+    "public.png",
+    "public.file-url",
+];
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
@@ -87,6 +95,68 @@ pub(super) fn build_history_detail(
     Ok(detail)
 }
 
+/// Builds one report entry. Compact mode exports the text projection the
+/// history shows; `with_images` also reads the images a detail would preview.
+pub(super) fn build_history_export_entry(
+    seed: HistoryDetailSeed,
+    compact_mode: bool,
+    with_images: bool,
+) -> Result<HistoryExportEntry> {
+    let mut entry = HistoryExportEntry {
+        timestamp: seed.timestamp,
+        data_type: seed.data_type,
+        source_bundle_id: seed.source_bundle_id,
+        byte_count: seed.byte_count,
+        text: String::new(),
+        files: Vec::new(),
+        images: Vec::new(),
+    };
+    let event = event_from_blob(&seed.event_data)?;
+    let display = match (compact_mode, seed.compact_display) {
+        (true, Some(compact_display)) => {
+            entry.data_type = "text".to_string();
+            compact_display
+        }
+        _ => seed.display,
+    };
+
+    if let Some(files) = (!compact_mode)
+        .then(|| parse_file_display(&display))
+        .flatten()
+    {
+        entry.text = files
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        entry.files = files.items;
+    } else {
+        entry.text = find_raw_utf8_display(&event)
+            .unwrap_or_else(|| String::from_utf8_lossy(&display).into_owned())
+            .replace('\0', "");
+    }
+
+    if with_images && !compact_mode {
+        entry.images = preview_segments_from_event(&event)
+            .into_iter()
+            .filter_map(|segment| match segment {
+                StoredPreviewSegment::Image {
+                    label,
+                    media_type,
+                    data,
+                } => Some(HistoryExportImage {
+                    label,
+                    media_type,
+                    data,
+                }),
+                _ => None,
+            })
+            .collect();
+    }
+    Ok(entry)
+}
+
 #[cfg(test)]
 pub(super) fn rich_preview_from_event_data(event_data: &[u8]) -> Vec<StoredPreviewSegment> {
     let Ok(event) = event_from_blob(event_data) else {
@@ -101,8 +171,13 @@ pub(super) fn html_preview_from_event_data(event_data: &[u8]) -> Option<String>
     bounded_html_preview(&event)
 }
 
+/// Decodes only the flavors a detail reads, so TIFF, RTF, and other large
+/// representations stay in the blob.
 fn event_from_blob(event_data: &[u8]) -> Result<Event> {
-    decode_event_blob(event_data)
+    EventBlobIndex::parse(event_data)
+        .and_then(|index| {
+            index.decode_flavors(|data_type| PREVIEW_FLAVOR_TYPES.contains(&data_type))
+        })
         .map_err(|error| rusqlite::Error::InvalidParameterName(error.to_string()))
 }
 
@@ -0,0 +1,454 @@
+//! History search query language.
+//!
+//! `HistoryQuery::parse` turns input such as
+//! `type:png app:com.figma.Figma after:2026-10-01 size:>1mb deploy` into a
+//! typed AST. Storage translates the filters to SQL over persisted summary
+//! columns and sends the remaining free-text terms to the search index.
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use rusqlite::types::Value;
+
+pub(crate) const MAX_QUERY_BYTES: usize = 256;
+pub(crate) const MAX_QUERY_TERMS: usize = 16;
+pub(crate) const MAX_QUERY_FILTERS: usize = 16;
+
+const IMAGE_DATA_TYPES: [&str; 10] = [
+    "png", "jpg", "jpeg", "gif", "webp", "tiff", "tif", "bmp", "heic", "heif",
+];
+const OTHER_DATA_TYPES: [&str; 9] = [
+    "text",
+    "rtf",
+    "html",
+    "file",
+    "folder",
+    "files",
+    "folders",
+    "files and folders",
+    "video",
+];
+
+/// Whether `data_type` is one the classifier can persist.
+pub(crate) fn is_known_data_type(data_type: &str) -> bool {
+    IMAGE_DATA_TYPES.contains(&data_type) || OTHER_DATA_TYPES.contains(&data_type)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct HistoryQuery {
+    pub(crate) terms: Vec<String>,
+    pub(crate) filters: Vec<HistoryFilter>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum HistoryFilter {
+    /// Any of the listed classified data types.
+    DataType(Vec<String>),
+    /// Exact source bundle identifier.
+    SourceBundleId(String),
+    /// Rows captured strictly before this Unix-millisecond instant.
+    Before(i64),
+    /// Rows captured at or after this Unix-millisecond instant.
+    After(i64),
+    ByteCount(Comparison, u64),
+    Remote(bool),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    const fn operator(self) -> &'static str {
+        match self {
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Equal => "=",
+            Self::GreaterOrEqual => ">=",
+            Self::Greater => ">",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HistoryQueryError {
+    TooLong,
+    TooManyTerms,
+    TooManyFilters,
+    UnterminatedQuote,
+    EmptyFilterValue,
+    UnknownDataType,
+    InvalidDate,
+    InvalidSize,
+    InvalidBoolean,
+}
+
+impl HistoryQuery {
+    /// Parses whitespace-separated terms and `field:value` filters.
+    ///
+    /// Double quotes group words into one term or filter value; a token that
+    /// starts with a quote is always a term. Unrecognized fields such as
+    /// `https:` are kept as terms so URLs remain searchable.
+    pub(crate) fn parse(input: &str) -> Result<Self, HistoryQueryError> {
+        if input.len() > MAX_QUERY_BYTES {
+            return Err(HistoryQueryError::TooLong);
+        }
+
+        let mut query = Self::default();
+        for (token, quoted) in tokenize(input)? {
+            let filter = if quoted {
+                None
+            } else {
+                token
+                    .split_once(':')
+                    .map(|(field, value)| parse_filter(&field.to_ascii_lowercase(), value))
+                    .transpose()?
+                    .flatten()
+            };
+            match filter {
+                Some(filter) => query.filters.push(filter),
+                None if !token.is_empty() => query.terms.push(token),
+                None => {}
+            }
+        }
+
+        if query.terms.len() > MAX_QUERY_TERMS {
+            return Err(HistoryQueryError::TooManyTerms);
+        }
+        if query.filters.len() > MAX_QUERY_FILTERS {
+            return Err(HistoryQueryError::TooManyFilters);
+        }
+        Ok(query)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.filters.is_empty()
+    }
+
+    /// Renders one `AND ...` clause per filter over the `event` alias.
+    ///
+    /// Values are appended to `parameters` and referenced by their 1-based
+    /// position, so the clause can follow any earlier bound parameters.
+    /// `data_type` is the SQL expression for the visible data type, which is the
+    /// literal `'text'` in compact mode.
+    pub(super) fn sql_filters(&self, data_type: &str, parameters: &mut Vec<Value>) -> String {
+        let mut bind = |value: Value| {
+            parameters.push(value);
+            format!("?{}", parameters.len())
+        };
+        let mut clauses = String::new();
+        for filter in &self.filters {
+            let clause = match filter {
+                HistoryFilter::DataType(data_types) => {
+                    let placeholders = data_types
+                        .iter()
+                        .map(|value| bind(Value::Text(value.clone())))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("{data_type} IN ({placeholders})")
+                }
+                HistoryFilter::SourceBundleId(source_bundle_id) => format!(
+                    "event.source_bundle_id = {}",
+                    bind(Value::Text(source_bundle_id.clone()))
+                ),
+                HistoryFilter::Before(timestamp) => {
+                    format!("event.timestamp < {}", bind(Value::Integer(*timestamp)))
+                }
+                HistoryFilter::After(timestamp) => {
+                    format!("event.timestamp >= {}", bind(Value::Integer(*timestamp)))
+                }
+                HistoryFilter::ByteCount(comparison, bytes) => format!(
+                    "event.byte_count {} {}",
+                    comparison.operator(),
+                    bind(Value::Integer(*bytes as i64))
+                ),
+                HistoryFilter::Remote(remote) => format!(
+                    "event.is_remote_clipboard = {}",
+                    bind(Value::Integer(i64::from(*remote)))
+                ),
+            };
+            clauses.push_str(" AND ");
+            clauses.push_str(&clause);
+        }
+        clauses
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(String, bool)>, HistoryQueryError> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut in_token = false;
+
+    for character in input.chars() {
+        match character {
+            '"' => {
+                if !in_token {
+                    quoted = true;
+                }
+                in_token = true;
+                in_quotes = !in_quotes;
+            }
+            character if character.is_whitespace() && !in_quotes => {
+                if in_token {
+                    tokens.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = false;
+                in_token = false;
+            }
+            character => {
+                in_token = true;
+                current.push(character);
+            }
+        }
+    }
+    if in_quotes {
+        return Err(HistoryQueryError::UnterminatedQuote);
+    }
+    if in_token {
+        tokens.push((current, quoted));
+    }
+    Ok(tokens)
+}
+
+fn parse_filter(field: &str, value: &str) -> Result<Option<HistoryFilter>, HistoryQueryError> {
+    if !matches!(
+        field,
+        "type" | "app" | "before" | "after" | "size" | "remote"
+    ) {
+        return Ok(None);
+    }
+    if value.is_empty() {
+        return Err(HistoryQueryError::EmptyFilterValue);
+    }
+
+    let filter = match field {
+        "type" => HistoryFilter::DataType(parse_data_types(value)?),
+        "app" => HistoryFilter::SourceBundleId(value.to_string()),
+        "before" => HistoryFilter::Before(parse_date_boundary(value)?),
+        "after" => HistoryFilter::After(parse_date_boundary(value)?),
+        "size" => {
+            let (comparison, bytes) = parse_size(value)?;
+            HistoryFilter::ByteCount(comparison, bytes)
+        }
+        _ => HistoryFilter::Remote(parse_boolean(value)?),
+    };
+    Ok(Some(filter))
+}
+
+fn parse_data_types(value: &str) -> Result<Vec<String>, HistoryQueryError> {
+    let mut data_types = Vec::new();
+    for name in value.to_ascii_lowercase().split(',') {
+        let expanded = if name == "image" {
+            IMAGE_DATA_TYPES.to_vec()
+        } else if is_known_data_type(name) {
+            vec![name]
+        } else {
+            return Err(HistoryQueryError::UnknownDataType);
+        };
+        for data_type in expanded {
+            if !data_types.iter().any(|existing| existing == data_type) {
+                data_types.push(data_type.to_string());
+            }
+        }
+    }
+    Ok(data_types)
+}
+
+/// Accepts a local calendar date (its midnight) or an RFC 3339 instant.
+fn parse_date_boundary(value: &str) -> Result<i64, HistoryQueryError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| Local.from_local_datetime(&midnight).earliest())
+            .map(|midnight| midnight.timestamp_millis())
+            .ok_or(HistoryQueryError::InvalidDate);
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|instant| instant.timestamp_millis())
+        .map_err(|_| HistoryQueryError::InvalidDate)
+}
+
+fn parse_size(value: &str) -> Result<(Comparison, u64), HistoryQueryError> {
+    let (comparison, amount) = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(prefix, comparison)| value.strip_prefix(prefix).map(|rest| (comparison, rest)))
+    .ok_or(HistoryQueryError::InvalidSize)?;
+
+    let split = amount
+        .find(|character: char| !character.is_ascii_digit() && character != '.')
+        .unwrap_or(amount.len());
+    let (number, unit) = amount.split_at(split);
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        _ => return Err(HistoryQueryError::InvalidSize),
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| HistoryQueryError::InvalidSize)?;
+    let bytes = (number * multiplier as f64).round();
+    if !bytes.is_finite() || bytes < 0.0 || bytes > i64::MAX as f64 {
+        return Err(HistoryQueryError::InvalidSize);
+    }
+    Ok((comparison, bytes as u64))
+}
+
+fn parse_boolean(value: &str) -> Result<bool, HistoryQueryError> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(HistoryQueryError::InvalidBoolean),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_parses_filters_terms_and_quoted_values() {
+        let query = HistoryQuery::parse(
+            "type:png,IMAGE app:com.figma.Figma size:>1mb remote:no \
+             \"docker compose\" https://example.com/a b",
+        )
+        .expect("query should parse");
+
+        assert_eq!(
+            query.terms,
+            ["docker compose", "https://example.com/a", "b"]
+        );
+        assert_eq!(
+            query.filters[0],
+            HistoryFilter::DataType(
+                IMAGE_DATA_TYPES
+                    .iter()
+                    .map(|data_type| data_type.to_string())
+                    .collect()
+            )
+        );
+        assert_eq!(
+            query.filters[1..],
+            [
+                HistoryFilter::SourceBundleId("com.figma.Figma".to_string()),
+                HistoryFilter::ByteCount(Comparison::Greater, 1024 * 1024),
+                HistoryFilter::Remote(false),
+            ]
+        );
+
+        let quoted = HistoryQuery::parse("type:\"files and folders\" \"type:png\"")
+            .expect("quoted query should parse");
+        assert_eq!(
+            quoted.filters,
+            [HistoryFilter::DataType(vec![
+                "files and folders".to_string()
+            ])]
+        );
+        assert_eq!(quoted.terms, ["type:png"]);
+        assert!(HistoryQuery::parse("   ")
+            .expect("blank query should parse")
+            .is_empty());
+    }
+
+    #[test]
+    fn query_dates_use_local_midnight_or_explicit_instants() {
+        let query = HistoryQuery::parse("after:2026-10-01 before:2026-10-02T08:30:00+02:00")
+            .expect("dates should parse");
+        let local_midnight = Local
+            .with_ymd_and_hms(2026, 10, 1, 0, 0, 0)
+            .earliest()
+            .expect("midnight should exist")
+            .timestamp_millis();
+
+        assert_eq!(
+            query.filters,
+            [
+                HistoryFilter::After(local_midnight),
+                HistoryFilter::Before(1_790_922_600_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn query_sizes_accept_units_and_comparisons() {
+        for (value, expected) in [
+            ("size:>=512", (Comparison::GreaterOrEqual, 512)),
+            ("size:<1.5kb", (Comparison::Less, 1536)),
+            ("size:<=2M", (Comparison::LessOrEqual, 2 * 1024 * 1024)),
+            ("size:=1gib", (Comparison::Equal, 1024 * 1024 * 1024)),
+        ] {
+            let query = HistoryQuery::parse(value).expect(value);
+            assert_eq!(
+                query.filters,
+                [HistoryFilter::ByteCount(expected.0, expected.1)],
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_queries_report_a_typed_error() {
+        for (value, expected) in [
+            ("type:pdf", HistoryQueryError::UnknownDataType),
+            ("type:", HistoryQueryError::EmptyFilterValue),
+            ("after:2026-13-01", HistoryQueryError::InvalidDate),
+            ("before:yesterday", HistoryQueryError::InvalidDate),
+            ("size:1mb", HistoryQueryError::InvalidSize),
+            ("size:>1tb", HistoryQueryError::InvalidSize),
+            ("size:>-1", HistoryQueryError::InvalidSize),
+            ("remote:maybe", HistoryQueryError::InvalidBoolean),
+            ("\"docker compose", HistoryQueryError::UnterminatedQuote),
+        ] {
+            assert_eq!(HistoryQuery::parse(value), Err(expected), "{value}");
+        }
+
+        let long = "a".repeat(MAX_QUERY_BYTES + 1);
+        assert_eq!(HistoryQuery::parse(&long), Err(HistoryQueryError::TooLong));
+        let terms = vec!["term"; MAX_QUERY_TERMS + 1].join(" ");
+        assert_eq!(
+            HistoryQuery::parse(&terms),
+            Err(HistoryQueryError::TooManyTerms)
+        );
+        let filters = vec!["remote:yes"; MAX_QUERY_FILTERS + 1].join(" ");
+        assert_eq!(
+            HistoryQuery::parse(&filters),
+            Err(HistoryQueryError::TooManyFilters)
+        );
+    }
+
+    #[test]
+    fn sql_filters_bind_every_value_after_existing_parameters() {
+        let query = HistoryQuery::parse("type:text,html app:com.example size:>10 remote:yes")
+            .expect("query should parse");
+        let mut parameters = vec![Value::Text("existing".to_string())];
+        let clause = query.sql_filters("event.data_type", &mut parameters);
+
+        assert_eq!(
+            clause,
+            " AND event.data_type IN (?2, ?3) AND event.source_bundle_id = ?4 \
+             AND event.byte_count > ?5 AND event.is_remote_clipboard = ?6"
+        );
+        assert_eq!(
+            parameters[1..],
+            [
+                Value::Text("text".to_string()),
+                Value::Text("html".to_string()),
+                Value::Text("com.example".to_string()),
+                Value::Integer(10),
+                Value::Integer(1),
+            ]
+        );
+    }
+}
@@ -1,10 +1,14 @@
-use rusqlite::{Connection, Result, Transaction};
+use rusqlite::{Connection, Result};
 
-pub(crate) const CURRENT_SCHEMA_VERSION: i64 = 2;
+pub(crate) const CURRENT_SCHEMA_VERSION: i64 = 13;
 pub(crate) const CLASSIFIER_METADATA_VERSION: i64 = 1;
 pub(crate) const CLASSIFIER_METADATA_KEY: &str = "classifier_metadata_version";
+pub(crate) const PAYLOAD_ENCRYPTION_VERSION: i64 = 1;
+pub(crate) const PAYLOAD_ENCRYPTION_KEY: &str = "payload_encryption_version";
+pub(crate) const INTEGRITY_KEY_METADATA_VERSION: i64 = 1;
+pub(crate) const INTEGRITY_KEY_METADATA_KEY: &str = "integrity_key_version";
 
-pub(crate) const REQUIRED_EVENT_COLUMNS: [&str; 13] = [
+pub(crate) const REQUIRED_EVENT_COLUMNS: [&str; 20] = [
     "content_hash",
     "event_data",
     "data_type",
@@ -18,14 +22,139 @@ pub(crate) const REQUIRED_EVENT_COLUMNS: [&str; 13] = [
     "byte_count",
     "timestamp",
     "metadata_version",
+    "pinned",
+    "first_seen_at",
+    "last_seen_at",
+    "copy_count",
+    "expires_at",
+    "integrity_mac",
+    "integrity_link",
 ];
 
+pub(crate) const SEARCH_INDEX_TRIGGERS: [&str; 4] = [
+    "clipboard_events_search_text_after_insert",
+    "clipboard_events_search_text_after_delete",
+    "clipboard_events_search_text_after_update",
+    "clipboard_events_search_unindex",
+];
+
+/// One registered `user_version` upgrade.
+pub(crate) struct Migration {
+    pub(crate) from: i64,
+    pub(crate) to: i64,
+    pub(crate) step: MigrationStep,
+}
+
+/// What a migration does to the existing `clipboard_events` rows.
+#[derive(Clone, Copy)]
+pub(crate) enum MigrationStep {
+    /// Decodes, reassesses, and reclassifies every row into a replacement
+    /// table. The replacement already has the current shape, so a rebuild
+    /// completes the migration.
+    Rebuild,
+    /// Additive DDL. Rows keep their payloads, MACs, and chain links.
+    Alter(fn(&Connection) -> Result<()>),
+    /// Seals the payload columns of every plaintext row in place.
+    SealPayloads,
+    /// Additive DDL whose new columns cover every row, after which the whole
+    /// chain is resealed.
+    AlterAndReseal(fn(&Connection) -> Result<()>),
+}
+
+/// Every schema upgrade, in order. Versions 0 and 1 stored legacy event
+/// encodings and no derived metadata, so only they still need a rebuild.
+pub(crate) const MIGRATIONS: [Migration; 13] = [
+    Migration {
+        from: 0,
+        to: 2,
+        step: MigrationStep::Rebuild,
+    },
+    Migration {
+        from: 1,
+        to: 2,
+        step: MigrationStep::Rebuild,
+    },
+    Migration {
+        from: 2,
+        to: 3,
+        step: MigrationStep::Alter(create_search_index_tables),
+    },
+    Migration {
+        from: 3,
+        to: 4,
+        step: MigrationStep::Alter(add_pinned_column),
+    },
+    Migration {
+        from: 4,
+        to: 5,
+        step: MigrationStep::Alter(create_label_tables),
+    },
+    Migration {
+        from: 5,
+        to: 6,
+        step: MigrationStep::Alter(add_copy_tracking_columns),
+    },
+    Migration {
+        from: 6,
+        to: 7,
+        step: MigrationStep::Alter(add_expiry_column),
+    },
+    Migration {
+        from: 7,
+        to: 8,
+        step: MigrationStep::SealPayloads,
+    },
+    Migration {
+        from: 8,
+        to: 9,
+        step: MigrationStep::AlterAndReseal(add_integrity_columns),
+    },
+    Migration {
+        from: 9,
+        to: 10,
+        step: MigrationStep::Alter(create_quarantine_table),
+    },
+    Migration {
+        from: 10,
+        to: 11,
+        step: MigrationStep::Alter(create_flavor_blob_tables),
+    },
+    Migration {
+        from: 11,
+        to: 12,
+        step: MigrationStep::Alter(create_flavor_blob_file_table),
+    },
+    Migration {
+        from: 12,
+        to: 13,
+        step: MigrationStep::Alter(drop_plaintext_search_index),
+    },
+];
+
+/// Returns the registered steps from `version` to `CURRENT_SCHEMA_VERSION`,
+/// ending early at a rebuild, or `None` when a version has no step.
+pub(crate) fn migration_path(version: i64) -> Option<Vec<&'static Migration>> {
+    let mut path = Vec::new();
+    let mut version = version;
+    while version < CURRENT_SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)?;
+        path.push(migration);
+        if matches!(migration.step, MigrationStep::Rebuild) {
+            break;
+        }
+        version = migration.to;
+    }
+    Some(path)
+}
+
 pub(crate) fn user_version(connection: &Connection) -> Result<i64> {
     connection.query_row("PRAGMA user_version", [], |row| row.get(0))
 }
 
-pub(crate) fn set_user_version(transaction: &Transaction<'_>, version: i64) -> Result<()> {
-    transaction.pragma_update(None, "user_version", version)
+pub(crate) fn set_user_version(connection: &Connection, version: i64) -> Result<()> {
+    connection.pragma_update(None, "user_version", version)
 }
 
 pub(crate) fn create_settings_table(connection: &Connection) -> Result<()> {
@@ -50,6 +179,63 @@ pub(crate) fn create_metadata_table(connection: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Creates the single-row table holding the database's wrapped payload key.
+pub(crate) fn create_payload_key_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS payload_key (
+            key_id INTEGER PRIMARY KEY CHECK (key_id = 1),
+            wrapped_key BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Creates the single-row table binding the newest history row's MAC.
+pub(crate) fn create_history_integrity_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS history_integrity (
+            key_id INTEGER PRIMARY KEY CHECK (key_id = 1),
+            head BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Rows that failed `check_history_integrity`, kept with their stored
+/// columns, still sealed under their original `content_hash`. They are never
+/// read back into history.
+pub(crate) fn create_quarantine_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS quarantined_events (
+            quarantine_id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL,
+            quarantined_at INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            event_data BLOB NOT NULL,
+            data_type TEXT NOT NULL,
+            display BLOB NOT NULL,
+            summary_display BLOB NOT NULL,
+            summary_truncated INTEGER NOT NULL,
+            compact_content_hash TEXT,
+            compact_display BLOB,
+            source_bundle_id TEXT,
+            is_remote_clipboard INTEGER NOT NULL,
+            byte_count INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            metadata_version INTEGER NOT NULL,
+            pinned INTEGER NOT NULL,
+            first_seen_at INTEGER NOT NULL,
+            last_seen_at INTEGER NOT NULL,
+            copy_count INTEGER NOT NULL,
+            expires_at INTEGER
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
 pub(crate) fn create_clipboard_events_table(connection: &Connection, table: &str) -> Result<()> {
     connection.execute(
         &format!(
@@ -66,7 +252,14 @@ pub(crate) fn create_clipboard_events_table(connection: &Connection, table: &str
                 is_remote_clipboard INTEGER NOT NULL,
                 byte_count INTEGER NOT NULL,
                 timestamp INTEGER NOT NULL,
-                metadata_version INTEGER NOT NULL
+                metadata_version INTEGER NOT NULL,
+                pinned INTEGER NOT NULL DEFAULT 0,
+                first_seen_at INTEGER NOT NULL,
+                last_seen_at INTEGER NOT NULL,
+                copy_count INTEGER NOT NULL DEFAULT 1,
+                expires_at INTEGER,
+                integrity_mac BLOB NOT NULL,
+                integrity_link BLOB NOT NULL
             )"
         ),
         [],
@@ -74,12 +267,45 @@ pub(crate) fn create_clipboard_events_table(connection: &Connection, table: &str
     Ok(())
 }
 
+/// Adds the version 4 pin flag. Existing rows start unpinned.
+fn add_pinned_column(connection: &Connection) -> Result<()> {
+    connection
+        .execute_batch("ALTER TABLE clipboard_events ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;")
+}
+
+/// Adds the version 6 copy statistics. An existing row counts as copied
+/// once, first and last seen at its ordering timestamp.
+fn add_copy_tracking_columns(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "ALTER TABLE clipboard_events ADD COLUMN first_seen_at INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE clipboard_events ADD COLUMN last_seen_at INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE clipboard_events ADD COLUMN copy_count INTEGER NOT NULL DEFAULT 1;
+         UPDATE clipboard_events SET first_seen_at = timestamp, last_seen_at = timestamp;",
+    )
+}
+
+/// Adds the version 7 expiry time. Existing rows never expire.
+fn add_expiry_column(connection: &Connection) -> Result<()> {
+    connection.execute_batch("ALTER TABLE clipboard_events ADD COLUMN expires_at INTEGER;")
+}
+
+/// Adds the version 9 MAC and link columns and the chain head. The columns
+/// start empty and are filled by the reseal that follows.
+fn add_integrity_columns(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "ALTER TABLE clipboard_events ADD COLUMN integrity_mac BLOB NOT NULL DEFAULT x'';
+         ALTER TABLE clipboard_events ADD COLUMN integrity_link BLOB NOT NULL DEFAULT x'';",
+    )?;
+    create_history_integrity_table(connection)
+}
+
 pub(crate) fn drop_clipboard_event_indexes(connection: &Connection) -> Result<()> {
     for index in [
         "idx_clipboard_events_content_hash",
         "idx_clipboard_events_sort_order",
         "idx_clipboard_events_timestamp",
         "idx_clipboard_events_compact",
+        "idx_clipboard_events_pinned",
     ] {
         connection.execute(&format!("DROP INDEX IF EXISTS {index}"), [])?;
     }
@@ -102,5 +328,249 @@ pub(crate) fn create_clipboard_event_indexes(connection: &Connection) -> Result<
          WHERE compact_content_hash IS NOT NULL",
         [],
     )?;
+    connection.execute(
+        "CREATE INDEX IF NOT EXISTS idx_clipboard_events_pinned
+         ON clipboard_events(pinned DESC, timestamp DESC, content_hash ASC)",
+        [],
+    )?;
     Ok(())
 }
+
+/// Creates the version 3 search text table and its external-content FTS5
+/// index. Version 13 drops both for `create_sealed_search_table`.
+///
+/// Storage writes `clipboard_events_search` by `content_hash`; the triggers
+/// keep the trigram index in step, so no caller touches the FTS5 table
+/// directly.
+pub(crate) fn create_search_index_tables(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS clipboard_events_search (
+            search_id INTEGER PRIMARY KEY,
+            content_hash TEXT NOT NULL UNIQUE,
+            body TEXT NOT NULL
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_events_fts USING fts5(
+            body,
+            content = 'clipboard_events_search',
+            content_rowid = 'search_id',
+            tokenize = 'trigram'
+        );
+        CREATE TRIGGER IF NOT EXISTS clipboard_events_search_after_insert
+        AFTER INSERT ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;
+        CREATE TRIGGER IF NOT EXISTS clipboard_events_search_after_delete
+        AFTER DELETE ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+        END;
+        CREATE TRIGGER IF NOT EXISTS clipboard_events_search_after_update
+        AFTER UPDATE ON clipboard_events_search BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;",
+    )
+}
+
+/// Creates the version 13 table of sealed search text, one row per
+/// `content_hash`. Its plaintext is only ever indexed in memory, by
+/// `create_memory_search_index`.
+pub(crate) fn create_sealed_search_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS clipboard_events_search (
+            content_hash TEXT PRIMARY KEY,
+            body BLOB NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Creates this connection's in-memory search text table and its
+/// external-content FTS5 trigram index.
+///
+/// Storage writes the text by `content_hash`; the triggers keep the trigram
+/// index in step and drop a row's text with its sealed copy, so no caller
+/// touches the FTS5 table directly.
+pub(crate) fn create_memory_search_index(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS clipboard_events_search_text (
+            search_id INTEGER PRIMARY KEY,
+            content_hash TEXT NOT NULL UNIQUE,
+            body TEXT NOT NULL
+        );
+        CREATE VIRTUAL TABLE IF NOT EXISTS temp.clipboard_events_fts USING fts5(
+            body,
+            content = 'clipboard_events_search_text',
+            content_rowid = 'search_id',
+            tokenize = 'trigram'
+        );
+        CREATE TEMP TRIGGER IF NOT EXISTS clipboard_events_search_text_after_insert
+        AFTER INSERT ON clipboard_events_search_text BEGIN
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;
+        CREATE TEMP TRIGGER IF NOT EXISTS clipboard_events_search_text_after_delete
+        AFTER DELETE ON clipboard_events_search_text BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+        END;
+        CREATE TEMP TRIGGER IF NOT EXISTS clipboard_events_search_text_after_update
+        AFTER UPDATE ON clipboard_events_search_text BEGIN
+            INSERT INTO clipboard_events_fts (clipboard_events_fts, rowid, body)
+            VALUES ('delete', old.search_id, old.body);
+            INSERT INTO clipboard_events_fts (rowid, body)
+            VALUES (new.search_id, new.body);
+        END;
+        CREATE TEMP TRIGGER IF NOT EXISTS clipboard_events_search_unindex
+        AFTER DELETE ON main.clipboard_events_search BEGIN
+            DELETE FROM clipboard_events_search_text
+            WHERE content_hash = old.content_hash;
+        END;",
+    )
+}
+
+/// Drops the version 3 plaintext search text table and its on-disk trigram
+/// index. The schema initialization that follows reseals the text from the
+/// stored rows.
+pub(crate) fn drop_plaintext_search_index(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "DROP TRIGGER IF EXISTS main.clipboard_events_search_after_insert;
+        DROP TRIGGER IF EXISTS main.clipboard_events_search_after_delete;
+        DROP TRIGGER IF EXISTS main.clipboard_events_search_after_update;
+        DROP TABLE IF EXISTS main.clipboard_events_fts;
+        DROP TABLE IF EXISTS main.clipboard_events_search;",
+    )
+}
+
+/// Creates the version 11 shared flavor payloads and the rows referencing
+/// them. Existing rows keep their payloads inline until they are written
+/// again.
+pub(crate) fn create_flavor_blob_tables(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS flavor_blobs (
+            digest TEXT PRIMARY KEY,
+            data BLOB NOT NULL,
+            byte_count INTEGER NOT NULL,
+            ref_count INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS flavor_blob_refs (
+            content_hash TEXT NOT NULL,
+            digest TEXT NOT NULL,
+            PRIMARY KEY (content_hash, digest)
+        );
+        CREATE INDEX IF NOT EXISTS idx_flavor_blob_refs_digest
+            ON flavor_blob_refs(digest);",
+    )
+}
+
+/// Creates the version 12 list of shared flavor payloads kept in files.
+/// Existing payloads stay in `flavor_blobs`.
+pub(crate) fn create_flavor_blob_file_table(connection: &Connection) -> Result<()> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS flavor_blob_files (
+            digest TEXT PRIMARY KEY
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Creates the collection and tag tables. Memberships reference history rows
+/// by `content_hash` without a foreign key; `store::labels` removes them
+/// alongside the row.
+pub(crate) fn create_label_tables(connection: &Connection) -> Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS collections (
+            collection_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+        CREATE TABLE IF NOT EXISTS collection_items (
+            collection_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            PRIMARY KEY (collection_id, content_hash)
+        );
+        CREATE INDEX IF NOT EXISTS idx_collection_items_content_hash
+            ON collection_items(content_hash);
+        CREATE TABLE IF NOT EXISTS tags (
+            tag_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+        CREATE TABLE IF NOT EXISTS item_tags (
+            tag_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            PRIMARY KEY (tag_id, content_hash)
+        );
+        CREATE INDEX IF NOT EXISTS idx_item_tags_content_hash
+            ON item_tags(content_hash);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_past_version_has_one_path_to_the_current_schema() {
+        for version in 0..CURRENT_SCHEMA_VERSION {
+            assert_eq!(
+                MIGRATIONS
+                    .iter()
+                    .filter(|migration| migration.from == version)
+                    .count(),
+                1,
+                "{version}"
+            );
+            let path = migration_path(version).expect("every version should have a path");
+            let last = path.last().expect("an old version should have a step");
+            assert!(
+                last.to == CURRENT_SCHEMA_VERSION || matches!(last.step, MigrationStep::Rebuild),
+                "{version}"
+            );
+            assert!(path
+                .windows(2)
+                .all(|steps| steps[0].to == steps[1].from && steps[0].from < steps[0].to));
+        }
+        assert!(migration_path(CURRENT_SCHEMA_VERSION)
+            .expect("the current version needs no step")
+            .is_empty());
+        assert!(MIGRATIONS
+            .iter()
+            .all(|migration| migration.to <= CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn the_plaintext_search_index_keeps_its_version_3_shape_until_version_13() {
+        let search_schema = |connection: &Connection| {
+            let mut statement = connection
+                .prepare(
+                    "SELECT name, sql FROM sqlite_master
+                     WHERE name LIKE 'clipboard_events_search%' OR name = 'clipboard_events_fts'
+                     ORDER BY name",
+                )
+                .expect("schema should prepare");
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .expect("schema should query")
+                .collect::<Result<Vec<_>>>()
+                .expect("schema should load");
+            rows
+        };
+        let fixture = Connection::open_in_memory().expect("fixture should open");
+        fixture
+            .execute_batch(include_str!("fixtures/schema_v3.sql"))
+            .expect("fixture should load");
+        let migrated = Connection::open_in_memory().expect("database should open");
+        create_search_index_tables(&migrated).expect("version 3 step should run");
+        assert_eq!(search_schema(&fixture).len(), 5);
+        assert_eq!(search_schema(&migrated), search_schema(&fixture));
+
+        drop_plaintext_search_index(&migrated).expect("version 13 step should run");
+        assert!(search_schema(&migrated).is_empty());
+    }
+}
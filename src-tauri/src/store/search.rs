@@ -0,0 +1,461 @@
+//! Pure full-text search projection, query normalization, and snippet parsing.
+//!
+//! Like classification, this module has no SQLite dependency. Storage decides
+//! when the search index is written; these functions decide which plain text a
+//! row contributes and how untrusted user input becomes an FTS5 expression.
+
+use crate::store::classification::{find_data, parse_file_display};
+use crate::store::models::{SearchSnippetSegment, MAX_SEARCH_SNIPPET_BYTES};
+use copy_event_listener::event::Event;
+
+/// Upper bound for the text indexed per row. Clipboard text beyond this prefix
+/// remains restorable but is not searchable.
+pub(super) const MAX_SEARCH_TEXT_BYTES: usize = 64 * 1024;
+/// The trigram tokenizer cannot match substrings shorter than three characters.
+pub(super) const MIN_SEARCH_TERM_CHARS: usize = 3;
+pub(super) const SNIPPET_HIGHLIGHT_START: char = '\u{e000}';
+pub(super) const SNIPPET_HIGHLIGHT_END: char = '\u{e001}';
+
+const HTML_SKIPPED_ELEMENTS: [&str; 4] = ["head", "script", "style", "title"];
+const HTML_BLOCK_ELEMENTS: [&str; 16] = [
+    "br", "p", "div", "li", "ul", "ol", "tr", "td", "th", "table", "h1", "h2", "h3", "h4", "h5",
+    "h6",
+];
+const RTF_SKIPPED_DESTINATIONS: [&str; 8] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+];
+
+/// Builds the bounded plain-text projection indexed for one stored row.
+///
+/// The compact projection, classified display, summary, and any HTML or RTF
+/// flavor are normalized to single-spaced text. A part already contained in an
+/// earlier part is skipped so formatted rows are not indexed twice.
+pub(super) fn search_text(
+    event: Option<&Event>,
+    display: &[u8],
+    summary_display: &[u8],
+    compact_display: Option<&[u8]>,
+) -> String {
+    let mut parts = Vec::new();
+    if let Some(compact_display) = compact_display.and_then(text_projection) {
+        push_search_part(&mut parts, &compact_display);
+    }
+    if let Some(display) = text_projection(display) {
+        push_search_part(&mut parts, &display);
+    }
+    if let Some(summary) = text_projection(summary_display) {
+        push_search_part(&mut parts, summary.strip_suffix("...").unwrap_or(&summary));
+    }
+    if let Some(event) = event {
+        if let Some(html) = find_data(event, "public.html") {
+            push_search_part(&mut parts, &html_text(&String::from_utf8_lossy(&html.data)));
+        }
+        if let Some(rtf) = find_data(event, "public.rtf") {
+            push_search_part(&mut parts, &rtf_text(&String::from_utf8_lossy(&rtf.data)));
+        }
+    }
+
+    let mut text = parts.join("\n");
+    if text.len() > MAX_SEARCH_TEXT_BYTES {
+        let mut end = MAX_SEARCH_TEXT_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+/// Converts parsed query terms into an FTS5 expression of quoted terms.
+///
+/// Every term is matched as a literal substring, so FTS5 operators and column
+/// filters typed by the user are never interpreted. Returns `None` when no term
+/// is long enough to match.
+pub(super) fn match_expression(terms: &[String]) -> Option<String> {
+    let terms = terms
+        .iter()
+        .filter(|term| term.chars().count() >= MIN_SEARCH_TERM_CHARS)
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Splits an FTS5 `snippet(...)` value into highlighted and plain segments,
+/// keeping at most `MAX_SEARCH_SNIPPET_BYTES` of text.
+pub(super) fn snippet_segments(snippet: &str) -> Vec<SearchSnippetSegment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut highlighted = false;
+    let mut remaining = MAX_SEARCH_SNIPPET_BYTES;
+
+    for character in snippet.chars() {
+        if character == SNIPPET_HIGHLIGHT_START || character == SNIPPET_HIGHLIGHT_END {
+            push_snippet_segment(&mut segments, &mut current, highlighted);
+            highlighted = character == SNIPPET_HIGHLIGHT_START;
+            continue;
+        }
+        if character.len_utf8() > remaining {
+            break;
+        }
+        remaining -= character.len_utf8();
+        current.push(character);
+    }
+    push_snippet_segment(&mut segments, &mut current, highlighted);
+    segments
+}
+
+fn push_snippet_segment(
+    segments: &mut Vec<SearchSnippetSegment>,
+    current: &mut String,
+    highlighted: bool,
+) {
+    if !current.is_empty() {
+        segments.push(SearchSnippetSegment {
+            text: std::mem::take(current),
+            highlighted,
+        });
+    }
+}
+
+fn text_projection(value: &[u8]) -> Option<String> {
+    if let Some(file_display) = parse_file_display(value) {
+        return Some(
+            file_display
+                .items
+                .into_iter()
+                .map(|item| item.name)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+    std::str::from_utf8(value).ok().map(str::to_string)
+}
+
+fn push_search_part(parts: &mut Vec<String>, text: &str) {
+    let normalized = text
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|character| {
+                    !matches!(
+                        *character,
+                        '\0' | SNIPPET_HIGHLIGHT_START | SNIPPET_HIGHLIGHT_END
+                    )
+                })
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if normalized.is_empty() || parts.iter().any(|part| part.contains(&normalized)) {
+        return;
+    }
+    parts.push(normalized);
+}
+
+fn html_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut output = String::new();
+    let mut position = 0;
+
+    while position < html.len() && output.len() < MAX_SEARCH_TEXT_BYTES {
+        let Some(offset) = html[position..].find(['<', '&']) else {
+            output.push_str(&html[position..]);
+            break;
+        };
+        let start = position + offset;
+        output.push_str(&html[position..start]);
+
+        if html.as_bytes()[start] == b'&' {
+            let (decoded, consumed) = decode_html_entity(&html[start..]);
+            output.push(decoded);
+            position = start + consumed;
+            continue;
+        }
+
+        let Some(tag_end) = html[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = &lower[start + 1..tag_end];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|character: char| character.is_ascii_whitespace() || character == '/')
+            .next()
+            .unwrap_or_default();
+        position = tag_end + 1;
+
+        if !closing && HTML_SKIPPED_ELEMENTS.contains(&name) {
+            position = lower[position..]
+                .find(&format!("</{name}"))
+                .map_or(html.len(), |end| position + end);
+        } else if HTML_BLOCK_ELEMENTS.contains(&name) {
+            output.push(' ');
+        }
+    }
+    output
+}
+
+fn decode_html_entity(value: &str) -> (char, usize) {
+    let Some(end) = value
+        .char_indices()
+        .take(12)
+        .find_map(|(index, character)| (character == ';').then_some(index))
+    else {
+        return ('&', 1);
+    };
+    let name = &value[1..end];
+    let decoded = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => name
+            .strip_prefix("#x")
+            .or_else(|| name.strip_prefix("#X"))
+            .map(|hex| u32::from_str_radix(hex, 16).ok())
+            .unwrap_or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+            .and_then(char::from_u32),
+    };
+    match decoded {
+        Some(character) => (character, end + 1),
+        None => ('&', 1),
+    }
+}
+
+fn rtf_text(rtf: &str) -> String {
+    let mut output = String::new();
+    let mut characters = rtf.chars().peekable();
+    let mut depth = 0_usize;
+    let mut skip_from_depth: Option<usize> = None;
+    let mut fallback_characters = 0_usize;
+
+    while let Some(character) = characters.next() {
+        if output.len() >= MAX_SEARCH_TEXT_BYTES {
+            break;
+        }
+        match character {
+            '{' => depth += 1,
+            '}' => {
+                if skip_from_depth == Some(depth) {
+                    skip_from_depth = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            '\\' => match characters.peek().copied() {
+                Some(letter) if letter.is_ascii_alphabetic() => {
+                    let mut word = String::new();
+                    while let Some(letter) = characters.next_if(char::is_ascii_alphabetic) {
+                        word.push(letter);
+                    }
+                    let mut parameter = String::new();
+                    if let Some(sign) = characters.next_if_eq(&'-') {
+                        parameter.push(sign);
+                    }
+                    while let Some(digit) = characters.next_if(char::is_ascii_digit) {
+                        parameter.push(digit);
+                    }
+                    characters.next_if_eq(&' ');
+
+                    if skip_from_depth.is_some() {
+                        continue;
+                    }
+                    if RTF_SKIPPED_DESTINATIONS.contains(&word.as_str()) {
+                        skip_from_depth = Some(depth);
+                        continue;
+                    }
+                    match word.as_str() {
+                        "par" | "line" | "tab" | "cell" | "row" => output.push(' '),
+                        "u" => {
+                            let code = parameter.parse::<i32>().unwrap_or_default();
+                            let code = if code < 0 { code + 65_536 } else { code };
+                            if let Some(decoded) = char::from_u32(code as u32) {
+                                output.push(decoded);
+                            }
+                            fallback_characters = 1;
+                        }
+                        _ => {}
+                    }
+                }
+                Some('\'') => {
+                    characters.next();
+                    let hex = characters.by_ref().take(2).collect::<String>();
+                    if skip_from_depth.is_none() {
+                        if fallback_characters > 0 {
+                            fallback_characters -= 1;
+                        } else if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                            output.push(char::from(byte));
+                        }
+                    }
+                }
+                Some('*') => {
+                    characters.next();
+                    skip_from_depth.get_or_insert(depth);
+                }
+                Some(escaped @ ('\\' | '{' | '}')) => {
+                    characters.next();
+                    if skip_from_depth.is_none() {
+                        output.push(escaped);
+                    }
+                }
+                _ => {
+                    characters.next();
+                }
+            },
+            '\r' | '\n' => {}
+            _ if skip_from_depth.is_some() => {}
+            _ if fallback_characters > 0 => fallback_characters -= 1,
+            _ => output.push(character),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use copy_event_listener::event::{Data, Item};
+
+    fn event(data_list: Vec<Data>) -> Event {
+        Event {
+            items: vec![Item { data_list }],
+        }
+    }
+
+    fn data(data_type: &str, value: &[u8]) -> Data {
+        Data {
+            r#type: data_type.to_string(),
+            data: value.to_vec(),
+        }
+    }
+
+    #[test]
+    fn search_text_extracts_formatted_flavors_without_markup_or_duplicates() {
+        let formatted = event(vec![
+            data(
+                "public.html",
+                b"<html><head><style>p { color: red; }</style></head>\
+                  <body><p>docker&nbsp;compose</p><script>ignored()</script><b>up -d</b></body></html>",
+            ),
+            data(
+                "public.rtf",
+                b"{\\rtf1\\ansi{\\fonttbl\\f0 Helvetica;}{\\*\\expandedcolortbl;;}\
+                  \\f0 caf\\'e9 \\u8364? total\\par}",
+            ),
+        ]);
+        let text = search_text(Some(&formatted), b"HTML", b"HTML", None);
+
+        assert!(text.contains("docker compose up -d"), "{text}");
+        assert!(text.contains("café € total"), "{text}");
+        for markup in [
+            "<",
+            "color",
+            "ignored",
+            "Helvetica",
+            "expandedcolortbl",
+            "\\",
+        ] {
+            assert!(!text.contains(markup), "{markup} leaked into {text}");
+        }
+
+        let plain = search_text(
+            None,
+            b"ssh deploy@example.internal",
+            b"ssh deploy@exa...",
+            Some(b"ssh   deploy@example.internal\n"),
+        );
+        assert_eq!(plain, "ssh deploy@example.internal");
+    }
+
+    #[test]
+    fn search_text_is_bounded_and_strips_snippet_markers() {
+        let long = format!(
+            "{}{}",
+            SNIPPET_HIGHLIGHT_START,
+            "é".repeat(MAX_SEARCH_TEXT_BYTES)
+        );
+        let text = search_text(None, long.as_bytes(), b"", None);
+
+        assert!(text.len() <= MAX_SEARCH_TEXT_BYTES);
+        assert!(!text.contains(SNIPPET_HIGHLIGHT_START));
+        assert_eq!(search_text(None, &[0xff, 0x00, 0x89], b"PNG", None), "PNG");
+    }
+
+    #[test]
+    fn html_entities_are_only_looked_for_within_their_length_limit() {
+        assert_eq!(decode_html_entity("&#xE9;é"), ('é', 6));
+        assert_eq!(
+            decode_html_entity(&format!("&é{};", "é".repeat(20))),
+            ('&', 1)
+        );
+        assert_eq!(
+            html_text(&format!("a&éé{}amp;b", "é".repeat(10))),
+            format!("a&éé{}amp;b", "é".repeat(10))
+        );
+    }
+
+    #[test]
+    fn match_expression_quotes_terms_and_drops_unmatchable_input() {
+        let terms = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            match_expression(&terms(&["docker", "compose"])).as_deref(),
+            Some("\"docker\" \"compose\"")
+        );
+        assert_eq!(
+            match_expression(&terms(&["body:x", "OR", "\"pass*", "NEAR"])).as_deref(),
+            Some("\"body:x\" \"\"\"pass*\" \"NEAR\"")
+        );
+        assert_eq!(
+            match_expression(&terms(&["docker compose"])).as_deref(),
+            Some("\"docker compose\"")
+        );
+        assert_eq!(
+            match_expression(&terms(&["密码本", "ab"])).as_deref(),
+            Some("\"密码本\"")
+        );
+        assert_eq!(match_expression(&terms(&["a", "bc"])), None);
+    }
+
+    #[test]
+    fn snippet_segments_split_highlights_and_respect_the_byte_budget() {
+        let snippet = format!("run {SNIPPET_HIGHLIGHT_START}docker{SNIPPET_HIGHLIGHT_END} compose");
+        assert_eq!(
+            snippet_segments(&snippet),
+            vec![
+                SearchSnippetSegment {
+                    text: "run ".to_string(),
+                    highlighted: false,
+                },
+                SearchSnippetSegment {
+                    text: "docker".to_string(),
+                    highlighted: true,
+                },
+                SearchSnippetSegment {
+                    text: " compose".to_string(),
+                    highlighted: false,
+                },
+            ]
+        );
+
+        let oversized = "界".repeat(MAX_SEARCH_SNIPPET_BYTES);
+        let bytes = snippet_segments(&oversized)
+            .iter()
+            .map(|segment| segment.text.len())
+            .sum::<usize>();
+        assert!(bytes <= MAX_SEARCH_SNIPPET_BYTES);
+    }
+}
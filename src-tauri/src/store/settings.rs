@@ -1,6 +1,12 @@
 use crate::i18n::LanguagePreference;
-use crate::store::models::DEFAULT_MAX_HISTORY_BYTES;
-use rusqlite::{Connection, Result};
+use crate::pasteboard_protocol::{SourceAppPolicy, MAX_SOURCE_BUNDLE_ID_BYTES};
+use crate::secret_detection::{SecretAction, SecretKind, SecretPolicy};
+use crate::store::models::{
+    DataTypeBudget, SourceAppRule, DEFAULT_MAX_HISTORY_BYTES, MAX_SOURCE_APP_RULES,
+};
+use crate::store::query::is_known_data_type;
+use rusqlite::{params, Connection, Result};
+use std::collections::BTreeMap;
 
 pub(super) const DEFAULT_MAX_ITEMS: u32 = 100;
 pub(super) const MAX_ITEMS_KEY: &str = "max_items";
@@ -8,18 +14,32 @@ pub(super) const MAX_HISTORY_BYTES_KEY: &str = "max_history_bytes";
 pub(super) const SHOW_IN_MENU_BAR_KEY: &str = "show_in_menu_bar";
 pub(super) const MENU_BAR_ITEM_LIMIT_KEY: &str = "menu_bar_item_limit";
 pub(super) const MOVE_RESTORED_ITEM_TO_TOP_KEY: &str = "move_restored_item_to_top";
+pub(super) const MOVE_RECOPIED_ITEM_TO_TOP_KEY: &str = "move_recopied_item_to_top";
 pub(super) const COMPACT_MODE_KEY: &str = "compact_mode";
 pub(super) const LANGUAGE_KEY: &str = "language";
+pub(super) const MAX_AGE_DAYS_KEY: &str = "max_age_days";
+pub(super) const SOURCE_ALLOW_ONLY_KEY: &str = "source_allow_only";
+/// Per-type budgets have no defaults; each limit is its own optional row
+/// keyed by one of these prefixes followed by the `data_type`.
+const DATA_TYPE_MAX_ITEMS_PREFIX: &str = "data_type_max_items:";
+const DATA_TYPE_MAX_BYTES_PREFIX: &str = "data_type_max_bytes:";
+/// Followed by a lowercased bundle identifier; the value is the rule.
+const SOURCE_APP_RULE_PREFIX: &str = "source_app_rule:";
+/// Followed by a `SecretKind`; a missing row means the kind's default action.
+const SECRET_POLICY_PREFIX: &str = "secret_policy:";
 
-pub(super) fn default_entries() -> [(&'static str, String); 7] {
+pub(super) fn default_entries() -> [(&'static str, String); 10] {
     [
         (MAX_ITEMS_KEY, DEFAULT_MAX_ITEMS.to_string()),
         (MAX_HISTORY_BYTES_KEY, DEFAULT_MAX_HISTORY_BYTES.to_string()),
         (SHOW_IN_MENU_BAR_KEY, "true".to_string()),
         (MENU_BAR_ITEM_LIMIT_KEY, "0".to_string()),
         (MOVE_RESTORED_ITEM_TO_TOP_KEY, "false".to_string()),
+        (MOVE_RECOPIED_ITEM_TO_TOP_KEY, "false".to_string()),
         (COMPACT_MODE_KEY, "false".to_string()),
         (LANGUAGE_KEY, "system".to_string()),
+        (MAX_AGE_DAYS_KEY, "0".to_string()),
+        (SOURCE_ALLOW_ONLY_KEY, "false".to_string()),
     ]
 }
 
@@ -39,6 +59,187 @@ pub(super) fn set_max_history_bytes(connection: &Connection, value: u64) -> Resu
     set(connection, MAX_HISTORY_BYTES_KEY, &value.to_string())
 }
 
+/// `0` keeps rows regardless of age.
+pub(super) fn get_max_age_days(connection: &Connection) -> Result<u32> {
+    get_u32(connection, MAX_AGE_DAYS_KEY, 0)
+}
+
+pub(super) fn set_max_age_days(connection: &Connection, value: u32) -> Result<()> {
+    set(connection, MAX_AGE_DAYS_KEY, &value.to_string())
+}
+
+/// Returns configured budgets ordered by `data_type`, which is also the order
+/// cleanup applies them in.
+pub(super) fn get_data_type_budgets(connection: &Connection) -> Result<Vec<DataTypeBudget>> {
+    let mut statement = connection.prepare(
+        "SELECT key, value FROM settings
+         WHERE substr(key, 1, ?1) = ?2 OR substr(key, 1, ?3) = ?4",
+    )?;
+    let rows = statement.query_map(
+        params![
+            DATA_TYPE_MAX_ITEMS_PREFIX.len() as i64,
+            DATA_TYPE_MAX_ITEMS_PREFIX,
+            DATA_TYPE_MAX_BYTES_PREFIX.len() as i64,
+            DATA_TYPE_MAX_BYTES_PREFIX,
+        ],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    )?;
+    let mut budgets = BTreeMap::new();
+    for row in rows {
+        let (key, value) = row?;
+        let invalid = |error: std::num::ParseIntError| {
+            rusqlite::Error::InvalidParameterName(error.to_string())
+        };
+        if let Some(data_type) = key.strip_prefix(DATA_TYPE_MAX_ITEMS_PREFIX) {
+            budget_entry(&mut budgets, data_type).max_items =
+                Some(value.parse::<u32>().map_err(invalid)?);
+        } else if let Some(data_type) = key.strip_prefix(DATA_TYPE_MAX_BYTES_PREFIX) {
+            budget_entry(&mut budgets, data_type).max_bytes =
+                Some(value.parse::<u64>().map_err(invalid)?);
+        }
+    }
+    Ok(budgets.into_values().collect())
+}
+
+/// Stores both limits for `data_type`; `None` removes that limit.
+pub(super) fn set_data_type_budget(
+    connection: &Connection,
+    data_type: &str,
+    max_items: Option<u32>,
+    max_bytes: Option<u64>,
+) -> Result<()> {
+    let items_key = format!("{DATA_TYPE_MAX_ITEMS_PREFIX}{data_type}");
+    let bytes_key = format!("{DATA_TYPE_MAX_BYTES_PREFIX}{data_type}");
+    match max_items {
+        Some(value) => set(connection, &items_key, &value.to_string())?,
+        None => delete(connection, &items_key)?,
+    }
+    match max_bytes {
+        Some(value) => set(connection, &bytes_key, &value.to_string()),
+        None => delete(connection, &bytes_key),
+    }
+}
+
+pub(super) fn get_source_app_policy(connection: &Connection) -> Result<SourceAppPolicy> {
+    let mut statement = connection.prepare(
+        "SELECT key, value FROM settings
+         WHERE substr(key, 1, ?1) = ?2
+         ORDER BY key",
+    )?;
+    let rows = statement.query_map(
+        params![SOURCE_APP_RULE_PREFIX.len() as i64, SOURCE_APP_RULE_PREFIX],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    )?;
+    let mut policy = SourceAppPolicy {
+        allow_only: get_bool(connection, SOURCE_ALLOW_ONLY_KEY, false)?,
+        ..SourceAppPolicy::default()
+    };
+    for row in rows {
+        let (key, value) = row?;
+        let bundle_id = key[SOURCE_APP_RULE_PREFIX.len()..].to_string();
+        match value.as_str() {
+            "deny" => policy.denied.push(bundle_id),
+            "allow" => policy.allowed.push(bundle_id),
+            _ => {
+                return Err(rusqlite::Error::InvalidParameterName(
+                    "invalid source application rule".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(policy)
+}
+
+/// Stores or removes the rule for `bundle_id`. Returns `false` without writing
+/// when the identifier is malformed or a new rule would exceed
+/// `MAX_SOURCE_APP_RULES`.
+pub(super) fn set_source_app_rule(
+    connection: &Connection,
+    bundle_id: &str,
+    rule: Option<SourceAppRule>,
+) -> Result<bool> {
+    let Some(bundle_id) = normalize_source_bundle_id(bundle_id) else {
+        return Ok(false);
+    };
+    let key = format!("{SOURCE_APP_RULE_PREFIX}{bundle_id}");
+    let Some(rule) = rule else {
+        delete(connection, &key)?;
+        return Ok(true);
+    };
+
+    if get(connection, &key)?.is_none() {
+        let rule_count: i64 = connection.query_row(
+            "SELECT COUNT(*) FROM settings WHERE substr(key, 1, ?1) = ?2",
+            params![SOURCE_APP_RULE_PREFIX.len() as i64, SOURCE_APP_RULE_PREFIX],
+            |row| row.get(0),
+        )?;
+        if rule_count as usize >= MAX_SOURCE_APP_RULES {
+            return Ok(false);
+        }
+    }
+    let value = match rule {
+        SourceAppRule::Deny => "deny",
+        SourceAppRule::Allow => "allow",
+    };
+    set(connection, &key, value)?;
+    Ok(true)
+}
+
+pub(super) fn set_source_allow_only(connection: &Connection, value: bool) -> Result<()> {
+    set(connection, SOURCE_ALLOW_ONLY_KEY, bool_value(value))
+}
+
+pub(super) fn get_secret_policy(connection: &Connection) -> Result<SecretPolicy> {
+    let mut policy = SecretPolicy::default();
+    for kind in SecretKind::ALL {
+        let Some(value) = get(connection, &secret_policy_key(kind))? else {
+            continue;
+        };
+        let action = SecretAction::from_code(&value).ok_or_else(|| {
+            rusqlite::Error::InvalidParameterName("invalid secret policy".to_string())
+        })?;
+        policy.set(kind, action);
+    }
+    Ok(policy)
+}
+
+pub(super) fn set_secret_policy(
+    connection: &Connection,
+    kind: SecretKind,
+    action: SecretAction,
+) -> Result<()> {
+    set(connection, &secret_policy_key(kind), action.as_str())
+}
+
+fn secret_policy_key(kind: SecretKind) -> String {
+    format!("{SECRET_POLICY_PREFIX}{}", kind.as_str())
+}
+
+/// Bundle identifiers are reverse-DNS ASCII; anything else cannot match a
+/// source marker the policy would ever see.
+fn normalize_source_bundle_id(bundle_id: &str) -> Option<String> {
+    let bundle_id = bundle_id.trim();
+    (!bundle_id.is_empty()
+        && bundle_id.len() <= MAX_SOURCE_BUNDLE_ID_BYTES
+        && bundle_id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_')))
+    .then(|| bundle_id.to_ascii_lowercase())
+}
+
+fn budget_entry<'budgets>(
+    budgets: &'budgets mut BTreeMap<String, DataTypeBudget>,
+    data_type: &str,
+) -> &'budgets mut DataTypeBudget {
+    budgets
+        .entry(data_type.to_string())
+        .or_insert_with(|| DataTypeBudget {
+            data_type: data_type.to_string(),
+            max_items: None,
+            max_bytes: None,
+        })
+}
+
 pub(super) fn get_show_in_menu_bar(connection: &Connection) -> Result<bool> {
     get_bool(connection, SHOW_IN_MENU_BAR_KEY, true)
 }
@@ -63,6 +264,14 @@ pub(super) fn set_move_restored_item_to_top(connection: &Connection, value: bool
     set(connection, MOVE_RESTORED_ITEM_TO_TOP_KEY, bool_value(value))
 }
 
+pub(super) fn get_move_recopied_item_to_top(connection: &Connection) -> Result<bool> {
+    get_bool(connection, MOVE_RECOPIED_ITEM_TO_TOP_KEY, false)
+}
+
+pub(super) fn set_move_recopied_item_to_top(connection: &Connection, value: bool) -> Result<()> {
+    set(connection, MOVE_RECOPIED_ITEM_TO_TOP_KEY, bool_value(value))
+}
+
 pub(super) fn get_compact_mode(connection: &Connection) -> Result<bool> {
     get_bool(connection, COMPACT_MODE_KEY, false)
 }
@@ -82,6 +291,71 @@ pub(super) fn set_language(connection: &Connection, value: LanguagePreference) -
     set(connection, LANGUAGE_KEY, value.code())
 }
 
+/// Every stored setting ordered by key, for a backup archive.
+pub(super) fn all_entries(connection: &Connection) -> Result<Vec<(String, String)>> {
+    let mut statement = connection.prepare("SELECT key, value FROM settings ORDER BY key")?;
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Replaces every setting with `entries` from a backup archive, falling back
+/// to the defaults for keys it omits. Returns `false` without writing when an
+/// entry is not a setting this version reads or its value would not parse.
+pub(super) fn restore_entries(
+    connection: &Connection,
+    entries: &[(String, String)],
+) -> Result<bool> {
+    let source_rules = entries
+        .iter()
+        .filter(|(key, _)| key.starts_with(SOURCE_APP_RULE_PREFIX))
+        .count();
+    if source_rules > MAX_SOURCE_APP_RULES
+        || !entries
+            .iter()
+            .all(|(key, value)| entry_is_valid(key, value))
+    {
+        return Ok(false);
+    }
+
+    connection.execute("DELETE FROM settings", [])?;
+    for (key, value) in default_entries() {
+        set(connection, key, &value)?;
+    }
+    for (key, value) in entries {
+        set(connection, key, value)?;
+    }
+    Ok(true)
+}
+
+fn entry_is_valid(key: &str, value: &str) -> bool {
+    let is_bool = matches!(value, "true" | "false" | "0" | "1");
+    match key {
+        MAX_ITEMS_KEY | MENU_BAR_ITEM_LIMIT_KEY | MAX_AGE_DAYS_KEY => value.parse::<u32>().is_ok(),
+        MAX_HISTORY_BYTES_KEY => value.parse::<u64>().is_ok(),
+        SHOW_IN_MENU_BAR_KEY
+        | MOVE_RESTORED_ITEM_TO_TOP_KEY
+        | MOVE_RECOPIED_ITEM_TO_TOP_KEY
+        | COMPACT_MODE_KEY
+        | SOURCE_ALLOW_ONLY_KEY => is_bool,
+        LANGUAGE_KEY => LanguagePreference::from_code(value).is_some(),
+        _ => {
+            if let Some(data_type) = key.strip_prefix(DATA_TYPE_MAX_ITEMS_PREFIX) {
+                is_known_data_type(data_type) && value.parse::<u32>().is_ok()
+            } else if let Some(data_type) = key.strip_prefix(DATA_TYPE_MAX_BYTES_PREFIX) {
+                is_known_data_type(data_type) && value.parse::<u64>().is_ok()
+            } else if let Some(bundle_id) = key.strip_prefix(SOURCE_APP_RULE_PREFIX) {
+                normalize_source_bundle_id(bundle_id).as_deref() == Some(bundle_id)
+                    && matches!(value, "deny" | "allow")
+            } else if let Some(kind) = key.strip_prefix(SECRET_POLICY_PREFIX) {
+                SecretKind::ALL.iter().any(|known| known.as_str() == kind)
+                    && SecretAction::from_code(value).is_some()
+            } else {
+                false
+            }
+        }
+    }
+}
+
 pub(super) fn get(connection: &Connection, key: &str) -> Result<Option<String>> {
     let mut statement = connection.prepare("SELECT value FROM settings WHERE key = ?1")?;
     let mut rows = statement.query([key])?;
@@ -96,6 +370,11 @@ pub(super) fn set(connection: &Connection, key: &str, value: &str) -> Result<()>
     Ok(())
 }
 
+fn delete(connection: &Connection, key: &str) -> Result<()> {
+    connection.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+    Ok(())
+}
+
 fn get_u32(connection: &Connection, key: &str, default: u32) -> Result<u32> {
     match get(connection, key)? {
         Some(value) => value
@@ -169,4 +448,32 @@ mod tests {
         set(&connection, MENU_BAR_ITEM_LIMIT_KEY, "invalid").unwrap();
         assert!(get_menu_bar_item_limit(&connection).is_err());
     }
+
+    #[test]
+    fn restored_entries_replace_settings_only_when_every_entry_is_readable() {
+        let source = connection();
+        set_max_items(&source, 42).unwrap();
+        set_source_app_rule(&source, "com.example.Vault", Some(SourceAppRule::Deny)).unwrap();
+        let exported = all_entries(&source).unwrap();
+
+        let target = connection();
+        set_compact_mode(&target, true).unwrap();
+        assert!(restore_entries(&target, &exported).unwrap());
+        assert_eq!(all_entries(&target).unwrap(), exported);
+        assert!(!get_compact_mode(&target).unwrap());
+
+        for (key, value) in [
+            ("unknown_setting", "1"),
+            (MAX_ITEMS_KEY, "-1"),
+            (COMPACT_MODE_KEY, "maybe"),
+            ("data_type_max_items:not-a-type", "5"),
+            ("source_app_rule:Com.Example.Vault", "deny"),
+            ("secret_policy:jwt", "shred"),
+        ] {
+            let mut entries = exported.clone();
+            entries.push((key.to_string(), value.to_string()));
+            assert!(!restore_entries(&target, &entries).unwrap(), "{key}");
+            assert_eq!(all_entries(&target).unwrap(), exported);
+        }
+    }
 }
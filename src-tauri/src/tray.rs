@@ -6,7 +6,7 @@ use crate::{
     report_restore_post_processing_failure, report_tray_operation_failure,
     restore_event_to_clipboard, schedule_history_mirror_for_tray, AppState,
 };
-use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder};
+use tauri::menu::{Menu, MenuBuilder, MenuItem, MenuItemBuilder};
 use tauri::tray::TrayIconBuilder;
 use tauri::{image::Image, AppHandle, Emitter, Manager, Runtime};
 
@@ -17,6 +17,7 @@ const OPEN_SETTINGS_ID: &str = "action::open-settings";
 const CLEAR_HISTORY_ID: &str = "action::clear-history";
 const QUIT_ID: &str = "action::quit";
 const HEADER_ID: &str = "label::recent-items";
+const PINNED_HEADER_ID: &str = "label::pinned-items";
 const EMPTY_STATE_ID: &str = "label::empty";
 pub(crate) const EVENT_MENU_START_INDEX: usize = 2;
 const MAX_MENU_LABEL_WIDTH: usize = 40;
@@ -24,6 +25,7 @@ const TRUNCATION_SUFFIX: &str = "...";
 const ERROR_APP_STATE_UNAVAILABLE: &str = "app_state_unavailable";
 const ERROR_CLIPBOARD_ITEM_UNAVAILABLE: &str = "clipboard_item_unavailable";
 const ERROR_CLIPBOARD_RESTORE_FAILED: &str = "clipboard_restore_failed";
+const ERROR_HISTORY_INTEGRITY_FAILED: &str = "history_integrity_failed";
 const ERROR_HISTORY_OPERATION_FAILED: &str = "history_operation_failed";
 const ERROR_MAIN_WINDOW_UNAVAILABLE: &str = "main_window_unavailable";
 const ERROR_MENU_BUILD_FAILED: &str = "menu_build_failed";
@@ -38,7 +40,9 @@ pub const SETTINGS_PAGE: &str = "settings";
 
 struct BuiltTrayMenu<R: Runtime> {
     menu: Menu<R>,
-    event_hashes: Vec<String>,
+    /// One slot per menu item from `EVENT_MENU_START_INDEX`; headers and
+    /// separators between the pinned and recent sections are `None`.
+    event_hashes: Vec<Option<String>>,
 }
 
 pub fn setup<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
@@ -158,6 +162,7 @@ fn restore_event<R: Runtime>(app: &AppHandle<R>, content_hash: &str) -> Result<(
         let seed = db
             .get_restore_seed(content_hash)
             .map_err(|_| ERROR_HISTORY_OPERATION_FAILED.to_string())?
+            .map_err(|_| ERROR_HISTORY_INTEGRITY_FAILED.to_string())?
             .ok_or_else(|| ERROR_CLIPBOARD_ITEM_UNAVAILABLE.to_string())?;
         let move_restored_item_to_top = db
             .get_move_restored_item_to_top()
@@ -225,7 +230,13 @@ fn build_menu<R: Runtime>(app: &AppHandle<R>) -> Result<BuiltTrayMenu<R>, String
         )
     };
     let strings = native_strings(language);
+    let (pinned_events, recent_events): (Vec<_>, Vec<_>) =
+        events.iter().partition(|event| event.pinned);
 
+    let pinned_items = MenuItemBuilder::with_id(PINNED_HEADER_ID, strings.pinned_clipboard_items)
+        .enabled(false)
+        .build(app)
+        .map_err(|_| ERROR_MENU_BUILD_FAILED.to_string())?;
     let recent_items = MenuItemBuilder::with_id(HEADER_ID, strings.recent_clipboard_items)
         .enabled(false)
         .build(app)
@@ -248,25 +259,33 @@ fn build_menu<R: Runtime>(app: &AppHandle<R>) -> Result<BuiltTrayMenu<R>, String
         .build(app)
         .map_err(|_| ERROR_MENU_BUILD_FAILED.to_string())?;
 
-    let mut builder = MenuBuilder::new(app).item(&recent_items).separator();
+    let mut builder = MenuBuilder::new(app);
+    let mut event_hashes = Vec::new();
 
-    if events.is_empty() {
-        builder = builder.item(&empty_state);
-    } else {
-        for event in &events {
-            let menu_label = event_menu_label(event, language);
-            let event_item_id = format!("{}{}", EVENT_ITEM_PREFIX, event.content_hash.as_str());
-            let item = MenuItemBuilder::with_id(event_item_id, menu_label)
-                .build(app)
-                .map_err(|_| ERROR_MENU_BUILD_FAILED.to_string())?;
-            builder = builder.item(&item);
+    if !pinned_events.is_empty() {
+        builder = builder.item(&pinned_items).separator();
+        for event in &pinned_events {
+            builder = builder.item(&event_menu_item(app, event, language)?);
+            event_hashes.push(Some(event.content_hash.clone()));
+        }
+        if !recent_events.is_empty() {
+            // Section separator, recent header, and its separator.
+            builder = builder.separator();
+            event_hashes.extend([None, None, None]);
+        }
+    }
+
+    if pinned_events.is_empty() || !recent_events.is_empty() {
+        builder = builder.item(&recent_items).separator();
+        if recent_events.is_empty() {
+            builder = builder.item(&empty_state);
+        }
+        for event in &recent_events {
+            builder = builder.item(&event_menu_item(app, event, language)?);
+            event_hashes.push(Some(event.content_hash.clone()));
         }
     }
 
-    let event_hashes = events
-        .iter()
-        .map(|event| event.content_hash.clone())
-        .collect();
     let menu = builder
         .separator()
         .item(&open_history)
@@ -280,6 +299,17 @@ fn build_menu<R: Runtime>(app: &AppHandle<R>) -> Result<BuiltTrayMenu<R>, String
     Ok(BuiltTrayMenu { menu, event_hashes })
 }
 
+fn event_menu_item<R: Runtime>(
+    app: &AppHandle<R>,
+    event: &TrayEvent,
+    language: Language,
+) -> Result<MenuItem<R>, String> {
+    let event_item_id = format!("{}{}", EVENT_ITEM_PREFIX, event.content_hash.as_str());
+    MenuItemBuilder::with_id(event_item_id, event_menu_label(event, language))
+        .build(app)
+        .map_err(|_| ERROR_MENU_BUILD_FAILED.to_string())
+}
+
 fn event_menu_label(event: &TrayEvent, language: Language) -> String {
     truncate_label(event_menu_full_label(event, language))
 }
@@ -462,6 +492,7 @@ mod tests {
             content_hash: "hash".to_string(),
             data_type: "files and folders".to_string(),
             display: Vec::new(),
+            pinned: false,
         };
 
         assert_eq!(
@@ -477,6 +508,7 @@ mod tests {
                 content_hash: format!("{index:064x}"),
                 data_type: "text".to_string(),
                 display: format!("clipboard item {index}").into_bytes(),
+                pinned: false,
             })
             .collect::<Vec<_>>();
 
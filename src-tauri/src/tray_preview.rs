@@ -40,7 +40,7 @@ struct PreviewLayout {
 }
 
 struct TrayPreviewMenuDelegateIvars {
-    content_hashes: Vec<String>,
+    content_hashes: Vec<Option<String>>,
     loader: PreviewLoader,
     panel: Retained<NSPanel>,
     text_view: Retained<NSTextView>,
@@ -79,7 +79,7 @@ thread_local! {
 impl TrayPreviewMenuDelegate {
     fn new(
         mtm: MainThreadMarker,
-        content_hashes: Vec<String>,
+        content_hashes: Vec<Option<String>>,
         loader: PreviewLoader,
         menu_anchor_x: f64,
     ) -> Retained<Self> {
@@ -109,7 +109,12 @@ impl TrayPreviewMenuDelegate {
             self.hide_preview();
             return;
         };
-        let Some(content_hash) = self.ivars().content_hashes.get(event_index) else {
+        let Some(content_hash) = self
+            .ivars()
+            .content_hashes
+            .get(event_index)
+            .and_then(Option::as_ref)
+        else {
             self.hide_preview();
             return;
         };
@@ -170,7 +175,7 @@ impl TrayPreviewMenuDelegate {
 pub(crate) fn install<R: Runtime>(
     app: &AppHandle<R>,
     tray: &TrayIcon<R>,
-    content_hashes: Vec<String>,
+    content_hashes: Vec<Option<String>>,
 ) -> Result<(), String> {
     let preview_app = app.clone();
     let loader: PreviewLoader = Arc::new(move |content_hash| {
@@ -11,29 +11,63 @@ const errorCodes = new Set<ErrorCode>([
   "database_unavailable",
   "database_operation_failed",
   "history_item_not_found",
+  "pinned_limit_reached",
+  "invalid_label_name",
+  "label_name_taken",
+  "label_not_found",
+  "label_limit_reached",
   "clipboard_write_failed",
   "restore_post_processing_failed",
   "invalid_setting",
   "invalid_history_cursor",
+  "invalid_search_query",
   "state_unavailable",
   "autostart_unavailable",
   "autostart_verification_failed",
   "history_mirror_failed",
   "capture_rejected",
+  "history_integrity_failed",
+  "invalid_backup_passphrase",
+  "invalid_backup",
+  "backup_file_unavailable",
+  "database_backup_not_found",
+  "database_corrupted",
+  "invalid_history_import",
+  "history_import_file_unavailable",
+  "history_export_file_unavailable",
+  "control_socket_unavailable",
+  "invalid_control_request",
 ]);
 
 const operations = new Set<Operation>([
   "startup",
   "capture_clipboard",
   "load_history",
+  "search_history",
   "load_history_detail",
   "restore_clipboard",
+  "pin_history",
+  "unpin_history",
+  "load_labels",
+  "update_collections",
+  "update_tags",
   "delete_history",
   "clear_history",
   "load_settings",
   "update_settings",
   "update_autostart",
   "write_history_mirror",
+  "expire_history",
+  "verify_history",
+  "check_history",
+  "export_backup",
+  "import_backup",
+  "load_database_backups",
+  "restore_database_backup",
+  "delete_database_backup",
+  "import_history",
+  "export_history",
+  "control_request",
 ]);
 
 export class TauriCommandError extends Error implements CommandError {
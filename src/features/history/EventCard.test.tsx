@@ -18,6 +18,10 @@ const textSummary: HistorySummary = {
   timestamp: 0,
   byte_count: 7,
   has_detail: false,
+  pinned: false,
+  first_seen_at: 0,
+  last_seen_at: 0,
+  copy_count: 1,
 };
 
 const htmlDetail: HistoryDetail = {
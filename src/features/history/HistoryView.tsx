@@ -10,7 +10,11 @@ import { DiagnosticErrorBanner } from "../../components/DiagnosticErrorBanner";
 import type { Messages, SupportedLanguage } from "../../i18n";
 import { useClipboardHistory } from "../../hooks/useClipboardHistory";
 import { useHistoryDetails } from "../../hooks/useHistoryDetails";
-import type { Operation } from "../../types";
+import type {
+  CaptureRejectedNotice,
+  Operation,
+  SecretKind,
+} from "../../types";
 import { canLoadHistoryDetail } from "./detailCache";
 import { EventCard } from "./EventCard";
 import { observeHistoryEnd } from "./historyInfiniteScroll";
@@ -77,6 +81,20 @@ function restoreScrollAnchor(
   });
 }
 
+const SECRET_CAPTURE_CODE_PREFIX = "capture.secret.";
+
+function captureNoticeMessage(
+  messages: Messages,
+  notice: CaptureRejectedNotice
+): string {
+  const kind = notice.code?.startsWith(SECRET_CAPTURE_CODE_PREFIX)
+    ? (notice.code.slice(SECRET_CAPTURE_CODE_PREFIX.length) as SecretKind)
+    : null;
+  return kind && kind in messages.secretKinds
+    ? messages.captureSecretRejected(messages.secretKinds[kind])
+    : messages.captureRejected;
+}
+
 export function HistoryView({
   compactMode,
   language,
@@ -120,7 +138,8 @@ export function HistoryView({
   const [actionFailure, setActionFailure] = useState<ActionFailure | null>(
     null
   );
-  const [captureNotice, setCaptureNotice] = useState(false);
+  const [captureNotice, setCaptureNotice] =
+    useState<CaptureRejectedNotice | null>(null);
 
   const refreshPreservingView = useCallback(async () => {
     const anchor = captureScrollAnchor(listRef.current);
@@ -317,9 +336,12 @@ export function HistoryView({
       }
       unlisteners.push(historyUnlisten);
 
-      const captureUnlisten = await listen("capture-rejected", () => {
-        setCaptureNotice(true);
-      });
+      const captureUnlisten = await listen<CaptureRejectedNotice>(
+        "capture-rejected",
+        event => {
+          setCaptureNotice(event.payload);
+        }
+      );
       if (disposed) {
         captureUnlisten();
         return;
@@ -382,11 +404,11 @@ export function HistoryView({
 
         {captureNotice && (
           <aside aria-live="polite" className="capture-notice" role="status">
-            <span>{messages.captureRejected}</span>
+            <span>{captureNoticeMessage(messages, captureNotice)}</span>
             <button
               aria-label={messages.dismiss}
               className="capture-notice-dismiss"
-              onClick={() => setCaptureNotice(false)}
+              onClick={() => setCaptureNotice(null)}
               type="button"
             >
               {messages.dismiss}
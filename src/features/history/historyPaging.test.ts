@@ -13,6 +13,10 @@ function summary(index: number): HistorySummary {
     timestamp: 10_000 - index,
     byte_count: 1,
     has_detail: true,
+    pinned: false,
+    first_seen_at: 10_000 - index,
+    last_seen_at: 10_000 - index,
+    copy_count: 1,
   };
 }
 
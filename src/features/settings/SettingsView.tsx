@@ -2,23 +2,41 @@ import {
   AlertTriangle,
   ArrowLeft,
   ArrowUpDown,
+  Download,
   Eye,
   EyeOff,
+  RotateCcw,
+  ShieldCheck,
   Trash2,
   Type,
+  Upload,
+  Wrench,
 } from "lucide-react";
-import { useEffect, useState } from "react";
+import { useCallback, useEffect, useState } from "react";
 import { invokeCommand } from "../../api/tauri";
 import { DiagnosticErrorBanner } from "../../components/DiagnosticErrorBanner";
 import {
+  getEventTypeLabel,
   isLanguagePreference,
   languageDisplayNames,
   languagePreferences,
 } from "../../i18n";
 import type { Messages, SupportedLanguage } from "../../i18n";
 import type { AppSettingsController } from "../../hooks/useAppSettings";
+import type {
+  DatabaseBackup,
+  HistoryBackupReport,
+  HistoryCheckReport,
+  HistoryImportReport,
+  HistoryIntegrityReport,
+  SecretAction,
+  SourceAppRule,
+} from "../../types";
 import { formatBytes } from "../../lib/display";
 
+const secretActions: SecretAction[] = ["skip", "mask", "expire"];
+const minBackupPassphraseLength = 8;
+
 interface SettingsViewProps {
   controller: AppSettingsController;
   language: SupportedLanguage;
@@ -27,6 +45,44 @@ interface SettingsViewProps {
 }
 
 const mebibyte = 1024 * 1024;
+const retentionPresetDays = [0, 30, 90, 365];
+const budgetDataTypes = [
+  "text",
+  "rtf",
+  "html",
+  "png",
+  "jpg",
+  "jpeg",
+  "gif",
+  "webp",
+  "tiff",
+  "tif",
+  "bmp",
+  "heic",
+  "heif",
+  "file",
+  "folder",
+  "files",
+  "folders",
+  "files and folders",
+  "video",
+];
+
+const bundleIdPattern = /^[A-Za-z0-9._-]{1,255}$/;
+
+function parseOptionalLimit(
+  input: string,
+  minimum: number,
+  maximum: number
+): number | null | undefined {
+  if (input.trim() === "") {
+    return null;
+  }
+  const value = Number(input);
+  return Number.isInteger(value) && value >= minimum && value <= maximum
+    ? value
+    : undefined;
+}
 
 export function SettingsView({
   controller,
@@ -40,8 +96,44 @@ export function SettingsView({
     useState("256");
   const [pendingMenuBarItemLimitInput, setPendingMenuBarItemLimitInput] =
     useState("0");
+  const [customRetentionSelected, setCustomRetentionSelected] = useState(false);
+  const [pendingMaxAgeDaysInput, setPendingMaxAgeDaysInput] = useState("30");
+  const [budgetDataType, setBudgetDataType] = useState(budgetDataTypes[0]);
+  const [pendingBudgetItemsInput, setPendingBudgetItemsInput] = useState("");
+  const [pendingBudgetMebibytesInput, setPendingBudgetMebibytesInput] =
+    useState("");
+  const [pendingSourceAppInput, setPendingSourceAppInput] = useState("");
+  const [pendingSourceAppRule, setPendingSourceAppRule] =
+    useState<SourceAppRule>("deny");
   const [showConfirmDialog, setShowConfirmDialog] = useState(false);
   const [clearingHistory, setClearingHistory] = useState(false);
+  const [verifyingHistory, setVerifyingHistory] = useState(false);
+  const [verifiedHistoryItems, setVerifiedHistoryItems] = useState<
+    number | null
+  >(null);
+  const [checkingHistory, setCheckingHistory] = useState(false);
+  const [historyCheckReport, setHistoryCheckReport] =
+    useState<HistoryCheckReport | null>(null);
+  const [backupPath, setBackupPath] = useState("");
+  const [backupPassphrase, setBackupPassphrase] = useState("");
+  const [backupAction, setBackupAction] = useState<
+    "export" | "import" | null
+  >(null);
+  const [backupStatus, setBackupStatus] = useState<string | null>(null);
+  const [databaseBackups, setDatabaseBackups] = useState<DatabaseBackup[]>(
+    []
+  );
+  const [selectedDatabaseBackup, setSelectedDatabaseBackup] = useState("");
+  const [restoringDatabaseBackup, setRestoringDatabaseBackup] =
+    useState(false);
+  const [databaseBackupRestored, setDatabaseBackupRestored] = useState(false);
+  const [deletingDatabaseBackup, setDeletingDatabaseBackup] = useState(false);
+  const [databaseBackupDeleted, setDatabaseBackupDeleted] = useState(false);
+  const isDatabaseBackupBusy =
+    restoringDatabaseBackup || deletingDatabaseBackup;
+  const isBackupReady =
+    backupPath.trim() !== "" &&
+    [...backupPassphrase].length >= minBackupPassphraseLength;
 
   const settingsHeader = (
     <header className="preferences-header">
@@ -65,9 +157,39 @@ export function SettingsView({
         String(Math.round(settings.max_history_bytes / mebibyte))
       );
       setPendingMenuBarItemLimitInput(String(settings.menu_bar_item_limit));
+      setCustomRetentionSelected(
+        !retentionPresetDays.includes(settings.max_age_days)
+      );
+      if (settings.max_age_days > 0) {
+        setPendingMaxAgeDaysInput(String(settings.max_age_days));
+      }
     }
   }, [settings]);
 
+  const { reportError } = controller;
+  const loadDatabaseBackups = useCallback(async () => {
+    try {
+      const backups = await invokeCommand<DatabaseBackup[]>(
+        "list_database_backups",
+        "load_database_backups"
+      );
+      setDatabaseBackups(backups);
+      setSelectedDatabaseBackup(current =>
+        backups.some(backup => backup.name === current)
+          ? current
+          : (backups[0]?.name ?? "")
+      );
+    } catch (caught) {
+      reportError(caught, "load_database_backups", () => {
+        void loadDatabaseBackups();
+      });
+    }
+  }, [reportError]);
+
+  useEffect(() => {
+    void loadDatabaseBackups();
+  }, [loadDatabaseBackups]);
+
   if (!settings) {
     return (
       <main className="content-panel preferences-panel settings-panel">
@@ -112,6 +234,81 @@ export function SettingsView({
   const isMenuBarItemLimitDirty =
     isMenuBarItemLimitValid &&
     parsedMenuBarItemLimit !== settings.menu_bar_item_limit;
+  const parsedMaxAgeDays = Number(pendingMaxAgeDaysInput);
+  const isMaxAgeDaysValid =
+    pendingMaxAgeDaysInput.trim() !== "" &&
+    Number.isInteger(parsedMaxAgeDays) &&
+    parsedMaxAgeDays >= 1 &&
+    parsedMaxAgeDays <= 3650;
+  const isMaxAgeDaysDirty =
+    isMaxAgeDaysValid && parsedMaxAgeDays !== settings.max_age_days;
+  const parsedBudgetItems = parseOptionalLimit(
+    pendingBudgetItemsInput,
+    1,
+    1000
+  );
+  const parsedBudgetMebibytes = parseOptionalLimit(
+    pendingBudgetMebibytesInput,
+    16,
+    4096
+  );
+  const isBudgetValid =
+    parsedBudgetItems !== undefined &&
+    parsedBudgetMebibytes !== undefined &&
+    (parsedBudgetItems !== null || parsedBudgetMebibytes !== null);
+
+  const selectBudgetDataType = (dataType: string) => {
+    const existing = settings.data_type_budgets.find(
+      budget => budget.data_type === dataType
+    );
+    setBudgetDataType(dataType);
+    setPendingBudgetItemsInput(
+      existing?.max_items == null ? "" : String(existing.max_items)
+    );
+    setPendingBudgetMebibytesInput(
+      existing?.max_bytes == null
+        ? ""
+        : String(Math.round(existing.max_bytes / mebibyte))
+    );
+  };
+
+  const applyDataTypeBudget = async () => {
+    if (
+      parsedBudgetItems === undefined ||
+      parsedBudgetMebibytes === undefined ||
+      !isBudgetValid
+    ) {
+      return;
+    }
+    await controller.updateDataTypeBudget({
+      data_type: budgetDataType,
+      max_items: parsedBudgetItems,
+      max_bytes:
+        parsedBudgetMebibytes === null ? null : parsedBudgetMebibytes * mebibyte,
+    });
+  };
+
+  const isSourceAppValid = bundleIdPattern.test(pendingSourceAppInput.trim());
+
+  const addSourceAppRule = async () => {
+    if (!isSourceAppValid) {
+      return;
+    }
+    await controller.updateSourceAppRule(
+      pendingSourceAppInput.trim(),
+      pendingSourceAppRule
+    );
+    setPendingSourceAppInput("");
+  };
+
+  const sourceAppRuleGroups = [
+    { label: messages.deniedSourceApps, bundleIds: settings.denied_source_apps },
+    {
+      label: messages.allowedSourceApps,
+      bundleIds: settings.allowed_source_apps,
+    },
+  ].filter(group => group.bundleIds.length > 0);
+
   const eventsToDelete = Math.max(
     0,
     settings.history_count - parsedPendingMaxItems
@@ -158,6 +355,165 @@ export function SettingsView({
     }
   };
 
+  const verifyHistoryIntegrity = async () => {
+    if (verifyingHistory) {
+      return;
+    }
+
+    setVerifyingHistory(true);
+    setVerifiedHistoryItems(null);
+    controller.dismissError();
+    try {
+      const report = await invokeCommand<HistoryIntegrityReport>(
+        "verify_history_integrity",
+        "verify_history"
+      );
+      setVerifiedHistoryItems(report.verified_items);
+    } catch (caught) {
+      controller.reportError(caught, "verify_history", () => {
+        void verifyHistoryIntegrity();
+      });
+    } finally {
+      setVerifyingHistory(false);
+    }
+  };
+
+  const checkHistoryIntegrity = async () => {
+    if (checkingHistory) {
+      return;
+    }
+
+    setCheckingHistory(true);
+    setHistoryCheckReport(null);
+    controller.dismissError();
+    try {
+      const report = await invokeCommand<HistoryCheckReport>(
+        "check_history_integrity",
+        "check_history"
+      );
+      setHistoryCheckReport(report);
+      if (report.quarantined_items > 0) {
+        await controller.loadSettings();
+      }
+    } catch (caught) {
+      controller.reportError(caught, "check_history", () => {
+        void checkHistoryIntegrity();
+      });
+    } finally {
+      setCheckingHistory(false);
+    }
+  };
+
+  const exportHistoryBackup = async () => {
+    if (backupAction !== null || !isBackupReady) {
+      return;
+    }
+
+    setBackupAction("export");
+    setBackupStatus(null);
+    controller.dismissError();
+    try {
+      const report = await invokeCommand<HistoryBackupReport>(
+        "export_history_backup",
+        "export_backup",
+        { path: backupPath.trim(), passphrase: backupPassphrase }
+      );
+      setBackupStatus(messages.backupExported(report.exported_items));
+    } catch (caught) {
+      controller.reportError(caught, "export_backup", () => {
+        void exportHistoryBackup();
+      });
+    } finally {
+      setBackupAction(null);
+    }
+  };
+
+  const importHistoryBackup = async () => {
+    if (backupAction !== null || !isBackupReady) {
+      return;
+    }
+
+    setBackupAction("import");
+    setBackupStatus(null);
+    controller.dismissError();
+    try {
+      const report = await invokeCommand<HistoryImportReport>(
+        "import_history_backup",
+        "import_backup",
+        { path: backupPath.trim(), passphrase: backupPassphrase }
+      );
+      setBackupPassphrase("");
+      setBackupStatus(
+        messages.backupImported(
+          report.imported_items,
+          report.merged_items,
+          report.skipped_items + report.rejected_items,
+          report.denied_items
+        )
+      );
+      await controller.loadSettings();
+    } catch (caught) {
+      controller.reportError(caught, "import_backup", () => {
+        void importHistoryBackup();
+      });
+    } finally {
+      setBackupAction(null);
+    }
+  };
+
+  const restoreDatabaseBackup = async () => {
+    if (isDatabaseBackupBusy || selectedDatabaseBackup === "") {
+      return;
+    }
+
+    setRestoringDatabaseBackup(true);
+    setDatabaseBackupRestored(false);
+    setDatabaseBackupDeleted(false);
+    controller.dismissError();
+    try {
+      await invokeCommand<void>(
+        "restore_database_backup",
+        "restore_database_backup",
+        { name: selectedDatabaseBackup }
+      );
+      setDatabaseBackupRestored(true);
+      await controller.loadSettings();
+    } catch (caught) {
+      controller.reportError(caught, "restore_database_backup", () => {
+        void restoreDatabaseBackup();
+      });
+    } finally {
+      setRestoringDatabaseBackup(false);
+      await loadDatabaseBackups();
+    }
+  };
+
+  const deleteDatabaseBackup = async () => {
+    if (isDatabaseBackupBusy || selectedDatabaseBackup === "") {
+      return;
+    }
+
+    setDeletingDatabaseBackup(true);
+    setDatabaseBackupRestored(false);
+    setDatabaseBackupDeleted(false);
+    controller.dismissError();
+    try {
+      await invokeCommand<void>(
+        "delete_database_backup",
+        "delete_database_backup",
+        { name: selectedDatabaseBackup }
+      );
+      setDatabaseBackupDeleted(true);
+    } catch (caught) {
+      controller.reportError(caught, "delete_database_backup", () => {
+        void deleteDatabaseBackup();
+      });
+    } finally {
+      setDeletingDatabaseBackup(false);
+      await loadDatabaseBackups();
+    }
+  };
+
   return (
     <>
       <main className="content-panel preferences-panel settings-panel">
@@ -302,6 +658,297 @@ export function SettingsView({
             )}
           </div>
 
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <label htmlFor="history-retention-select">
+                {messages.historyRetention}
+              </label>
+              <p>{messages.historyRetentionDescription}</p>
+            </div>
+            <div className="preference-control storage-input-row">
+              <select
+                className="language-select"
+                disabled={controller.updating}
+                id="history-retention-select"
+                onChange={event => {
+                  if (event.target.value === "custom") {
+                    setCustomRetentionSelected(true);
+                    return;
+                  }
+                  setCustomRetentionSelected(false);
+                  void controller.updateMaxAgeDays(Number(event.target.value));
+                }}
+                value={
+                  customRetentionSelected
+                    ? "custom"
+                    : String(settings.max_age_days)
+                }
+              >
+                {retentionPresetDays.map(days => (
+                  <option key={days} value={String(days)}>
+                    {days === 0
+                      ? messages.retentionForever
+                      : messages.retentionDays(days)}
+                  </option>
+                ))}
+                <option value="custom">{messages.retentionCustom}</option>
+              </select>
+              {customRetentionSelected && (
+                <>
+                  <input
+                    aria-label={messages.historyRetention}
+                    className="storage-input"
+                    disabled={controller.updating}
+                    max="3650"
+                    min="1"
+                    onChange={event =>
+                      setPendingMaxAgeDaysInput(event.target.value)
+                    }
+                    type="number"
+                    value={pendingMaxAgeDaysInput}
+                  />
+                  <button
+                    className="btn btn-primary"
+                    disabled={
+                      controller.updating ||
+                      !isMaxAgeDaysValid ||
+                      !isMaxAgeDaysDirty
+                    }
+                    onClick={() =>
+                      void controller.updateMaxAgeDays(parsedMaxAgeDays)
+                    }
+                    type="button"
+                  >
+                    {messages.apply}
+                  </button>
+                </>
+              )}
+            </div>
+            {customRetentionSelected && !isMaxAgeDaysValid && (
+              <p className="settings-error" role="alert">
+                {messages.retentionCustomError}
+              </p>
+            )}
+          </div>
+
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <label htmlFor="data-type-budget-select">
+                {messages.dataTypeBudgets}
+              </label>
+              <p>{messages.dataTypeBudgetsDescription}</p>
+              {settings.data_type_budgets.map(budget => {
+                const label = getEventTypeLabel(messages, budget.data_type);
+                return (
+                  <p key={budget.data_type}>
+                    {messages.dataTypeBudgetSummary(
+                      label,
+                      budget.max_items,
+                      budget.max_bytes === null
+                        ? null
+                        : formatBytes(budget.max_bytes, language)
+                    )}{" "}
+                    <button
+                      aria-label={messages.removeDataTypeBudget(label)}
+                      className="btn btn-secondary"
+                      disabled={controller.updating}
+                      onClick={() =>
+                        void controller.updateDataTypeBudget({
+                          data_type: budget.data_type,
+                          max_items: null,
+                          max_bytes: null,
+                        })
+                      }
+                      title={messages.removeDataTypeBudget(label)}
+                      type="button"
+                    >
+                      <Trash2 aria-hidden="true" size={13} />
+                    </button>
+                  </p>
+                );
+              })}
+            </div>
+            <div className="preference-control storage-input-row">
+              <select
+                aria-label={messages.dataTypeBudgetType}
+                className="language-select"
+                disabled={controller.updating}
+                id="data-type-budget-select"
+                onChange={event => selectBudgetDataType(event.target.value)}
+                value={budgetDataType}
+              >
+                {budgetDataTypes.map(dataType => (
+                  <option key={dataType} value={dataType}>
+                    {getEventTypeLabel(messages, dataType)}
+                  </option>
+                ))}
+              </select>
+              <input
+                aria-label={messages.dataTypeBudgetItems}
+                className="storage-input"
+                disabled={controller.updating}
+                max="1000"
+                min="1"
+                onChange={event =>
+                  setPendingBudgetItemsInput(event.target.value)
+                }
+                placeholder={messages.dataTypeBudgetItems}
+                type="number"
+                value={pendingBudgetItemsInput}
+              />
+              <input
+                aria-label="MiB"
+                className="storage-input history-budget-input"
+                disabled={controller.updating}
+                max="4096"
+                min="16"
+                onChange={event =>
+                  setPendingBudgetMebibytesInput(event.target.value)
+                }
+                placeholder="MiB"
+                type="number"
+                value={pendingBudgetMebibytesInput}
+              />
+              <button
+                className="btn btn-primary"
+                disabled={controller.updating || !isBudgetValid}
+                onClick={() => void applyDataTypeBudget()}
+                type="button"
+              >
+                {messages.apply}
+              </button>
+            </div>
+            {(parsedBudgetItems === undefined ||
+              parsedBudgetMebibytes === undefined) && (
+              <p className="settings-error" role="alert">
+                {messages.dataTypeBudgetError}
+              </p>
+            )}
+          </div>
+
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <label htmlFor="source-app-input">{messages.sourceApps}</label>
+              <p>{messages.sourceAppsDescription}</p>
+              {sourceAppRuleGroups.map(group => (
+                <p key={group.label}>
+                  {group.label}:{" "}
+                  {group.bundleIds.map(bundleId => (
+                    <span key={bundleId}>
+                      {bundleId}{" "}
+                      <button
+                        aria-label={messages.removeSourceAppRule(bundleId)}
+                        className="btn btn-secondary"
+                        disabled={controller.updating}
+                        onClick={() =>
+                          void controller.updateSourceAppRule(bundleId, null)
+                        }
+                        title={messages.removeSourceAppRule(bundleId)}
+                        type="button"
+                      >
+                        <Trash2 aria-hidden="true" size={13} />
+                      </button>{" "}
+                    </span>
+                  ))}
+                </p>
+              ))}
+            </div>
+            <div className="preference-control storage-input-row">
+              <input
+                className="storage-input source-app-input"
+                disabled={controller.updating}
+                id="source-app-input"
+                maxLength={255}
+                onChange={event => setPendingSourceAppInput(event.target.value)}
+                placeholder={messages.sourceAppBundleId}
+                type="text"
+                value={pendingSourceAppInput}
+              />
+              <select
+                className="language-select"
+                disabled={controller.updating}
+                onChange={event =>
+                  setPendingSourceAppRule(event.target.value as SourceAppRule)
+                }
+                value={pendingSourceAppRule}
+              >
+                <option value="deny">{messages.sourceAppDeny}</option>
+                <option value="allow">{messages.sourceAppAllow}</option>
+              </select>
+              <button
+                className="btn btn-primary"
+                disabled={controller.updating || !isSourceAppValid}
+                onClick={() => void addSourceAppRule()}
+                type="button"
+              >
+                {messages.sourceAppAdd}
+              </button>
+            </div>
+            {pendingSourceAppInput.trim() !== "" && !isSourceAppValid && (
+              <p className="settings-error" role="alert">
+                {messages.sourceAppError}
+              </p>
+            )}
+          </div>
+
+          <label className="preference-row">
+            <span className="preference-copy">
+              <span className="preference-title">
+                {messages.sourceAllowOnly}
+              </span>
+              <span className="preference-description">
+                {settings.source_allow_only
+                  ? messages.sourceAllowOnlyEnabled
+                  : messages.sourceAllowOnlyDisabled}
+              </span>
+            </span>
+            <span className="mac-switch">
+              <input
+                checked={settings.source_allow_only}
+                disabled={controller.updating}
+                onChange={event =>
+                  void controller.updateSourceAllowOnly(event.target.checked)
+                }
+                type="checkbox"
+              />
+              <span className="mac-switch-track" />
+            </span>
+          </label>
+
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <span className="preference-title">
+                {messages.secretDetection}
+              </span>
+              <p>{messages.secretDetectionDescription}</p>
+            </div>
+            {settings.secret_policies.map(policy => (
+              <label
+                className="preference-control storage-input-row"
+                key={policy.kind}
+              >
+                <span>{messages.secretKinds[policy.kind]}</span>
+                <select
+                  className="language-select"
+                  disabled={controller.updating}
+                  onChange={event =>
+                    void controller.updateSecretPolicy(
+                      policy.kind,
+                      event.target.value as SecretAction
+                    )
+                  }
+                  value={policy.action}
+                >
+                  {secretActions.map(action => (
+                    <option key={action} value={action}>
+                      {messages.secretActions[action]}
+                    </option>
+                  ))}
+                </select>
+              </label>
+            ))}
+          </div>
+
           <label className="preference-row preference-row-stacked">
             <span className="preference-copy">
               <span className="preference-title">{messages.launchAtLogin}</span>
@@ -390,6 +1037,31 @@ export function SettingsView({
             </span>
           </label>
 
+          <label className="preference-row">
+            <span className="preference-copy">
+              <span className="preference-title">
+                {messages.moveRecopiedItemsToTop}
+              </span>
+              <span className="preference-description">
+                <ArrowUpDown size={13} />
+                {settings.move_recopied_item_to_top
+                  ? messages.recopyOrderingEnabled
+                  : messages.recopyOrderingDisabled}
+              </span>
+            </span>
+            <span className="mac-switch">
+              <input
+                checked={settings.move_recopied_item_to_top}
+                disabled={controller.updating}
+                onChange={event =>
+                  void controller.updateRecopyOrdering(event.target.checked)
+                }
+                type="checkbox"
+              />
+              <span className="mac-switch-track" />
+            </span>
+          </label>
+
           <label className="preference-row">
             <span className="preference-copy">
               <span className="preference-title">{messages.showInMenuBar}</span>
@@ -488,6 +1160,169 @@ export function SettingsView({
               {clearingHistory ? messages.clearingHistory : messages.clearAll}
             </button>
           </div>
+
+          <div className="preference-row">
+            <span className="preference-copy">
+              <span className="preference-title">
+                {messages.historyIntegrity}
+              </span>
+              <span className="preference-description">
+                {verifiedHistoryItems === null
+                  ? messages.historyIntegrityDescription
+                  : messages.historyIntegrityVerified(verifiedHistoryItems)}
+              </span>
+            </span>
+            <button
+              className="btn btn-secondary settings-clear-button"
+              disabled={verifyingHistory}
+              onClick={() => void verifyHistoryIntegrity()}
+              type="button"
+            >
+              <ShieldCheck aria-hidden="true" size={15} />
+              {verifyingHistory
+                ? messages.verifyingHistory
+                : messages.verifyHistory}
+            </button>
+          </div>
+
+          <div className="preference-row">
+            <span className="preference-copy">
+              <span className="preference-title">{messages.historyCheck}</span>
+              <span className="preference-description">
+                {historyCheckReport === null
+                  ? messages.historyCheckDescription
+                  : messages.historyChecked(
+                      historyCheckReport.checked_items,
+                      historyCheckReport.quarantined_items
+                    )}
+              </span>
+            </span>
+            <button
+              className="btn btn-secondary settings-clear-button"
+              disabled={checkingHistory}
+              onClick={() => void checkHistoryIntegrity()}
+              type="button"
+            >
+              <Wrench aria-hidden="true" size={15} />
+              {checkingHistory
+                ? messages.checkingHistory
+                : messages.checkHistory}
+            </button>
+          </div>
+
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <label htmlFor="backup-path-input">{messages.historyBackup}</label>
+              <p>{backupStatus ?? messages.historyBackupDescription}</p>
+            </div>
+            <div className="preference-control storage-input-row">
+              <input
+                className="storage-input source-app-input"
+                disabled={backupAction !== null}
+                id="backup-path-input"
+                onChange={event => setBackupPath(event.target.value)}
+                placeholder={messages.backupPath}
+                type="text"
+                value={backupPath}
+              />
+              <input
+                aria-label={messages.backupPassphrase}
+                autoComplete="off"
+                className="storage-input source-app-input"
+                disabled={backupAction !== null}
+                maxLength={1024}
+                onChange={event => setBackupPassphrase(event.target.value)}
+                placeholder={messages.backupPassphrase}
+                type="password"
+                value={backupPassphrase}
+              />
+              <button
+                className="btn btn-secondary"
+                disabled={backupAction !== null || !isBackupReady}
+                onClick={() => void exportHistoryBackup()}
+                type="button"
+              >
+                <Download aria-hidden="true" size={15} />
+                {backupAction === "export"
+                  ? messages.exportingBackup
+                  : messages.exportBackup}
+              </button>
+              <button
+                className="btn btn-secondary"
+                disabled={backupAction !== null || !isBackupReady}
+                onClick={() => void importHistoryBackup()}
+                type="button"
+              >
+                <Upload aria-hidden="true" size={15} />
+                {backupAction === "import"
+                  ? messages.importingBackup
+                  : messages.importBackup}
+              </button>
+            </div>
+          </div>
+
+          <div className="preference-row preference-row-stacked">
+            <div className="preference-copy">
+              <label htmlFor="database-backup-select">
+                {messages.databaseSnapshots}
+              </label>
+              <p>
+                {databaseBackupRestored
+                  ? messages.snapshotRestored
+                  : databaseBackupDeleted
+                    ? messages.snapshotDeleted
+                    : databaseBackups.some(backup => backup.plaintext)
+                      ? messages.plaintextSnapshotNotice
+                      : messages.databaseSnapshotsDescription}
+              </p>
+            </div>
+            <div className="preference-control storage-input-row">
+              <select
+                className="language-select"
+                disabled={isDatabaseBackupBusy || databaseBackups.length === 0}
+                id="database-backup-select"
+                onChange={event =>
+                  setSelectedDatabaseBackup(event.target.value)
+                }
+                value={selectedDatabaseBackup}
+              >
+                {databaseBackups.length === 0 && (
+                  <option value="">{messages.noDatabaseSnapshots}</option>
+                )}
+                {databaseBackups.map(backup => (
+                  <option key={backup.name} value={backup.name}>
+                    {messages.databaseSnapshotOption(
+                      new Date(backup.created_at).toLocaleString(language),
+                      backup.schema_version,
+                      backup.plaintext
+                    )}
+                  </option>
+                ))}
+              </select>
+              <button
+                className="btn btn-secondary"
+                disabled={isDatabaseBackupBusy || selectedDatabaseBackup === ""}
+                onClick={() => void restoreDatabaseBackup()}
+                type="button"
+              >
+                <RotateCcw aria-hidden="true" size={15} />
+                {restoringDatabaseBackup
+                  ? messages.restoringSnapshot
+                  : messages.restoreSnapshot}
+              </button>
+              <button
+                className="btn btn-secondary"
+                disabled={isDatabaseBackupBusy || selectedDatabaseBackup === ""}
+                onClick={() => void deleteDatabaseBackup()}
+                type="button"
+              >
+                <Trash2 aria-hidden="true" size={15} />
+                {deletingDatabaseBackup
+                  ? messages.deletingSnapshot
+                  : messages.deleteSnapshot}
+              </button>
+            </div>
+          </div>
         </section>
       </main>
 
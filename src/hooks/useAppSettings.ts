@@ -4,7 +4,14 @@ import {
   normalizeCommandError,
   TauriCommandError,
 } from "../api/tauri";
-import type { AppSettings, Operation } from "../types";
+import type {
+  AppSettings,
+  DataTypeBudget,
+  Operation,
+  SecretAction,
+  SecretKind,
+  SourceAppRule,
+} from "../types";
 import type { LanguagePreference } from "../i18n";
 import { runOptimisticMutation } from "./settingsMutation";
 
@@ -157,6 +164,114 @@ export function useAppSettings(loadAutostart: boolean, enabled = true) {
     [runSettingsMutation]
   );
 
+  const updateMaxAgeDays = useCallback(
+    async (maxAgeDays: number) => {
+      await runSettingsMutation(
+        {
+          command: "set_max_age_days",
+          args: { maxAgeDays },
+          patch: { max_age_days: maxAgeDays },
+        },
+        () => {
+          void updateMaxAgeDays(maxAgeDays);
+        }
+      );
+    },
+    [runSettingsMutation]
+  );
+
+  const updateDataTypeBudget = useCallback(
+    async (budget: DataTypeBudget) => {
+      const others = (settings?.data_type_budgets ?? []).filter(
+        existing => existing.data_type !== budget.data_type
+      );
+      const budgets =
+        budget.max_items === null && budget.max_bytes === null
+          ? others
+          : [...others, budget].sort((left, right) =>
+              left.data_type < right.data_type ? -1 : 1
+            );
+      await runSettingsMutation(
+        {
+          command: "set_data_type_budget",
+          args: {
+            dataType: budget.data_type,
+            maxItems: budget.max_items,
+            maxBytes: budget.max_bytes,
+          },
+          patch: { data_type_budgets: budgets },
+        },
+        () => {
+          void updateDataTypeBudget(budget);
+        }
+      );
+    },
+    [runSettingsMutation, settings]
+  );
+
+  const updateSourceAppRule = useCallback(
+    async (bundleId: string, rule: SourceAppRule | null) => {
+      const normalized = bundleId.trim().toLowerCase();
+      const without = (bundleIds: string[] = []) =>
+        bundleIds.filter(existing => existing !== normalized);
+      const denied = without(settings?.denied_source_apps);
+      const allowed = without(settings?.allowed_source_apps);
+      if (rule === "deny") {
+        denied.push(normalized);
+        denied.sort();
+      } else if (rule === "allow") {
+        allowed.push(normalized);
+        allowed.sort();
+      }
+      await runSettingsMutation(
+        {
+          command: "set_source_app_rule",
+          args: { bundleId, rule },
+          patch: { denied_source_apps: denied, allowed_source_apps: allowed },
+        },
+        () => {
+          void updateSourceAppRule(bundleId, rule);
+        }
+      );
+    },
+    [runSettingsMutation, settings]
+  );
+
+  const updateSourceAllowOnly = useCallback(
+    async (sourceAllowOnly: boolean) => {
+      await runSettingsMutation(
+        {
+          command: "set_source_allow_only",
+          args: { sourceAllowOnly },
+          patch: { source_allow_only: sourceAllowOnly },
+        },
+        () => {
+          void updateSourceAllowOnly(sourceAllowOnly);
+        }
+      );
+    },
+    [runSettingsMutation]
+  );
+
+  const updateSecretPolicy = useCallback(
+    async (kind: SecretKind, action: SecretAction) => {
+      const secretPolicies = (settings?.secret_policies ?? []).map(policy =>
+        policy.kind === kind ? { kind, action } : policy
+      );
+      await runSettingsMutation(
+        {
+          command: "set_secret_policy",
+          args: { kind, action },
+          patch: { secret_policies: secretPolicies },
+        },
+        () => {
+          void updateSecretPolicy(kind, action);
+        }
+      );
+    },
+    [runSettingsMutation, settings]
+  );
+
   const updateMenuBarVisibility = useCallback(
     async (showInMenuBar: boolean) => {
       await runSettingsMutation(
@@ -205,6 +320,22 @@ export function useAppSettings(loadAutostart: boolean, enabled = true) {
     [runSettingsMutation]
   );
 
+  const updateRecopyOrdering = useCallback(
+    async (moveRecopiedItemToTop: boolean) => {
+      await runSettingsMutation(
+        {
+          command: "set_move_recopied_item_to_top",
+          args: { moveRecopiedItemToTop },
+          patch: { move_recopied_item_to_top: moveRecopiedItemToTop },
+        },
+        () => {
+          void updateRecopyOrdering(moveRecopiedItemToTop);
+        }
+      );
+    },
+    [runSettingsMutation]
+  );
+
   const updateCompactMode = useCallback(
     async (compactMode: boolean) => {
       await runSettingsMutation(
@@ -377,9 +508,15 @@ export function useAppSettings(loadAutostart: boolean, enabled = true) {
     loadSettings,
     updateMaxItems,
     updateMaxHistoryBytes,
+    updateMaxAgeDays,
+    updateDataTypeBudget,
+    updateSourceAppRule,
+    updateSourceAllowOnly,
+    updateSecretPolicy,
     updateMenuBarVisibility,
     updateMenuBarItemLimit,
     updateRestoreOrdering,
+    updateRecopyOrdering,
     updateCompactMode,
     updateLanguage,
     updateAutostart,
@@ -1,4 +1,4 @@
-import type { ErrorCode, Operation } from "./types";
+import type { ErrorCode, Operation, SecretAction, SecretKind } from "./types";
 
 export const languagePreferences = ["system", "en", "zh-CN", "zh-TW"] as const;
 
@@ -30,6 +30,40 @@ export interface Messages {
   historyBudget: string;
   historyBudgetDescription: string;
   historyBudgetError: string;
+  historyRetention: string;
+  historyRetentionDescription: string;
+  retentionForever: string;
+  retentionDays: (days: number) => string;
+  retentionCustom: string;
+  retentionCustomError: string;
+  dataTypeBudgets: string;
+  dataTypeBudgetsDescription: string;
+  dataTypeBudgetType: string;
+  dataTypeBudgetItems: string;
+  dataTypeBudgetSummary: (
+    label: string,
+    items: number | null,
+    size: string | null
+  ) => string;
+  removeDataTypeBudget: (label: string) => string;
+  dataTypeBudgetError: string;
+  sourceApps: string;
+  sourceAppsDescription: string;
+  sourceAppBundleId: string;
+  sourceAppDeny: string;
+  sourceAppAllow: string;
+  sourceAppAdd: string;
+  deniedSourceApps: string;
+  allowedSourceApps: string;
+  removeSourceAppRule: (bundleId: string) => string;
+  sourceAppError: string;
+  sourceAllowOnly: string;
+  sourceAllowOnlyEnabled: string;
+  sourceAllowOnlyDisabled: string;
+  secretDetection: string;
+  secretDetectionDescription: string;
+  secretKinds: Record<SecretKind, string>;
+  secretActions: Record<SecretAction, string>;
   apply: string;
   storageLimitError: string;
   language: string;
@@ -48,6 +82,9 @@ export interface Messages {
   moveRestoredItemsToTop: string;
   restoreOrderingEnabled: string;
   restoreOrderingDisabled: string;
+  moveRecopiedItemsToTop: string;
+  recopyOrderingEnabled: string;
+  recopyOrderingDisabled: string;
   showInMenuBar: string;
   menuBarEnabled: string;
   menuBarDisabled: string;
@@ -58,6 +95,46 @@ export interface Messages {
   clearAll: string;
   clearHistoryDescription: (count: number) => string;
   clearingHistory: string;
+  historyIntegrity: string;
+  historyIntegrityDescription: string;
+  historyIntegrityVerified: (count: number) => string;
+  verifyHistory: string;
+  verifyingHistory: string;
+  historyCheck: string;
+  historyCheckDescription: string;
+  historyChecked: (checked: number, quarantined: number) => string;
+  checkHistory: string;
+  checkingHistory: string;
+  historyBackup: string;
+  historyBackupDescription: string;
+  backupPath: string;
+  backupPassphrase: string;
+  exportBackup: string;
+  exportingBackup: string;
+  importBackup: string;
+  importingBackup: string;
+  backupExported: (count: number) => string;
+  backupImported: (
+    imported: number,
+    merged: number,
+    skipped: number,
+    denied: number
+  ) => string;
+  databaseSnapshots: string;
+  databaseSnapshotsDescription: string;
+  noDatabaseSnapshots: string;
+  databaseSnapshotOption: (
+    createdAt: string,
+    schemaVersion: number,
+    plaintext: boolean
+  ) => string;
+  plaintextSnapshotNotice: string;
+  restoreSnapshot: string;
+  restoringSnapshot: string;
+  snapshotRestored: string;
+  deleteSnapshot: string;
+  deletingSnapshot: string;
+  snapshotDeleted: string;
   loadMore: string;
   loadingMore: string;
   loadedHistoryCount: (loaded: number, total: number) => string;
@@ -87,6 +164,7 @@ export interface Messages {
   remoteClipboard: string;
   previewTruncated: string;
   captureRejected: string;
+  captureSecretRejected: (kind: string) => string;
   retry: string;
   dismiss: string;
   diagnosticDetails: string;
@@ -123,8 +201,14 @@ const englishOperationErrors: Record<Operation, string> = {
   startup: "Copy Stack could not finish starting.",
   capture_clipboard: "This clipboard item could not be saved.",
   load_history: "Clipboard history could not be loaded.",
+  search_history: "Clipboard history could not be searched.",
   load_history_detail: "This clipboard preview could not be loaded.",
   restore_clipboard: "This item could not be restored to the clipboard.",
+  pin_history: "This clipboard item could not be pinned.",
+  unpin_history: "This clipboard item could not be unpinned.",
+  load_labels: "Collections and tags could not be loaded.",
+  update_collections: "The collection could not be updated.",
+  update_tags: "The tag could not be updated.",
   delete_history: "This clipboard item could not be deleted.",
   clear_history: "Clipboard history could not be cleared.",
   load_settings: "Settings could not be loaded.",
@@ -132,34 +216,79 @@ const englishOperationErrors: Record<Operation, string> = {
     "The setting could not be updated. Its saved value was restored.",
   update_autostart: "The login startup setting could not be updated.",
   write_history_mirror: "The optional history export could not be updated.",
+  expire_history: "Expired clipboard history could not be removed.",
+  verify_history: "Clipboard history could not be verified.",
+  check_history: "Clipboard history could not be checked.",
+  export_backup: "The history backup could not be written.",
+  import_backup: "The history backup could not be imported.",
+  load_database_backups: "Database snapshots could not be loaded.",
+  restore_database_backup: "The database snapshot could not be restored.",
+  delete_database_backup: "The database snapshot could not be deleted.",
+  import_history: "Clipboard history could not be imported.",
+  export_history: "Clipboard history could not be exported.",
+  control_request: "A control socket request could not be handled.",
 };
 
 const simplifiedChineseOperationErrors: Record<Operation, string> = {
   startup: "Copy Stack 无法完成启动。",
   capture_clipboard: "无法保存此剪贴板内容。",
   load_history: "无法加载剪贴板历史。",
+  search_history: "无法搜索剪贴板历史。",
   load_history_detail: "无法加载此剪贴板预览。",
   restore_clipboard: "无法将此项目恢复到剪贴板。",
+  pin_history: "无法固定此剪贴板项目。",
+  unpin_history: "无法取消固定此剪贴板项目。",
+  load_labels: "无法加载收藏集和标签。",
+  update_collections: "无法更新收藏集。",
+  update_tags: "无法更新标签。",
   delete_history: "无法删除此剪贴板项目。",
   clear_history: "无法清空剪贴板历史。",
   load_settings: "无法加载设置。",
   update_settings: "无法更新设置，已恢复保存的值。",
   update_autostart: "无法更新登录启动设置。",
   write_history_mirror: "无法更新可选的历史记录导出。",
+  expire_history: "无法删除已过期的剪贴板历史。",
+  verify_history: "无法校验剪贴板历史。",
+  check_history: "无法检查剪贴板历史。",
+  export_backup: "无法写入历史记录备份。",
+  import_backup: "无法导入历史记录备份。",
+  load_database_backups: "无法加载数据库快照。",
+  restore_database_backup: "无法恢复数据库快照。",
+  delete_database_backup: "无法删除数据库快照。",
+  import_history: "无法导入剪贴板历史。",
+  export_history: "无法导出剪贴板历史。",
+  control_request: "无法处理控制套接字请求。",
 };
 
 const traditionalChineseOperationErrors: Record<Operation, string> = {
   startup: "Copy Stack 無法完成啟動。",
   capture_clipboard: "無法儲存此剪貼簿內容。",
   load_history: "無法載入剪貼簿歷史。",
+  search_history: "無法搜尋剪貼簿歷史。",
   load_history_detail: "無法載入此剪貼簿預覽。",
   restore_clipboard: "無法將此項目還原至剪貼簿。",
+  pin_history: "無法釘選此剪貼簿項目。",
+  unpin_history: "無法取消釘選此剪貼簿項目。",
+  load_labels: "無法載入收藏集和標籤。",
+  update_collections: "無法更新收藏集。",
+  update_tags: "無法更新標籤。",
   delete_history: "無法刪除此剪貼簿項目。",
   clear_history: "無法清除剪貼簿歷史。",
   load_settings: "無法載入設定。",
   update_settings: "無法更新設定，已還原儲存的值。",
   update_autostart: "無法更新登入啟動設定。",
   write_history_mirror: "無法更新選用的歷史記錄匯出。",
+  expire_history: "無法刪除已過期的剪貼簿歷史。",
+  verify_history: "無法驗證剪貼簿歷史。",
+  check_history: "無法檢查剪貼簿歷史。",
+  export_backup: "無法寫入歷史記錄備份。",
+  import_backup: "無法匯入歷史記錄備份。",
+  load_database_backups: "無法載入資料庫快照。",
+  restore_database_backup: "無法還原資料庫快照。",
+  delete_database_backup: "無法刪除資料庫快照。",
+  import_history: "無法匯入剪貼簿歷史。",
+  export_history: "無法匯出剪貼簿歷史。",
+  control_request: "無法處理控制通訊端請求。",
 };
 
 const translations: Record<SupportedLanguage, Messages> = {
@@ -178,6 +307,56 @@ const translations: Record<SupportedLanguage, Messages> = {
     historyBudgetDescription:
       "Maximum local history size in MiB (16–4096). Oldest items are removed first.",
     historyBudgetError: "Enter a whole number from 16 to 4096 MiB.",
+    historyRetention: "Keep history for",
+    historyRetentionDescription:
+      "Items not copied within this period are removed, even when pinned and while Copy Stack is idle.",
+    retentionForever: "Forever",
+    retentionDays: days => (days === 1 ? "1 day" : `${days} days`),
+    retentionCustom: "Custom",
+    retentionCustomError: "Enter a whole number from 1 to 3650 days.",
+    dataTypeBudgets: "Per-type limits",
+    dataTypeBudgetsDescription:
+      "Cap one kind of item so it cannot push everything else out. Leave a field empty to rely on the overall limit.",
+    dataTypeBudgetType: "Item type",
+    dataTypeBudgetItems: "Items",
+    dataTypeBudgetSummary: (label, items, size) =>
+      `${label}: ${[items === null ? null : englishClipCount(items), size]
+        .filter(Boolean)
+        .join(", ")}`,
+    removeDataTypeBudget: label => `Remove ${label} limit`,
+    dataTypeBudgetError:
+      "Enter 1–1000 items, 16–4096 MiB, or both.",
+    sourceApps: "Source apps",
+    sourceAppsDescription:
+      "Never save copies from denied apps. Items already in history are kept.",
+    sourceAppBundleId: "Bundle identifier, such as com.example.App",
+    sourceAppDeny: "Deny",
+    sourceAppAllow: "Allow",
+    sourceAppAdd: "Add",
+    deniedSourceApps: "Denied",
+    allowedSourceApps: "Allowed",
+    removeSourceAppRule: bundleId => `Remove rule for ${bundleId}`,
+    sourceAppError:
+      "Enter a bundle identifier using letters, digits, dots, hyphens, or underscores.",
+    sourceAllowOnly: "Only save from allowed apps",
+    sourceAllowOnlyEnabled:
+      "Copies from apps that are not allowed, or with an unknown source, are not saved.",
+    sourceAllowOnlyDisabled: "Copies from any app that is not denied are saved.",
+    secretDetection: "Secret detection",
+    secretDetectionDescription:
+      "Copied text that looks like a credential or card number is not saved, saved masked, or deleted after 10 minutes. Items already in history are not checked.",
+    secretKinds: {
+      aws_credential: "AWS credential",
+      github_token: "GitHub token",
+      jwt: "JSON Web Token",
+      private_key: "Private key",
+      payment_card: "Payment card number",
+    },
+    secretActions: {
+      skip: "Don't save",
+      mask: "Save masked",
+      expire: "Delete after 10 minutes",
+    },
     apply: "Apply",
     storageLimitError: "Enter a whole number between 1 and 1000.",
     language: "Language",
@@ -201,6 +380,9 @@ const translations: Record<SupportedLanguage, Messages> = {
     moveRestoredItemsToTop: "Move restored items to top",
     restoreOrderingEnabled: "Restored clips refresh history order.",
     restoreOrderingDisabled: "Restored clips keep their current order.",
+    moveRecopiedItemsToTop: "Move re-copied items to top",
+    recopyOrderingEnabled: "Copying a saved clip again refreshes history order.",
+    recopyOrderingDisabled: "Copying a saved clip again keeps its current order.",
     showInMenuBar: "Show in menu bar",
     menuBarEnabled: "Recent clips are available from the tray menu.",
     menuBarDisabled: "The tray menu is hidden.",
@@ -217,6 +399,50 @@ const translations: Record<SupportedLanguage, Messages> = {
         ? "There is no clipboard history to clear."
         : `Permanently delete all ${englishEventCount(count)} stored on this Mac.`,
     clearingHistory: "Clearing...",
+    historyIntegrity: "History integrity",
+    historyIntegrityDescription:
+      "Check that no stored item was changed, added, or removed outside Copy Stack.",
+    historyIntegrityVerified: count =>
+      `All ${englishEventCount(count)} verified.`,
+    verifyHistory: "Verify",
+    verifyingHistory: "Verifying...",
+    historyCheck: "History repair",
+    historyCheckDescription:
+      "Check that every stored item can still be read, and set aside any that cannot.",
+    historyChecked: (checked, quarantined) =>
+      quarantined === 0
+        ? `All ${englishEventCount(checked)} can be read.`
+        : `Set aside ${englishEventCount(quarantined)} of ${checked} that could not be read.`,
+    checkHistory: "Check",
+    checkingHistory: "Checking...",
+    historyBackup: "History backup",
+    historyBackupDescription:
+      "Export settings and history to an encrypted file, or merge one in. Importing replaces the current settings.",
+    backupPath: "Absolute path to the backup file",
+    backupPassphrase: "Passphrase (at least 8 characters)",
+    exportBackup: "Export",
+    exportingBackup: "Exporting...",
+    importBackup: "Import",
+    importingBackup: "Importing...",
+    backupExported: count => `Exported ${englishEventCount(count)}.`,
+    backupImported: (imported, merged, skipped, denied) =>
+      `Imported ${englishEventCount(imported)}, merged ${merged}, skipped ${skipped}, blocked by capture rules ${denied}.`,
+    databaseSnapshots: "Database snapshots",
+    databaseSnapshotsDescription:
+      "Copy Stack saves a snapshot before it upgrades the database. Restoring one replaces history and settings, which are saved as a snapshot first.",
+    noDatabaseSnapshots: "No snapshots yet",
+    databaseSnapshotOption: (createdAt, schemaVersion, plaintext) =>
+      plaintext
+        ? `${createdAt} (schema ${schemaVersion}, unencrypted)`
+        : `${createdAt} (schema ${schemaVersion})`,
+    plaintextSnapshotNotice:
+      "The snapshot from before encryption holds history unencrypted. It is kept until you delete it, so the upgrade can be undone.",
+    restoreSnapshot: "Restore",
+    restoringSnapshot: "Restoring...",
+    snapshotRestored: "Snapshot restored.",
+    deleteSnapshot: "Delete",
+    deletingSnapshot: "Deleting...",
+    snapshotDeleted: "Snapshot deleted.",
     loadMore: "Load more",
     loadingMore: "Loading more...",
     loadedHistoryCount: (loaded, total) =>
@@ -261,6 +487,8 @@ const translations: Record<SupportedLanguage, Messages> = {
     previewTruncated: "Summary shortened",
     captureRejected:
       "A clipboard item was not saved because it exceeded a safety limit or requested privacy.",
+    captureSecretRejected: kind =>
+      `A clipboard item was not saved because it looked like a secret (${kind}).`,
     retry: "Retry",
     dismiss: "Dismiss",
     diagnosticDetails: "Safe diagnostic details",
@@ -272,7 +500,19 @@ const translations: Record<SupportedLanguage, Messages> = {
     commandError: (operation, code) =>
       code === "restore_post_processing_failed"
         ? "Copied, but the history or menu refresh failed."
-        : englishOperationErrors[operation],
+        : code === "history_integrity_failed"
+          ? "Stored history failed its integrity check and was not used. It may have been changed outside Copy Stack."
+          : code === "invalid_backup_passphrase"
+            ? "Backup passphrases must be at least 8 characters."
+            : code === "invalid_backup"
+              ? "The backup is damaged, from a newer version, or the passphrase is wrong. Nothing was imported."
+              : code === "database_backup_not_found"
+                ? "That snapshot no longer exists. Nothing was restored."
+                : code === "database_corrupted"
+                  ? "The database file is damaged below the item level. Nothing was changed; restore a database snapshot."
+                  : code === "invalid_history_import"
+                    ? "The file is not a Copy Stack history export. Nothing was imported."
+                    : englishOperationErrors[operation],
     reduceHistory: "Reduce stored history?",
     reduceHistoryDescription: (current, next, deleteCount) =>
       `Changing the storage limit from ${current} to ${next} will remove ${englishEventCount(deleteCount)} from local storage, starting with the oldest.`,
@@ -295,6 +535,52 @@ const translations: Record<SupportedLanguage, Messages> = {
     historyBudgetDescription:
       "本地历史记录的最大大小（MiB，16–4096）。超出后会先删除最旧的项目。",
     historyBudgetError: "请输入 16 到 4096 之间的整数（MiB）。",
+    historyRetention: "历史记录保留时间",
+    historyRetentionDescription:
+      "在此期间内未再次复制的项目会被删除，即使已固定或 Copy Stack 处于空闲状态。",
+    retentionForever: "永久",
+    retentionDays: days => `${days} 天`,
+    retentionCustom: "自定义",
+    retentionCustomError: "请输入 1 到 3650 之间的整数（天）。",
+    dataTypeBudgets: "按类型限制",
+    dataTypeBudgetsDescription:
+      "为某一类项目单独设置上限，避免它挤掉其他历史记录。留空的字段只使用总体限制。",
+    dataTypeBudgetType: "项目类型",
+    dataTypeBudgetItems: "条数",
+    dataTypeBudgetSummary: (label, items, size) =>
+      `${label}：${[items === null ? null : `${items} 条`, size]
+        .filter(Boolean)
+        .join("，")}`,
+    removeDataTypeBudget: label => `移除${label}限制`,
+    dataTypeBudgetError: "请输入 1–1000 条、16–4096 MiB，或同时输入两者。",
+    sourceApps: "来源应用",
+    sourceAppsDescription: "不保存来自被拒绝应用的复制内容。已在历史记录中的项目会保留。",
+    sourceAppBundleId: "Bundle 标识符，例如 com.example.App",
+    sourceAppDeny: "拒绝",
+    sourceAppAllow: "允许",
+    sourceAppAdd: "添加",
+    deniedSourceApps: "已拒绝",
+    allowedSourceApps: "已允许",
+    removeSourceAppRule: bundleId => `移除 ${bundleId} 的规则`,
+    sourceAppError: "请输入仅包含字母、数字、点、连字符或下划线的 Bundle 标识符。",
+    sourceAllowOnly: "仅保存来自允许应用的内容",
+    sourceAllowOnlyEnabled: "来自未允许应用或来源未知的复制内容不会被保存。",
+    sourceAllowOnlyDisabled: "来自任何未被拒绝应用的复制内容都会被保存。",
+    secretDetection: "敏感信息检测",
+    secretDetectionDescription:
+      "看起来像凭据或银行卡号的复制文本会不保存、遮盖后保存，或在 10 分钟后删除。已在历史记录中的项目不会被检查。",
+    secretKinds: {
+      aws_credential: "AWS 凭据",
+      github_token: "GitHub 令牌",
+      jwt: "JSON Web 令牌",
+      private_key: "私钥",
+      payment_card: "银行卡号",
+    },
+    secretActions: {
+      skip: "不保存",
+      mask: "遮盖后保存",
+      expire: "10 分钟后删除",
+    },
     apply: "应用",
     storageLimitError: "请输入 1 到 1000 之间的整数。",
     language: "语言",
@@ -314,6 +600,9 @@ const translations: Record<SupportedLanguage, Messages> = {
     moveRestoredItemsToTop: "将恢复的项目移到顶部",
     restoreOrderingEnabled: "恢复剪贴板内容后会更新历史记录顺序。",
     restoreOrderingDisabled: "恢复剪贴板内容后会保留当前顺序。",
+    moveRecopiedItemsToTop: "将再次复制的项目移到顶部",
+    recopyOrderingEnabled: "再次复制已保存的内容时会更新历史记录顺序。",
+    recopyOrderingDisabled: "再次复制已保存的内容时会保留当前顺序。",
     showInMenuBar: "在菜单栏中显示",
     menuBarEnabled: "可从菜单栏访问最近的剪贴板内容。",
     menuBarDisabled: "菜单栏图标已隐藏。",
@@ -330,6 +619,50 @@ const translations: Record<SupportedLanguage, Messages> = {
         ? "目前没有可清空的剪贴板历史。"
         : `永久删除这台 Mac 上存储的全部 ${count} 条剪贴板记录。`,
     clearingHistory: "正在清空...",
+    historyIntegrity: "历史记录完整性",
+    historyIntegrityDescription:
+      "检查是否有存储的项目在 Copy Stack 之外被修改、添加或删除。",
+    historyIntegrityVerified: count =>
+      `全部 ${count} 条剪贴板记录均已通过校验。`,
+    verifyHistory: "校验",
+    verifyingHistory: "正在校验...",
+    historyCheck: "历史记录修复",
+    historyCheckDescription:
+      "检查每个存储的项目是否仍可读取，并隔离无法读取的项目。",
+    historyChecked: (checked, quarantined) =>
+      quarantined === 0
+        ? `全部 ${checked} 条剪贴板记录均可读取。`
+        : `已隔离 ${checked} 条中无法读取的 ${quarantined} 条剪贴板记录。`,
+    checkHistory: "检查",
+    checkingHistory: "正在检查...",
+    historyBackup: "历史记录备份",
+    historyBackupDescription:
+      "将设置和历史记录导出为加密文件，或合并导入。导入会替换当前设置。",
+    backupPath: "备份文件的绝对路径",
+    backupPassphrase: "密码（至少 8 个字符）",
+    exportBackup: "导出",
+    exportingBackup: "正在导出...",
+    importBackup: "导入",
+    importingBackup: "正在导入...",
+    backupExported: count => `已导出 ${count} 条剪贴板记录。`,
+    backupImported: (imported, merged, skipped, denied) =>
+      `已导入 ${imported} 条，合并 ${merged} 条，跳过 ${skipped} 条，${denied} 条被捕获规则拦截。`,
+    databaseSnapshots: "数据库快照",
+    databaseSnapshotsDescription:
+      "Copy Stack 会在升级数据库前保存快照。恢复快照会替换历史记录和设置，替换前会先将它们保存为快照。",
+    noDatabaseSnapshots: "暂无快照",
+    databaseSnapshotOption: (createdAt, schemaVersion, plaintext) =>
+      plaintext
+        ? `${createdAt}（架构 ${schemaVersion}，未加密）`
+        : `${createdAt}（架构 ${schemaVersion}）`,
+    plaintextSnapshotNotice:
+      "加密前的快照以未加密形式保存历史记录。它会一直保留到你删除为止，以便撤销升级。",
+    restoreSnapshot: "恢复",
+    restoringSnapshot: "正在恢复...",
+    snapshotRestored: "快照已恢复。",
+    deleteSnapshot: "删除",
+    deletingSnapshot: "正在删除...",
+    snapshotDeleted: "快照已删除。",
     loadMore: "加载更多",
     loadingMore: "正在加载...",
     loadedHistoryCount: (loaded, total) => `已显示 ${loaded}/${total} 条。`,
@@ -371,6 +704,8 @@ const translations: Record<SupportedLanguage, Messages> = {
     previewTruncated: "摘要已缩短",
     captureRejected:
       "由于内容超出安全限制或请求保护隐私，一条剪贴板内容未被保存。",
+    captureSecretRejected: kind =>
+      `一条剪贴板内容看起来像敏感信息（${kind}），未被保存。`,
     retry: "重试",
     dismiss: "关闭",
     diagnosticDetails: "安全诊断信息",
@@ -382,7 +717,19 @@ const translations: Record<SupportedLanguage, Messages> = {
     commandError: (operation, code) =>
       code === "restore_post_processing_failed"
         ? "已复制，但历史记录或菜单刷新失败。"
-        : simplifiedChineseOperationErrors[operation],
+        : code === "history_integrity_failed"
+          ? "存储的历史记录未通过完整性校验，因此未被使用。它可能在 Copy Stack 之外被修改过。"
+          : code === "invalid_backup_passphrase"
+            ? "备份密码至少需要 8 个字符。"
+            : code === "invalid_backup"
+              ? "备份已损坏、来自更新的版本，或密码错误。未导入任何内容。"
+              : code === "database_backup_not_found"
+                ? "该快照已不存在。未恢复任何内容。"
+                : code === "database_corrupted"
+                  ? "数据库文件已损坏，无法按项目修复。未做任何更改；请恢复一个数据库快照。"
+                  : code === "invalid_history_import"
+                    ? "该文件不是 Copy Stack 历史记录导出文件。未导入任何内容。"
+                    : simplifiedChineseOperationErrors[operation],
     reduceHistory: "减少存储的历史记录？",
     reduceHistoryDescription: (current, next, deleteCount) =>
       `将存储上限从 ${current} 改为 ${next}，会从最旧的记录开始删除本地存储中的 ${deleteCount} 条剪贴板记录。`,
@@ -405,6 +752,52 @@ const translations: Record<SupportedLanguage, Messages> = {
     historyBudgetDescription:
       "本機歷史記錄的最大大小（MiB，16–4096）。超出後會先刪除最舊的項目。",
     historyBudgetError: "請輸入 16 到 4096 之間的整數（MiB）。",
+    historyRetention: "歷史記錄保留時間",
+    historyRetentionDescription:
+      "在此期間內未再次複製的項目會被刪除，即使已釘選或 Copy Stack 處於閒置狀態。",
+    retentionForever: "永久",
+    retentionDays: days => `${days} 天`,
+    retentionCustom: "自訂",
+    retentionCustomError: "請輸入 1 到 3650 之間的整數（天）。",
+    dataTypeBudgets: "依類型限制",
+    dataTypeBudgetsDescription:
+      "為某一類項目單獨設定上限，避免它擠掉其他歷史記錄。留空的欄位只使用整體限制。",
+    dataTypeBudgetType: "項目類型",
+    dataTypeBudgetItems: "筆數",
+    dataTypeBudgetSummary: (label, items, size) =>
+      `${label}：${[items === null ? null : `${items} 筆`, size]
+        .filter(Boolean)
+        .join("，")}`,
+    removeDataTypeBudget: label => `移除${label}限制`,
+    dataTypeBudgetError: "請輸入 1–1000 筆、16–4096 MiB，或同時輸入兩者。",
+    sourceApps: "來源應用程式",
+    sourceAppsDescription: "不儲存來自被拒絕應用程式的複製內容。已在歷史記錄中的項目會保留。",
+    sourceAppBundleId: "Bundle 識別碼，例如 com.example.App",
+    sourceAppDeny: "拒絕",
+    sourceAppAllow: "允許",
+    sourceAppAdd: "新增",
+    deniedSourceApps: "已拒絕",
+    allowedSourceApps: "已允許",
+    removeSourceAppRule: bundleId => `移除 ${bundleId} 的規則`,
+    sourceAppError: "請輸入僅包含字母、數字、點、連字號或底線的 Bundle 識別碼。",
+    sourceAllowOnly: "僅儲存來自允許應用程式的內容",
+    sourceAllowOnlyEnabled: "來自未允許應用程式或來源未知的複製內容不會被儲存。",
+    sourceAllowOnlyDisabled: "來自任何未被拒絕應用程式的複製內容都會被儲存。",
+    secretDetection: "敏感資訊偵測",
+    secretDetectionDescription:
+      "看起來像憑證或信用卡號的複製文字會不儲存、遮蔽後儲存，或在 10 分鐘後刪除。已在歷史記錄中的項目不會被檢查。",
+    secretKinds: {
+      aws_credential: "AWS 憑證",
+      github_token: "GitHub 權杖",
+      jwt: "JSON Web 權杖",
+      private_key: "私密金鑰",
+      payment_card: "信用卡號",
+    },
+    secretActions: {
+      skip: "不儲存",
+      mask: "遮蔽後儲存",
+      expire: "10 分鐘後刪除",
+    },
     apply: "套用",
     storageLimitError: "請輸入 1 到 1000 之間的整數。",
     language: "語言",
@@ -424,6 +817,9 @@ const translations: Record<SupportedLanguage, Messages> = {
     moveRestoredItemsToTop: "將還原的項目移至頂端",
     restoreOrderingEnabled: "還原剪貼簿內容後會更新歷史記錄順序。",
     restoreOrderingDisabled: "還原剪貼簿內容後會保留目前順序。",
+    moveRecopiedItemsToTop: "將再次複製的項目移至頂端",
+    recopyOrderingEnabled: "再次複製已儲存的內容時會更新歷史記錄順序。",
+    recopyOrderingDisabled: "再次複製已儲存的內容時會保留目前順序。",
     showInMenuBar: "在選單列中顯示",
     menuBarEnabled: "可從選單列存取最近的剪貼簿內容。",
     menuBarDisabled: "選單列圖示已隱藏。",
@@ -440,6 +836,50 @@ const translations: Record<SupportedLanguage, Messages> = {
         ? "目前沒有可清除的剪貼簿歷史。"
         : `永久刪除這台 Mac 上儲存的全部 ${count} 筆剪貼簿記錄。`,
     clearingHistory: "正在清除...",
+    historyIntegrity: "歷史記錄完整性",
+    historyIntegrityDescription:
+      "檢查是否有儲存的項目在 Copy Stack 之外遭到修改、新增或刪除。",
+    historyIntegrityVerified: count =>
+      `全部 ${count} 筆剪貼簿記錄均已通過驗證。`,
+    verifyHistory: "驗證",
+    verifyingHistory: "正在驗證...",
+    historyCheck: "歷史記錄修復",
+    historyCheckDescription:
+      "檢查每個儲存的項目是否仍可讀取，並隔離無法讀取的項目。",
+    historyChecked: (checked, quarantined) =>
+      quarantined === 0
+        ? `全部 ${checked} 筆剪貼簿記錄均可讀取。`
+        : `已隔離 ${checked} 筆中無法讀取的 ${quarantined} 筆剪貼簿記錄。`,
+    checkHistory: "檢查",
+    checkingHistory: "正在檢查...",
+    historyBackup: "歷史記錄備份",
+    historyBackupDescription:
+      "將設定和歷史記錄匯出為加密檔案，或合併匯入。匯入會取代目前的設定。",
+    backupPath: "備份檔案的絕對路徑",
+    backupPassphrase: "密碼（至少 8 個字元）",
+    exportBackup: "匯出",
+    exportingBackup: "正在匯出...",
+    importBackup: "匯入",
+    importingBackup: "正在匯入...",
+    backupExported: count => `已匯出 ${count} 筆剪貼簿記錄。`,
+    backupImported: (imported, merged, skipped, denied) =>
+      `已匯入 ${imported} 筆，合併 ${merged} 筆，略過 ${skipped} 筆，${denied} 筆被擷取規則攔截。`,
+    databaseSnapshots: "資料庫快照",
+    databaseSnapshotsDescription:
+      "Copy Stack 會在升級資料庫前儲存快照。還原快照會取代歷史記錄和設定，取代前會先將它們儲存為快照。",
+    noDatabaseSnapshots: "尚無快照",
+    databaseSnapshotOption: (createdAt, schemaVersion, plaintext) =>
+      plaintext
+        ? `${createdAt}（結構描述 ${schemaVersion}，未加密）`
+        : `${createdAt}（結構描述 ${schemaVersion}）`,
+    plaintextSnapshotNotice:
+      "加密前的快照以未加密形式儲存歷史記錄。它會一直保留到你刪除為止，以便復原升級。",
+    restoreSnapshot: "還原",
+    restoringSnapshot: "正在還原...",
+    snapshotRestored: "快照已還原。",
+    deleteSnapshot: "刪除",
+    deletingSnapshot: "正在刪除...",
+    snapshotDeleted: "快照已刪除。",
     loadMore: "載入更多",
     loadingMore: "正在載入...",
     loadedHistoryCount: (loaded, total) => `已顯示 ${loaded}/${total} 筆。`,
@@ -481,6 +921,8 @@ const translations: Record<SupportedLanguage, Messages> = {
     previewTruncated: "摘要已縮短",
     captureRejected:
       "由於內容超出安全限制或要求保護隱私，一筆剪貼簿內容未被儲存。",
+    captureSecretRejected: kind =>
+      `一筆剪貼簿內容看起來像敏感資訊（${kind}），未被儲存。`,
     retry: "重試",
     dismiss: "關閉",
     diagnosticDetails: "安全診斷資訊",
@@ -492,7 +934,19 @@ const translations: Record<SupportedLanguage, Messages> = {
     commandError: (operation, code) =>
       code === "restore_post_processing_failed"
         ? "已複製，但歷史記錄或選單重新整理失敗。"
-        : traditionalChineseOperationErrors[operation],
+        : code === "history_integrity_failed"
+          ? "儲存的歷史記錄未通過完整性驗證，因此未被使用。它可能在 Copy Stack 之外遭到修改。"
+          : code === "invalid_backup_passphrase"
+            ? "備份密碼至少需要 8 個字元。"
+            : code === "invalid_backup"
+              ? "備份已損壞、來自較新的版本，或密碼錯誤。未匯入任何內容。"
+              : code === "database_backup_not_found"
+                ? "該快照已不存在。未還原任何內容。"
+                : code === "database_corrupted"
+                  ? "資料庫檔案已損壞，無法按項目修復。未做任何變更；請還原一個資料庫快照。"
+                  : code === "invalid_history_import"
+                    ? "該檔案不是 Copy Stack 歷史記錄匯出檔案。未匯入任何內容。"
+                    : traditionalChineseOperationErrors[operation],
     reduceHistory: "減少儲存的歷史記錄？",
     reduceHistoryDescription: (current, next, deleteCount) =>
       `將儲存上限從 ${current} 改為 ${next}，會從最舊的記錄開始刪除本機儲存中的 ${deleteCount} 筆剪貼簿記錄。`,
@@ -31,6 +31,26 @@ export interface HistorySummary {
   timestamp: number;
   byte_count: number;
   has_detail: boolean;
+  pinned: boolean;
+  first_seen_at: number;
+  last_seen_at: number;
+  copy_count: number;
+}
+
+export interface SearchSnippetSegment {
+  text: string;
+  highlighted: boolean;
+}
+
+export interface HistorySearchResult {
+  summary: HistorySummary;
+  snippet: SearchSnippetSegment[];
+}
+
+export interface HistorySearchPage {
+  items: HistorySearchResult[];
+  next_cursor: string | null;
+  has_more: boolean;
 }
 
 export interface HistoryDetail {
@@ -48,12 +68,57 @@ export interface HistoryPage {
   total_bytes: number;
 }
 
+export interface HistoryCollection {
+  id: number;
+  name: string;
+  item_count: number;
+}
+
+export interface HistoryTag {
+  name: string;
+  item_count: number;
+}
+
+export interface HistoryLabels {
+  collection_ids: number[];
+  tags: string[];
+}
+
+export interface DataTypeBudget {
+  data_type: string;
+  max_items: number | null;
+  max_bytes: number | null;
+}
+
+export type SourceAppRule = "deny" | "allow";
+
+export type SecretKind =
+  | "aws_credential"
+  | "github_token"
+  | "jwt"
+  | "private_key"
+  | "payment_card";
+
+export type SecretAction = "skip" | "mask" | "expire";
+
+export interface SecretPolicySetting {
+  kind: SecretKind;
+  action: SecretAction;
+}
+
 export interface AppSettings {
   max_items: number;
   max_history_bytes: number;
+  max_age_days: number;
+  data_type_budgets: DataTypeBudget[];
+  denied_source_apps: string[];
+  allowed_source_apps: string[];
+  source_allow_only: boolean;
+  secret_policies: SecretPolicySetting[];
   show_in_menu_bar: boolean;
   menu_bar_item_limit: number;
   move_restored_item_to_top: boolean;
+  move_recopied_item_to_top: boolean;
   compact_mode: boolean;
   language: string;
   resolved_language: string;
@@ -63,6 +128,46 @@ export interface AppSettings {
   max_event_bytes: number;
 }
 
+export interface HistoryIntegrityReport {
+  verified_items: number;
+}
+
+export interface HistoryCheckReport {
+  checked_items: number;
+  quarantined_items: number;
+  undecryptable_items: number;
+  undecodable_items: number;
+  mismatched_items: number;
+}
+
+export interface HistoryBackupReport {
+  exported_items: number;
+}
+
+export type HistoryExportFormat = "markdown" | "csv" | "html";
+
+export interface HistoryExportReport {
+  exported_items: number;
+}
+
+export type ClipboardManager = "maccy" | "copyq" | "clipy";
+
+export interface HistoryImportReport {
+  imported_items: number;
+  merged_items: number;
+  skipped_items: number;
+  denied_items: number;
+  rejected_items: number;
+}
+
+export interface DatabaseBackup {
+  name: string;
+  created_at: number;
+  schema_version: number;
+  size_bytes: number;
+  plaintext: boolean;
+}
+
 export interface CaptureRejectedNotice {
   code?: string;
   reason?: string;
@@ -74,34 +179,69 @@ export type ErrorCode =
   | "database_unavailable"
   | "database_operation_failed"
   | "history_item_not_found"
+  | "pinned_limit_reached"
+  | "invalid_label_name"
+  | "label_name_taken"
+  | "label_not_found"
+  | "label_limit_reached"
   | "clipboard_write_failed"
   | "restore_post_processing_failed"
   | "invalid_setting"
   | "invalid_history_cursor"
+  | "invalid_search_query"
   | "state_unavailable"
   | "autostart_unavailable"
   | "autostart_verification_failed"
   | "history_mirror_failed"
   | "capture_rejected"
+  | "history_integrity_failed"
+  | "invalid_backup_passphrase"
+  | "invalid_backup"
+  | "backup_file_unavailable"
+  | "database_backup_not_found"
+  | "database_corrupted"
+  | "invalid_history_import"
+  | "history_import_file_unavailable"
+  | "history_export_file_unavailable"
+  | "control_socket_unavailable"
+  | "invalid_control_request"
   | "unknown";
 
 export type Operation =
   | "startup"
   | "capture_clipboard"
   | "load_history"
+  | "search_history"
   | "load_history_detail"
   | "restore_clipboard"
+  | "pin_history"
+  | "unpin_history"
+  | "load_labels"
+  | "update_collections"
+  | "update_tags"
   | "delete_history"
   | "clear_history"
   | "load_settings"
   | "update_settings"
   | "update_autostart"
-  | "write_history_mirror";
+  | "write_history_mirror"
+  | "expire_history"
+  | "verify_history"
+  | "check_history"
+  | "export_backup"
+  | "import_backup"
+  | "load_database_backups"
+  | "restore_database_backup"
+  | "delete_database_backup"
+  | "import_history"
+  | "export_history"
+  | "control_request";
 
 export interface CommandError {
   code: ErrorCode;
   operation: Operation;
   retryable: boolean;
+  detail?: string;
 }
 
 export interface SafeDiagnostic {
@@ -112,4 +252,5 @@ export interface SafeDiagnostic {
   code: ErrorCode;
   operation: Operation;
   retryable: boolean;
+  detail?: string;
 }